@@ -327,6 +327,7 @@ pub fn testnet_genesis(
 		},
 		pallet_vesting: Default::default(),
 		pallet_gilt: Default::default(),
+		pallet_accounting: Default::default(),
 	}
 }
 
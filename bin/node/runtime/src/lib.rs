@@ -80,6 +80,7 @@ use sp_version::NativeVersion;
 use sp_version::RuntimeVersion;
 use static_assertions::const_assert;
 use pallet_contracts::weights::WeightInfo;
+use totem_utils::traits::accounting::Posting;
 
 #[cfg(any(feature = "std", test))]
 pub use frame_system::Call as SystemCall;
@@ -152,6 +153,23 @@ impl OnUnbalanced<NegativeImbalance> for DealWithFees {
 				// for tips, if any, 80% to treasury, 20% to author (though this can be anything)
 				tips.ration_merge_into(80, 20, &mut split);
 			}
+			// post the shares as revenue of the recipients, the fees are already paid so a failure is only reported.
+			let treasury = Treasury::account_id();
+			TransactionPayment::post_to_ledgers(
+				&treasury,
+				<Runtime as pallet_transaction_payment::Config>::Accounting::account_for_fee_revenue(
+					split.0.peek(),
+					treasury.clone(),
+				),
+			);
+			let author = Authorship::author();
+			TransactionPayment::post_to_ledgers(
+				&author,
+				<Runtime as pallet_transaction_payment::Config>::Accounting::account_for_fee_revenue(
+					split.1.peek(),
+					author.clone(),
+				),
+			);
 			Treasury::on_unbalanced(split.0);
 			Author::on_unbalanced(split.1);
 		}
@@ -399,6 +417,8 @@ impl pallet_transaction_payment::Config for Runtime {
 	type TransactionByteFee = TransactionByteFee;
 	type WeightToFee = IdentityFee<Balance>;
 	type FeeMultiplierUpdate = TargetedFeeAdjustment<Self, TargetBlockFullness, AdjustmentVariable, MinimumMultiplier>;
+	type Accounting = pallet_accounting::Module<Self>;
+	type Event = Event;
 }
 
 parameter_types! {
@@ -1085,6 +1105,11 @@ mod totem {
 	impl pallet_accounting::Config for Runtime {
 		type Event = Event;
 		type AccountingConversions = conversion_handler::ConversionHandler;
+		type ChartOfAccountsOrigin = EnsureOneOf<
+			AccountId,
+			EnsureRoot<AccountId>,
+			pallet_collective::EnsureProportionMoreThan<_1, _2, AccountId, CouncilCollective>,
+		>;
 	}
 
 	impl pallet_archive::Config for Runtime {
@@ -1100,27 +1125,52 @@ mod totem {
 		type BonsaiConversions = conversion_handler::ConversionHandler;
 	}
 
+	parameter_types! {
+		pub const MaxOrderItems: u32 = 100;
+		pub const MaxTaxLines: u32 = 10;
+		pub const MaxExpiriesPerBlock: u32 = 50;
+		pub const MaxUnfundedInvoices: u32 = 500;
+		pub const MaxProjectDepth: u32 = 10;
+	}
+
 	impl pallet_orders::Config for Runtime {
 		type Event = Event;
 		type Accounting = pallet_accounting::Module<Self>;
 		type Prefunding = pallet_prefunding::Module<Self>;
 		type OrderConversions = conversion_handler::ConversionHandler;
 		type Bonsai = pallet_bonsai::Module<Self>;
+		type Projects = pallet_teams::Module<Self>;
+		type MaxOrderItems = MaxOrderItems;
+		type MaxTaxLines = MaxTaxLines;
 	}
 
 	impl pallet_prefunding::Config for Runtime {
 		type Event = Event;
 		type Currency = pallet_balances::Module<Self>;
 		type PrefundingConversions = conversion_handler::ConversionHandler;
+		type ArbitrationOrigin = EnsureOneOf<
+			AccountId,
+			EnsureRoot<AccountId>,
+			pallet_collective::EnsureProportionMoreThan<_1, _2, AccountId, CouncilCollective>,
+		>;
+		type MaxExpiriesPerBlock = MaxExpiriesPerBlock;
+		type MaxUnfundedInvoices = MaxUnfundedInvoices;
+		type MaxTaxLines = MaxTaxLines;
+		type Orders = pallet_orders::Module<Self>;
 	}
 
 	impl pallet_teams::Config for Runtime {
 		type Event = Event;
+		type MaxProjectDepth = MaxProjectDepth;
 	}
 
 	impl pallet_timekeeping::Config for Runtime {
 		type Event = Event;
 		type Projects = Teams;
+		type Currency = Balances;
+		type Accounting = pallet_accounting::Module<Self>;
+		type Calendar = pallet_accounting::Module<Self>;
+		type TimekeepingConversions = conversion_handler::ConversionHandler;
 	}
 }
 
@@ -1132,7 +1182,7 @@ construct_runtime!(
 	{
 		System: frame_system::{Module, Call, Config, Storage, Event<T>},
 		// Totem
-		Accounting: pallet_accounting::{Module, Call, Storage, Event<T>},
+		Accounting: pallet_accounting::{Module, Call, Config, Storage, Event<T>},
 		Archive: pallet_archive::{Module, Call, Storage, Event<T>},
 		Bonsai: pallet_bonsai::{Module, Call, Storage, Event<T>},
 		Orders: pallet_orders::{Module, Call, Storage, Event<T>},
@@ -1146,7 +1196,7 @@ construct_runtime!(
 		Authorship: pallet_authorship::{Module, Call, Storage, Inherent},
 		Indices: pallet_indices::{Module, Call, Storage, Config<T>, Event<T>},
 		Balances: pallet_balances::{Module, Call, Storage, Config<T>, Event<T>},
-		TransactionPayment: pallet_transaction_payment::{Module, Storage},
+		TransactionPayment: pallet_transaction_payment::{Module, Storage, Event<T>},
 		ElectionProviderMultiPhase: pallet_election_provider_multi_phase::{Module, Call, Storage, Event<T>, ValidateUnsigned},
 		Staking: pallet_staking::{Module, Call, Config<T>, Storage, Event<T>, ValidateUnsigned},
 		Session: pallet_session::{Module, Call, Storage, Event, Config<T>},
@@ -1424,6 +1474,37 @@ impl_runtime_apis! {
 		}
 	}
 
+	impl pallet_accounting_rpc_runtime_api::AccountingApi<
+		Block,
+		AccountId,
+		BlockNumber,
+	> for Runtime {
+		fn trial_balance(
+			who: AccountId,
+			period: Option<pallet_accounting_rpc_runtime_api::ReportPeriod<BlockNumber>>,
+		) -> Vec<pallet_accounting_rpc_runtime_api::TrialBalanceLine> {
+			Accounting::trial_balance(who, period)
+		}
+		fn balance_sheet(
+			who: AccountId,
+			period: Option<pallet_accounting_rpc_runtime_api::ReportPeriod<BlockNumber>>,
+		) -> Vec<pallet_accounting_rpc_runtime_api::ReportLine> {
+			Accounting::balance_sheet(who, period)
+		}
+		fn profit_and_loss(
+			who: AccountId,
+			period: Option<pallet_accounting_rpc_runtime_api::ReportPeriod<BlockNumber>>,
+		) -> Vec<pallet_accounting_rpc_runtime_api::ReportLine> {
+			Accounting::profit_and_loss(who, period)
+		}
+		fn reconcile() -> Vec<pallet_accounting_rpc_runtime_api::Mismatch<AccountId>> {
+			Accounting::reconcile()
+		}
+		fn sales_tax(jurisdiction: AccountId) -> Vec<pallet_accounting_rpc_runtime_api::TaxLiability<AccountId>> {
+			Accounting::sales_tax(jurisdiction)
+		}
+	}
+
 	impl pallet_mmr::primitives::MmrApi<
 		Block,
 		mmr::Leaf,
@@ -154,6 +154,7 @@ mod tests;
 mod tests_local;
 mod tests_composite;
 mod tests_reentrancy;
+mod tests_accounting;
 mod benchmarking;
 pub mod weights;
 
@@ -308,6 +309,7 @@ pub mod pallet {
 			let wipeout = new_free + new_reserved < existential_deposit;
 			let new_free = if wipeout { Zero::zero() } else { new_free };
 			let new_reserved = if wipeout { Zero::zero() } else { new_reserved };
+			let old = Self::account(&who);
 
 			let (free, reserved) = Self::mutate_account(&who, |account| {
 				if new_free > account.free {
@@ -327,6 +329,8 @@ pub mod pallet {
 
 				(account.free, account.reserved)
 			})?;
+			Self::post_balance_change(&who, Status::Free, old.free, free);
+			Self::post_balance_change(&who, Status::Reserved, old.reserved, reserved);
 			Self::deposit_event(Event::BalanceSet(who, free, reserved));
 			Ok(().into())
 		}
@@ -398,6 +402,8 @@ pub mod pallet {
 		/// Final argument indicates the destination balance type.
 		/// \[from, to, balance, destination_status\]
 		ReserveRepatriated(T::AccountId, T::AccountId, T::Balance, Status),
+		/// A change of the balances of an account could not be posted to the Totem ledgers. \[who\]
+		LedgerPostingFailed(T::AccountId),
 	}
 
 	/// Old name generated by `decl_event`.
@@ -513,6 +519,7 @@ pub mod pallet {
 
 			for &(ref who, free) in self.balances.iter() {
 				assert!(T::AccountStore::insert(who, AccountData { free, ..Default::default() }).is_ok());
+				Pallet::<T, I>::post_to_ledgers(who, T::Accounting::account_for_opening_balance(who.clone()));
 			}
 		}
 	}
@@ -660,6 +667,10 @@ pub struct DustCleaner<T: Config<I>, I: 'static = ()>(Option<(T::AccountId, Nega
 impl<T: Config<I>, I: 'static> Drop for DustCleaner<T, I> {
 	fn drop(&mut self) {
 		if let Some((who, dust)) = self.0.take() {
+			Module::<T, I>::post_to_ledgers(
+				&who,
+				T::Accounting::account_for_withdrawal(who.clone(), dust.peek(), Status::Free),
+			);
 			Module::<T, I>::deposit_event(Event::DustLost(who, dust.peek()));
 			T::DustRemoval::on_unbalanced(dust);
 		}
@@ -1115,6 +1126,10 @@ impl<T: Config<I>, I: 'static> Currency<T::AccountId> for Pallet<T, I> where
 			}
 		)?;
 
+		Self::post_to_ledgers(
+			transactor,
+			T::Accounting::account_for_transfer(transactor.clone(), Status::Free, dest.clone(), Status::Free, value),
+		);
 		// Emit transfer event.
 		Self::deposit_event(Event::Transfer(transactor.clone(), dest.clone(), value));
 
@@ -1136,6 +1151,8 @@ impl<T: Config<I>, I: 'static> Currency<T::AccountId> for Pallet<T, I> where
 	) -> (Self::NegativeImbalance, Self::Balance) {
 		if value.is_zero() { return (NegativeImbalance::zero(), Zero::zero()) }
 		if Self::total_balance(&who).is_zero() { return (NegativeImbalance::zero(), value) }
+		// The amounts slashed from the free and the reserved balance by the attempt that succeeds.
+		let mut slashed: (Self::Balance, Self::Balance) = (Zero::zero(), Zero::zero());
 
 		for attempt in 0..2 {
 			match Self::try_mutate_account(who,
@@ -1157,12 +1174,14 @@ impl<T: Config<I>, I: 'static> Currency<T::AccountId> for Pallet<T, I> where
 						// If we have remaining slash, take it from reserved balance.
 						let reserved_slash = cmp::min(account.reserved, remaining_slash);
 						account.reserved -= reserved_slash; // Safe because of above check
+						slashed = (free_slash, reserved_slash);
 						Ok((
 							NegativeImbalance::new(free_slash + reserved_slash),
 							value - free_slash - reserved_slash, // Safe because value is gt or eq total slashed
 						))
 					} else {
 						// Else we are done!
+						slashed = (free_slash, Zero::zero());
 						Ok((
 							NegativeImbalance::new(free_slash),
 							value - free_slash, // Safe because value is gt or eq to total slashed
@@ -1170,7 +1189,15 @@ impl<T: Config<I>, I: 'static> Currency<T::AccountId> for Pallet<T, I> where
 					}
 				}
 			) {
-				Ok(r) => return r,
+				Ok(r) => {
+					let (free_slash, reserved_slash) = slashed;
+					Self::post_to_ledgers(who, T::Accounting::account_for_slash(who.clone(), free_slash, Status::Free));
+					Self::post_to_ledgers(
+						who,
+						T::Accounting::account_for_slash(who.clone(), reserved_slash, Status::Reserved),
+					);
+					return r
+				},
 				Err(_) => (),
 			}
 		}
@@ -1188,11 +1215,16 @@ impl<T: Config<I>, I: 'static> Currency<T::AccountId> for Pallet<T, I> where
 	) -> Result<Self::PositiveImbalance, DispatchError> {
 		if value.is_zero() { return Ok(PositiveImbalance::zero()) }
 
-		Self::try_mutate_account(who, |account, is_new| -> Result<Self::PositiveImbalance, DispatchError> {
+		let imbalance = Self::try_mutate_account(who, |account, is_new|
+			-> Result<Self::PositiveImbalance, DispatchError>
+		{
 			ensure!(!is_new, Error::<T, I>::DeadAccount);
 			account.free = account.free.checked_add(&value).ok_or(Error::<T, I>::Overflow)?;
 			Ok(PositiveImbalance::new(value))
-		})
+		})?;
+
+		Self::post_to_ledgers(who, T::Accounting::account_for_deposit(who.clone(), imbalance.peek(), Status::Free));
+		Ok(imbalance)
 	}
 
 	/// Deposit some `value` into the free balance of `who`, possibly creating a new account.
@@ -1223,6 +1255,7 @@ impl<T: Config<I>, I: 'static> Currency<T::AccountId> for Pallet<T, I> where
 			Ok(PositiveImbalance::new(value))
 		}).unwrap_or_else(|_| Self::PositiveImbalance::zero());
 
+		Self::post_to_ledgers(who, T::Accounting::account_for_deposit(who.clone(), r.peek(), Status::Free));
 		r
 	}
 
@@ -1237,7 +1270,7 @@ impl<T: Config<I>, I: 'static> Currency<T::AccountId> for Pallet<T, I> where
 	) -> result::Result<Self::NegativeImbalance, DispatchError> {
 		if value.is_zero() { return Ok(NegativeImbalance::zero()); }
 
-		Self::try_mutate_account(who, |account, _|
+		let imbalance = Self::try_mutate_account(who, |account, _|
 			-> Result<Self::NegativeImbalance, DispatchError>
 		{
 			let new_free_account = account.free.checked_sub(&value)
@@ -1254,14 +1287,17 @@ impl<T: Config<I>, I: 'static> Currency<T::AccountId> for Pallet<T, I> where
 			account.free = new_free_account;
 
 			Ok(NegativeImbalance::new(value))
-		})
+		})?;
+
+		Self::post_to_ledgers(who, T::Accounting::account_for_withdrawal(who.clone(), imbalance.peek(), Status::Free));
+		Ok(imbalance)
 	}
 
 	/// Force the new free balance of a target account `who` to some new value `balance`.
 	fn make_free_balance_be(who: &T::AccountId, value: Self::Balance)
 		-> SignedImbalance<Self::Balance, Self::PositiveImbalance>
 	{
-		Self::try_mutate_account(who, |account, is_new|
+		let imbalance = Self::try_mutate_account(who, |account, is_new|
 			-> Result<SignedImbalance<Self::Balance, Self::PositiveImbalance>, DispatchError>
 		{
 			let ed = T::ExistentialDeposit::get();
@@ -1282,7 +1318,16 @@ impl<T: Config<I>, I: 'static> Currency<T::AccountId> for Pallet<T, I> where
 			};
 			account.free = value;
 			Ok(imbalance)
-		}).unwrap_or_else(|_| SignedImbalance::Positive(Self::PositiveImbalance::zero()))
+		}).unwrap_or_else(|_| SignedImbalance::Positive(Self::PositiveImbalance::zero()));
+
+		let posting = match &imbalance {
+			SignedImbalance::Positive(deposit) =>
+				T::Accounting::account_for_deposit(who.clone(), deposit.peek(), Status::Free),
+			SignedImbalance::Negative(withdrawal) =>
+				T::Accounting::account_for_withdrawal(who.clone(), withdrawal.peek(), Status::Free),
+		};
+		Self::post_to_ledgers(who, posting);
+		imbalance
 	}
 }
 
@@ -1317,6 +1362,7 @@ impl<T: Config<I>, I: 'static> ReservableCurrency<T::AccountId> for Pallet<T, I>
 			Self::ensure_can_withdraw(&who, value.clone(), WithdrawReasons::RESERVE, account.free)
 		})?;
 
+		Self::post_to_ledgers(who, T::Accounting::account_for_reserve(who.clone(), value, Status::Reserved));
 		Self::deposit_event(Event::Reserved(who.clone(), value));
 		Ok(())
 	}
@@ -1345,6 +1391,7 @@ impl<T: Config<I>, I: 'static> ReservableCurrency<T::AccountId> for Pallet<T, I>
 			}
 		};
 
+		Self::post_to_ledgers(who, T::Accounting::account_for_reserve(who.clone(), actual, Status::Free));
 		Self::deposit_event(Event::Unreserved(who.clone(), actual.clone()));
 		value - actual
 	}
@@ -1378,7 +1425,13 @@ impl<T: Config<I>, I: 'static> ReservableCurrency<T::AccountId> for Pallet<T, I>
 				// underflow should never happen, but it if does, there's nothing to be done here.
 				(NegativeImbalance::new(actual), value - actual)
 			}) {
-				Ok(r) => return r,
+				Ok(r) => {
+					Self::post_to_ledgers(
+						who,
+						T::Accounting::account_for_slash(who.clone(), r.0.peek(), Status::Reserved),
+					);
+					return r
+				},
 				Err(_) => (),
 			}
 		}
@@ -1430,6 +1483,10 @@ impl<T: Config<I>, I: 'static> ReservableCurrency<T::AccountId> for Pallet<T, I>
 			}
 		)?;
 
+		Self::post_to_ledgers(
+			slashed,
+			T::Accounting::account_for_transfer(slashed.clone(), Status::Reserved, beneficiary.clone(), status, actual),
+		);
 		Self::deposit_event(Event::ReserveRepatriated(slashed.clone(), beneficiary.clone(), actual, status));
 		Ok(value - actual)
 	}
@@ -0,0 +1,274 @@
+//! Tests of the balance changes posted to the Totem ledgers.
+
+#![cfg(test)]
+
+use sp_runtime::{
+	traits::IdentityLookup,
+	testing::Header,
+};
+use sp_core::H256;
+use sp_io;
+use sp_std::cell::RefCell;
+use frame_support::{
+	assert_ok,
+	dispatch::DispatchResultWithPostInfo,
+	parameter_types,
+	traits::{BalanceStatus as Status, Currency, ExistenceRequirement::AllowDeath, ReservableCurrency, WithdrawReasons},
+};
+use totem_utils::{ok, traits::accounting::Posting};
+use crate::{
+	self as pallet_balances,
+	totem::TotemLockableCurrency,
+	Config,
+};
+
+/// Holds the funds under the totem locks.
+const ESCROW: u64 = 99;
+
+type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
+type Block = frame_system::mocking::MockBlock<Test>;
+
+frame_support::construct_runtime!(
+	pub enum Test where
+		Block = Block,
+		NodeBlock = Block,
+		UncheckedExtrinsic = UncheckedExtrinsic,
+	{
+		System: frame_system::{Module, Call, Config, Storage, Event<T>},
+		Balances: pallet_balances::{Module, Call, Storage, Config<T>, Event<T>},
+	}
+);
+
+parameter_types! {
+	pub const BlockHashCount: u64 = 250;
+	pub const ExistentialDeposit: u64 = 1;
+}
+impl frame_system::Config for Test {
+	type BaseCallFilter = ();
+	type BlockWeights = ();
+	type BlockLength = ();
+	type DbWeight = ();
+	type Origin = Origin;
+	type Index = u64;
+	type BlockNumber = u64;
+	type Call = Call;
+	type Hash = H256;
+	type Hashing = ::sp_runtime::traits::BlakeTwo256;
+	type AccountId = u64;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type Header = Header;
+	type Event = Event;
+	type BlockHashCount = BlockHashCount;
+	type Version = ();
+	type PalletInfo = PalletInfo;
+	type AccountData = super::AccountData<u64>;
+	type OnNewAccount = ();
+	type OnKilledAccount = ();
+	type SystemWeightInfo = ();
+	type SS58Prefix = ();
+}
+impl Config for Test {
+	type Balance = u64;
+	type DustRemoval = ();
+	type Event = Event;
+	type ExistentialDeposit = ExistentialDeposit;
+	type AccountStore = frame_system::Pallet<Test>;
+	type MaxLocks = ();
+	type WeightInfo = ();
+	type Accounting = RecordingAccounting;
+}
+
+/// A balance change posted to the ledgers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Posted {
+	Transfer(u64, Status, u64, Status, u64),
+	Reserve(u64, u64, Status),
+	Slash(u64, u64, Status),
+	Deposit(u64, u64, Status),
+	Withdrawal(u64, u64, Status),
+}
+
+thread_local! {
+	static POSTED: RefCell<Vec<Posted>> = RefCell::new(Vec::new());
+}
+
+/// Takes the balance changes posted since the last call.
+fn posted() -> Vec<Posted> {
+	POSTED.with(|p| p.borrow_mut().drain(..).collect())
+}
+
+fn record(posting: Posted) -> DispatchResultWithPostInfo {
+	POSTED.with(|p| p.borrow_mut().push(posting));
+	ok()
+}
+
+/// Records the balance changes instead of posting them.
+pub struct RecordingAccounting;
+
+impl Posting<u64, H256, u64, u64> for RecordingAccounting {
+	type Account = u64;
+	type PostingIndex = u128;
+	type LedgerBalance = i128;
+
+	fn handle_multiposting_amounts(
+		_keys: Vec<(u64, Self::Account, Self::LedgerBalance, bool, H256, u64, u64)>,
+	) -> DispatchResultWithPostInfo {
+		unimplemented!("Balances only post balance changes")
+	}
+
+	fn account_for_fees(_f: u64, _t: u64, _r: u64, _p: u64) -> DispatchResultWithPostInfo {
+		ok()
+	}
+
+	fn account_for_fee_revenue(_f: u64, _r: u64) -> DispatchResultWithPostInfo {
+		ok()
+	}
+
+	fn account_for_transfer(f: u64, fs: Status, t: u64, ts: Status, a: u64) -> DispatchResultWithPostInfo {
+		record(Posted::Transfer(f, fs, t, ts, a))
+	}
+
+	fn account_for_reserve(w: u64, a: u64, s: Status) -> DispatchResultWithPostInfo {
+		record(Posted::Reserve(w, a, s))
+	}
+
+	fn account_for_slash(w: u64, a: u64, s: Status) -> DispatchResultWithPostInfo {
+		record(Posted::Slash(w, a, s))
+	}
+
+	fn account_for_deposit(w: u64, a: u64, s: Status) -> DispatchResultWithPostInfo {
+		record(Posted::Deposit(w, a, s))
+	}
+
+	fn account_for_withdrawal(w: u64, a: u64, s: Status) -> DispatchResultWithPostInfo {
+		record(Posted::Withdrawal(w, a, s))
+	}
+
+	fn account_for_opening_balance(_w: u64) -> DispatchResultWithPostInfo {
+		ok()
+	}
+
+	fn record_sales_tax(_v: u64, _j: u64, _a: Self::LedgerBalance) -> DispatchResultWithPostInfo {
+		ok()
+	}
+
+	fn get_escrow_account() -> u64 {
+		ESCROW
+	}
+
+	fn get_pseudo_random_hash(_s: u64, _r: u64) -> H256 {
+		unimplemented!("Used as a mock, shouldn't be called")
+	}
+}
+
+/// Accounts 1 and 2 start with 100 each.
+fn new_test_ext() -> sp_io::TestExternalities {
+	let mut t = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+	pallet_balances::GenesisConfig::<Test> {
+		balances: vec![(1, 100), (2, 100)],
+	}.assimilate_storage(&mut t).unwrap();
+
+	let mut ext = sp_io::TestExternalities::new(t);
+	ext.execute_with(|| {
+		System::set_block_number(1);
+		posted();
+	});
+	ext
+}
+
+#[test]
+fn transfer_is_posted() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(<Balances as Currency<_>>::transfer(&1, &2, 30, AllowDeath));
+
+		assert_eq!(posted(), vec![Posted::Transfer(1, Status::Free, 2, Status::Free, 30)]);
+	});
+}
+
+#[test]
+fn reserve_and_unreserve_are_posted() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Balances::reserve(&1, 40));
+		// Only the reserved balance is unreserved.
+		assert_eq!(Balances::unreserve(&1, 50), 10);
+
+		assert_eq!(posted(), vec![Posted::Reserve(1, 40, Status::Reserved), Posted::Reserve(1, 40, Status::Free)]);
+	});
+}
+
+#[test]
+fn slash_is_posted_from_the_free_then_the_reserved_balance() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Balances::reserve(&1, 40));
+		posted();
+
+		assert_eq!(Balances::slash(&1, 80).1, 0);
+
+		assert_eq!(Balances::free_balance(&1), 0);
+		assert_eq!(Balances::reserved_balance(&1), 20);
+		assert_eq!(posted(), vec![Posted::Slash(1, 60, Status::Free), Posted::Slash(1, 20, Status::Reserved)]);
+	});
+}
+
+#[test]
+fn slash_of_a_required_account_posts_what_is_slashed_down_to_the_existential_deposit() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Balances::reserve(&1, 40));
+		// The account must be kept alive, the full slash fails and the second attempt leaves the existential deposit.
+		assert_ok!(System::inc_consumers(&1));
+		posted();
+
+		assert_eq!(Balances::slash(&1, 150).1, 51);
+
+		assert_eq!(Balances::free_balance(&1), 0);
+		assert_eq!(Balances::reserved_balance(&1), 1);
+		assert_eq!(posted(), vec![Posted::Slash(1, 60, Status::Free), Posted::Slash(1, 39, Status::Reserved)]);
+	});
+}
+
+#[test]
+fn repatriated_reserve_is_posted() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Balances::reserve(&1, 40));
+		posted();
+
+		assert_ok!(Balances::repatriate_reserved(&1, &2, 30, Status::Free), 0);
+		assert_ok!(Balances::repatriate_reserved(&1, &2, 30, Status::Reserved), 20);
+
+		assert_eq!(
+			posted(),
+			vec![
+				Posted::Transfer(1, Status::Reserved, 2, Status::Free, 30),
+				Posted::Transfer(1, Status::Reserved, 2, Status::Reserved, 10),
+			]
+		);
+		assert_eq!(Balances::free_balance(&2), 130);
+		assert_eq!(Balances::reserved_balance(&2), 10);
+	});
+}
+
+#[test]
+fn totem_locks_move_the_difference_to_and_from_the_escrow() {
+	new_test_ext().execute_with(|| {
+		let id = *b"prefund0";
+		Balances::totem_set_lock(id, &1, 60, 10, WithdrawReasons::RESERVE);
+		assert_eq!((Balances::free_balance(&1), Balances::free_balance(&ESCROW)), (40, 60));
+
+		// Lowering the lock returns the difference
+		Balances::totem_set_lock(id, &1, 25, 10, WithdrawReasons::RESERVE);
+		assert_eq!((Balances::free_balance(&1), Balances::free_balance(&ESCROW)), (75, 25));
+		assert_eq!(Balances::totem_locks(&1).iter().map(|l| l.amount).collect::<Vec<_>>(), vec![25]);
+
+		// The lock is kept past its deadline until it is removed
+		System::set_block_number(20);
+		Balances::totem_set_lock(*b"prefund1", &1, 5, 30, WithdrawReasons::RESERVE);
+		assert_eq!(Balances::totem_locks(&1).len(), 2);
+
+		Balances::totem_remove_lock(id, &1);
+		Balances::totem_remove_lock(*b"prefund1", &1);
+		assert_eq!(Balances::free_balance(&1), 100);
+		assert_eq!(Balances::total_balance(&ESCROW), 0);
+		assert!(Balances::totem_locks(&1).is_empty());
+		assert!(!pallet_balances::TotemLocks::<Test>::contains_key(&1));
+	});
+}
@@ -43,6 +43,7 @@ frame_support::construct_runtime!(
 	{
 		System: frame_system::{Module, Call, Config, Storage, Event<T>},
 		Balances: pallet_balances::{Module, Call, Storage, Config<T>, Event<T>},
+		TransactionPayment: pallet_transaction_payment::{Module, Storage, Event<T>},
 	}
 );
 
@@ -84,6 +85,8 @@ impl pallet_transaction_payment::Config for Test {
 	type TransactionByteFee = TransactionByteFee;
 	type WeightToFee = IdentityFee<u64>;
 	type FeeMultiplierUpdate = ();
+	type Accounting = ();
+	type Event = Event;
 }
 
 impl Config for Test {
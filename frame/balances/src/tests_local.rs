@@ -45,6 +45,7 @@ frame_support::construct_runtime!(
 	{
 		System: frame_system::{Module, Call, Config, Storage, Event<T>},
 		Balances: pallet_balances::{Module, Call, Storage, Config<T>, Event<T>},
+		TransactionPayment: pallet_transaction_payment::{Module, Storage, Event<T>},
 	}
 );
 
@@ -86,6 +87,8 @@ impl pallet_transaction_payment::Config for Test {
 	type TransactionByteFee = TransactionByteFee;
 	type WeightToFee = IdentityFee<u64>;
 	type FeeMultiplierUpdate = ();
+	type Accounting = ();
+	type Event = Event;
 }
 parameter_types! {
 	pub const MaxLocks: u32 = 50;
@@ -1,7 +1,7 @@
 // Custom totem stuff.
 
 use super::*;
-use frame_support::traits::WithdrawReasons;
+use frame_support::{dispatch::DispatchResultWithPostInfo, traits::WithdrawReasons};
 use totem_utils::traits::accounting::Posting;
 
 /// A currency whose accounts can have liquidity restrictions.
@@ -14,10 +14,11 @@ pub trait TotemLockableCurrency<AccountId>: Currency<AccountId> {
 
     /// Create a new balance lock on account `who`.
     ///
-    /// If the new lock is valid (i.e. not already expired), it will push the struct to
-    /// the `Locks` vec in storage. Note that you can lock more funds than a user has.
+    /// The locked funds are moved from the free balance of `who` to the escrow account. If
+    /// `who` cannot pay them, the lock is not set.
     ///
-    /// If the lock `id` already exists, this will update it.
+    /// If the lock `id` already exists, this will update it and only the difference in the
+    /// amounts is moved to or from the escrow.
     fn totem_set_lock(
         id: LockIdentifier,
         who: &AccountId,
@@ -42,7 +43,7 @@ pub trait TotemLockableCurrency<AccountId>: Currency<AccountId> {
         reasons: WithdrawReasons,
     );
 
-    /// Remove an existing lock.
+    /// Remove an existing lock, returning its funds from the escrow account to `who`.
     fn totem_remove_lock(id: LockIdentifier, who: &AccountId);
 }
 
@@ -56,6 +57,7 @@ where
 
     // Set a lock on the balance of `who`.
     // Is a no-op if lock amount is zero or `reasons` `is_none()`.
+    // Only the difference with the amount already locked under `id` is moved to or from the escrow.
     fn totem_set_lock(
         id: LockIdentifier,
         who: &T::AccountId,
@@ -67,9 +69,8 @@ where
             return;
         }
 
-        let now = frame_system::Pallet::<T>::block_number();
-
         // Add or update the new lock
+        let mut locked = Zero::zero();
         let mut new_lock = Some(TotemBalanceLock {
             id,
             amount,
@@ -81,11 +82,10 @@ where
             .filter_map(|l| {
                 if l.id == id {
                     // Update lock
+                    locked = l.amount;
                     new_lock.take()
-                } else if l.until < now {
-                    // Deadline has passed
-                    None
                 } else {
+                    // Locks past their deadline keep their funds in the escrow until they are removed
                     Some(l)
                 }
             })
@@ -94,8 +94,13 @@ where
             locks.push(lock)
         }
 
-        // Now apply the lock by transfering to the escrow
-        if let Ok(_) = Self::transfer_to_the_escrow(who, amount) {
+        // Now apply the lock by transfering the difference to or from the escrow
+        let escrowed = if amount > locked {
+            Self::transfer_to_the_escrow(who, amount - locked)
+        } else {
+            Self::transfer_from_the_escrow(who, locked - amount)
+        };
+        if let Ok(_) = escrowed {
             Self::totem_update_locks(who, &locks[..]);
         }
     }
@@ -113,8 +118,7 @@ where
             return;
         }
 
-        let now = frame_system::Pallet::<T>::block_number();
-
+        let mut locked = Zero::zero();
         let mut new_lock = Some(TotemBalanceLock {
             id,
             amount,
@@ -123,19 +127,17 @@ where
         });
         let mut locks = Self::totem_locks(who)
             .into_iter()
-            .filter_map(|l| {
+            .map(|l| {
                 if l.id == id {
-                    new_lock.take().map(|nl| TotemBalanceLock {
+                    locked = l.amount;
+                    new_lock.take().map_or(l.clone(), |nl| TotemBalanceLock {
                         id: l.id,
                         amount: l.amount.max(nl.amount),
                         reasons: l.reasons | nl.reasons,
                         until: nl.until,
                     })
-                } else if l.until < now {
-                    // Deadline has passed
-                    None
                 } else {
-                    Some(l)
+                    l
                 }
             })
             .collect::<Vec<_>>();
@@ -143,26 +145,28 @@ where
             locks.push(lock)
         }
 
-        // Now apply the lock by transfering to the escrow
-        if let Ok(_) = Self::transfer_to_the_escrow(who, amount) {
+        // Now apply the lock by transfering the increase to the escrow
+        if let Ok(_) = Self::transfer_to_the_escrow(who, amount.max(locked) - locked) {
             Self::totem_update_locks(who, &locks[..]);
         }
     }
 
+    // Remove the lock and return its funds from the escrow.
     fn totem_remove_lock(id: LockIdentifier, who: &T::AccountId) {
         let mut locks = Self::totem_locks(who);
-
-        let mut i = 0;
-        while i != locks.len() {
-            if locks[i].id == id {
-                let l = locks.remove(i);
-                Self::make_free_balance_be(who, l.amount);
+        let mut released: T::Balance = Zero::zero();
+        locks.retain(|l| {
+            if l.id == id {
+                released = released.saturating_add(l.amount);
+                false
             } else {
-                i += 1;
+                true
             }
-        }
+        });
 
-        Self::totem_update_locks(who, &locks[..]);
+        if let Ok(_) = Self::transfer_from_the_escrow(who, released) {
+            Self::totem_update_locks(who, &locks[..]);
+        }
     }
 }
 
@@ -179,7 +183,7 @@ impl<T: Config<I>, I: 'static> Pallet<T, I> {
 
         let existed = TotemLocks::<T, I>::contains_key(who);
         if locks.is_empty() {
-            Locks::<T, I>::remove(who);
+            TotemLocks::<T, I>::remove(who);
             if existed {
                 // TODO: use Locks::<T, I>::hashed_key
                 // https://github.com/paritytech/substrate/issues/4969
@@ -202,13 +206,45 @@ impl<T: Config<I>, I: 'static> Pallet<T, I> {
         }
     }
 
+    /// Reports a failed posting of a change to the balances of `who`.
+    /// The balances have already changed and an accounting error must not undo them, so the failure is logged and
+    /// signalled with an event. The accounting reconciliation shows the difference in the ledgers.
+    pub(crate) fn post_to_ledgers(who: &T::AccountId, posting: DispatchResultWithPostInfo) {
+        if let Err(e) = posting {
+            log::error!(
+                target: "runtime::balances",
+                "Failed to post a balance change of {:?} to the ledgers: {:?}",
+                who,
+                e.error,
+            );
+            Self::deposit_event(Event::LedgerPostingFailed(who.clone()));
+        }
+    }
+
+    /// Posts the change of the `status` balance of `who` from `old` to `new`, that was made outside of the ledgers.
+    pub(crate) fn post_balance_change(who: &T::AccountId, status: Status, old: T::Balance, new: T::Balance) {
+        if new > old {
+            Self::post_to_ledgers(who, T::Accounting::account_for_deposit(who.clone(), new - old, status));
+        } else if new < old {
+            Self::post_to_ledgers(who, T::Accounting::account_for_withdrawal(who.clone(), old - new, status));
+        }
+    }
+
     fn transfer_to_the_escrow(who: &T::AccountId, amount: T::Balance) -> result::Result<(), DispatchError> {
         let imba = Self::withdraw(who, amount, WithdrawReasons::ESCROW, ExistenceRequirement::KeepAlive)?;
 
         let escrow_account: T::AccountId = T::Accounting::get_escrow_account();
 
-        Self::make_free_balance_be(&escrow_account, amount);
-        let _imba_resolved = imba;
+        <Self as Currency<_>>::resolve_creating(&escrow_account, imba);
+
+        Ok(())
+    }
+
+    fn transfer_from_the_escrow(who: &T::AccountId, amount: T::Balance) -> result::Result<(), DispatchError> {
+        let escrow_account: T::AccountId = T::Accounting::get_escrow_account();
+
+        let imba = Self::withdraw(&escrow_account, amount, WithdrawReasons::ESCROW, ExistenceRequirement::AllowDeath)?;
+        <Self as Currency<_>>::resolve_creating(who, imba);
 
         Ok(())
     }
@@ -0,0 +1,25 @@
+//! Runtime API definition for the accounting reports.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::Codec;
+use sp_std::prelude::*;
+
+pub use pallet_accounting::{Mismatch, ReportLine, ReportPeriod, TaxLiability, TrialBalanceLine};
+
+sp_api::decl_runtime_apis! {
+    /// The period is either a fiscal period of the identity, by its index numbered from 0, or a range of blocks.
+    /// Without a period the reports show the current balances.
+    pub trait AccountingApi<AccountId, BlockNumber> where
+        AccountId: Codec,
+        BlockNumber: Codec,
+    {
+        fn trial_balance(who: AccountId, period: Option<ReportPeriod<BlockNumber>>) -> Vec<TrialBalanceLine>;
+        fn balance_sheet(who: AccountId, period: Option<ReportPeriod<BlockNumber>>) -> Vec<ReportLine>;
+        fn profit_and_loss(who: AccountId, period: Option<ReportPeriod<BlockNumber>>) -> Vec<ReportLine>;
+        /// Every difference between the ledgers and the balances they account for.
+        fn reconcile() -> Vec<Mismatch<AccountId>>;
+        /// The sales tax owed to the tax jurisdiction, by vendor.
+        fn sales_tax(jurisdiction: AccountId) -> Vec<TaxLiability<AccountId>>;
+    }
+}
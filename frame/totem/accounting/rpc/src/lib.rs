@@ -0,0 +1,158 @@
+//! RPC interface for the accounting reports.
+
+use codec::Codec;
+use jsonrpc_core::{Error as RpcError, ErrorCode, Result};
+use jsonrpc_derive::rpc;
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_runtime::{generic::BlockId, traits::Block as BlockT};
+use std::sync::Arc;
+
+pub use self::gen_client::Client as AccountingClient;
+pub use pallet_accounting_rpc_runtime_api::AccountingApi as AccountingRuntimeApi;
+use pallet_accounting_rpc_runtime_api::{Mismatch, ReportLine, ReportPeriod, TaxLiability, TrialBalanceLine};
+
+#[rpc]
+pub trait AccountingApi<BlockHash, AccountId, BlockNumber> {
+    #[rpc(name = "accounting_trialBalance")]
+    fn trial_balance(
+        &self,
+        who: AccountId,
+        period: Option<ReportPeriod<BlockNumber>>,
+        at: Option<BlockHash>,
+    ) -> Result<Vec<TrialBalanceLine>>;
+
+    #[rpc(name = "accounting_balanceSheet")]
+    fn balance_sheet(
+        &self,
+        who: AccountId,
+        period: Option<ReportPeriod<BlockNumber>>,
+        at: Option<BlockHash>,
+    ) -> Result<Vec<ReportLine>>;
+
+    #[rpc(name = "accounting_profitAndLoss")]
+    fn profit_and_loss(
+        &self,
+        who: AccountId,
+        period: Option<ReportPeriod<BlockNumber>>,
+        at: Option<BlockHash>,
+    ) -> Result<Vec<ReportLine>>;
+
+    #[rpc(name = "accounting_reconcile")]
+    fn reconcile(&self, at: Option<BlockHash>) -> Result<Vec<Mismatch<AccountId>>>;
+
+    #[rpc(name = "accounting_salesTax")]
+    fn sales_tax(&self, jurisdiction: AccountId, at: Option<BlockHash>) -> Result<Vec<TaxLiability<AccountId>>>;
+}
+
+/// A struct that implements the [`AccountingApi`].
+pub struct Accounting<C, B> {
+    client: Arc<C>,
+    _marker: std::marker::PhantomData<B>,
+}
+
+impl<C, B> Accounting<C, B> {
+    /// Create new `Accounting` with the given reference to the client.
+    pub fn new(client: Arc<C>) -> Self {
+        Self {
+            client,
+            _marker: Default::default(),
+        }
+    }
+}
+
+/// Error type of this RPC api.
+pub enum Error {
+    /// The call to runtime failed.
+    RuntimeError,
+}
+
+impl From<Error> for i64 {
+    fn from(e: Error) -> i64 {
+        match e {
+            Error::RuntimeError => 1,
+        }
+    }
+}
+
+fn runtime_error(message: &str, e: impl std::fmt::Debug) -> RpcError {
+    RpcError {
+        code: ErrorCode::ServerError(Error::RuntimeError.into()),
+        message: message.into(),
+        data: Some(format!("{:?}", e).into()),
+    }
+}
+
+impl<C, Block, AccountId, BlockNumber> AccountingApi<<Block as BlockT>::Hash, AccountId, BlockNumber>
+    for Accounting<C, Block>
+where
+    Block: BlockT,
+    C: 'static + ProvideRuntimeApi<Block> + HeaderBackend<Block>,
+    C::Api: AccountingRuntimeApi<Block, AccountId, BlockNumber>,
+    AccountId: Codec,
+    BlockNumber: Codec,
+{
+    fn trial_balance(
+        &self,
+        who: AccountId,
+        period: Option<ReportPeriod<BlockNumber>>,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> Result<Vec<TrialBalanceLine>> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(||
+            // If the block hash is not supplied assume the best block.
+            self.client.info().best_hash));
+
+        api.trial_balance(&at, who, period).map_err(|e| runtime_error("Unable to query the trial balance.", e))
+    }
+
+    fn balance_sheet(
+        &self,
+        who: AccountId,
+        period: Option<ReportPeriod<BlockNumber>>,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> Result<Vec<ReportLine>> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(||
+            // If the block hash is not supplied assume the best block.
+            self.client.info().best_hash));
+
+        api.balance_sheet(&at, who, period).map_err(|e| runtime_error("Unable to query the balance sheet.", e))
+    }
+
+    fn profit_and_loss(
+        &self,
+        who: AccountId,
+        period: Option<ReportPeriod<BlockNumber>>,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> Result<Vec<ReportLine>> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(||
+            // If the block hash is not supplied assume the best block.
+            self.client.info().best_hash));
+
+        api.profit_and_loss(&at, who, period).map_err(|e| runtime_error("Unable to query the profit and loss.", e))
+    }
+
+    fn reconcile(&self, at: Option<<Block as BlockT>::Hash>) -> Result<Vec<Mismatch<AccountId>>> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(||
+            // If the block hash is not supplied assume the best block.
+            self.client.info().best_hash));
+
+        api.reconcile(&at).map_err(|e| runtime_error("Unable to reconcile the ledgers.", e))
+    }
+
+    fn sales_tax(
+        &self,
+        jurisdiction: AccountId,
+        at: Option<<Block as BlockT>::Hash>,
+    ) -> Result<Vec<TaxLiability<AccountId>>> {
+        let api = self.client.runtime_api();
+        let at = BlockId::hash(at.unwrap_or_else(||
+            // If the block hash is not supplied assume the best block.
+            self.client.info().best_hash));
+
+        api.sales_tax(&at, jurisdiction).map_err(|e| runtime_error("Unable to query the sales tax.", e))
+    }
+}
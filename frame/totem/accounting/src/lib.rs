@@ -86,26 +86,215 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
 pub mod mock;
+mod tests;
 
-use frame_support::{codec::Codec, dispatch::EncodeLike, fail, pallet_prelude::*};
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+
+use frame_support::{codec::Codec, fail, pallet_prelude::*, traits::BalanceStatus, transactional};
 use frame_system::pallet_prelude::*;
 
 use sp_arithmetic::traits::BaseArithmetic;
 use sp_runtime::traits::{Convert, Hash, Member};
-use sp_std::{prelude::*, vec};
+use sp_std::{convert::TryFrom, prelude::*, vec};
 
-use totem_utils::traits::accounting::Posting;
-use totem_utils::types::{Account, LedgerBalance, PostingIndex};
-use totem_utils::{ok, StorageMapExt};
+use totem_utils::chart_of_accounts::{LedgerAccount, StatementType};
+use totem_utils::traits::accounting::{FiscalCalendar, Posting};
+use totem_utils::types::{Account, FiscalPeriod, LedgerBalance, PostingIndex};
+use totem_utils::{ok, StorageMapExt, Update};
 
 /// Note: Debit and Credit balances are account specific - see chart of accounts.
 #[repr(u8)]
-#[derive(Decode, Encode)]
+#[derive(Decode, Encode, Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
 pub enum Indicator {
     Debit = 0,
     Credit = 1,
 }
-impl EncodeLike<Indicator> for bool {}
+
+impl Indicator {
+    /// The recipes flag debit postings with `true` and credit postings with `false`.
+    pub fn is_debit(&self) -> bool {
+        *self == Indicator::Debit
+    }
+
+    /// Inverse of `is_debit`.
+    pub fn from_debit(debit: bool) -> Self {
+        if debit {
+            Indicator::Debit
+        } else {
+            Indicator::Credit
+        }
+    }
+}
+
+/// A single line of a manual journal entry (opening balances and adjustments).
+///
+/// The amount is signed in the same way as the recipes: positive increases the ledger balance,
+/// negative decreases it. The indicator says on which side of the entry the line is booked.
+#[derive(PartialEq, Eq, Clone, Debug, Encode, Decode)]
+pub struct JournalLine<BlockNumber> {
+    pub account: Account,
+    pub amount: LedgerBalance,
+    pub indicator: Indicator,
+    /// The block number of the period to which the line applies.
+    pub applicable_period: BlockNumber,
+}
+
+/// Definition of a ledger account in the on-chain chart of accounts.
+#[derive(PartialEq, Eq, Clone, Debug, Encode, Decode)]
+pub struct AccountDefinition<Hash> {
+    /// Hash of the account name. The name itself is not stored on chain.
+    pub name_hash: Hash,
+    /// The side on which the balance of the account normally increases.
+    pub normal_balance: Indicator,
+    /// The account above this one in the reporting hierarchy.
+    pub parent: Option<Account>,
+    /// Only active accounts can be posted to.
+    pub active: bool,
+    /// Whether manual journal entries (opening balances and adjustments) can post to the account.
+    pub manual_posting_allowed: bool,
+}
+
+/// The version of the accounting storage, used by `on_runtime_upgrade` to migrate it once.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Releases {
+    /// Neither the chart of accounts nor the ledgers of the XTX balances.
+    V1_0_0,
+    /// The default chart of accounts, with the XTX balances posted to the ledgers.
+    V2_0_0,
+}
+
+impl Default for Releases {
+    fn default() -> Self {
+        Releases::V1_0_0
+    }
+}
+
+/// Balance of a ledger account of an identity, as reported in the trial balance.
+#[derive(PartialEq, Eq, Clone, Debug, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct TrialBalanceLine {
+    pub account: Account,
+    #[cfg_attr(feature = "std", serde(with = "serde_balance"))]
+    pub balance: LedgerBalance,
+}
+
+/// Balances rolled up by the statement type, category and category group digits of the account number.
+/// This is the level at which the balance sheet and the profit and loss are presented.
+#[derive(PartialEq, Eq, Clone, Debug, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct ReportLine {
+    pub statement_type: u8,
+    pub category: u8,
+    pub category_group: u8,
+    #[cfg_attr(feature = "std", serde(with = "serde_balance"))]
+    pub balance: LedgerBalance,
+}
+
+/// The part of the books a report covers.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub enum ReportPeriod<BlockNumber> {
+    /// A fiscal period of the identity, by its index numbered from 0.
+    FiscalPeriod(u16),
+    /// The postings applicable to the blocks from the first to the last, both included.
+    Blocks(BlockNumber, BlockNumber),
+}
+
+/// A difference between a ledger account and the balance it accounts for, found by the reconciliation check.
+#[derive(PartialEq, Eq, Clone, Debug, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct Mismatch<AccountId> {
+    /// The identity, or `None` for the global ledger.
+    pub identity: Option<AccountId>,
+    pub account: Account,
+    /// The balance of the ledger account.
+    #[cfg_attr(feature = "std", serde(with = "serde_balance"))]
+    pub ledger: LedgerBalance,
+    /// The balance the ledger account should have.
+    #[cfg_attr(feature = "std", serde(with = "serde_balance"))]
+    pub expected: LedgerBalance,
+}
+
+/// The sales tax a vendor owes to a tax jurisdiction.
+#[derive(PartialEq, Eq, Clone, Debug, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct TaxLiability<AccountId> {
+    pub vendor: AccountId,
+    #[cfg_attr(feature = "std", serde(with = "serde_balance"))]
+    pub balance: LedgerBalance,
+}
+
+/// Ledger balances are `i128` and do not fit in a JSON number, they are passed as strings.
+#[cfg(feature = "std")]
+mod serde_balance {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer, T: std::fmt::Display>(t: &T, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&t.to_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>, T: std::str::FromStr>(deserializer: D) -> Result<T, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse::<T>().map_err(|_| serde::de::Error::custom("Parse from string failed"))
+    }
+}
+
+/// The accounts used by the Totem Accounting Recipes, with their name and normal balance.
+/// They are added to the chart of accounts at genesis, and by the runtime upgrade on chains started without them.
+pub fn default_accounts() -> Vec<(LedgerAccount, &'static str, Indicator)> {
+    use Indicator::*;
+
+    vec![
+        (LedgerAccount::XTX_BALANCE, "XTX Balance", Debit),
+        (LedgerAccount::XTX_RESERVED, "XTX Reserved Balance", Debit),
+        (LedgerAccount::PREFUNDING, "Totem Runtime Deposit (Escrow)", Debit),
+        (LedgerAccount::INVENTORY, "Inventory", Debit),
+        (LedgerAccount::ACCOUNTS_RECEIVABLE, "Accounts receivable", Debit),
+        (LedgerAccount::TRANSFERS_SUSPENSE, "XTX Transfers Suspense", Debit),
+        (LedgerAccount::INPUT_TAX_RECEIVABLE, "Sales Tax Receivable (Input Tax)", Debit),
+        (LedgerAccount::FIXED_ASSETS, "Property, Plant and Equipment", Debit),
+        (LedgerAccount::ACCOUNTS_PAYABLE, "Accounts payable", Credit),
+        (LedgerAccount::SALES_TAX_PAYABLE, "Sales Tax Payable (Output Tax)", Credit),
+        (LedgerAccount::RETAINED_EARNINGS, "Retained earnings", Credit),
+        (LedgerAccount::SALES, "Product or Service Sales", Credit),
+        (LedgerAccount::GOODS_SALES, "Sales of Goods", Credit),
+        (LedgerAccount::TRANSACTION_FEE_REVENUE, "Totem Transaction Fee Revenue", Credit),
+        (LedgerAccount::PURCHASES, "Purchases of Goods", Debit),
+        (LedgerAccount::LABOUR, "Labour", Debit),
+        (LedgerAccount::TRANSACTION_FEES, "Totem Transaction Fees", Debit),
+        (LedgerAccount::TRANSACTION_TIPS, "Totem Transaction Tips", Debit),
+        (LedgerAccount::SLASHING_LOSSES, "XTX Slashing Losses", Debit),
+        (LedgerAccount::SALES_LEDGER_BY_PAYER, "Sales Ledger by Payer", Debit),
+        (LedgerAccount::RUNTIME_LEDGER_BY_MODULE, "Runtime Ledger by Module", Debit),
+        (LedgerAccount::PURCHASE_LEDGER_BY_VENDOR, "Purchase Ledger by Vendor", Debit),
+        (LedgerAccount::SALES_LEDGER_CONTROL, "Sales Ledger Control", Debit),
+        (LedgerAccount::RUNTIME_LEDGER_CONTROL, "Runtime Ledger Control", Debit),
+        (LedgerAccount::PURCHASE_LEDGER_CONTROL, "Purchase Ledger Control", Debit),
+    ]
+}
+
+/// The accounts that mirror the XTX balances, only the runtime posts to them.
+/// A manual journal entry on any of them would stop the ledgers reconciling with the balances.
+pub fn runtime_accounts() -> Vec<LedgerAccount> {
+    vec![
+        LedgerAccount::XTX_BALANCE,
+        LedgerAccount::XTX_RESERVED,
+        LedgerAccount::PREFUNDING,
+        LedgerAccount::TRANSFERS_SUSPENSE,
+    ]
+}
+
+/// Manual journal entries can post to any account except the runtime accounts.
+fn manual_posting_allowed(account: Account) -> bool {
+    !runtime_accounts().iter().any(|a| a.number() == account)
+}
 
 #[frame_support::pallet]
 pub mod pallet {
@@ -146,6 +335,19 @@ pub mod pallet {
         (T::BlockNumber, LedgerBalance, Indicator, T::Hash, T::BlockNumber),
     >;
 
+    #[pallet::storage]
+    #[pallet::getter(fn balance_by_period)]
+    /// Accounting Balances by the fiscal period (index) of the applicable block of the postings.
+    pub type BalanceByPeriod<T: Config> =
+        StorageDoubleMap<_, Blake2_128Concat, (T::AccountId, Account), Twox64Concat, u16, LedgerBalance>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn closing_entry_by_period)]
+    /// The amount posted to each account of an identity by the entries closing a fiscal period (index).
+    /// The balances of a closed period before it was rolled into retained earnings are recovered from it.
+    pub type ClosingEntryByPeriod<T: Config> =
+        StorageDoubleMap<_, Blake2_128Concat, (T::AccountId, Account), Twox64Concat, u16, LedgerBalance>;
+
     #[pallet::storage]
     #[pallet::getter(fn global_ledger)]
     /// Yay! Totem!
@@ -153,10 +355,38 @@ pub mod pallet {
 
     #[pallet::storage]
     #[pallet::getter(fn taxes_by_jurisdiction)]
-    /// Address to book the sales tax to and the tax jurisdiction (Experimental, may be deprecated in future).
+    /// Sales tax owed by the vendor (first) to the tax jurisdiction (second), accumulated from invoices.
     pub type TaxesByJurisdiction<T: Config> =
         StorageMap<_, Blake2_128Concat, (T::AccountId, T::AccountId), LedgerBalance>;
 
+    #[pallet::storage]
+    #[pallet::getter(fn chart_of_accounts)]
+    /// The ledger accounts that can be posted to.
+    pub type ChartOfAccounts<T: Config> = StorageMap<_, Blake2_128Concat, Account, AccountDefinition<T::Hash>>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn fiscal_periods)]
+    /// The last block of each fiscal period of an identity, in ascending order.
+    /// The first period starts at genesis, each following period starts the block after the previous one ends.
+    pub type FiscalPeriods<T: Config> = StorageMap<_, Blake2_128Concat, T::AccountId, Vec<T::BlockNumber>>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn closed_until)]
+    /// The last block of the latest closed fiscal period of an identity.
+    /// Nothing can be posted to a block up to and including this one.
+    pub type ClosedUntil<T: Config> = StorageMap<_, Blake2_128Concat, T::AccountId, T::BlockNumber>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn last_applicable_block)]
+    /// The latest applicable block posted to by an identity after the last of its fiscal periods.
+    /// A new fiscal period cannot end before it, otherwise the balances of the open-ended period would span two periods.
+    pub type LastApplicableBlock<T: Config> = StorageMap<_, Blake2_128Concat, T::AccountId, T::BlockNumber>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn storage_version)]
+    /// The version of the storage, chains started before the chart of accounts are at `V1_0_0`.
+    pub type StorageVersion<T: Config> = StorageValue<_, Releases, ValueQuery>;
+
     // TODO
     // Quantities Accounting
     // Depreciation (calculated everytime there is a transaction so as not to overwork the runtime) - sets "last seen block" to calculate the delta for depreciation
@@ -166,6 +396,52 @@ pub mod pallet {
         type Event: From<Event<Self>> + IsType<<Self as frame_system::Config>::Event>;
 
         type AccountingConversions: Convert<Self::Balance, LedgerBalance> + Convert<LedgerBalance, i128>;
+
+        /// Origin allowed to change the chart of accounts (root or council).
+        type ChartOfAccountsOrigin: EnsureOrigin<Self::Origin>;
+    }
+
+    #[pallet::genesis_config]
+    pub struct GenesisConfig {
+        /// Initial chart of accounts: account number, account name, normal balance and parent account.
+        pub accounts: Vec<(Account, Vec<u8>, Indicator, Option<Account>)>,
+    }
+
+    #[cfg(feature = "std")]
+    impl Default for GenesisConfig {
+        /// The accounts used by the Totem Accounting Recipes.
+        fn default() -> Self {
+            Self {
+                accounts: default_accounts()
+                    .into_iter()
+                    .map(|(account, name, normal_balance)| {
+                        (account.number(), name.as_bytes().to_vec(), normal_balance, None)
+                    })
+                    .collect(),
+            }
+        }
+    }
+
+    #[pallet::genesis_build]
+    impl<T: Config> GenesisBuild<T> for GenesisConfig {
+        fn build(&self) {
+            for (account, name, normal_balance, parent) in self.accounts.iter() {
+                assert!(
+                    LedgerAccount::try_from(*account).is_ok(),
+                    "the chart of accounts should only contain valid account numbers.",
+                );
+                let definition = AccountDefinition {
+                    name_hash: T::Hashing::hash(name.as_slice()),
+                    normal_balance: *normal_balance,
+                    parent: *parent,
+                    active: true,
+                    manual_posting_allowed: manual_posting_allowed(*account),
+                };
+                ChartOfAccounts::<T>::insert(account, definition);
+            }
+            // The endowments are posted by the balances pallet as it builds its own genesis.
+            StorageVersion::<T>::put(Releases::V2_0_0);
+        }
     }
 
     #[pallet::error]
@@ -180,27 +456,200 @@ pub mod pallet {
         BalanceValueOverflow,
         /// Global Balance Value overflowed.
         GlobalBalanceValueOverflow,
-        /// System failure in Account Posting.
-        SystemFailure,
         /// Overflow error, amount too big.
         AmountOverflow,
+        /// The journal entry has no lines.
+        EmptyJournalEntry,
+        /// An opening balance cannot be back-dated or post-dated.
+        OpeningBalancePeriod,
+        /// The sum of the debits does not equal the sum of the credits for at least one identity.
+        UnbalancedEntry,
+        /// The account number does not follow the chart of accounts structure.
+        InvalidAccountNumber,
+        /// The account is already in the chart of accounts.
+        AccountAlreadyExists,
+        /// The account is not in the chart of accounts.
+        UnknownAccount,
+        /// The parent account is not in the chart of accounts.
+        UnknownParentAccount,
+        /// The account has been deactivated and cannot be posted to.
+        AccountDeactivated,
+        /// A fiscal period must end after the previous one.
+        FiscalPeriodOrder,
+        /// A fiscal period cannot end before a block that has already been posted to.
+        FiscalPeriodEndsBeforePostings,
+        /// The maximum number of fiscal periods has been reached.
+        TooManyFiscalPeriods,
+        /// The fiscal period of the block cannot be numbered.
+        FiscalPeriodIndexOverflow,
+        /// All the fiscal periods have been closed.
+        NoOpenPeriod,
+        /// The fiscal period has not ended yet.
+        PeriodNotEnded,
+        /// The applicable period of the posting has been closed.
+        PeriodClosed,
+        /// The account is only posted to by the runtime, it cannot be part of a manual journal entry.
+        ManualPostingNotAllowed,
         // /// An error occured posting to accounts.
         // PostingToAccount,
     }
 
     #[pallet::hooks]
-    impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {}
+    impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+        /// The chart of accounts used to be seeded at genesis only, and chains started before then have none.
+        /// Neither were the balances posted to the ledgers, they are brought on as opening balances.
+        /// This is done once, when upgrading from `V1_0_0`.
+        fn on_runtime_upgrade() -> Weight {
+            if Self::storage_version() != Releases::V1_0_0 {
+                return T::DbWeight::get().reads(1);
+            }
+
+            let weight = Self::seed_chart_of_accounts().saturating_add(Self::post_opening_balances());
+            StorageVersion::<T>::put(Releases::V2_0_0);
+
+            weight.saturating_add(T::DbWeight::get().reads_writes(1, 1))
+        }
+
+        /// Reports the differences between the ledgers and the balances, they do not fail the upgrade.
+        #[cfg(feature = "try-runtime")]
+        fn post_upgrade() -> Result<(), &'static str> {
+            for mismatch in Self::reconcile() {
+                log::warn!(target: "runtime::accounting", "ledger does not reconcile: {:?}", mismatch);
+            }
+
+            Ok(())
+        }
+    }
 
     #[pallet::call]
     impl<T: Config> Pallet<T> {
+        /// Brings balances from a prior system onto Totem.
+        /// The entry is posted in the current block, and must balance (debits equal credits).
+        #[pallet::weight(0/*TODO*/)]
+        #[transactional]
+        fn opening_balance(
+            origin: OriginFor<T>,
+            lines: Vec<JournalLine<T::BlockNumber>>,
+            reference: T::Hash,
+        ) -> DispatchResultWithPostInfo {
+            let who = ensure_signed(origin)?;
+            let current_block = frame_system::Pallet::<T>::block_number();
+            // Opening balances are recognised when they are brought in, they cannot be re-targeted.
+            ensure!(lines.iter().all(|l| l.applicable_period == current_block), Error::<T>::OpeningBalancePeriod);
+
+            Self::post_journal_entry(who.clone(), lines, reference)?;
+            Self::deposit_event(Event::OpeningBalance(who, reference));
+
+            ok()
+        }
+
+        /// Posts a manual correction (generally accruals) to the ledgers.
+        /// Each line may be re-targeted to the period it relates to by its applicable period block number.
         #[pallet::weight(0/*TODO*/)]
-        fn opening_balance(_origin: OriginFor<T>) -> DispatchResultWithPostInfo {
-            todo!()
+        #[transactional]
+        fn adjustment(
+            origin: OriginFor<T>,
+            lines: Vec<JournalLine<T::BlockNumber>>,
+            reference: T::Hash,
+        ) -> DispatchResultWithPostInfo {
+            let who = ensure_signed(origin)?;
+
+            Self::post_journal_entry(who.clone(), lines, reference)?;
+            Self::deposit_event(Event::Adjustment(who, reference));
+
+            ok()
+        }
+
+        /// Adds a ledger account to the chart of accounts.
+        #[pallet::weight(0/*TODO*/)]
+        fn add_account(
+            origin: OriginFor<T>,
+            account: Account,
+            name_hash: T::Hash,
+            normal_balance: Indicator,
+            parent: Option<Account>,
+        ) -> DispatchResultWithPostInfo {
+            T::ChartOfAccountsOrigin::ensure_origin(origin)?;
+
+            ensure!(LedgerAccount::try_from(account).is_ok(), Error::<T>::InvalidAccountNumber);
+            ensure!(!ChartOfAccounts::<T>::contains_key(&account), Error::<T>::AccountAlreadyExists);
+            if let Some(parent) = parent {
+                ensure!(ChartOfAccounts::<T>::contains_key(&parent), Error::<T>::UnknownParentAccount);
+            }
+
+            let definition = AccountDefinition {
+                name_hash,
+                normal_balance,
+                parent,
+                active: true,
+                manual_posting_allowed: manual_posting_allowed(account),
+            };
+            ChartOfAccounts::<T>::insert(&account, definition);
+            Self::deposit_event(Event::AccountAdded(account));
+
+            ok()
+        }
+
+        /// Deactivates a ledger account. The balances remain, but no further postings are accepted.
+        #[pallet::weight(0/*TODO*/)]
+        fn deactivate_account(origin: OriginFor<T>, account: Account) -> DispatchResultWithPostInfo {
+            T::ChartOfAccountsOrigin::ensure_origin(origin)?;
+
+            match ChartOfAccounts::<T>::mutate_(&account, |definition| definition.active = false) {
+                Update::Done => (),
+                Update::KeyNotFound => fail!(Error::<T>::UnknownAccount),
+            }
+            Self::deposit_event(Event::AccountDeactivated(account));
+
+            ok()
         }
 
+        /// Adds a fiscal period ending at the given block after the fiscal periods of the caller.
+        /// The period cannot end before a block the caller has already posted to.
         #[pallet::weight(0/*TODO*/)]
-        fn adjustment(_origin: OriginFor<T>) -> DispatchResultWithPostInfo {
-            todo!()
+        fn add_fiscal_period(origin: OriginFor<T>, end: T::BlockNumber) -> DispatchResultWithPostInfo {
+            let who = ensure_signed(origin)?;
+
+            let mut periods = Self::fiscal_periods(&who).unwrap_or_default();
+            if let Some(last) = periods.last() {
+                ensure!(end > *last, Error::<T>::FiscalPeriodOrder);
+            }
+            if let Some(last_applicable) = Self::last_applicable_block(&who) {
+                ensure!(end >= last_applicable, Error::<T>::FiscalPeriodEndsBeforePostings);
+            }
+            // The open-ended period after the new one must still have an index.
+            ensure!(periods.len() < u16::MAX as usize, Error::<T>::TooManyFiscalPeriods);
+            periods.push(end);
+            FiscalPeriods::<T>::insert(&who, periods);
+            Self::deposit_event(Event::FiscalPeriodAdded(who, end));
+
+            ok()
+        }
+
+        /// Closes the earliest open fiscal period of the caller once it has ended.
+        /// The profit and loss accounts are rolled into retained earnings at the end of the period, and
+        /// no more postings can be made to the period or to the periods before it.
+        #[pallet::weight(0/*TODO*/)]
+        #[transactional]
+        fn close_period(origin: OriginFor<T>) -> DispatchResultWithPostInfo {
+            let who = ensure_signed(origin)?;
+
+            let closed_until = Self::closed_until(&who);
+            let (index, end) = Self::fiscal_periods(&who)
+                .unwrap_or_default()
+                .into_iter()
+                .enumerate()
+                .find(|(_, end)| closed_until.map_or(true, |closed| *end > closed))
+                .ok_or(Error::<T>::NoOpenPeriod)?;
+            ensure!(frame_system::Pallet::<T>::block_number() > end, Error::<T>::PeriodNotEnded);
+
+            // The number of periods is bounded by `add_fiscal_period`.
+            let index = u16::try_from(index).map_err(|_| Error::<T>::FiscalPeriodIndexOverflow)?;
+            Self::post_closing_entries(who.clone(), index, end)?;
+            ClosedUntil::<T>::insert(&who, end);
+            Self::deposit_event(Event::PeriodClosed(who, end));
+
+            ok()
         }
     }
 
@@ -208,6 +657,12 @@ pub mod pallet {
     #[pallet::generate_deposit(pub(super) fn deposit_event)]
     pub enum Event<T: Config> {
         LegderUpdate(<T as frame_system::Config>::AccountId, Account, LedgerBalance, PostingIndex),
+        OpeningBalance(<T as frame_system::Config>::AccountId, <T as frame_system::Config>::Hash),
+        Adjustment(<T as frame_system::Config>::AccountId, <T as frame_system::Config>::Hash),
+        AccountAdded(Account),
+        AccountDeactivated(Account),
+        FiscalPeriodAdded(<T as frame_system::Config>::AccountId, <T as frame_system::Config>::BlockNumber),
+        PeriodClosed(<T as frame_system::Config>::AccountId, <T as frame_system::Config>::BlockNumber),
     }
 }
 
@@ -220,8 +675,15 @@ impl<T: Config> Pallet<T> {
     /// The Totem Accounting Recipes are constructed using this simple function.
     /// The second Blocknumber is for re-targeting the entry in the accounts, i.e. for adjustments prior to or after the current period (generally accruals).
     fn post_amounts(
-        (o, a, c, d, h, b, t): (T::AccountId, Account, LedgerBalance, bool, T::Hash, T::BlockNumber, T::BlockNumber),
+        (o, a, c, _, h, b, t): (T::AccountId, Account, LedgerBalance, bool, T::Hash, T::BlockNumber, T::BlockNumber),
     ) -> DispatchResultWithPostInfo {
+        // Only accounts in the chart of accounts can be posted to.
+        let definition = Self::chart_of_accounts(&a).ok_or(Error::<T>::UnknownAccount)?;
+        ensure!(definition.active, Error::<T>::AccountDeactivated);
+        let period = FiscalPeriod::of(&Self::fiscal_periods(&o).unwrap_or_default(), Self::closed_until(&o), t)
+            .ok_or(Error::<T>::FiscalPeriodIndexOverflow)?;
+        ensure!(!period.closed, Error::<T>::PeriodClosed);
+
         let posting_index = match Self::posting_number() {
             // Get and increment the posting number
             Some(index) => index.checked_add(1).ok_or(Error::<T>::PostingIndexOverflow)?,
@@ -230,26 +692,39 @@ impl<T: Config> Pallet<T> {
         let ab: LedgerBalance = c.abs();
         let balance_key = (o.clone(), a);
         let posting_key = (o.clone(), a, posting_index);
-        let detail = (b, ab, d, h, t);
+        // The side is derived from the sign and the normal balance, as in `is_balanced`, so that the detail can be
+        // summed back into the balance.
+        let indicator = Indicator::from_debit((c >= 0) == definition.normal_balance.is_debit());
+        let detail = (b, ab, indicator, h, t);
         // !! Warning !!
         // Values could feasibly overflow, with no visibility on other accounts. In this event this function returns an error.
-        // Reversals must occur in the parent function (i.e. that calls this function).
+        // The parent function must be transactional so that the prior postings are rolled back.
         // As all values passed to this function are already signed +/- we only need to sum to the previous balance and check for overflow
         // Updates are only made to storage once tests below are passed for debits or credits.
+        // An account that has never been posted to has a zero balance.
         let new_balance = Self::balance_by_ledger(&balance_key)
-            .ok_or(Error::<T>::BalanceByLedgerFetching)?
+            .unwrap_or_default()
             .checked_add(c)
             .ok_or(Error::<T>::BalanceValueOverflow)?;
-        let new_global_balance = Self::global_ledger(&a)
-            .ok_or(Error::<T>::GlobalLedgerFetching)?
+        let new_global_balance =
+            Self::global_ledger(&a).unwrap_or_default().checked_add(c).ok_or(Error::<T>::GlobalBalanceValueOverflow)?;
+        let new_period_balance = Self::balance_by_period(&balance_key, period.index)
+            .unwrap_or_default()
             .checked_add(c)
-            .ok_or(Error::<T>::GlobalBalanceValueOverflow)?;
+            .ok_or(Error::<T>::BalanceValueOverflow)?;
 
         PostingNumber::<T>::put(posting_index);
-        IdAccountPostingIdList::<T>::mutate_(&balance_key, |list| list.push(posting_index));
-        AccountsById::<T>::mutate_(&o, |accounts_by_id| accounts_by_id.retain(|h| h != &a));
-        AccountsById::<T>::mutate_(&o, |accounts_by_id| accounts_by_id.push(a));
+        IdAccountPostingIdList::<T>::append(&balance_key, posting_index);
+        AccountsById::<T>::mutate(&o, |accounts_by_id| {
+            let accounts_by_id = accounts_by_id.get_or_insert_with(Vec::new);
+            accounts_by_id.retain(|h| h != &a);
+            accounts_by_id.push(a);
+        });
         BalanceByLedger::<T>::insert(&balance_key, new_balance);
+        BalanceByPeriod::<T>::insert(&balance_key, period.index, new_period_balance);
+        if period.end.is_none() && Self::last_applicable_block(&o).map_or(true, |last| t > last) {
+            LastApplicableBlock::<T>::insert(&o, t);
+        }
         PostingDetail::<T>::insert(&posting_key, detail);
         GlobalLedger::<T>::insert(&a, new_global_balance);
 
@@ -257,6 +732,470 @@ impl<T: Config> Pallet<T> {
 
         ok()
     }
+
+    /// Adds the default accounts missing from the chart of accounts.
+    /// Accounts already in the chart are left unchanged, including deactivated ones.
+    fn seed_chart_of_accounts() -> Weight {
+        let accounts = default_accounts();
+        let mut added: Weight = 0;
+
+        for (account, name, normal_balance) in accounts.iter() {
+            let account = account.number();
+            if ChartOfAccounts::<T>::contains_key(&account) {
+                continue;
+            }
+            let definition = AccountDefinition {
+                name_hash: T::Hashing::hash(name.as_bytes()),
+                normal_balance: *normal_balance,
+                parent: None,
+                active: true,
+                manual_posting_allowed: manual_posting_allowed(account),
+            };
+            ChartOfAccounts::<T>::insert(&account, definition);
+            added += 1;
+        }
+
+        T::DbWeight::get().reads_writes(accounts.len() as Weight, added)
+    }
+
+    /// Posts the balances of every account as opening balances.
+    /// A failed posting is logged, the identity is then reported by `reconcile` and can be corrected by
+    /// `opening_balance`.
+    fn post_opening_balances() -> Weight {
+        let mut weight: Weight = 0;
+
+        for (who, _) in frame_system::Account::<T>::iter() {
+            let actual_weight = match Self::post_opening_balance(&who) {
+                Ok(info) => info.actual_weight,
+                Err(e) => {
+                    log::error!(
+                        target: "runtime::accounting",
+                        "Failed to post the opening balances of {:?}: {:?}",
+                        who,
+                        e.error,
+                    );
+                    e.post_info.actual_weight
+                }
+            };
+            // The account itself, its locks and its three XTX ledgers are read before anything is posted.
+            weight =
+                weight.saturating_add(T::DbWeight::get().reads(5)).saturating_add(actual_weight.unwrap_or_default());
+        }
+
+        weight
+    }
+
+    /// Posts the differences between the XTX ledgers of an identity and its balances against retained earnings,
+    /// so that the ledgers reconcile with the balances. Nothing is posted when they already do.
+    /// The actual weight is that of the postings.
+    #[transactional]
+    fn post_opening_balance(who: &T::AccountId) -> DispatchResultWithPostInfo {
+        let current_block = frame_system::Module::<T>::block_number();
+        let reference = Self::get_fee_reference(who);
+        let mut postings = Vec::new();
+        let mut net: LedgerBalance = 0;
+
+        for (a, expected) in Self::expected_balances(who) {
+            let ledger = Self::balance_by_ledger((who.clone(), a)).unwrap_or_default();
+            let difference = expected.checked_sub(ledger).ok_or(Error::<T>::AmountOverflow)?;
+            if difference == 0 {
+                continue;
+            }
+            net = net.checked_add(difference).ok_or(Error::<T>::AmountOverflow)?;
+            // The XTX accounts have a debit normal balance.
+            postings.push((who.clone(), a, difference, difference > 0, reference, current_block, current_block));
+        }
+        if net != 0 {
+            // More XTX than posted increases retained earnings, less XTX decreases them.
+            let retained_earnings = LedgerAccount::RETAINED_EARNINGS.number();
+            postings.push((who.clone(), retained_earnings, net, net < 0, reference, current_block, current_block));
+        }
+        ensure!(Self::is_balanced(&postings)?, Error::<T>::UnbalancedEntry);
+
+        let weight = Self::posting_weight().saturating_mul(postings.len() as Weight);
+        for posting in postings {
+            Self::post_amounts(posting)?;
+        }
+
+        Ok(Some(weight).into())
+    }
+
+    /// Weight of `post_amounts`: the account definition, the fiscal periods and the balances are read, the
+    /// balances, the posting and its indexes are written.
+    fn posting_weight() -> Weight {
+        T::DbWeight::get().reads_writes(9, 8)
+    }
+
+    /// The balances of the XTX accounts of an identity, as they should be for its free balance, reserved balance
+    /// and Totem locks.
+    fn expected_balances(who: &T::AccountId) -> Vec<(Account, LedgerBalance)> {
+        let convert = <T::AccountingConversions as Convert<T::Balance, LedgerBalance>>::convert;
+        let locked: LedgerBalance = pallet_balances::Pallet::<T>::totem_locks(who)
+            .iter()
+            .fold(0, |total, lock| total.saturating_add(convert(lock.amount)));
+        let free: LedgerBalance = convert(pallet_balances::Pallet::<T>::free_balance(who));
+        let reserved: LedgerBalance = convert(pallet_balances::Pallet::<T>::reserved_balance(who));
+
+        vec![
+            (LedgerAccount::XTX_BALANCE.number(), free.saturating_sub(locked)),
+            (LedgerAccount::XTX_RESERVED.number(), reserved),
+            (LedgerAccount::PREFUNDING.number(), locked),
+        ]
+    }
+
+    /// Checks that a batch of postings is balanced: for every identity, and for the batch as a whole,
+    /// the debits equal the credits.
+    /// The amounts are signed by increase/decrease, the side of each posting is therefore derived from its sign and
+    /// the normal balance of the account: an increase is on the side of the normal balance, a decrease on the other.
+    /// Memorandum accounts (statement type 3) are single sided and are not part of the double entry.
+    fn is_balanced(
+        postings: &[(T::AccountId, Account, LedgerBalance, bool, T::Hash, T::BlockNumber, T::BlockNumber)],
+    ) -> Result<bool, Error<T>> {
+        // Debits less credits for each identity in the batch
+        let mut net_by_id: Vec<(T::AccountId, LedgerBalance)> = Vec::new();
+        let mut net_global: LedgerBalance = 0;
+
+        for (o, a, c, _, _, _, _) in postings.iter() {
+            if Self::is_memorandum(*a) {
+                continue;
+            }
+            let normal_balance = Self::chart_of_accounts(a).ok_or(Error::<T>::UnknownAccount)?.normal_balance;
+            let signed = match normal_balance {
+                Indicator::Debit => *c,
+                Indicator::Credit => c.checked_neg().ok_or(Error::<T>::AmountOverflow)?,
+            };
+            match net_by_id.iter_mut().find(|(id, _)| id == o) {
+                Some((_, net)) => *net = net.checked_add(signed).ok_or(Error::<T>::AmountOverflow)?,
+                None => net_by_id.push((o.clone(), signed)),
+            }
+            net_global = net_global.checked_add(signed).ok_or(Error::<T>::AmountOverflow)?;
+        }
+
+        Ok(net_global == 0 && net_by_id.iter().all(|(_, net)| *net == 0))
+    }
+
+    /// Profit and loss accounts are rolled into retained earnings when a fiscal period is closed.
+    fn is_profit_and_loss(a: Account) -> bool {
+        match LedgerAccount::try_from(a) {
+            Ok(account) => account.statement_type() == StatementType::ProfitAndLoss,
+            Err(_) => false,
+        }
+    }
+
+    /// Memorandum accounts are reported outside of the balance sheet and the profit and loss.
+    fn is_memorandum(a: Account) -> bool {
+        match LedgerAccount::try_from(a) {
+            Ok(account) => account.statement_type() == StatementType::Memorandum,
+            Err(_) => false,
+        }
+    }
+
+    /// Posts a balanced manual journal entry for a single identity.
+    /// Must be called from a transactional context so that a failing line rolls back the lines before it.
+    fn post_journal_entry(
+        who: T::AccountId,
+        lines: Vec<JournalLine<T::BlockNumber>>,
+        reference: T::Hash,
+    ) -> DispatchResultWithPostInfo {
+        ensure!(!lines.is_empty(), Error::<T>::EmptyJournalEntry);
+        // Unknown accounts are rejected when they are posted to.
+        ensure!(
+            lines.iter().all(|l| Self::chart_of_accounts(&l.account).map_or(true, |d| d.manual_posting_allowed)),
+            Error::<T>::ManualPostingNotAllowed
+        );
+
+        let current_block = frame_system::Pallet::<T>::block_number();
+        let postings: Vec<_> = lines
+            .into_iter()
+            .map(|l| {
+                (
+                    who.clone(),
+                    l.account,
+                    l.amount,
+                    l.indicator.is_debit(),
+                    reference,
+                    current_block,
+                    l.applicable_period,
+                )
+            })
+            .collect();
+        ensure!(Self::is_balanced(&postings)?, Error::<T>::UnbalancedEntry);
+
+        for posting in postings {
+            Self::post_amounts(posting)?;
+        }
+
+        ok()
+    }
+
+    /// Balances of all the ledger accounts used by an identity.
+    /// With a report period these are the balances at the end of the period, before it was closed: the balance
+    /// sheet and memorandum accounts are summed up to the end of the period, the profit and loss accounts over the
+    /// period only.
+    pub fn trial_balance(who: T::AccountId, period: Option<ReportPeriod<T::BlockNumber>>) -> Vec<TrialBalanceLine> {
+        Self::balances(who, period, true)
+    }
+
+    /// The balance sheet of an identity, by category and category group.
+    /// With a report period it is the balance sheet at the end of the period, after it was closed.
+    pub fn balance_sheet(who: T::AccountId, period: Option<ReportPeriod<T::BlockNumber>>) -> Vec<ReportLine> {
+        Self::roll_up(Self::balances(who, period, false), StatementType::BalanceSheet)
+    }
+
+    /// The profit and loss of an identity, by category and category group.
+    /// With a report period it is the profit and loss of that period only.
+    pub fn profit_and_loss(who: T::AccountId, period: Option<ReportPeriod<T::BlockNumber>>) -> Vec<ReportLine> {
+        Self::roll_up(Self::balances(who, period, true), StatementType::ProfitAndLoss)
+    }
+
+    /// Balances of the ledger accounts used by an identity, either the current ones or those at the end of a
+    /// report period. The entries closing a fiscal period are left out of the latter when `before_closing` is set.
+    fn balances(
+        who: T::AccountId,
+        period: Option<ReportPeriod<T::BlockNumber>>,
+        before_closing: bool,
+    ) -> Vec<TrialBalanceLine> {
+        let mut accounts = Self::accounts_by_id(&who).unwrap_or_default();
+        accounts.sort();
+
+        accounts
+            .into_iter()
+            .map(|account| {
+                let key = (who.clone(), account);
+                let balance = match period {
+                    None => Self::balance_by_ledger(&key).unwrap_or_default(),
+                    Some(ReportPeriod::FiscalPeriod(index)) => {
+                        Self::balance_of_fiscal_period(&key, index, before_closing)
+                    }
+                    Some(ReportPeriod::Blocks(first, last)) => {
+                        Self::balance_of_blocks(&key, first, last, before_closing)
+                    }
+                };
+                TrialBalanceLine {
+                    account,
+                    balance,
+                }
+            })
+            .collect()
+    }
+
+    /// Balance of a ledger account of an identity at the end of a fiscal period, from the period balances.
+    fn balance_of_fiscal_period(key: &(T::AccountId, Account), index: u16, before_closing: bool) -> LedgerBalance {
+        let balance = if Self::is_profit_and_loss(key.1) {
+            Self::balance_by_period(key, index).unwrap_or_default()
+        } else {
+            BalanceByPeriod::<T>::iter_prefix(key)
+                .filter(|(i, _)| *i <= index)
+                .fold(0, |total: LedgerBalance, (_, b)| total.saturating_add(b))
+        };
+        if before_closing {
+            balance.saturating_sub(Self::closing_entry_by_period(key, index).unwrap_or_default())
+        } else {
+            balance
+        }
+    }
+
+    /// Balance of a ledger account of an identity at the end of a range of blocks, from the posting details.
+    /// The entries closing a fiscal period are recognised by their reference, posted on the last block of the period.
+    fn balance_of_blocks(
+        key: &(T::AccountId, Account),
+        first: T::BlockNumber,
+        last: T::BlockNumber,
+        before_closing: bool,
+    ) -> LedgerBalance {
+        let (who, account) = key;
+        let profit_and_loss = Self::is_profit_and_loss(*account);
+        let normal_balance = Self::chart_of_accounts(account).map_or(Indicator::Debit, |d| d.normal_balance);
+
+        Self::id_account_posting_id_list(key)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|index| Self::posting_detail((who.clone(), *account, index)))
+            .filter(|(_, _, _, h, t)| {
+                let in_range = *t >= first && *t <= last;
+                *t <= last
+                    && (in_range || !profit_and_loss)
+                    && !(before_closing && in_range && *h == Self::closing_reference(who, *t))
+            })
+            .fold(0, |total: LedgerBalance, (_, amount, indicator, _, _)| {
+                if indicator == normal_balance {
+                    total.saturating_add(amount)
+                } else {
+                    total.saturating_sub(amount)
+                }
+            })
+    }
+
+    /// The sales tax owed to a tax jurisdiction, by vendor.
+    pub fn sales_tax(jurisdiction: T::AccountId) -> Vec<TaxLiability<T::AccountId>> {
+        TaxesByJurisdiction::<T>::iter()
+            .filter(|((_, j), balance)| *j == jurisdiction && *balance != 0)
+            .map(|((vendor, _), balance)| TaxLiability {
+                vendor,
+                balance,
+            })
+            .collect()
+    }
+
+    /// Adds the sales tax of an invoice, or reverses it when negative, to what the vendor owes to the tax jurisdiction.
+    fn add_sales_tax(
+        vendor: T::AccountId,
+        jurisdiction: T::AccountId,
+        amount: LedgerBalance,
+    ) -> DispatchResultWithPostInfo {
+        let key = (vendor, jurisdiction);
+        let balance = Self::taxes_by_jurisdiction(&key)
+            .unwrap_or_default()
+            .checked_add(amount)
+            .ok_or(Error::<T>::BalanceValueOverflow)?;
+        TaxesByJurisdiction::<T>::insert(&key, balance);
+
+        ok()
+    }
+
+    /// Rolls the profit and loss of a fiscal period into retained earnings.
+    /// Each profit and loss account is brought back to zero for the period and the net result is posted to
+    /// retained earnings, on the last block of the period.
+    /// Only the accounts used by the identity are read, one period balance each.
+    /// Must be called from a transactional context.
+    fn post_closing_entries(who: T::AccountId, index: u16, end: T::BlockNumber) -> DispatchResultWithPostInfo {
+        let current_block = frame_system::Pallet::<T>::block_number();
+        let reference = Self::closing_reference(&who, end);
+
+        let mut postings = Vec::new();
+        // Debits less credits of the closing lines
+        let mut net: LedgerBalance = 0;
+        for a in Self::accounts_by_id(&who).unwrap_or_default() {
+            if !Self::is_profit_and_loss(a) {
+                continue;
+            }
+            let balance = Self::balance_by_period((who.clone(), a), index).unwrap_or_default();
+            if balance == 0 {
+                continue;
+            }
+            let normal_balance = Self::chart_of_accounts(&a).ok_or(Error::<T>::UnknownAccount)?.normal_balance;
+            // Reversing the balance is booked on the side opposite to its sign.
+            let debit = (balance > 0) != normal_balance.is_debit();
+            let amount = balance.checked_abs().ok_or(Error::<T>::AmountOverflow)?;
+            net = if debit {
+                net.checked_add(amount)
+            } else {
+                net.checked_sub(amount)
+            }
+            .ok_or(Error::<T>::AmountOverflow)?;
+            let reversal = balance.checked_neg().ok_or(Error::<T>::AmountOverflow)?;
+            postings.push((who.clone(), a, reversal, debit, reference, current_block, end));
+        }
+        if net != 0 {
+            // A profit (more debits than credits to close) increases retained earnings, a loss decreases them.
+            let retained_earnings = LedgerAccount::RETAINED_EARNINGS.number();
+            postings.push((who.clone(), retained_earnings, net, net < 0, reference, current_block, end));
+        }
+        ensure!(Self::is_balanced(&postings)?, Error::<T>::UnbalancedEntry);
+
+        for posting in postings {
+            let (_, a, amount, _, _, _, _) = posting;
+            Self::post_amounts(posting)?;
+            ClosingEntryByPeriod::<T>::insert((who.clone(), a), index, amount);
+        }
+
+        ok()
+    }
+
+    /// Reference of the entries closing the fiscal period of an identity ending at the block `end`.
+    fn closing_reference(who: &T::AccountId, end: T::BlockNumber) -> T::Hash {
+        T::Hashing::hash_of(&(who, end))
+    }
+
+    /// Reference of the fee and balance postings of an identity in the current extrinsic.
+    /// These are posted during block execution, where the offchain random seed used by `get_pseudo_random_hash`
+    /// is not available.
+    fn get_fee_reference(who: &T::AccountId) -> T::Hash {
+        let input = (who, frame_system::Module::<T>::block_number(), frame_system::Module::<T>::extrinsic_index());
+
+        T::Hashing::hash_of(&input)
+    }
+
+    /// Compares the ledgers with the balances they account for, and returns every difference:
+    ///
+    /// * the XTX Balance of an identity with its free balance, less the amount locked by prefunding,
+    /// * the XTX Reserved Balance of an identity with its reserved balance,
+    /// * the Prefunding account of an identity with the amount locked by prefunding,
+    /// * the global ledger of each account with the sum of the balances of all identities.
+    ///
+    /// Only the identities that have been posted to are checked. The differences are only reported, they are
+    /// corrected by posting an `opening_balance`.
+    pub fn reconcile() -> Vec<Mismatch<T::AccountId>> {
+        let mut mismatches = Vec::new();
+
+        for (who, _) in AccountsById::<T>::iter() {
+            for (account, expected) in Self::expected_balances(&who) {
+                let ledger = Self::balance_by_ledger((who.clone(), account)).unwrap_or_default();
+                if ledger != expected {
+                    mismatches.push(Mismatch {
+                        identity: Some(who.clone()),
+                        account,
+                        ledger,
+                        expected,
+                    });
+                }
+            }
+        }
+
+        let mut totals: Vec<(Account, LedgerBalance)> = Vec::new();
+        for ((_, account), balance) in BalanceByLedger::<T>::iter() {
+            match totals.iter_mut().find(|(a, _)| *a == account) {
+                Some((_, total)) => *total = total.saturating_add(balance),
+                None => totals.push((account, balance)),
+            }
+        }
+        for (account, ledger) in GlobalLedger::<T>::iter() {
+            let expected = totals.iter().find(|(a, _)| *a == account).map_or(0, |(_, total)| *total);
+            if ledger != expected {
+                mismatches.push(Mismatch {
+                    identity: None,
+                    account,
+                    ledger,
+                    expected,
+                });
+            }
+        }
+
+        mismatches
+    }
+
+    /// The ledger account holding the free or the reserved XTX balance.
+    fn xtx_account(status: BalanceStatus) -> Account {
+        match status {
+            BalanceStatus::Free => LedgerAccount::XTX_BALANCE.number(),
+            BalanceStatus::Reserved => LedgerAccount::XTX_RESERVED.number(),
+        }
+    }
+
+    fn roll_up(lines: Vec<TrialBalanceLine>, statement_type: StatementType) -> Vec<ReportLine> {
+        let mut report: Vec<ReportLine> = Vec::new();
+
+        for line in lines {
+            let account = match LedgerAccount::try_from(line.account) {
+                Ok(account) if account.statement_type() == statement_type => account,
+                _ => continue,
+            };
+            let category = account.category() as u8;
+            let category_group = account.category_group();
+            match report.iter_mut().find(|r| r.category == category && r.category_group == category_group) {
+                Some(r) => r.balance = r.balance.saturating_add(line.balance),
+                None => report.push(ReportLine {
+                    statement_type: statement_type as u8,
+                    category,
+                    category_group,
+                    balance: line.balance,
+                }),
+            }
+        }
+        report.sort_by_key(|r| (r.category, r.category_group));
+
+        report
+    }
 }
 
 pub use pallet::*;
@@ -272,37 +1211,19 @@ where
 
     /// The Totem Accounting Recipes are constructed using this function which handles posting to multiple accounts.
     /// It is exposed to other modules as a trait
-    /// If for whatever reason an error occurs during the storage processing which is sequential
-    /// this function also handles reversing out the prior accounting entries
-    /// Therefore the recipes that are passed as arguments need to be be accompanied with a reversal
-    /// Obviously the last posting does not need a reversal for if it errors, then it was not posted in the first place.
+    /// The keys must balance per identity before anything is written to storage.
+    /// The postings are processed sequentially inside a storage transaction, so if for whatever reason an error
+    /// occurs the prior postings are rolled back and nothing is written.
+    #[transactional]
     fn handle_multiposting_amounts(
-        fwd: Vec<(T::AccountId, Account, LedgerBalance, bool, T::Hash, T::BlockNumber, T::BlockNumber)>,
-        rev: Vec<(T::AccountId, Account, LedgerBalance, bool, T::Hash, T::BlockNumber, T::BlockNumber)>,
-        mut trk: Vec<(T::AccountId, Account, LedgerBalance, bool, T::Hash, T::BlockNumber, T::BlockNumber)>,
+        keys: Vec<(T::AccountId, Account, LedgerBalance, bool, T::Hash, T::BlockNumber, T::BlockNumber)>,
     ) -> DispatchResultWithPostInfo {
-        let length_limit = rev.len();
-
-        // Iterate over forward keys. If Ok add reversal key to tracking, if error, then reverse out prior postings.
-        for (pos, a) in fwd.clone().iter().enumerate() {
-            match Self::post_amounts(a.clone()) {
-                Ok(_) => {
-                    if pos < length_limit {
-                        trk.push(rev[pos].clone())
-                    }
-                }
-                Err(_e) => {
-                    // Error before the value was updated. Need to reverse-out the earlier debit amount and account combination
-                    // as this has already changed in storage.
-                    for b in trk.iter() {
-                        if let Err(_e) = Self::post_amounts(b.clone()) {
-                            fail!(Error::<T>::SystemFailure);
-                        }
-                    }
-                    fail!(Error::<T>::AmountOverflow);
-                }
-            }
+        ensure!(Self::is_balanced(&keys)?, Error::<T>::UnbalancedEntry);
+
+        for key in keys {
+            Self::post_amounts(key)?;
         }
+
         ok()
     }
 
@@ -316,46 +1237,242 @@ where
     /// This function takes the transaction fee and prepares to account for it in accounting.
     /// This is one of the few functions that will set the ledger accounts to be updated here. Fees
     /// are native to the Substrate Framework, and there may be other use cases.
-    fn account_for_fees(fee: T::Balance, payer: T::AccountId) -> DispatchResultWithPostInfo {
-        // Take the fee amount and convert for use with accounting. Fee is of type T::Balance which is u128.
-        // As amount will always be positive, convert for use in accounting
-        let fee_converted: LedgerBalance =
-            <T::AccountingConversions as Convert<T::Balance, LedgerBalance>>::convert(fee);
-        // Convert this for the inversion
-        let mut to_invert: LedgerBalance = fee_converted.clone();
-        to_invert = to_invert * -1;
-        let increase_amount: LedgerBalance = fee_converted.into();
-        let decrease_amount: LedgerBalance = to_invert.into();
-
-        let account_1: Account = 250_50029000_0000_u64; // debit  increase 250500290000000 Totem Transaction Fees
-        let account_2: Account = 110_10004000_0000_u64; // credit decrease 110100040000000 XTX Balance
+    /// The fee includes the tip, which is accounted for separately. The refund is the part of the fee that was
+    /// overpaid and returned to the payer.
+    /// The XTX withdrawn and refunded is posted by the balances pallet against the transfers suspense account,
+    /// which these postings clear.
+    fn account_for_fees(
+        fee: T::Balance,
+        tip: T::Balance,
+        refund: T::Balance,
+        payer: T::AccountId,
+    ) -> DispatchResultWithPostInfo {
+        // Take the amounts and convert for use with accounting. As amounts will always be positive, the sign
+        // is given by the posting below.
+        let convert = <T::AccountingConversions as Convert<T::Balance, LedgerBalance>>::convert;
+        let fee: LedgerBalance = convert(fee);
+        let tip: LedgerBalance = convert(tip);
+        let refund: LedgerBalance = convert(refund);
+        let fee_excluding_tip = fee.checked_sub(tip).ok_or(Error::<T>::AmountOverflow)?;
 
         // This sets the change block and the applicable posting period. For this context they will always be
         // the same.
         let current_block = <frame_system::Module<T>>::block_number(); // For audit on change
         let current_block_dupe = current_block.clone(); // Applicable period for accounting
 
-        // Generate dummy Hash reference (it has no real bearing but allows posting to happen)
-        let fee_hash: T::Hash = Self::get_pseudo_random_hash(payer.clone(), payer.clone());
+        let fee_hash: T::Hash = Self::get_fee_reference(&payer);
 
         // Keys for posting
+        let mut forward_keys = Vec::new();
+        let expenses = vec![
+            (LedgerAccount::TRANSACTION_FEES.number(), fee_excluding_tip),
+            (LedgerAccount::TRANSACTION_TIPS.number(), tip),
+        ];
+        for (account, amount) in expenses.into_iter().filter(|(_, amount)| *amount != 0) {
+            // debit  increase
+            forward_keys.push((payer.clone(), account, amount, true, fee_hash, current_block, current_block_dupe));
+            // credit decrease
+            forward_keys.push((
+                payer.clone(),
+                LedgerAccount::TRANSFERS_SUSPENSE.number(),
+                -amount,
+                false,
+                fee_hash,
+                current_block,
+                current_block_dupe,
+            ));
+        }
+        if refund != 0 {
+            let account_1: Account = LedgerAccount::TRANSFERS_SUSPENSE.number(); // debit  increase
+            let account_2: Account = LedgerAccount::TRANSACTION_FEES.number(); // credit decrease
+            forward_keys.push((payer.clone(), account_1, refund, true, fee_hash, current_block, current_block_dupe));
+            forward_keys.push((payer.clone(), account_2, -refund, false, fee_hash, current_block, current_block_dupe));
+        }
+
+        Self::handle_multiposting_amounts(forward_keys)
+    }
+
+    /// Posts the share of the transaction fees received by the block author or the treasury.
+    /// The XTX deposited is posted by the balances pallet against the transfers suspense account, which this clears.
+    fn account_for_fee_revenue(fee: T::Balance, recipient: T::AccountId) -> DispatchResultWithPostInfo {
+        let increase_amount: LedgerBalance =
+            <T::AccountingConversions as Convert<T::Balance, LedgerBalance>>::convert(fee);
+        if increase_amount == 0 {
+            return ok();
+        }
+
+        let account_1: Account = LedgerAccount::TRANSFERS_SUSPENSE.number(); // debit  increase
+        let account_2: Account = LedgerAccount::TRANSACTION_FEE_REVENUE.number(); // credit increase
+
+        let current_block = <frame_system::Module<T>>::block_number(); // For audit on change
+        let current_block_dupe = current_block.clone(); // Applicable period for accounting
+
+        let fee_hash: T::Hash = Self::get_fee_reference(&recipient);
+
         let forward_keys = vec![
-            (payer.clone(), account_1, increase_amount, true, fee_hash, current_block, current_block_dupe),
-            (payer.clone(), account_2, decrease_amount, false, fee_hash, current_block, current_block_dupe),
+            (recipient.clone(), account_1, increase_amount, true, fee_hash, current_block, current_block_dupe),
+            (recipient.clone(), account_2, increase_amount, false, fee_hash, current_block, current_block_dupe),
         ];
 
-        // Reversal keys in case of errors
-        let reversal_keys = vec![
-            (payer.clone(), account_1, decrease_amount, false, fee_hash, current_block, current_block_dupe),
-            (payer.clone(), account_2, increase_amount, true, fee_hash, current_block, current_block_dupe),
+        Self::handle_multiposting_amounts(forward_keys)
+    }
+
+    /// Posts a movement of XTX between two identities, or between the free and reserved balances of one identity.
+    /// Each side is booked against the transfers suspense account, which the Totem recipe paying for the movement
+    /// clears. Otherwise the identity reclassifies it with an adjustment.
+    fn account_for_transfer(
+        from: T::AccountId,
+        from_status: BalanceStatus,
+        to: T::AccountId,
+        to_status: BalanceStatus,
+        amount: T::Balance,
+    ) -> DispatchResultWithPostInfo {
+        let increase_amount: LedgerBalance =
+            <T::AccountingConversions as Convert<T::Balance, LedgerBalance>>::convert(amount);
+        if increase_amount == 0 || (from == to && from_status == to_status) {
+            return ok();
+        }
+        let decrease_amount: LedgerBalance = -increase_amount;
+
+        let account_1: Account = Self::xtx_account(from_status); // credit decrease
+        let account_2: Account = LedgerAccount::TRANSFERS_SUSPENSE.number(); // debit  increase
+        let account_3: Account = Self::xtx_account(to_status); // debit  increase
+        let account_4: Account = LedgerAccount::TRANSFERS_SUSPENSE.number(); // credit decrease
+
+        let current_block = <frame_system::Module<T>>::block_number(); // For audit on change
+        let current_block_dupe = current_block.clone(); // Applicable period for accounting
+
+        let transfer_hash: T::Hash = Self::get_fee_reference(&from);
+
+        let forward_keys = vec![
+            (from.clone(), account_1, decrease_amount, false, transfer_hash, current_block, current_block_dupe),
+            (from.clone(), account_2, increase_amount, true, transfer_hash, current_block, current_block_dupe),
+            (to.clone(), account_3, increase_amount, true, transfer_hash, current_block, current_block_dupe),
+            (to.clone(), account_4, decrease_amount, false, transfer_hash, current_block, current_block_dupe),
         ];
 
-        let track_rev_keys =
-            Vec::<(T::AccountId, Account, LedgerBalance, bool, T::Hash, T::BlockNumber, T::BlockNumber)>::with_capacity(
-                2,
-            );
+        Self::handle_multiposting_amounts(forward_keys)
+    }
+
+    /// Posts a movement between the free and the reserved XTX balance of an identity.
+    fn account_for_reserve(who: T::AccountId, amount: T::Balance, status: BalanceStatus) -> DispatchResultWithPostInfo {
+        let increase_amount: LedgerBalance =
+            <T::AccountingConversions as Convert<T::Balance, LedgerBalance>>::convert(amount);
+        if increase_amount == 0 {
+            return ok();
+        }
+        let decrease_amount: LedgerBalance = -increase_amount;
+
+        let (account_1, account_2) = match status {
+            BalanceStatus::Reserved => (LedgerAccount::XTX_RESERVED.number(), LedgerAccount::XTX_BALANCE.number()),
+            BalanceStatus::Free => (LedgerAccount::XTX_BALANCE.number(), LedgerAccount::XTX_RESERVED.number()),
+        }; // debit  increase, credit decrease
 
-        Self::handle_multiposting_amounts(forward_keys, reversal_keys, track_rev_keys)
+        let current_block = <frame_system::Module<T>>::block_number(); // For audit on change
+        let current_block_dupe = current_block.clone(); // Applicable period for accounting
+
+        let reserve_hash: T::Hash = Self::get_fee_reference(&who);
+
+        let forward_keys = vec![
+            (who.clone(), account_1, increase_amount, true, reserve_hash, current_block, current_block_dupe),
+            (who.clone(), account_2, decrease_amount, false, reserve_hash, current_block, current_block_dupe),
+        ];
+
+        Self::handle_multiposting_amounts(forward_keys)
+    }
+
+    /// Posts the loss of a slash from the free or the reserved XTX balance of an identity.
+    fn account_for_slash(who: T::AccountId, amount: T::Balance, status: BalanceStatus) -> DispatchResultWithPostInfo {
+        let increase_amount: LedgerBalance =
+            <T::AccountingConversions as Convert<T::Balance, LedgerBalance>>::convert(amount);
+        if increase_amount == 0 {
+            return ok();
+        }
+        let decrease_amount: LedgerBalance = -increase_amount;
+
+        let account_1: Account = LedgerAccount::SLASHING_LOSSES.number(); // debit  increase
+        let account_2: Account = Self::xtx_account(status); // credit decrease
+
+        let current_block = <frame_system::Module<T>>::block_number(); // For audit on change
+        let current_block_dupe = current_block.clone(); // Applicable period for accounting
+
+        let slash_hash: T::Hash = Self::get_fee_reference(&who);
+
+        let forward_keys = vec![
+            (who.clone(), account_1, increase_amount, true, slash_hash, current_block, current_block_dupe),
+            (who.clone(), account_2, decrease_amount, false, slash_hash, current_block, current_block_dupe),
+        ];
+
+        Self::handle_multiposting_amounts(forward_keys)
+    }
+
+    /// Posts XTX deposited into the free or the reserved balance of an identity from outside of the ledgers.
+    /// It is booked against the transfers suspense account, like a transfer received.
+    fn account_for_deposit(who: T::AccountId, amount: T::Balance, status: BalanceStatus) -> DispatchResultWithPostInfo {
+        let increase_amount: LedgerBalance =
+            <T::AccountingConversions as Convert<T::Balance, LedgerBalance>>::convert(amount);
+        if increase_amount == 0 {
+            return ok();
+        }
+        let decrease_amount: LedgerBalance = -increase_amount;
+
+        let account_1: Account = Self::xtx_account(status); // debit  increase
+        let account_2: Account = LedgerAccount::TRANSFERS_SUSPENSE.number(); // credit decrease
+
+        let current_block = <frame_system::Module<T>>::block_number(); // For audit on change
+        let current_block_dupe = current_block.clone(); // Applicable period for accounting
+
+        let deposit_hash: T::Hash = Self::get_fee_reference(&who);
+
+        let forward_keys = vec![
+            (who.clone(), account_1, increase_amount, true, deposit_hash, current_block, current_block_dupe),
+            (who.clone(), account_2, decrease_amount, false, deposit_hash, current_block, current_block_dupe),
+        ];
+
+        Self::handle_multiposting_amounts(forward_keys)
+    }
+
+    /// Posts XTX withdrawn from the free or the reserved balance of an identity to outside of the ledgers.
+    /// It is booked against the transfers suspense account, like a transfer sent.
+    fn account_for_withdrawal(
+        who: T::AccountId,
+        amount: T::Balance,
+        status: BalanceStatus,
+    ) -> DispatchResultWithPostInfo {
+        let increase_amount: LedgerBalance =
+            <T::AccountingConversions as Convert<T::Balance, LedgerBalance>>::convert(amount);
+        if increase_amount == 0 {
+            return ok();
+        }
+        let decrease_amount: LedgerBalance = -increase_amount;
+
+        let account_1: Account = LedgerAccount::TRANSFERS_SUSPENSE.number(); // debit  increase
+        let account_2: Account = Self::xtx_account(status); // credit decrease
+
+        let current_block = <frame_system::Module<T>>::block_number(); // For audit on change
+        let current_block_dupe = current_block.clone(); // Applicable period for accounting
+
+        let withdrawal_hash: T::Hash = Self::get_fee_reference(&who);
+
+        let forward_keys = vec![
+            (who.clone(), account_1, increase_amount, true, withdrawal_hash, current_block, current_block_dupe),
+            (who.clone(), account_2, decrease_amount, false, withdrawal_hash, current_block, current_block_dupe),
+        ];
+
+        Self::handle_multiposting_amounts(forward_keys)
+    }
+
+    /// Brings the XTX an identity holds onto its ledgers against retained earnings, such as its genesis endowment.
+    fn account_for_opening_balance(who: T::AccountId) -> DispatchResultWithPostInfo {
+        Self::post_opening_balance(&who)
+    }
+
+    fn record_sales_tax(
+        vendor: T::AccountId,
+        jurisdiction: T::AccountId,
+        amount: LedgerBalance,
+    ) -> DispatchResultWithPostInfo {
+        Self::add_sales_tax(vendor, jurisdiction, amount)
     }
 
     fn get_pseudo_random_hash(sender: T::AccountId, recipient: T::AccountId) -> T::Hash {
@@ -371,3 +1488,9 @@ where
         T::Hashing::hash(input.encode().as_slice()) // default hash BlakeTwo256
     }
 }
+
+impl<T: Config> FiscalCalendar<T::AccountId, T::BlockNumber> for Pallet<T> {
+    fn fiscal_period(o: T::AccountId, b: T::BlockNumber) -> Option<FiscalPeriod<T::BlockNumber>> {
+        FiscalPeriod::of(&Self::fiscal_periods(&o).unwrap_or_default(), Self::closed_until(&o), b)
+    }
+}
@@ -2,6 +2,9 @@
 
 use sp_runtime::traits::Convert;
 
+#[cfg(test)]
+pub use self::runtime::*;
+
 pub struct Conversions;
 
 impl Convert<u128, i128> for Conversions {
@@ -21,3 +24,100 @@ impl Convert<u64, i128> for Conversions {
         u as i128
     }
 }
+
+#[cfg(test)]
+mod runtime {
+    use super::Conversions;
+    use crate as pallet_accounting;
+
+    use frame_support::{parameter_types, traits::GenesisBuild, weights::constants::RocksDbWeight};
+    use frame_system::EnsureRoot;
+    use sp_core::H256;
+    use sp_runtime::{
+        testing::Header,
+        traits::{BlakeTwo256, IdentityLookup},
+    };
+
+    type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
+    type Block = frame_system::mocking::MockBlock<Test>;
+
+    pub const ALICE: u64 = 1;
+    pub const BOB: u64 = 2;
+
+    // Configure a mock runtime to test the pallet.
+    frame_support::construct_runtime!(
+        pub enum Test where
+            Block = Block,
+            NodeBlock = Block,
+            UncheckedExtrinsic = UncheckedExtrinsic,
+        {
+            System: frame_system::{Module, Call, Config, Storage, Event<T>},
+            Balances: pallet_balances::{Module, Call, Storage, Config<T>, Event<T>},
+            Accounting: pallet_accounting::{Module, Call, Config, Storage, Event<T>},
+        }
+    );
+
+    parameter_types! {
+        pub const BlockHashCount: u64 = 250;
+        pub const SS58Prefix: u8 = 42;
+        pub const ExistentialDeposit: u64 = 1;
+    }
+
+    impl frame_system::Config for Test {
+        type BaseCallFilter = ();
+        type BlockWeights = ();
+        type BlockLength = ();
+        type DbWeight = RocksDbWeight;
+        type Origin = Origin;
+        type Call = Call;
+        type Index = u64;
+        type BlockNumber = u64;
+        type Hash = H256;
+        type Hashing = BlakeTwo256;
+        type AccountId = u64;
+        type Lookup = IdentityLookup<Self::AccountId>;
+        type Header = Header;
+        type Event = Event;
+        type BlockHashCount = BlockHashCount;
+        type Version = ();
+        type PalletInfo = PalletInfo;
+        type AccountData = pallet_balances::AccountData<u64>;
+        type OnNewAccount = ();
+        type OnKilledAccount = ();
+        type SystemWeightInfo = ();
+        type SS58Prefix = SS58Prefix;
+    }
+
+    impl pallet_balances::Config for Test {
+        type MaxLocks = ();
+        type Balance = u64;
+        type DustRemoval = ();
+        type Event = Event;
+        type ExistentialDeposit = ExistentialDeposit;
+        type AccountStore = System;
+        type WeightInfo = ();
+        type Accounting = ();
+    }
+
+    impl pallet_accounting::Config for Test {
+        type Event = Event;
+        type AccountingConversions = Conversions;
+        type ChartOfAccountsOrigin = EnsureRoot<u64>;
+    }
+
+    /// A chain started before the chart of accounts existed: nothing is set at genesis.
+    pub fn new_test_ext() -> sp_io::TestExternalities {
+        let t = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+        let mut ext: sp_io::TestExternalities = t.into();
+        ext.execute_with(|| System::set_block_number(1));
+        ext
+    }
+    /// A chain started with the default chart of accounts.
+    pub fn new_test_ext_with_accounts() -> sp_io::TestExternalities {
+        let mut t = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+        pallet_accounting::GenesisConfig::default().assimilate_storage::<Test>(&mut t).unwrap();
+        let mut ext: sp_io::TestExternalities = t.into();
+        ext.execute_with(|| System::set_block_number(1));
+        ext
+    }
+}
@@ -0,0 +1,632 @@
+#![cfg(test)]
+
+use crate::mock::*;
+use crate::{
+    default_accounts, runtime_accounts, Call as AccountingCall, Error, Event as AccountingEvent, GlobalLedger,
+    Indicator, JournalLine, Mismatch, Releases, ReportLine, ReportPeriod, TaxLiability, TrialBalanceLine,
+};
+
+use frame_support::{
+    assert_noop, assert_ok,
+    dispatch::DispatchResultWithPostInfo,
+    traits::{Currency, Get, OnRuntimeUpgrade, ReservableCurrency, UnfilteredDispatchable},
+    weights::constants::RocksDbWeight,
+};
+use pallet_balances::{Reasons, TotemBalanceLock, TotemLocks};
+use sp_core::H256;
+use sp_runtime::{traits::Hash, DispatchError};
+use std::convert::TryFrom;
+use totem_utils::chart_of_accounts::LedgerAccount;
+
+fn dispatch(origin: Origin, call: AccountingCall<Test>) -> DispatchResultWithPostInfo {
+    call.dispatch_bypass_filter(origin)
+}
+
+fn last_event() -> Event {
+    System::events().pop().expect("Event expected").event
+}
+
+fn line(account: LedgerAccount, amount: i128, indicator: Indicator, applicable_period: u64) -> JournalLine<u64> {
+    JournalLine {
+        account: account.number(),
+        amount,
+        indicator,
+        applicable_period,
+    }
+}
+
+// Brings `amount` onto the inventory of `ALICE` against retained earnings.
+fn opening_balance(amount: i128) -> DispatchResultWithPostInfo {
+    let lines = vec![
+        line(LedgerAccount::INVENTORY, amount, Indicator::Debit, System::block_number()),
+        line(LedgerAccount::RETAINED_EARNINGS, amount, Indicator::Credit, System::block_number()),
+    ];
+    dispatch(Origin::signed(ALICE), AccountingCall::opening_balance(lines, H256::repeat_byte(1)))
+}
+
+// Adds `amount` to the inventory and the retained earnings of `ALICE`, applicable to the block `applicable_period`.
+fn adjustment(amount: i128, applicable_period: u64) -> DispatchResultWithPostInfo {
+    let lines = vec![
+        line(LedgerAccount::INVENTORY, amount, Indicator::Debit, applicable_period),
+        line(LedgerAccount::RETAINED_EARNINGS, amount, Indicator::Credit, applicable_period),
+    ];
+    dispatch(Origin::signed(ALICE), AccountingCall::adjustment(lines, H256::repeat_byte(2)))
+}
+
+#[test]
+fn upgraded_chain_without_a_chart_of_accounts_can_post() {
+    new_test_ext().execute_with(|| {
+        assert_noop!(opening_balance(100), Error::<Test>::UnknownAccount);
+
+        Accounting::on_runtime_upgrade();
+
+        assert_ok!(opening_balance(100));
+        assert_eq!(Accounting::balance_by_ledger((ALICE, LedgerAccount::INVENTORY.number())), Some(100));
+        assert_eq!(Accounting::balance_by_ledger((ALICE, LedgerAccount::RETAINED_EARNINGS.number())), Some(100));
+    });
+}
+
+#[test]
+fn runtime_upgrade_keeps_the_existing_chart_of_accounts() {
+    new_test_ext().execute_with(|| {
+        Accounting::on_runtime_upgrade();
+        for (account, _, normal_balance) in default_accounts() {
+            let definition = Accounting::chart_of_accounts(account.number()).unwrap();
+            assert_eq!(definition.normal_balance, normal_balance);
+            assert!(definition.active);
+        }
+
+        let inventory = LedgerAccount::INVENTORY.number();
+        assert_ok!(dispatch(Origin::root(), AccountingCall::deactivate_account(inventory)));
+        Accounting::on_runtime_upgrade();
+
+        assert!(!Accounting::chart_of_accounts(inventory).unwrap().active);
+        assert_noop!(opening_balance(100), Error::<Test>::AccountDeactivated);
+    });
+}
+
+#[test]
+fn runtime_upgrade_posts_the_balances_once() {
+    new_test_ext().execute_with(|| {
+        let xtx_balance = LedgerAccount::XTX_BALANCE.number();
+        let _ = Balances::deposit_creating(&ALICE, 100);
+        assert_ok!(Balances::reserve(&ALICE, 30));
+
+        let weight = Accounting::on_runtime_upgrade();
+
+        assert_eq!(Accounting::storage_version(), Releases::V2_0_0);
+        assert_eq!(Accounting::balance_by_ledger((ALICE, xtx_balance)), Some(70));
+        assert_eq!(Accounting::balance_by_ledger((ALICE, LedgerAccount::XTX_RESERVED.number())), Some(30));
+        assert_eq!(Accounting::balance_by_ledger((ALICE, LedgerAccount::RETAINED_EARNINGS.number())), Some(100));
+        assert!(Accounting::reconcile().is_empty());
+        // The weight accounts for the three postings.
+        assert!(weight > Accounting::posting_weight() * 3);
+
+        // The mock balances are not posted, the difference is reported but not posted again.
+        let _ = Balances::deposit_creating(&ALICE, 50);
+        assert_eq!(Accounting::on_runtime_upgrade(), RocksDbWeight::get().reads(1));
+
+        assert_eq!(Accounting::balance_by_ledger((ALICE, xtx_balance)), Some(70));
+        let mismatch = Mismatch {
+            identity: Some(ALICE),
+            account: xtx_balance,
+            ledger: 70,
+            expected: 120,
+        };
+        assert_eq!(Accounting::reconcile(), vec![mismatch]);
+    });
+}
+
+#[test]
+fn chain_started_with_the_chart_of_accounts_is_not_migrated() {
+    new_test_ext_with_accounts().execute_with(|| {
+        let _ = Balances::deposit_creating(&ALICE, 100);
+
+        assert_eq!(Accounting::storage_version(), Releases::V2_0_0);
+        assert_eq!(Accounting::on_runtime_upgrade(), RocksDbWeight::get().reads(1));
+        assert_eq!(Accounting::balance_by_ledger((ALICE, LedgerAccount::XTX_BALANCE.number())), None);
+    });
+}
+
+// A sub-account of inventory, not in the default chart of accounts.
+const STOCK: u64 = 110_10006000_0001;
+
+fn add_account(origin: Origin, account: u64, parent: Option<u64>) -> DispatchResultWithPostInfo {
+    let name_hash = <Test as frame_system::Config>::Hashing::hash(b"Stock");
+    dispatch(origin, AccountingCall::add_account(account, name_hash, Indicator::Debit, parent))
+}
+
+#[test]
+fn add_account_checks_the_account() {
+    new_test_ext_with_accounts().execute_with(|| {
+        let inventory = LedgerAccount::INVENTORY.number();
+
+        assert_noop!(add_account(Origin::signed(ALICE), STOCK, None), DispatchError::BadOrigin);
+        assert_noop!(add_account(Origin::root(), 999, None), Error::<Test>::InvalidAccountNumber);
+        assert_noop!(add_account(Origin::root(), inventory, None), Error::<Test>::AccountAlreadyExists);
+        assert_noop!(add_account(Origin::root(), STOCK, Some(STOCK)), Error::<Test>::UnknownParentAccount);
+
+        assert_ok!(add_account(Origin::root(), STOCK, Some(inventory)));
+        let definition = Accounting::chart_of_accounts(STOCK).unwrap();
+        assert_eq!(definition.parent, Some(inventory));
+        assert!(definition.active);
+        assert!(definition.manual_posting_allowed);
+        assert_eq!(last_event(), AccountingEvent::AccountAdded(STOCK).into());
+    });
+}
+
+#[test]
+fn deactivated_account_keeps_its_balance_but_cannot_be_posted_to() {
+    new_test_ext_with_accounts().execute_with(|| {
+        let inventory = LedgerAccount::INVENTORY.number();
+        assert_ok!(opening_balance(100));
+
+        assert_noop!(
+            dispatch(Origin::signed(ALICE), AccountingCall::deactivate_account(inventory)),
+            DispatchError::BadOrigin
+        );
+        assert_noop!(
+            dispatch(Origin::root(), AccountingCall::deactivate_account(STOCK)),
+            Error::<Test>::UnknownAccount
+        );
+        assert_ok!(dispatch(Origin::root(), AccountingCall::deactivate_account(inventory)));
+        assert_eq!(last_event(), AccountingEvent::AccountDeactivated(inventory).into());
+
+        assert!(!Accounting::chart_of_accounts(inventory).unwrap().active);
+        assert_eq!(Accounting::balance_by_ledger((ALICE, inventory)), Some(100));
+        assert_noop!(opening_balance(100), Error::<Test>::AccountDeactivated);
+    });
+}
+
+#[test]
+fn postings_are_only_accepted_by_active_accounts_in_the_chart() {
+    new_test_ext_with_accounts().execute_with(|| {
+        let inventory = LedgerAccount::INVENTORY;
+        let stock = posting(ALICE, LedgerAccount::try_from(STOCK).unwrap(), 100);
+
+        assert_noop!(Accounting::post_amounts(stock), Error::<Test>::UnknownAccount);
+        assert_ok!(add_account(Origin::root(), STOCK, None));
+        assert_ok!(Accounting::post_amounts(stock));
+
+        assert_ok!(dispatch(Origin::root(), AccountingCall::deactivate_account(inventory.number())));
+        assert_noop!(Accounting::post_amounts(posting(ALICE, inventory, 100)), Error::<Test>::AccountDeactivated);
+    });
+}
+
+#[test]
+fn reconcile_reports_every_mismatch() {
+    new_test_ext_with_accounts().execute_with(|| {
+        let xtx_balance = LedgerAccount::XTX_BALANCE.number();
+        let prefunding = LedgerAccount::PREFUNDING.number();
+        let mismatch = |identity, account, ledger, expected| Mismatch {
+            identity,
+            account,
+            ledger,
+            expected,
+        };
+        let _ = Balances::deposit_creating(&ALICE, 100);
+        assert_ok!(Accounting::post_opening_balance(&ALICE));
+        assert!(Accounting::reconcile().is_empty());
+
+        // The mock balances are not posted.
+        let _ = Balances::deposit_creating(&ALICE, 50);
+        assert_eq!(Accounting::reconcile(), vec![mismatch(Some(ALICE), xtx_balance, 100, 150)]);
+        assert_ok!(Accounting::post_opening_balance(&ALICE));
+        assert!(Accounting::reconcile().is_empty());
+
+        // Funds locked by prefunding without the prefunding postings.
+        let lock = TotemBalanceLock {
+            id: *b"prefundi",
+            amount: 30,
+            reasons: Reasons::All,
+            until: 10,
+        };
+        TotemLocks::<Test>::insert(ALICE, vec![lock]);
+        assert_eq!(
+            Accounting::reconcile(),
+            vec![mismatch(Some(ALICE), xtx_balance, 150, 120), mismatch(Some(ALICE), prefunding, 0, 30)]
+        );
+        assert_ok!(Accounting::post_opening_balance(&ALICE));
+        assert!(Accounting::reconcile().is_empty());
+
+        // The global ledger no longer adds up to the ledgers of the identities.
+        GlobalLedger::<Test>::insert(xtx_balance, 0);
+        assert_eq!(Accounting::reconcile(), vec![mismatch(None, xtx_balance, 0, 120)]);
+    });
+}
+
+#[test]
+fn opening_balance_posts_a_balanced_entry() {
+    new_test_ext_with_accounts().execute_with(|| {
+        assert_ok!(opening_balance(100));
+
+        let inventory = LedgerAccount::INVENTORY.number();
+        let retained_earnings = LedgerAccount::RETAINED_EARNINGS.number();
+        assert_eq!(Accounting::balance_by_ledger((ALICE, inventory)), Some(100));
+        assert_eq!(Accounting::balance_by_ledger((ALICE, retained_earnings)), Some(100));
+        assert_eq!(Accounting::global_ledger(inventory), Some(100));
+        assert_eq!(Accounting::global_ledger(retained_earnings), Some(100));
+    });
+}
+
+#[test]
+fn opening_balance_must_balance() {
+    new_test_ext_with_accounts().execute_with(|| {
+        let lines = vec![
+            line(LedgerAccount::INVENTORY, 100, Indicator::Debit, 1),
+            line(LedgerAccount::RETAINED_EARNINGS, 90, Indicator::Credit, 1),
+        ];
+        let call = AccountingCall::opening_balance(lines, H256::repeat_byte(1));
+
+        assert_noop!(dispatch(Origin::signed(ALICE), call), Error::<Test>::UnbalancedEntry);
+    });
+}
+
+#[test]
+fn journal_entry_must_have_lines() {
+    new_test_ext_with_accounts().execute_with(|| {
+        let opening_balance = AccountingCall::opening_balance(vec![], H256::repeat_byte(1));
+        let adjustment = AccountingCall::adjustment(vec![], H256::repeat_byte(2));
+
+        assert_noop!(dispatch(Origin::signed(ALICE), opening_balance), Error::<Test>::EmptyJournalEntry);
+        assert_noop!(dispatch(Origin::signed(ALICE), adjustment), Error::<Test>::EmptyJournalEntry);
+    });
+}
+
+#[test]
+fn opening_balance_cannot_be_re_targeted() {
+    new_test_ext_with_accounts().execute_with(|| {
+        System::set_block_number(5);
+        let lines = vec![
+            line(LedgerAccount::INVENTORY, 100, Indicator::Debit, 4),
+            line(LedgerAccount::RETAINED_EARNINGS, 100, Indicator::Credit, 4),
+        ];
+        let call = AccountingCall::opening_balance(lines, H256::repeat_byte(1));
+
+        assert_noop!(dispatch(Origin::signed(ALICE), call), Error::<Test>::OpeningBalancePeriod);
+    });
+}
+
+#[test]
+fn manual_entries_cannot_post_to_the_runtime_accounts() {
+    new_test_ext_with_accounts().execute_with(|| {
+        for account in runtime_accounts() {
+            assert!(!Accounting::chart_of_accounts(account.number()).unwrap().manual_posting_allowed);
+            let lines = vec![
+                line(account, 100, Indicator::Debit, 1),
+                line(LedgerAccount::RETAINED_EARNINGS, 100, Indicator::Credit, 1),
+            ];
+            let opening_balance = AccountingCall::opening_balance(lines.clone(), H256::repeat_byte(1));
+            let adjustment = AccountingCall::adjustment(lines, H256::repeat_byte(2));
+
+            assert_noop!(dispatch(Origin::signed(ALICE), opening_balance), Error::<Test>::ManualPostingNotAllowed);
+            assert_noop!(dispatch(Origin::signed(ALICE), adjustment), Error::<Test>::ManualPostingNotAllowed);
+        }
+    });
+}
+
+#[test]
+fn adjustment_is_posted_to_the_period_it_is_re_targeted_to() {
+    new_test_ext_with_accounts().execute_with(|| {
+        assert_ok!(dispatch(Origin::signed(ALICE), AccountingCall::add_fiscal_period(10)));
+        System::set_block_number(20);
+
+        assert_ok!(adjustment(100, 5));
+
+        let inventory = LedgerAccount::INVENTORY.number();
+        let retained_earnings = LedgerAccount::RETAINED_EARNINGS.number();
+        assert_eq!(Accounting::balance_by_period((ALICE, inventory), 0), Some(100));
+        assert_eq!(Accounting::balance_by_period((ALICE, retained_earnings), 0), Some(100));
+        assert_eq!(Accounting::balance_by_period((ALICE, inventory), 1), None);
+        assert_eq!(Accounting::balance_by_ledger((ALICE, inventory)), Some(100));
+    });
+}
+
+#[test]
+fn adjustment_cannot_be_posted_to_a_closed_period() {
+    new_test_ext_with_accounts().execute_with(|| {
+        assert_ok!(dispatch(Origin::signed(ALICE), AccountingCall::add_fiscal_period(10)));
+        System::set_block_number(20);
+        assert_ok!(dispatch(Origin::signed(ALICE), AccountingCall::close_period()));
+
+        assert_noop!(adjustment(100, 5), Error::<Test>::PeriodClosed);
+        assert_ok!(adjustment(100, 15));
+        assert_eq!(Accounting::balance_by_period((ALICE, LedgerAccount::INVENTORY.number()), 1), Some(100));
+    });
+}
+
+#[test]
+fn fiscal_periods_must_end_in_order() {
+    new_test_ext_with_accounts().execute_with(|| {
+        assert_ok!(dispatch(Origin::signed(ALICE), AccountingCall::add_fiscal_period(10)));
+
+        assert_noop!(
+            dispatch(Origin::signed(ALICE), AccountingCall::add_fiscal_period(10)),
+            Error::<Test>::FiscalPeriodOrder
+        );
+        assert_noop!(
+            dispatch(Origin::signed(ALICE), AccountingCall::add_fiscal_period(5)),
+            Error::<Test>::FiscalPeriodOrder
+        );
+        assert_ok!(dispatch(Origin::signed(ALICE), AccountingCall::add_fiscal_period(20)));
+        assert_eq!(Accounting::fiscal_periods(ALICE), Some(vec![10, 20]));
+        // The fiscal periods are those of the caller only.
+        assert_ok!(dispatch(Origin::signed(BOB), AccountingCall::add_fiscal_period(5)));
+    });
+}
+
+#[test]
+fn fiscal_period_cannot_end_before_a_posted_block() {
+    new_test_ext_with_accounts().execute_with(|| {
+        System::set_block_number(20);
+        assert_ok!(adjustment(100, 15));
+
+        assert_noop!(
+            dispatch(Origin::signed(ALICE), AccountingCall::add_fiscal_period(10)),
+            Error::<Test>::FiscalPeriodEndsBeforePostings
+        );
+        assert_ok!(dispatch(Origin::signed(ALICE), AccountingCall::add_fiscal_period(15)));
+        assert_eq!(Accounting::balance_by_period((ALICE, LedgerAccount::INVENTORY.number()), 0), Some(100));
+    });
+}
+
+#[test]
+fn only_an_ended_open_period_can_be_closed() {
+    new_test_ext_with_accounts().execute_with(|| {
+        assert_noop!(dispatch(Origin::signed(ALICE), AccountingCall::close_period()), Error::<Test>::NoOpenPeriod);
+
+        assert_ok!(dispatch(Origin::signed(ALICE), AccountingCall::add_fiscal_period(10)));
+        System::set_block_number(10);
+        assert_noop!(dispatch(Origin::signed(ALICE), AccountingCall::close_period()), Error::<Test>::PeriodNotEnded);
+
+        System::set_block_number(11);
+        assert_ok!(dispatch(Origin::signed(ALICE), AccountingCall::close_period()));
+        assert_eq!(Accounting::closed_until(ALICE), Some(10));
+        assert_noop!(dispatch(Origin::signed(ALICE), AccountingCall::close_period()), Error::<Test>::NoOpenPeriod);
+    });
+}
+
+// Sells `sales` of inventory and spends `labour` of it, applicable to the block `applicable_period`.
+fn trade(sales: i128, labour: i128, applicable_period: u64) -> DispatchResultWithPostInfo {
+    let lines = vec![
+        line(LedgerAccount::INVENTORY, sales, Indicator::Debit, applicable_period),
+        line(LedgerAccount::SALES, sales, Indicator::Credit, applicable_period),
+        line(LedgerAccount::LABOUR, labour, Indicator::Debit, applicable_period),
+        line(LedgerAccount::INVENTORY, -labour, Indicator::Credit, applicable_period),
+    ];
+    dispatch(Origin::signed(ALICE), AccountingCall::adjustment(lines, H256::repeat_byte(2)))
+}
+
+// Trades in the first period of `ALICE`, ending at block 10, closes it and trades in the second period.
+fn close_a_traded_period() {
+    assert_ok!(dispatch(Origin::signed(ALICE), AccountingCall::add_fiscal_period(10)));
+    assert_ok!(trade(300, 100, 5));
+    System::set_block_number(20);
+    assert_ok!(dispatch(Origin::signed(ALICE), AccountingCall::close_period()));
+    assert_ok!(trade(50, 0, 15));
+}
+
+#[test]
+fn closing_a_period_rolls_the_profit_and_loss_into_retained_earnings() {
+    new_test_ext_with_accounts().execute_with(|| {
+        assert_ok!(dispatch(Origin::signed(ALICE), AccountingCall::add_fiscal_period(10)));
+        assert_ok!(trade(300, 100, 5));
+        System::set_block_number(20);
+
+        assert_ok!(dispatch(Origin::signed(ALICE), AccountingCall::close_period()));
+
+        let sales = LedgerAccount::SALES.number();
+        let labour = LedgerAccount::LABOUR.number();
+        let retained_earnings = LedgerAccount::RETAINED_EARNINGS.number();
+        assert_eq!(Accounting::balance_by_period((ALICE, sales), 0), Some(0));
+        assert_eq!(Accounting::balance_by_period((ALICE, labour), 0), Some(0));
+        assert_eq!(Accounting::balance_by_period((ALICE, retained_earnings), 0), Some(200));
+        assert_eq!(Accounting::closing_entry_by_period((ALICE, sales), 0), Some(-300));
+        assert_eq!(Accounting::closing_entry_by_period((ALICE, labour), 0), Some(-100));
+        assert_eq!(Accounting::closing_entry_by_period((ALICE, retained_earnings), 0), Some(200));
+
+        // The closed profit and loss cannot be changed by back-dated postings.
+        assert_noop!(trade(10, 0, 10), Error::<Test>::PeriodClosed);
+        assert_ok!(trade(10, 0, 11));
+    });
+}
+
+#[test]
+fn trial_balance_of_a_period_is_before_closing() {
+    new_test_ext_with_accounts().execute_with(|| {
+        close_a_traded_period();
+
+        let line = |account: LedgerAccount, balance| TrialBalanceLine {
+            account: account.number(),
+            balance,
+        };
+        assert_eq!(
+            Accounting::trial_balance(ALICE, Some(ReportPeriod::FiscalPeriod(0))),
+            vec![
+                line(LedgerAccount::INVENTORY, 200),
+                line(LedgerAccount::RETAINED_EARNINGS, 0),
+                line(LedgerAccount::SALES, 300),
+                line(LedgerAccount::LABOUR, 100),
+            ]
+        );
+        assert_eq!(
+            Accounting::trial_balance(ALICE, Some(ReportPeriod::FiscalPeriod(1))),
+            vec![
+                line(LedgerAccount::INVENTORY, 250),
+                line(LedgerAccount::RETAINED_EARNINGS, 200),
+                line(LedgerAccount::SALES, 50),
+                line(LedgerAccount::LABOUR, 0),
+            ]
+        );
+        assert_eq!(
+            Accounting::trial_balance(ALICE, None),
+            Accounting::trial_balance(ALICE, Some(ReportPeriod::FiscalPeriod(1)))
+        );
+    });
+}
+
+#[test]
+fn balance_sheet_of_a_period_includes_the_periods_before_it() {
+    new_test_ext_with_accounts().execute_with(|| {
+        close_a_traded_period();
+
+        let line = |category, balance| ReportLine {
+            statement_type: 1,
+            category,
+            category_group: 0,
+            balance,
+        };
+        assert_eq!(
+            Accounting::balance_sheet(ALICE, Some(ReportPeriod::FiscalPeriod(0))),
+            vec![line(1, 200), line(3, 200)]
+        );
+        assert_eq!(
+            Accounting::balance_sheet(ALICE, Some(ReportPeriod::FiscalPeriod(1))),
+            vec![line(1, 250), line(3, 200)]
+        );
+    });
+}
+
+#[test]
+fn profit_and_loss_of_a_period_is_that_period_only() {
+    new_test_ext_with_accounts().execute_with(|| {
+        close_a_traded_period();
+
+        let line = |category, balance| ReportLine {
+            statement_type: 2,
+            category,
+            category_group: 0,
+            balance,
+        };
+        assert_eq!(
+            Accounting::profit_and_loss(ALICE, Some(ReportPeriod::FiscalPeriod(0))),
+            vec![line(4, 300), line(5, 100)]
+        );
+        assert_eq!(
+            Accounting::profit_and_loss(ALICE, Some(ReportPeriod::FiscalPeriod(1))),
+            vec![line(4, 50), line(5, 0)]
+        );
+        // The current profit and loss is that of the open periods.
+        assert_eq!(Accounting::profit_and_loss(ALICE, None), vec![line(4, 50), line(5, 0)]);
+    });
+}
+
+#[test]
+fn reports_can_cover_a_range_of_blocks() {
+    new_test_ext_with_accounts().execute_with(|| {
+        close_a_traded_period();
+        let blocks = |first, last| Some(ReportPeriod::Blocks(first, last));
+        let fiscal_period = |index| Some(ReportPeriod::FiscalPeriod(index));
+
+        // The ranges of the fiscal periods report the same as the fiscal periods.
+        assert_eq!(Accounting::trial_balance(ALICE, blocks(0, 10)), Accounting::trial_balance(ALICE, fiscal_period(0)));
+        assert_eq!(
+            Accounting::trial_balance(ALICE, blocks(11, 20)),
+            Accounting::trial_balance(ALICE, fiscal_period(1))
+        );
+        assert_eq!(Accounting::balance_sheet(ALICE, blocks(0, 10)), Accounting::balance_sheet(ALICE, fiscal_period(0)));
+        assert_eq!(
+            Accounting::profit_and_loss(ALICE, blocks(0, 10)),
+            Accounting::profit_and_loss(ALICE, fiscal_period(0))
+        );
+
+        // A range across the close includes the profit and loss of both periods.
+        let line = |category, balance| ReportLine {
+            statement_type: 2,
+            category,
+            category_group: 0,
+            balance,
+        };
+        assert_eq!(Accounting::profit_and_loss(ALICE, blocks(0, 20)), vec![line(4, 350), line(5, 100)]);
+        assert_eq!(Accounting::profit_and_loss(ALICE, blocks(12, 20)), vec![line(4, 50), line(5, 0)]);
+        assert_eq!(Accounting::profit_and_loss(ALICE, blocks(6, 14)), vec![line(4, 0), line(5, 0)]);
+    });
+}
+
+// A posting of `amount` to `account` of `who` in the current block.
+fn posting(who: u64, account: LedgerAccount, amount: i128) -> (u64, u64, i128, bool, H256, u64, u64) {
+    (who, account.number(), amount, amount > 0, H256::repeat_byte(3), 1, 1)
+}
+
+#[test]
+fn postings_must_balance_for_every_identity() {
+    new_test_ext_with_accounts().execute_with(|| {
+        let inventory = LedgerAccount::INVENTORY;
+        let retained_earnings = LedgerAccount::RETAINED_EARNINGS;
+
+        let balanced = vec![posting(ALICE, inventory, 100), posting(ALICE, retained_earnings, 100)];
+        assert!(Accounting::is_balanced(&balanced).unwrap());
+
+        // The batch balances as a whole, but not for each identity.
+        let across_identities = vec![posting(ALICE, inventory, 100), posting(BOB, retained_earnings, 100)];
+        assert!(!Accounting::is_balanced(&across_identities).unwrap());
+
+        let unbalanced = vec![posting(ALICE, inventory, 100), posting(ALICE, retained_earnings, 90)];
+        assert!(!Accounting::is_balanced(&unbalanced).unwrap());
+    });
+}
+
+#[test]
+fn postings_are_balanced_by_the_normal_balance_of_the_accounts() {
+    new_test_ext_with_accounts().execute_with(|| {
+        // Increasing inventory and decreasing retained earnings are both debits, whatever the indicators say.
+        let lines = vec![
+            line(LedgerAccount::INVENTORY, 100, Indicator::Debit, 1),
+            line(LedgerAccount::RETAINED_EARNINGS, -100, Indicator::Credit, 1),
+        ];
+        let call = AccountingCall::adjustment(lines, H256::repeat_byte(2));
+
+        assert_noop!(dispatch(Origin::signed(ALICE), call), Error::<Test>::UnbalancedEntry);
+
+        // Moving a balance between two accounts with the same normal balance is an increase and a decrease.
+        let lines = vec![
+            line(LedgerAccount::INVENTORY, 100, Indicator::Debit, 1),
+            line(LedgerAccount::FIXED_ASSETS, -100, Indicator::Credit, 1),
+        ];
+        assert_ok!(dispatch(Origin::signed(ALICE), AccountingCall::adjustment(lines, H256::repeat_byte(2))));
+        assert_eq!(Accounting::balance_by_ledger((ALICE, LedgerAccount::FIXED_ASSETS.number())), Some(-100));
+    });
+}
+
+#[test]
+fn memorandum_accounts_are_not_part_of_the_balance() {
+    new_test_ext_with_accounts().execute_with(|| {
+        let postings = vec![
+            posting(ALICE, LedgerAccount::INVENTORY, 100),
+            posting(ALICE, LedgerAccount::RETAINED_EARNINGS, 100),
+            posting(ALICE, LedgerAccount::SALES_LEDGER_BY_PAYER, 100),
+            posting(BOB, LedgerAccount::PURCHASE_LEDGER_BY_VENDOR, 100),
+        ];
+
+        assert!(Accounting::is_balanced(&postings).unwrap());
+    });
+}
+
+fn liability(vendor: u64, balance: i128) -> TaxLiability<u64> {
+    TaxLiability {
+        vendor,
+        balance,
+    }
+}
+
+#[test]
+fn sales_tax_is_accumulated_by_vendor_and_jurisdiction() {
+    new_test_ext().execute_with(|| {
+        let jurisdiction = 3;
+        assert_ok!(Accounting::add_sales_tax(ALICE, jurisdiction, 90));
+        assert_ok!(Accounting::add_sales_tax(BOB, jurisdiction, 40));
+        assert_ok!(Accounting::add_sales_tax(ALICE, jurisdiction, 10));
+
+        assert_eq!(Accounting::taxes_by_jurisdiction((ALICE, jurisdiction)), Some(100));
+        let liabilities = Accounting::sales_tax(jurisdiction);
+        assert_eq!(liabilities.len(), 2);
+        assert!(liabilities.contains(&liability(ALICE, 100)));
+        assert!(liabilities.contains(&liability(BOB, 40)));
+        assert!(Accounting::sales_tax(4).is_empty());
+
+        // Reversing the invoices clears what the vendor owes
+        assert_ok!(Accounting::add_sales_tax(ALICE, jurisdiction, -100));
+        assert_eq!(Accounting::sales_tax(jurisdiction), vec![liability(BOB, 40)]);
+        assert_noop!(Accounting::add_sales_tax(BOB, jurisdiction, i128::MAX), Error::<Test>::BalanceValueOverflow);
+    });
+}
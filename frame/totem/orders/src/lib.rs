@@ -42,12 +42,23 @@
 //! A basic workflow is as follows:
 //!
 //! * In general orders are assigned to a partner that the ordering identity already knows and is required to be accepted by that party to become active.
-//! * Orders can be made without already knowing the seller - these are called market orders
+//! * Orders can be made without already knowing the seller - these are called market orders. Any identity can submit
+//! a proposal against a market order. When the buyer accepts one of the proposals it becomes a closed order with the proposer
+//! as the seller, the order is prefunded, and all the other proposals are rejected.
 //! * The order can be prefunded by calling into the prefunding module, which updates the accounting ledgers.
 //! * Once the order is accepted, the work must begin, and once completed, the vendor sets the state to completed.
 //! * The completion state also generates the invoice, and relevant accounting postings for both the buyer and the seller.
+//! The order amount includes any sales taxes set by the seller, which are posted separately from the net amount.
+//! Each order item is invoiced as a separate line, posted to the revenue and expense or asset accounts of the order type.
 //! * The completed work is then approved by the buyer (or disputed or rejected). An approval triggers the release of prefunds and
 //! the invoice is marked as settled in the accounts for both parties
+//! * Alternatively the vendor can invoice portions of an accepted order as milestones are reached. The buyer approves each
+//! milestone, releasing only that portion of the prefunds, until the prefunded amount has been paid in full.
+//! * A disputed order remains prefunded until the nominated arbiter rules on how much of the prefunds is released.
+//! * Orders that are not prefunded are sold on credit. Once accepted, the vendor invoices the order with a due date for payment
+//! and the buyer pays the invoice directly.
+//! * Invoices of orders assigned to a project are recorded as costs against the project budget in the teams module.
+//! An invoice exceeding an enforced project budget is rejected.
 //!
 //! The main types used in this module are:
 //!
@@ -55,22 +66,39 @@
 //! * UnitPrice = i128; // This does not need a unit of currency because it is allways the internal functional currency
 //! * Quantity = u128;
 //! * UnitOfMeasure = u16;
-//! * buy_or_sell: u16, // 0: buy, 1: sell, extensible
-//! * amount: AccountBalanceOf<T>, // amount should be the sum of all the items untiprices * quantities
+//! * buy_or_sell: OrderDirection, // Buy(0), Sell(1)
+//! * amount: AccountBalanceOf<T>, // amount must be the sum of all the items untiprices * quantities
 //! * open_closed: bool, // 0: open(true) 1: closed(false)
-//! * order_type: u16, // 0 Services, 1 Goods, 2 Inventory
+//! * order_type: OrderType, // Service(0), Goods(1), Inventory(2), Asset(3)
+//! * order_status: OrderStatus, // changes only as allowed by the transition table `OrderStatus::transition`
 //! * deadline: u64, // prefunding acceptance deadline
 //! * due_date: u64, // due date is the future delivery date (in blocks)
 
 #![cfg_attr(not(feature = "std"), no_std)]
 
-use frame_support::{dispatch::EncodeLike, fail, pallet_prelude::*};
+mod mock;
+mod tests;
+
+use frame_support::{
+    dispatch::{EncodeLike, Input},
+    fail,
+    pallet_prelude::*,
+    transactional,
+};
 use frame_system::pallet_prelude::*;
 
 use sp_runtime::traits::Convert;
-use sp_std::{prelude::*, vec};
-
-use totem_utils::traits::{accounting::Posting, bonsai::Storing, orders::Validating, prefunding::Encumbrance};
+use sp_std::{convert::TryFrom, prelude::*, vec};
+
+use totem_utils::chart_of_accounts::LedgerAccount;
+use totem_utils::traits::{
+    accounting::Posting,
+    bonsai::Storing,
+    orders::{Arbitrating, Validating},
+    prefunding::Encumbrance,
+    teams::{Budgeting as TeamsBudgeting, Validating as TeamsValidating},
+};
+use totem_utils::types::{InvoiceLine, TaxLine, TaxLineError, TeamRole};
 use totem_utils::{ok, StorageMapExt};
 
 // Totem Config Types
@@ -89,8 +117,6 @@ pub type UnLocked<T> = <<T as Config>::Prefunding as Encumbrance<
 >>::LockStatus;
 
 // Module Types
-type OrderStatus = u16; // Generic Status for whatever the HashReference refers to
-
 #[repr(u16)]
 #[derive(Debug, Decode, Encode, Clone, Copy, PartialEq, Eq)]
 pub enum ApprovalStatus {
@@ -105,18 +131,226 @@ impl Default for ApprovalStatus {
     }
 }
 
+impl ApprovalStatus {
+    /// The approval transitions: a submitted or rejected order can be approved, and the approval of an order can be
+    /// withdrawn or turned into a rejection. Returns the new status if allowed, otherwise `None`.
+    pub fn transition(self, next: ApprovalStatus) -> Option<ApprovalStatus> {
+        use ApprovalStatus::*;
+
+        match (self, next) {
+            (Submitted, Accepted) | (Rejected, Accepted) | (Accepted, Submitted) | (Accepted, Rejected) => Some(next),
+            _ => None,
+        }
+    }
+}
+
+/// Encoded as the u16 status code stored before the status was typed.
+#[repr(u16)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderStatus {
+    Submitted = 0,
+    Accepted = 1,
+    Rejected = 2,
+    Disputed = 3,
+    /// No longer set. Kept so that the orders stored with this status still decode, no transition leads to or from it.
+    Blocked = 4,
+    Invoiced = 5,
+    Completed = 6,
+}
+impl Default for OrderStatus {
+    fn default() -> Self {
+        OrderStatus::Submitted
+    }
+}
+
+/// The party changing the status of an order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderParty {
+    Commander,
+    Fulfiller,
+    /// The market, when a proposal against a market order is accepted
+    Market,
+    /// The arbiter, when ruling on a disputed order
+    Arbiter,
+}
+
+impl OrderStatus {
+    /// The transition table for all orders. Returns the new status if the party is allowed to move the order
+    /// from the current status to `next`, otherwise `None`.
+    pub fn transition(self, party: OrderParty, next: OrderStatus) -> Option<OrderStatus> {
+        use OrderParty::*;
+        use OrderStatus::*;
+
+        match (self, party, next) {
+            // The fulfiller accepts or rejects the order
+            (Submitted, Fulfiller, Accepted) | (Submitted, Fulfiller, Rejected) => Some(next),
+            // The market order is closed when a proposal is accepted, and the other proposals are rejected
+            (Submitted, Market, Accepted) | (Submitted, Market, Rejected) => Some(next),
+            // The commander changes a rejected order and submits it again
+            (Rejected, Commander, Submitted) => Some(next),
+            // The fulfiller invoices the order once the work is completed
+            (Accepted, Fulfiller, Invoiced) => Some(next),
+            // Either party can dispute an accepted or invoiced order
+            (Accepted, Commander, Disputed)
+            | (Accepted, Fulfiller, Disputed)
+            | (Invoiced, Commander, Disputed)
+            | (Invoiced, Fulfiller, Disputed) => Some(next),
+            // The arbiter's ruling completes the order if funds are released to the fulfiller, otherwise rejects it.
+            // A dispute raised against the prefunding directly leaves the order accepted or invoiced.
+            (Disputed, Arbiter, Completed)
+            | (Disputed, Arbiter, Rejected)
+            | (Accepted, Arbiter, Completed)
+            | (Accepted, Arbiter, Rejected)
+            | (Invoiced, Arbiter, Completed)
+            | (Invoiced, Arbiter, Rejected) => Some(next),
+            // The commander pays the invoice, or the last milestone of an accepted order
+            (Invoiced, Commander, Completed) | (Accepted, Commander, Completed) => Some(next),
+            _ => None,
+        }
+    }
+}
+
+/// Encoded as the u16 order type stored before the order type was typed.
+#[repr(u16)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderType {
+    Service = 0,
+    Goods = 1,
+    Inventory = 2,
+    Asset = 3,
+}
+impl Default for OrderType {
+    fn default() -> Self {
+        OrderType::Service
+    }
+}
+
+impl OrderType {
+    /// The revenue account of the seller and the expense or asset account of the buyer that the invoices are posted to.
+    pub fn ledger_accounts(self) -> (LedgerAccount, LedgerAccount) {
+        match self {
+            OrderType::Service => (LedgerAccount::SALES, LedgerAccount::LABOUR),
+            OrderType::Goods => (LedgerAccount::GOODS_SALES, LedgerAccount::PURCHASES),
+            OrderType::Inventory => (LedgerAccount::GOODS_SALES, LedgerAccount::INVENTORY),
+            OrderType::Asset => (LedgerAccount::GOODS_SALES, LedgerAccount::FIXED_ASSETS),
+        }
+    }
+}
+
+/// Encoded as the u16 buy or sell code stored before the direction was typed.
+#[repr(u16)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderDirection {
+    Buy = 0,
+    Sell = 1,
+}
+impl Default for OrderDirection {
+    fn default() -> Self {
+        OrderDirection::Buy
+    }
+}
+
+impl TryFrom<u16> for OrderStatus {
+    type Error = ();
+
+    fn try_from(value: u16) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Submitted),
+            1 => Ok(Self::Accepted),
+            2 => Ok(Self::Rejected),
+            3 => Ok(Self::Disputed),
+            4 => Ok(Self::Blocked),
+            5 => Ok(Self::Invoiced),
+            6 => Ok(Self::Completed),
+            _ => Err(()),
+        }
+    }
+}
+
+impl EncodeLike for OrderStatus {}
+impl EncodeLike<OrderStatus> for u16 {}
+
+impl Decode for OrderStatus {
+    fn decode<I: Input>(input: &mut I) -> Result<Self, codec::Error> {
+        Self::try_from(u16::decode(input)?).map_err(|_| codec::Error::from("[OrderStatus::decode] Value out of range"))
+    }
+}
+
+impl Encode for OrderStatus {
+    fn encode(&self) -> Vec<u8> {
+        (*self as u16).encode()
+    }
+}
+
+impl TryFrom<u16> for OrderType {
+    type Error = ();
+
+    fn try_from(value: u16) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Service),
+            1 => Ok(Self::Goods),
+            2 => Ok(Self::Inventory),
+            3 => Ok(Self::Asset),
+            _ => Err(()),
+        }
+    }
+}
+
+impl EncodeLike for OrderType {}
+impl EncodeLike<OrderType> for u16 {}
+
+impl Decode for OrderType {
+    fn decode<I: Input>(input: &mut I) -> Result<Self, codec::Error> {
+        Self::try_from(u16::decode(input)?).map_err(|_| codec::Error::from("[OrderType::decode] Value out of range"))
+    }
+}
+
+impl Encode for OrderType {
+    fn encode(&self) -> Vec<u8> {
+        (*self as u16).encode()
+    }
+}
+
+impl TryFrom<u16> for OrderDirection {
+    type Error = ();
+
+    fn try_from(value: u16) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Buy),
+            1 => Ok(Self::Sell),
+            _ => Err(()),
+        }
+    }
+}
+
+impl EncodeLike for OrderDirection {}
+impl EncodeLike<OrderDirection> for u16 {}
+
+impl Decode for OrderDirection {
+    fn decode<I: Input>(input: &mut I) -> Result<Self, codec::Error> {
+        Self::try_from(u16::decode(input)?)
+            .map_err(|_| codec::Error::from("[OrderDirection::decode] Value out of range"))
+    }
+}
+
+impl Encode for OrderDirection {
+    fn encode(&self) -> Vec<u8> {
+        (*self as u16).encode()
+    }
+}
+
 /// The order header: contains common values for all items
 #[derive(PartialEq, Eq, Copy, Clone, Debug, Encode, Decode, Default)]
 pub struct OrderHeader<AccountId> {
     pub commander: AccountId,
     pub fulfiller: AccountId,
     pub approver: AccountId,
-    pub order_status: u16,
+    pub order_status: OrderStatus,
     pub approval_status: ApprovalStatus,
-    pub buy_or_sell: u16,
+    pub buy_or_sell: OrderDirection,
     pub amount: i128,
     pub market_order: bool,
-    pub order_type: u16,
+    pub order_type: OrderType,
     pub deadline: u32,
     pub due_date: u32,
 }
@@ -175,6 +409,11 @@ pub mod pallet {
     #[pallet::getter(fn postulate)]
     pub type Postulate<T: Config> = StorageMap<_, Blake2_128Concat, T::Hash, Vec<T::Hash>>;
 
+    #[pallet::storage]
+    #[pallet::getter(fn market_orders)]
+    /// Market orders that are open for proposals.
+    pub type MarketOrders<T: Config> = StorageMap<_, Blake2_128Concat, T::Hash, ()>;
+
     #[pallet::storage]
     #[pallet::getter(fn orders)]
     pub type Orders<T: Config> = StorageMap<_, Blake2_128Concat, T::Hash, OrderHeader<T::AccountId>>;
@@ -183,6 +422,21 @@ pub mod pallet {
     #[pallet::getter(fn order_items)]
     pub type OrderItems<T: Config> = StorageMap<_, Blake2_128Concat, T::Hash, Vec<OrderItem<T::Hash>>>;
 
+    #[pallet::storage]
+    #[pallet::getter(fn order_taxes)]
+    /// Sales taxes the fulfiller includes in the invoices for the order.
+    pub type OrderTaxes<T: Config> = StorageMap<_, Blake2_128Concat, T::Hash, Vec<TaxLine<T::AccountId>>>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn unfunded)]
+    /// Orders without prefunding, that are invoiced on credit.
+    pub type Unfunded<T: Config> = StorageMap<_, Blake2_128Concat, T::Hash, bool, ValueQuery>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn order_project)]
+    /// The project of an order, whose team approvers can approve the order as well as the nominated approver.
+    pub type OrderProject<T: Config> = StorageMap<_, Blake2_128Concat, T::Hash, T::Hash>;
+
     #[pallet::config] //TODO declare configs that are constant
     pub trait Config: frame_system::Config + pallet_accounting::Config {
         type Event: From<Event<Self>> + IsType<<Self as frame_system::Config>::Event>;
@@ -197,6 +451,13 @@ pub mod pallet {
         type Accounting: Posting<Self::AccountId, Self::Hash, Self::BlockNumber, Self::Balance>;
         type Prefunding: Encumbrance<Self::AccountId, Self::Hash, Self::BlockNumber>;
         type Bonsai: Storing<Self::Hash>;
+        type Projects: TeamsValidating<Self::AccountId, Self::Hash> + TeamsBudgeting<Self::Hash>;
+        /// The maximum number of items in an order.
+        #[pallet::constant]
+        type MaxOrderItems: Get<u32>;
+        /// The maximum number of sales taxes on the invoices of an order.
+        #[pallet::constant]
+        type MaxTaxLines: Get<u32>;
     }
 
     #[pallet::error]
@@ -215,28 +476,12 @@ pub mod pallet {
         ErrorCannotBeBoth2,
         /// You should not be doing this!
         ErrorURNobody,
-        /// Order already accepted - cannot change now!
-        ErrorOrderStatus1,
-        /// Incorrect Order Status!
-        ErrorOrderStatus2,
-        /// The order has an unkown state!
-        ErrorOrderStatus3,
+        /// The order cannot be changed to this status, or not by you in its current status
+        ErrorStatusTransition,
         /// The submitted status not allowed.
         ErrorApprStatus,
         /// Already approved!
         ErrorApproved,
-        /// Order status is not allowed!
-        ErrorStatusNotAllowed1,
-        /// Order already accepted. Order status is not allowed!
-        ErrorStatusNotAllowed2,
-        /// The order has a status that cannot be changed!
-        ErrorStatusNotAllowed3,
-        /// The order has an unkown state!
-        ErrorStatusNotAllowed4,
-        /// The order has an unkown state!
-        ErrorStatusNotAllowed5,
-        /// This is not your order or wrong status
-        ErrorStatusNotAllowed6,
         /// Not allowed to fulfill your own order!
         ErrorFulfiller,
         /// Amount cannot be less than zero!
@@ -245,12 +490,8 @@ pub mod pallet {
         ErrorShortDeadline,
         /// Due date must be at least 1 hour after deadline
         ErrorShortDueDate,
-        /// This situation is not implemented yet: Invoice is disputed
-        ErrorNotImplmented1,
         /// Unable to fetch order with this reference
         ErrorGettingOrder,
-        /// Error setting prefunding state
-        ErrorSetPrefundState,
         /// Error from prefunding module - in check approver
         ErrorInPrefunding1,
         /// Error in Processing Order Acceptance status
@@ -267,6 +508,48 @@ pub mod pallet {
         ErrorInPrefunding7,
         /// Error Cannot make an market order against a parent order
         ErrorMarketOrder,
+        /// Error in prefunding raising the dispute
+        ErrorInPrefunding8,
+        /// Error in prefunding to send milestone invoice
+        ErrorInPrefunding9,
+        /// Error in prefunding settling milestone
+        ErrorInPrefunding10,
+        /// This order is prefunded
+        ErrorPrefundedOrder,
+        /// Error in prefunding to send unfunded invoice
+        ErrorInPrefunding11,
+        /// Error in prefunding settling unfunded invoice
+        ErrorInPrefunding12,
+        /// This is not a market order open for proposals
+        ErrorMarketOrder2,
+        /// Market orders are prefunded once a proposal is accepted
+        ErrorMarketOrder3,
+        /// This proposal was not submitted against the market order
+        ErrorNotProposal,
+        /// The market order has not been approved yet
+        ErrorNotApproved,
+        /// Error setting the prefunding for the accepted proposal
+        ErrorInPrefunding13,
+        /// The order must have at least one item
+        ErrorNoItems,
+        /// The order has too many items
+        ErrorTooManyItems,
+        /// The items total overflows
+        ErrorItemsOverflow,
+        /// The amount is not the sum of the items unit prices * quantities
+        ErrorItemsTotal,
+        /// The unit price of an item must be greater than zero
+        ErrorUnitPrice,
+        /// The deadline and due date of a proposal cannot be later than those of the market order
+        ErrorProposalDates,
+        /// The commander is not a member of the team of the project
+        ErrorNotTeamMember,
+        /// The order has too many sales taxes
+        ErrorTooManyTaxLines,
+        /// A sales tax rate cannot be zero
+        ErrorZeroTaxRate,
+        /// Each tax jurisdiction can only be taxed once
+        ErrorDuplicateJurisdiction,
     }
 
     #[pallet::hooks]
@@ -285,19 +568,28 @@ pub mod pallet {
                 Some(order) => {
                     // Order is owned by sender, status unaccepted a
                     let approver: T::AccountId = order.approver;
-                    let order_status: u16 = order.order_status;
-                    if (approver.clone(), order_status) == (who, 0_u16) {
+                    if approver != who {
+                        fail!(Error::<T>::ErrorNotApprover);
+                    }
+                    if order.order_status == OrderStatus::Submitted {
                         Owner::<T>::mutate_(&order.commander, |owner| owner.retain(|v| v != &tx_keys_medium.record_id));
                         Beneficiary::<T>::mutate_(&order.fulfiller, |owner| {
                             owner.retain(|v| v != &tx_keys_medium.record_id)
                         });
                         // <Approver<T>>::mutate(&approver, |owner| {
                         Approver::<T>::mutate_(approver, |owner| owner.retain(|v| v != &tx_keys_medium.record_id));
+                        // Reject the proposals against a market order
+                        for proposal in Self::postulate(&tx_keys_medium.record_id).unwrap_or_default() {
+                            Self::reject_proposal(proposal);
+                        }
                         Postulate::<T>::remove(&tx_keys_medium.record_id);
+                        MarketOrders::<T>::remove(&tx_keys_medium.record_id);
                         Orders::<T>::remove(&tx_keys_medium.record_id);
                         OrderItems::<T>::remove(&tx_keys_medium.record_id);
+                        Unfunded::<T>::remove(&tx_keys_medium.record_id);
+                        OrderProject::<T>::remove(&tx_keys_medium.record_id);
                     } else {
-                        fail!(Error::<T>::ErrorStatusNotAllowed6);
+                        fail!(Error::<T>::ErrorStatusTransition);
                     }
                 }
                 // Order does not exist
@@ -315,10 +607,10 @@ pub mod pallet {
             origin: OriginFor<T>,
             approver: T::AccountId,
             fulfiller: T::AccountId,
-            buy_or_sell: u16,
+            buy_or_sell: OrderDirection,
             total_amount: i128,
             market_order: bool,
-            order_type: u16,
+            order_type: OrderType,
             deadline: u32,
             due_date: u32,
             order_items: Vec<OrderItem<T::Hash>>,
@@ -330,18 +622,35 @@ pub mod pallet {
             if Orders::<T>::contains_key(&tx_keys_large.record_id) {
                 fail!(Error::<T>::ErrorHashExists);
             }
+            Self::validate_items(total_amount, &order_items)?;
 
             let mut approval_status = ApprovalStatus::Submitted;
             // Check that it is an open order
             if market_order {
                 // process open order - ignore fulfiller
                 // check that the order does not have a parent - by default the parent and the record_id must be the same
-                if tx_keys_large.record_id == tx_keys_large.parent_id {
-                } else {
+                if tx_keys_large.record_id != tx_keys_large.parent_id {
                     fail!(Error::<T>::ErrorMarketOrder);
                 }
-                // Go further - Store the Order
-                ();
+                if Self::check_approver(who.clone(), approver.clone(), tx_keys_large.record_id.clone()) {
+                    approval_status = ApprovalStatus::Accepted;
+                }
+                // The fulfiller is not known until a proposal is accepted, until then the commander stands in
+                let order_header: OrderHeader<T::AccountId> = OrderHeader {
+                    commander: who.clone(),
+                    fulfiller: who.clone(),
+                    approver: approver,
+                    order_status: OrderStatus::Submitted,
+                    approval_status: approval_status,
+                    buy_or_sell: buy_or_sell,
+                    amount: total_amount,
+                    market_order: market_order,
+                    order_type: order_type,
+                    deadline: deadline,
+                    due_date: due_date,
+                };
+                Self::set_order(who.clone(), who.clone(), tx_keys_large.record_id, order_header, order_items)?;
+                MarketOrders::<T>::insert(&tx_keys_large.record_id, ());
             } else {
                 // closed order, fulfiller must be completed and it must not be the origin
                 if fulfiller == who {
@@ -350,8 +659,8 @@ pub mod pallet {
                 // The order may have a parent - by default the parent and the record_id are the same, but they may also be different
                 if tx_keys_large.record_id == tx_keys_large.parent_id {
                     // This order has no parent therefore is a simple unfunded order with a known fulfiller
-                    // TODO
-                    ();
+                    // It will be invoiced on credit once accepted
+                    Unfunded::<T>::insert(&tx_keys_large.record_id, true);
                 } else {
                     // This order has a parent therefore it is a proposal and this means there is a fulfiller
                     // check that that the parent hash exists
@@ -367,7 +676,7 @@ pub mod pallet {
                         // This is NOT an error but requires further processing by the approver.
                         // As this is a proposal against a parent order then associate the child with the parent
                         // This does not happen when it is a simple order
-                        Postulate::<T>::mutate_(&tx_keys_large.parent_id, |v| v.push(tx_keys_large.record_id));
+                        Postulate::<T>::append(&tx_keys_large.parent_id, tx_keys_large.record_id);
                         // <TxList<T>>::mutate(list_key, |tx_list| tx_list.push(u));
                     }
                 }
@@ -375,7 +684,7 @@ pub mod pallet {
                     commander: who.clone(),
                     fulfiller: fulfiller.clone(),
                     approver: who.clone(),
-                    order_status: 0u16,
+                    order_status: OrderStatus::Submitted,
                     approval_status: approval_status,
                     buy_or_sell: buy_or_sell,
                     amount: total_amount,
@@ -399,10 +708,10 @@ pub mod pallet {
             origin: OriginFor<T>,
             approver: T::AccountId,
             fulfiller: T::AccountId,
-            buy_or_sell: u16,               // 0: buy, 1: sell, extensible
+            buy_or_sell: OrderDirection,    // Buy(0), Sell(1)
             total_amount: i128,             // amount should be the sum of all the items untiprices * quantities
             market_order: bool,             // 0: open(false) 1: closed(true)
-            order_type: u16,                // 0: service, 1: inventory, 2: asset extensible
+            order_type: OrderType,          // Service(0), Goods(1), Inventory(2), Asset(3)
             deadline: u32,                  // prefunding acceptance deadline
             due_date: u32,                  // due date is the future delivery date (in blocks)
             order_item: OrderItem<T::Hash>, // for simple items there will only be one item, item number is accessed by its position in Vec
@@ -481,7 +790,8 @@ pub mod pallet {
 
         #[pallet::weight(0/*TODO*/)]
         /// Sets the approval status of an order
-        /// Can only be used by the nominated approver (must be known to the ordering party)
+        /// Can only be used by the nominated approver (must be known to the ordering party), or by an approver
+        /// of the team of the project of the order
         fn change_approval(
             origin: OriginFor<T>,
             h: T::Hash,
@@ -500,8 +810,9 @@ pub mod pallet {
 
         #[pallet::weight(0/*TODO*/)]
         /// Can be used by buyer or seller
-        /// Buyer - Used by the buyer to accept or reject (TODO) the invoice that was raised by the seller.
-        /// Seller - Used to accept, reject or invoice the order.
+        /// Buyer - Used by the buyer to accept or dispute the invoice that was raised by the seller.
+        /// Seller - Used to accept, reject, invoice or dispute the order.
+        #[transactional]
         fn handle_spfso(
             origin: OriginFor<T>,
             h: T::Hash,
@@ -518,15 +829,11 @@ pub mod pallet {
                 // This is the buyer
                 //TODO if the order us passed as an arg it doesn't need to be read again
                 Self::accept_prefunded_invoice(who.clone(), h.clone(), s, order_hdr.clone(), tx_uid)?;
-                Self::deposit_event(Event::InvoiceSettled(tx_uid));
+                Self::deposit_event(Event::OrderStatusUpdate(tx_uid));
             } else if who == fulfiller {
                 // This is the seller
                 //TODO if the order us passed as an arg it doesn't need to be read again
-                if let Err(_) =
-                    Self::set_state_simple_prefunded_closed_order(who.clone(), h.clone(), s, order_hdr.clone(), tx_uid)
-                {
-                    fail!(Error::<T>::ErrorSetPrefundState);
-                }
+                Self::set_state_simple_prefunded_closed_order(who.clone(), h.clone(), s, order_hdr.clone(), tx_uid)?;
             } else {
                 fail!(Error::<T>::ErrorURNobody)
             }
@@ -535,6 +842,274 @@ pub mod pallet {
 
             ok()
         }
+
+        #[pallet::weight(0/*TODO*/)]
+        /// Used by the buyer to assign the order to a project of which they are a team member, before it is approved.
+        /// The approvers of the project team can then approve the order, as well as the nominated approver.
+        fn set_order_project(
+            origin: OriginFor<T>,
+            h: T::Hash,
+            project_hash: T::Hash,
+            tx_uid: T::Hash,
+        ) -> DispatchResultWithPostInfo {
+            let who = ensure_signed(origin)?;
+            <T::Bonsai as Storing<T::Hash>>::store_uuid(tx_uid.clone())?;
+            let order_hdr = Self::orders(&h).ok_or(Error::<T>::ErrorGettingOrder)?;
+            if who != order_hdr.commander {
+                fail!(Error::<T>::ErrorURNobody);
+            }
+            if order_hdr.order_status != OrderStatus::Submitted {
+                fail!(Error::<T>::ErrorStatusTransition);
+            }
+            if order_hdr.approval_status == ApprovalStatus::Accepted {
+                fail!(Error::<T>::ErrorApproved);
+            }
+            if !T::Projects::has_team_role(who, project_hash.clone(), TeamRole::Member) {
+                fail!(Error::<T>::ErrorNotTeamMember);
+            }
+            OrderProject::<T>::insert(&h, project_hash);
+            Self::deposit_event(Event::OrderUpdated(tx_uid));
+
+            ok()
+        }
+
+        #[pallet::weight(0/*TODO*/)]
+        /// Used by the seller to set the sales taxes included in the invoices for the order, before invoicing.
+        fn set_order_taxes(
+            origin: OriginFor<T>,
+            h: T::Hash,
+            taxes: Vec<TaxLine<T::AccountId>>,
+            tx_uid: T::Hash,
+        ) -> DispatchResultWithPostInfo {
+            let who = ensure_signed(origin)?;
+            <T::Bonsai as Storing<T::Hash>>::store_uuid(tx_uid.clone())?;
+            let order_hdr = Self::orders(&h).ok_or(Error::<T>::ErrorGettingOrder)?;
+            if who != order_hdr.fulfiller {
+                fail!(Error::<T>::ErrorURNobody);
+            }
+            match order_hdr.order_status {
+                OrderStatus::Submitted | OrderStatus::Accepted => (),
+                _ => fail!(Error::<T>::ErrorStatusTransition),
+            }
+            Self::validate_taxes(&taxes)?;
+            OrderTaxes::<T>::insert(&h, taxes);
+            Self::deposit_event(Event::OrderUpdated(tx_uid));
+
+            ok()
+        }
+
+        #[pallet::weight(0/*TODO*/)]
+        /// Used by the seller to invoice a portion of an accepted order when a milestone is reached.
+        #[transactional]
+        fn invoice_milestone(
+            origin: OriginFor<T>,
+            h: T::Hash,
+            amount: u128,
+            tx_uid: T::Hash,
+        ) -> DispatchResultWithPostInfo {
+            let who = ensure_signed(origin)?;
+            <T::Bonsai as Storing<T::Hash>>::store_uuid(tx_uid.clone())?;
+            let order_hdr = Self::orders(&h).ok_or(Error::<T>::ErrorGettingOrder)?;
+            if who != order_hdr.fulfiller {
+                fail!(Error::<T>::ErrorURNobody);
+            }
+            if order_hdr.order_status != OrderStatus::Accepted {
+                fail!(Error::<T>::ErrorStatusTransition);
+            }
+            let (seller_account, buyer_account) = order_hdr.order_type.ledger_accounts();
+            let line = InvoiceLine {
+                amount: i128::try_from(amount).map_err(|_| Error::<T>::ErrorAmount)?,
+                seller_account,
+                buyer_account,
+            };
+            let lines = vec![line];
+            Self::record_project_cost(&h, &lines)?;
+            match <T::Prefunding as Encumbrance<T::AccountId, T::Hash, T::BlockNumber>>::send_milestone_invoice(
+                who,
+                lines,
+                h,
+                tx_uid,
+                Self::order_taxes(&h).unwrap_or_default(),
+            ) {
+                Ok(_) => (),
+                Err(_e) => fail!(Error::<T>::ErrorInPrefunding9),
+            }
+            Self::deposit_event(Event::OrderStatusUpdate(tx_uid));
+
+            ok()
+        }
+
+        #[pallet::weight(0/*TODO*/)]
+        /// Used by the buyer to approve and pay an invoiced milestone.
+        /// Paying the last of the prefunded amount completes the order.
+        #[transactional]
+        fn approve_milestone(
+            origin: OriginFor<T>,
+            h: T::Hash,
+            milestone: u16,
+            tx_uid: T::Hash,
+        ) -> DispatchResultWithPostInfo {
+            let who = ensure_signed(origin)?;
+            <T::Bonsai as Storing<T::Hash>>::store_uuid(tx_uid.clone())?;
+            let mut order_hdr = Self::orders(&h).ok_or(Error::<T>::ErrorGettingOrder)?;
+            if who != order_hdr.commander {
+                fail!(Error::<T>::ErrorURNobody);
+            }
+            if order_hdr.order_status != OrderStatus::Accepted {
+                fail!(Error::<T>::ErrorStatusTransition);
+            }
+            match <T::Prefunding as Encumbrance<T::AccountId, T::Hash, T::BlockNumber>>::settle_milestone(
+                who.clone(),
+                h,
+                milestone,
+                tx_uid,
+            ) {
+                Ok(_) => (),
+                Err(_e) => fail!(Error::<T>::ErrorInPrefunding10),
+            }
+            // Prefunding releases the reference once the prefunded amount has been paid in full
+            if !<T::Prefunding as Encumbrance<T::AccountId, T::Hash, T::BlockNumber>>::check_ref_owner(who, h) {
+                Self::transition(&mut order_hdr, OrderParty::Commander, OrderStatus::Completed)?;
+                Orders::<T>::insert(&h, order_hdr);
+                Self::deposit_event(Event::OrderCompleted(tx_uid));
+            } else {
+                Self::deposit_event(Event::OrderStatusUpdate(tx_uid));
+            }
+
+            ok()
+        }
+
+        #[pallet::weight(0/*TODO*/)]
+        /// Submits a proposal to fulfill a market order. Any identity other than the buyer can submit a proposal
+        /// with its own amount, deadline, due date and items. The proposal is stored as an order against the market order.
+        fn postulate_order(
+            origin: OriginFor<T>,
+            total_amount: i128,
+            deadline: u32,
+            due_date: u32,
+            order_items: Vec<OrderItem<T::Hash>>,
+            tx_keys_large: TXKeysL<T::Hash>,
+        ) -> DispatchResultWithPostInfo {
+            let who = ensure_signed(origin)?;
+            <T::Bonsai as Storing<T::Hash>>::store_uuid(tx_keys_large.tx_uid.clone())?;
+            Self::postulate_simple_prefunded_open_order(
+                who,
+                tx_keys_large.parent_id,
+                tx_keys_large.record_id,
+                total_amount,
+                deadline,
+                due_date,
+                order_items,
+            )?;
+            Self::deposit_event(Event::OrderPostulated(tx_keys_large.tx_uid, tx_keys_large.record_id));
+
+            ok()
+        }
+
+        #[pallet::weight(0/*TODO*/)]
+        /// Used by the buyer to accept one of the proposals against a market order.
+        /// The proposal becomes a closed order prefunded for the proposer and all the other proposals are rejected.
+        /// The proposer then accepts the order in the same way as any other prefunded order.
+        #[transactional]
+        fn accept_proposal(
+            origin: OriginFor<T>,
+            parent: T::Hash,
+            proposal: T::Hash,
+            tx_uid: T::Hash,
+        ) -> DispatchResultWithPostInfo {
+            let who = ensure_signed(origin)?;
+            <T::Bonsai as Storing<T::Hash>>::store_uuid(tx_uid.clone())?;
+            Self::accept_postulate(who, parent, proposal, tx_uid)?;
+            Self::deposit_event(Event::ProposalAccepted(tx_uid, proposal));
+
+            ok()
+        }
+
+        #[pallet::weight(0/*TODO*/)]
+        /// Can be used by buyer or seller of an order that is not prefunded
+        /// Buyer - Used to pay the invoice raised by the seller (Completed).
+        /// Seller - Used to accept (Accepted) or reject (Rejected) the order.
+        #[transactional]
+        fn handle_unfunded_order(
+            origin: OriginFor<T>,
+            h: T::Hash,
+            s: OrderStatus,
+            tx_uid: T::Hash,
+        ) -> DispatchResultWithPostInfo {
+            let who = ensure_signed(origin)?;
+            <T::Bonsai as Storing<T::Hash>>::store_uuid(tx_uid.clone())?;
+            let mut order_hdr = Self::orders(&h).ok_or(Error::<T>::ErrorGettingOrder)?;
+            if !Self::unfunded(&h) {
+                fail!(Error::<T>::ErrorPrefundedOrder);
+            }
+            let party = Self::order_party(&who, &order_hdr).ok_or(Error::<T>::ErrorURNobody)?;
+            Self::transition(&mut order_hdr, party, s)?;
+            match s {
+                // Seller accepts or rejects the order
+                OrderStatus::Accepted | OrderStatus::Rejected => (),
+                // Buyer pays the invoice
+                OrderStatus::Completed => {
+                    match <T::Prefunding as Encumbrance<T::AccountId, T::Hash, T::BlockNumber>>::settle_unfunded_invoice(
+                        who, h, tx_uid,
+                    ) {
+                        Ok(_) => (),
+                        Err(_e) => fail!(Error::<T>::ErrorInPrefunding12),
+                    }
+                }
+                // There are no prefunds for the arbiter to rule on
+                _ => fail!(Error::<T>::ErrorStatusTransition),
+            }
+            Orders::<T>::insert(&h, order_hdr);
+            match s {
+                OrderStatus::Completed => Self::deposit_event(Event::OrderCompleted(tx_uid)),
+                _ => Self::deposit_event(Event::OrderStatusUpdate(tx_uid)),
+            }
+
+            ok()
+        }
+
+        #[pallet::weight(0/*TODO*/)]
+        /// Used by the seller to invoice an accepted order that is not prefunded.
+        /// The buyer is expected to pay the invoice by the due date (in blocks).
+        #[transactional]
+        fn invoice_unfunded_order(
+            origin: OriginFor<T>,
+            h: T::Hash,
+            due_date: u32,
+            tx_uid: T::Hash,
+        ) -> DispatchResultWithPostInfo {
+            let who = ensure_signed(origin)?;
+            <T::Bonsai as Storing<T::Hash>>::store_uuid(tx_uid.clone())?;
+            let mut order_hdr = Self::orders(&h).ok_or(Error::<T>::ErrorGettingOrder)?;
+            if !Self::unfunded(&h) {
+                fail!(Error::<T>::ErrorPrefundedOrder);
+            }
+            if who != order_hdr.fulfiller {
+                fail!(Error::<T>::ErrorURNobody);
+            }
+            Self::transition(&mut order_hdr, OrderParty::Fulfiller, OrderStatus::Invoiced)?;
+            if order_hdr.amount < 0i128 {
+                fail!(Error::<T>::ErrorAmount);
+            }
+            let lines = Self::invoice_lines(&h, &order_hdr);
+            Self::record_project_cost(&h, &lines)?;
+            match <T::Prefunding as Encumbrance<T::AccountId, T::Hash, T::BlockNumber>>::send_unfunded_invoice(
+                who,
+                order_hdr.commander.clone(),
+                lines,
+                <T::OrderConversions as Convert<u32, T::BlockNumber>>::convert(due_date),
+                h,
+                tx_uid,
+                Self::order_taxes(&h).unwrap_or_default(),
+            ) {
+                Ok(_) => (),
+                Err(_e) => fail!(Error::<T>::ErrorInPrefunding11),
+            }
+            Orders::<T>::insert(&h, order_hdr);
+            Self::deposit_event(Event::OrderStatusUpdate(tx_uid));
+
+            ok()
+        }
     }
 
     #[pallet::event]
@@ -547,6 +1122,9 @@ pub mod pallet {
         OrderStatusUpdate(T::Hash),
         OrderCompleted(T::Hash),
         InvoiceSettled(T::Hash),
+        OrderPostulated(T::Hash, T::Hash),
+        ProposalAccepted(T::Hash, T::Hash),
+        ProposalRejected(T::Hash),
     }
 }
 
@@ -571,6 +1149,102 @@ impl<T: Config> Pallet<T> {
         approved
     }
 
+    /// Whether the identity is an approver of the team of the project the order is assigned to
+    fn is_team_approver(a: T::AccountId, h: &T::Hash) -> bool {
+        Self::order_project(h)
+            .map_or(false, |project_hash| T::Projects::has_team_role(a, project_hash, TeamRole::Approver))
+    }
+
+    /// Records the invoiced lines as costs of the project the order is assigned to, if any
+    fn record_project_cost(h: &T::Hash, lines: &[InvoiceLine]) -> DispatchResultWithPostInfo {
+        match Self::order_project(h) {
+            Some(project_hash) => {
+                let total = InvoiceLine::total(lines).ok_or(Error::<T>::ErrorItemsOverflow)?;
+                let amount = u128::try_from(total).map_err(|_| Error::<T>::ErrorAmount)?;
+                T::Projects::record_cost(project_hash, amount)
+            }
+            None => ok(),
+        }
+    }
+
+    /// The party of the order that the identity is, if any
+    fn order_party(who: &T::AccountId, order: &OrderHeader<T::AccountId>) -> Option<OrderParty> {
+        if who == &order.commander {
+            Some(OrderParty::Commander)
+        } else if who == &order.fulfiller {
+            Some(OrderParty::Fulfiller)
+        } else {
+            None
+        }
+    }
+
+    /// Changes the status of the order, provided the transition table allows the party to do so.
+    /// Every status change goes through here.
+    fn transition(
+        order: &mut OrderHeader<T::AccountId>,
+        party: OrderParty,
+        s: OrderStatus,
+    ) -> DispatchResultWithPostInfo {
+        order.order_status = order.order_status.transition(party, s).ok_or(Error::<T>::ErrorStatusTransition)?;
+
+        ok()
+    }
+
+    /// Checks that there are no more sales taxes than the maximum, that no rate is zero and that each jurisdiction is
+    /// taxed only once.
+    fn validate_taxes(taxes: &[TaxLine<T::AccountId>]) -> DispatchResultWithPostInfo {
+        match TaxLine::validate(taxes, T::MaxTaxLines::get()) {
+            Ok(()) => ok(),
+            Err(TaxLineError::TooMany) => fail!(Error::<T>::ErrorTooManyTaxLines),
+            Err(TaxLineError::ZeroRate) => fail!(Error::<T>::ErrorZeroTaxRate),
+            Err(TaxLineError::DuplicateJurisdiction) => fail!(Error::<T>::ErrorDuplicateJurisdiction),
+        }
+    }
+
+    /// Checks that the order has items, no more than the maximum, that every unit price is positive, and that the
+    /// amount is the sum of all the items unit prices * quantities.
+    fn validate_items(amount: i128, items: &[OrderItem<T::Hash>]) -> DispatchResultWithPostInfo {
+        if items.is_empty() {
+            fail!(Error::<T>::ErrorNoItems);
+        }
+        if items.len() > T::MaxOrderItems::get() as usize {
+            fail!(Error::<T>::ErrorTooManyItems);
+        }
+        if items.iter().any(|item| item.unit_price <= 0) {
+            fail!(Error::<T>::ErrorUnitPrice);
+        }
+        let total = items
+            .iter()
+            .try_fold(0i128, |total, item| {
+                let quantity = i128::try_from(item.quantity).ok()?;
+                item.unit_price.checked_mul(quantity)?.checked_add(total)
+            })
+            .ok_or(Error::<T>::ErrorItemsOverflow)?;
+        if total != amount {
+            fail!(Error::<T>::ErrorItemsTotal);
+        }
+
+        ok()
+    }
+
+    /// The invoice lines for the order, one for each item, posted to the ledger accounts of the order type.
+    /// Orders without items are invoiced as a single line for the order amount.
+    fn invoice_lines(h: &T::Hash, order: &OrderHeader<T::AccountId>) -> Vec<InvoiceLine> {
+        let (seller_account, buyer_account) = order.order_type.ledger_accounts();
+        let line = |amount| InvoiceLine {
+            amount,
+            seller_account,
+            buyer_account,
+        };
+        match Self::order_items(h) {
+            // The items were validated against the amount, so the line amounts cannot overflow
+            Some(items) if !items.is_empty() => {
+                items.iter().map(|item| line(item.unit_price.saturating_mul(item.quantity as i128))).collect()
+            }
+            _ => vec![line(order.amount)],
+        }
+    }
+
     /// API Open an order for a specific AccountId and prefund it. This is equivalent to an encumbrance.
     /// The amount is the functional currency and conversions are not necessary at this stage of accounting.
     /// The UI therefore handles presentation or reporting currency translations at spot rate
@@ -581,30 +1255,25 @@ impl<T: Config> Pallet<T> {
         commander: T::AccountId,
         approver: T::AccountId,
         fulfiller: T::AccountId,
-        buy_or_sell: u16,   // 0: buy, 1: sell, extensible
-        amount: i128,       // amount should be the sum of all the items untiprices * quantities
-        market_order: bool, // 0: open(false) 1: closed(true)
-        order_type: u16,    // 0: personal, 1: business, extensible
-        deadline: u32,      // prefunding acceptance deadline
-        due_date: u32,      // due date is the future delivery date (in blocks)
+        buy_or_sell: OrderDirection, // Buy(0), Sell(1)
+        amount: i128,                // amount should be the sum of all the items untiprices * quantities
+        market_order: bool,          // 0: open(false) 1: closed(true)
+        order_type: OrderType,       // Service(0), Goods(1), Inventory(2), Asset(3)
+        deadline: u32,               // prefunding acceptance deadline
+        due_date: u32,               // due date is the future delivery date (in blocks)
         order_hash: T::Hash,
         order_item: OrderItem<T::Hash>, // for simple items there will only be one item, item number is accessed by its position in Vec
         bonsai_token: T::Hash,
         uid: T::Hash,
     ) -> DispatchResultWithPostInfo {
         // Set order status to submitted by default
-        // submitted(0), accepted(1), rejected(2), disputed(3), blocked(4), invoiced(5),
-        let order_status: OrderStatus = 0;
-        let mut fulfiller_override: T::AccountId = fulfiller.clone();
+        let order_status = OrderStatus::Submitted;
+        Self::validate_items(amount, &[order_item.clone()])?;
 
-        // TODO Rewrite this MARKET_ORDER reversing the bool. This is because the API open_closed will be replaced by market_order bool.
-        // let mut market_order: bool = false;
         match market_order {
-            true => {
-                // market_order = true;
-                fulfiller_override = commander.clone();
-            }
-            // This is an open order. No need to check the fulfiller, but will override with the commander for time being.
+            // This is an open order. The fulfiller is not known yet so it cannot be prefunded here.
+            // Market orders are created without prefunding and prefunded once a proposal is accepted.
+            true => fail!(Error::<T>::ErrorMarketOrder3),
             false => {
                 // this is a closed order, still will need to check or set the approver status
                 // if fulfiller is the commander throw error
@@ -638,7 +1307,7 @@ impl<T: Config> Pallet<T> {
             }
             let order_header: OrderHeader<T::AccountId> = OrderHeader {
                 commander: commander.clone(),
-                fulfiller: fulfiller_override.clone(),
+                fulfiller: fulfiller.clone(),
                 approver: approver,
                 order_status: order_status,
                 approval_status: approval_status,
@@ -706,26 +1375,12 @@ impl<T: Config> Pallet<T> {
     fn change_approval_state(a: T::AccountId, h: T::Hash, s: ApprovalStatus, b: T::Hash) -> DispatchResultWithPostInfo {
         // is the supplied account the approver of the hash supplied?
         let mut order_hdr: OrderHeader<T::AccountId> = Self::orders(&h).ok_or("some error")?;
-        if a == order_hdr.approver && order_hdr.order_status == 0 {
-            match order_hdr.order_status {
-                0 | 2 => {
-                    // can only change to approved (1)
-                    match s {
-                        ApprovalStatus::Accepted => (),
-                        _ => fail!(Error::<T>::ErrorApprStatus),
-                    }
-                }
-                1 => {
-                    // Can only change to 0 or 2
-                    match s {
-                        ApprovalStatus::Submitted | ApprovalStatus::Rejected => (),
-                        _ => fail!(Error::<T>::ErrorApprStatus),
-                    }
-                }
-                _ => fail!(Error::<T>::ErrorApprStatus),
+        if a == order_hdr.approver || Self::is_team_approver(a.clone(), &h) {
+            // The approval can only change before the order is accepted
+            if order_hdr.order_status != OrderStatus::Submitted {
+                fail!(Error::<T>::ErrorStatusTransition);
             }
-            // All tests passed, set status to whatever.
-            order_hdr.order_status = s as u16;
+            order_hdr.approval_status = order_hdr.approval_status.transition(s).ok_or(Error::<T>::ErrorApprStatus)?;
             Orders::<T>::insert(&h, order_hdr);
         } else {
             fail!(Error::<T>::ErrorNotApprover);
@@ -751,35 +1406,35 @@ impl<T: Config> Pallet<T> {
     ) -> DispatchResultWithPostInfo {
         // Check that the hash exist
         // let order_hdr: OrderHeader<T::AccountId> = Self:order_header(&reference).ok_or("some error")?;
-        let order_hdr: OrderHeader<T::AccountId> = Self::orders(&reference).ok_or("some error")?;
-        // check that the Order state is 0 or 2 (submitted or rejected)
+        let mut order_hdr: OrderHeader<T::AccountId> = Self::orders(&reference).ok_or("some error")?;
+        // check that the Order state is 0 or 2 (submitted or rejected), a rejected order is submitted again
         // check that the approval is 0 or 2 pending approval or rejected
         match order_hdr.order_status {
-            0 | 2 => {
-                match order_hdr.approval_status {
-                    ApprovalStatus::Submitted | ApprovalStatus::Rejected => (), // submitted pending approval or rejected
-                    ApprovalStatus::Accepted => fail!(Error::<T>::ErrorApproved),
-                }
-            }
-            1 => fail!(Error::<T>::ErrorOrderStatus1),
-            _ => fail!(Error::<T>::ErrorOrderStatus2),
+            OrderStatus::Submitted => (),
+            OrderStatus::Rejected => Self::transition(&mut order_hdr, OrderParty::Commander, OrderStatus::Submitted)?,
+            _ => fail!(Error::<T>::ErrorStatusTransition),
         };
+        if order_hdr.approval_status == ApprovalStatus::Accepted {
+            fail!(Error::<T>::ErrorApproved);
+        }
         // check that at least one of these has changed:
         // let mut dl: u64;
         // let mut dd: u64;
         let current_block = frame_system::Module::<T>::block_number();
+        // only the commander can change the order
+        if order_hdr.commander != commander {
+            fail!(Error::<T>::ErrorURNobody);
+        }
         // apply a new fulfiller but check that it isn't the commander
-        if order_hdr.commander == commander {
+        if fulfiller == commander {
             fail!(Error::<T>::ErrorFulfiller);
         }
         if order_hdr.amount != amount {
             if amount < 0i128 {
                 fail!(Error::<T>::ErrorAmount);
             }
-
-            // IMPORTANT TODO
-            // Check that the amount is the sum of all the items
         }
+        Self::validate_items(amount, &[order_item.clone()])?;
 
         let current_block_converted: u32 =
             <T::OrderConversions as Convert<T::BlockNumber, u32>>::convert(current_block);
@@ -807,7 +1462,7 @@ impl<T: Config> Pallet<T> {
             commander: commander.clone(),
             fulfiller: fulfiller.clone(),
             approver: approver.clone(),
-            order_status: 0,
+            order_status: order_hdr.order_status,
             approval_status: order_hdr.approval_status,
             buy_or_sell: order_hdr.buy_or_sell,
             amount: amount,
@@ -816,8 +1471,6 @@ impl<T: Config> Pallet<T> {
             deadline: deadline,
             due_date: due_date,
         };
-        // currently just places all the items in the storage WITHOUT CHECKING
-        // TODO check for changes and confirm that amount = sum of all amounts
         let vec_order_items = vec![order_item];
         Self::set_order(order_hdr.commander, fulfiller, reference.clone(), order_header, vec_order_items)?;
         // prefunding can only be cancelled if deadline has passed, otherwise the prefunding remains as a deposit
@@ -839,71 +1492,71 @@ impl<T: Config> Pallet<T> {
         mut order: OrderHeader<T::AccountId>,
         uid: T::Hash,
     ) -> DispatchResultWithPostInfo {
-        match order.order_status {
-            0 => {
-                // Order not accepted yet. Update the status in this module
-                match s {
-                    1 => {
-                        // Order Accepted
-                        // Update the prefunding status (confirm locked funds)
-                        let lock: UnLocked<T> = <T::OrderConversions as Convert<bool, UnLocked<T>>>::convert(true);
-                        match <T::Prefunding as Encumbrance<T::AccountId, T::Hash, T::BlockNumber>>::set_release_state(
-                            f, lock, h, uid,
-                        ) {
-                            Ok(_) => (),
-                            Err(_e) => fail!(Error::<T>::ErrorInPrefunding2),
-                        }
+        use OrderStatus::*;
+
+        let status = order.order_status;
+        Self::transition(&mut order, OrderParty::Fulfiller, s)?;
+        match (status, s) {
+            // Order not accepted yet. Update the status in this module
+            (Submitted, Accepted) => {
+                // Order Accepted
+                // Update the prefunding status (confirm locked funds)
+                let lock: UnLocked<T> = <T::OrderConversions as Convert<bool, UnLocked<T>>>::convert(true);
+                match <T::Prefunding as Encumbrance<T::AccountId, T::Hash, T::BlockNumber>>::set_release_state(
+                    f, lock, h, uid,
+                ) {
+                    Ok(_) => (),
+                    Err(_e) => fail!(Error::<T>::ErrorInPrefunding2),
+                }
+            }
+            (Submitted, Rejected) => {
+                // order rejected
+                let lock: UnLocked<T> = <T::OrderConversions as Convert<bool, UnLocked<T>>>::convert(false);
+                // We do not need to set release state for releasing funds for fulfiller.
+                // set release state for releasing funds for commander.
+                match <T::Prefunding as Encumbrance<T::AccountId, T::Hash, T::BlockNumber>>::set_release_state(
+                    order.commander.clone(),
+                    lock,
+                    h,
+                    uid.clone(),
+                ) {
+                    Ok(_) => (),
+                    Err(_e) => {
+                        fail!(Error::<T>::ErrorInPrefunding3);
                     }
-                    2 => {
-                        // order rejected
-                        let lock: UnLocked<T> = <T::OrderConversions as Convert<bool, UnLocked<T>>>::convert(false);
-                        // We do not need to set release state for releasing funds for fulfiller.
-                        // set release state for releasing funds for commander.
-                        match <T::Prefunding as Encumbrance<T::AccountId, T::Hash, T::BlockNumber>>::set_release_state(
-                            order.commander.clone(),
-                            lock,
-                            h,
-                            uid.clone(),
-                        ) {
-                            Ok(_) => (),
-                            Err(_e) => {
-                                fail!(Error::<T>::ErrorInPrefunding3);
-                            }
-                        }
-                        // now release the funds lock
-                        match <T::Prefunding as Encumbrance<T::AccountId,T::Hash,T::BlockNumber>>::unlock_funds_for_owner(order.commander.clone(),h, uid.clone()) {
-                            Ok(_) => (),
-                            Err(_e) => {
-                                fail!(Error::<T>::ErrorInPrefunding4);
-                            },
-                        }
+                }
+                // now release the funds lock
+                match <T::Prefunding as Encumbrance<T::AccountId, T::Hash, T::BlockNumber>>::unlock_funds_for_owner(
+                    order.commander.clone(),
+                    h,
+                    uid.clone(),
+                ) {
+                    Ok(_) => (),
+                    Err(_e) => {
+                        fail!(Error::<T>::ErrorInPrefunding4);
                     }
-                    _ => fail!(Error::<T>::ErrorStatusNotAllowed1),
                 }
             }
-            // Order already in accepted state - Update the status
-            1 => {
-                match s {
-                    5 => {
-                        // Order Completed. Now we are going to issue the invoice.
-                        match <T::Prefunding as Encumbrance<T::AccountId, T::Hash, T::BlockNumber>>::send_simple_invoice(
-                            f.clone(),
-                            order.commander.clone(),
-                            order.amount,
-                            h,
-                            uid,
-                        ) {
-                            Ok(_) => (),
-                            Err(_e) => fail!(Error::<T>::ErrorInPrefunding5),
-                        }
-                    }
-                    _ => fail!(Error::<T>::ErrorStatusNotAllowed2),
+            // Order Completed. Now we are going to issue the invoice.
+            (Accepted, Invoiced) => {
+                let lines = Self::invoice_lines(&h, &order);
+                Self::record_project_cost(&h, &lines)?;
+                match <T::Prefunding as Encumbrance<T::AccountId, T::Hash, T::BlockNumber>>::send_simple_invoice(
+                    f.clone(),
+                    order.commander.clone(),
+                    lines,
+                    h,
+                    uid,
+                    Self::order_taxes(&h).unwrap_or_default(),
+                ) {
+                    Ok(_) => (),
+                    Err(_e) => fail!(Error::<T>::ErrorInPrefunding5),
                 }
             }
-            2 | 5 => fail!(Error::<T>::ErrorStatusNotAllowed3),
-            _ => fail!(Error::<T>::ErrorStatusNotAllowed4),
+            // Order disputed before invoicing, or invoice not accepted by the buyer
+            (_, Disputed) => Self::dispute_prefunded_order(f.clone(), h, uid)?,
+            _ => fail!(Error::<T>::ErrorStatusTransition),
         }
-        order.order_status = s;
         Orders::<T>::remove(&h);
         Orders::<T>::insert(&h, order);
 
@@ -912,7 +1565,7 @@ impl<T: Config> Pallet<T> {
         ok()
     }
 
-    /// Used by the buyer to accept or reject (TODO) the invoice that was raised by the seller.
+    /// Used by the buyer to accept or dispute the invoice that was raised by the seller.
     fn accept_prefunded_invoice(
         o: T::AccountId,
         h: T::Hash,
@@ -920,16 +1573,17 @@ impl<T: Config> Pallet<T> {
         mut order: OrderHeader<T::AccountId>,
         uid: T::Hash,
     ) -> DispatchResultWithPostInfo {
-        // check that this is the fulfiller
-        if order.order_status != 5 {
-            fail!(Error::<T>::ErrorOrderStatus3)
-        }
+        use OrderStatus::*;
+
+        // Only an invoiced order can be accepted, but the buyer can also dispute an order that has only been accepted
+        let status = order.order_status;
+        Self::transition(&mut order, OrderParty::Commander, s)?;
 
         // Order has been invoiced. The buyer is now deciding to accept or other
-        match s {
-            // Invoice is disputed. TODO provide the ability to change the invoice and resubmit
-            3 => fail!(Error::<T>::ErrorNotImplmented1),
-            6 => {
+        match (status, s) {
+            // Invoice is disputed. The prefunded funds stay locked until the arbiter rules.
+            (_, Disputed) => Self::dispute_prefunded_order(o.clone(), h, uid)?,
+            (Invoiced, Completed) => {
                 // Invoice Accepted. Now pay-up!.
                 match <T::Prefunding as Encumbrance<T::AccountId, T::Hash, T::BlockNumber>>::settle_prefunded_invoice(
                     o.clone(),
@@ -943,21 +1597,143 @@ impl<T: Config> Pallet<T> {
                 }
                 Self::deposit_event(Event::InvoiceSettled(uid));
             }
-            _ => fail!(Error::<T>::ErrorStatusNotAllowed5),
+            // Milestones are approved one at a time
+            _ => fail!(Error::<T>::ErrorStatusTransition),
         }
         // Update the status in this module
-
-        order.order_status = s;
         Orders::<T>::remove(&h);
         Orders::<T>::insert(&h, order);
 
         ok()
     }
 
+    /// Either party can dispute an accepted or invoiced order. The arbiter then rules on the release of the prefunded funds.
+    fn dispute_prefunded_order(o: T::AccountId, h: T::Hash, uid: T::Hash) -> DispatchResultWithPostInfo {
+        match <T::Prefunding as Encumbrance<T::AccountId, T::Hash, T::BlockNumber>>::raise_dispute(o, h, uid) {
+            Ok(_) => (),
+            Err(_e) => fail!(Error::<T>::ErrorInPrefunding8),
+        }
+
+        ok()
+    }
+
     /// This is used by any party that wants to accept a market order in whole or part.
     /// This is non-blocking and can accept many applicants
-    fn postulate_simple_prefunded_open_order() -> DispatchResultWithPostInfo {
-        fail!("TODO")
+    fn postulate_simple_prefunded_open_order(
+        f: T::AccountId,
+        parent: T::Hash,
+        h: T::Hash,
+        amount: i128,
+        deadline: u32,
+        due_date: u32,
+        items: Vec<OrderItem<T::Hash>>,
+    ) -> DispatchResultWithPostInfo {
+        if Orders::<T>::contains_key(&h) {
+            fail!(Error::<T>::ErrorHashExists);
+        }
+        let market_order = Self::orders(&parent).ok_or(Error::<T>::ErrorHashExists2)?;
+        if !market_order.market_order || market_order.order_status != OrderStatus::Submitted {
+            fail!(Error::<T>::ErrorMarketOrder2);
+        }
+        if f == market_order.commander {
+            fail!(Error::<T>::ErrorFulfiller);
+        }
+        if amount < 0i128 {
+            fail!(Error::<T>::ErrorAmount);
+        }
+        // The proposal must leave time to accept the prefunding and to deliver, within the dates of the market order
+        let current_block: u32 =
+            <T::OrderConversions as Convert<T::BlockNumber, u32>>::convert(frame_system::Module::<T>::block_number());
+        if deadline < current_block.saturating_add(11520_u32) {
+            fail!(Error::<T>::ErrorShortDeadline);
+        }
+        if due_date < current_block.saturating_add(11760_u32) || due_date <= deadline {
+            fail!(Error::<T>::ErrorShortDueDate);
+        }
+        if deadline > market_order.deadline || due_date > market_order.due_date {
+            fail!(Error::<T>::ErrorProposalDates);
+        }
+        Self::validate_items(amount, &items)?;
+        // The proposal is a closed order from the market order commander to the proposer
+        let order_header: OrderHeader<T::AccountId> = OrderHeader {
+            commander: market_order.commander.clone(),
+            fulfiller: f.clone(),
+            approver: market_order.approver,
+            order_status: OrderStatus::Submitted,
+            approval_status: market_order.approval_status,
+            buy_or_sell: market_order.buy_or_sell,
+            amount: amount,
+            market_order: false,
+            order_type: market_order.order_type,
+            deadline: deadline,
+            due_date: due_date,
+        };
+        Self::set_order(market_order.commander, f, h, order_header, items)?;
+        Postulate::<T>::append(&parent, h);
+
+        ok()
+    }
+
+    /// Accepts one proposal against the market order and prefunds it for the proposer. The other proposals are rejected
+    /// and the market order is closed.
+    fn accept_postulate(c: T::AccountId, parent: T::Hash, h: T::Hash, uid: T::Hash) -> DispatchResultWithPostInfo {
+        let mut market_order = Self::orders(&parent).ok_or(Error::<T>::ErrorHashExists2)?;
+        if c != market_order.commander {
+            fail!(Error::<T>::ErrorURNobody);
+        }
+        if !market_order.market_order {
+            fail!(Error::<T>::ErrorMarketOrder2);
+        }
+        // The market order has been fulfilled by the proposal
+        Self::transition(&mut market_order, OrderParty::Market, OrderStatus::Accepted)?;
+        if market_order.approval_status != ApprovalStatus::Accepted {
+            fail!(Error::<T>::ErrorNotApproved);
+        }
+        let proposals = Self::postulate(&parent).unwrap_or_default();
+        if !proposals.contains(&h) {
+            fail!(Error::<T>::ErrorNotProposal);
+        }
+        let mut order = Self::orders(&h).ok_or(Error::<T>::ErrorGettingOrder)?;
+
+        // Prefund the accepted proposal for the proposer
+        let balance_amount: u128 = <T::OrderConversions as Convert<i128, u128>>::convert(order.amount);
+        let deadline: T::BlockNumber = <T::OrderConversions as Convert<u32, T::BlockNumber>>::convert(order.deadline);
+        if let Err(_) = T::Prefunding::prefunding_for(c, order.fulfiller.clone(), balance_amount, deadline, h, uid) {
+            fail!(Error::<T>::ErrorInPrefunding13);
+        }
+        // The proposal is approved with the market order
+        if order.approval_status != ApprovalStatus::Accepted {
+            order.approval_status =
+                order.approval_status.transition(ApprovalStatus::Accepted).ok_or(Error::<T>::ErrorApprStatus)?;
+        }
+        Orders::<T>::insert(&h, order);
+
+        for proposal in proposals.into_iter().filter(|p| p != &h) {
+            Self::reject_proposal(proposal);
+        }
+
+        Orders::<T>::insert(&parent, market_order);
+        Postulate::<T>::remove(&parent);
+        MarketOrders::<T>::remove(&parent);
+
+        ok()
+    }
+
+    /// Rejects a proposal that was not accepted. Proposals that can no longer be rejected are left unchanged.
+    fn reject_proposal(h: T::Hash) {
+        let rejected = Orders::<T>::mutate(&h, |order| match order {
+            Some(order) if Self::transition(order, OrderParty::Market, OrderStatus::Rejected).is_ok() => {
+                // An approved proposal has its approval turned into a rejection
+                if let Some(approval_status) = order.approval_status.transition(ApprovalStatus::Rejected) {
+                    order.approval_status = approval_status;
+                }
+                true
+            }
+            _ => false,
+        });
+        if rejected {
+            Self::deposit_event(Event::ProposalRejected(h));
+        }
     }
 }
 
@@ -972,3 +1748,26 @@ impl<T: Config> Validating<T::AccountId, T::Hash> for Pallet<T> {
         }
     }
 }
+
+impl<T: Config> Arbitrating<T::Hash> for Pallet<T> {
+    /// Moves the disputed order out of dispute once the arbiter has ruled.
+    /// References that are not orders, such as escrows, are left alone.
+    fn dispute_resolved(h: T::Hash, released: bool, uid: T::Hash) -> DispatchResultWithPostInfo {
+        let mut order_hdr = match Self::orders(&h) {
+            Some(order_hdr) => order_hdr,
+            None => return ok(),
+        };
+        let s = match released {
+            true => OrderStatus::Completed,
+            false => OrderStatus::Rejected,
+        };
+        Self::transition(&mut order_hdr, OrderParty::Arbiter, s)?;
+        Orders::<T>::insert(&h, order_hdr);
+        match s {
+            OrderStatus::Completed => Self::deposit_event(Event::OrderCompleted(uid)),
+            _ => Self::deposit_event(Event::OrderStatusUpdate(uid)),
+        }
+
+        ok()
+    }
+}
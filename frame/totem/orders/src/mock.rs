@@ -2,112 +2,359 @@
 
 use crate as pallet_orders;
 
-use frame_support::{
-	parameter_types, ord_parameter_types, traits::{OnInitialize, OnFinalize, GenesisBuild},
-};
+use frame_support::{dispatch::DispatchResultWithPostInfo, parameter_types, traits::BalanceStatus};
+use frame_system::EnsureRoot;
+use pallet_prefunding::LockStatus;
 use sp_core::H256;
-use sp_runtime::{traits::{BlakeTwo256, IdentityLookup}, testing::Header};
+use sp_runtime::{
+    testing::Header,
+    traits::{BlakeTwo256, Convert, Hash, IdentityLookup},
+};
+use std::cell::RefCell;
+use totem_utils::ok;
+use totem_utils::traits::{
+    accounting::Posting,
+    bonsai::Storing,
+    teams::{Budgeting, Validating},
+};
+use totem_utils::types::TeamRole;
 
 type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
 type Block = frame_system::mocking::MockBlock<Test>;
 
+pub const BUYER: u64 = 1;
+pub const SELLER: u64 = 2;
+pub const OTHER_SELLER: u64 = 3;
+pub const APPROVER: u64 = 4;
+/// Holds the locked funds.
+pub const ESCROW: u64 = 99;
+
 // Configure a mock runtime to test the pallet.
 frame_support::construct_runtime!(
-	pub enum Test where
-		Block = Block,
-		NodeBlock = Block,
-		UncheckedExtrinsic = UncheckedExtrinsic,
-	{
-		System: frame_system::{Module, Call, Config, Storage, Event<T>},
-		Balances: pallet_balances::{Module, Call, Config<T>, Storage, Event<T>},
-		Orders: pallet_gilt::{Module, Call, Config, Storage, Event<T>},
-	}
+    pub enum Test where
+        Block = Block,
+        NodeBlock = Block,
+        UncheckedExtrinsic = UncheckedExtrinsic,
+    {
+        System: frame_system::{Module, Call, Config, Storage, Event<T>},
+        Balances: pallet_balances::{Module, Call, Config<T>, Storage, Event<T>},
+        Timestamp: pallet_timestamp::{Module, Call, Storage, Inherent},
+        Accounting: pallet_accounting::{Module, Call, Config, Storage, Event<T>},
+        Prefunding: pallet_prefunding::{Module, Call, Storage, Event<T>},
+        Orders: pallet_orders::{Module, Call, Storage, Event<T>},
+    }
 );
 
 parameter_types! {
-	pub const BlockHashCount: u64 = 250;
-	pub const SS58Prefix: u8 = 42;
+    pub const BlockHashCount: u64 = 250;
+    pub const SS58Prefix: u8 = 42;
 }
 
 impl frame_system::Config for Test {
-	type BaseCallFilter = ();
-	type BlockWeights = ();
-	type BlockLength = ();
-	type DbWeight = ();
-	type Origin = Origin;
-	type Call = Call;
-	type Index = u64;
-	type BlockNumber = u64;
-	type Hash = H256;
-	type Hashing = BlakeTwo256;
-	type AccountId = u64;
-	type Lookup = IdentityLookup<Self::AccountId>;
-	type Header = Header;
-	type Event = Event;
-	type BlockHashCount = BlockHashCount;
-	type Version = ();
-	type PalletInfo = PalletInfo;
-	type AccountData = pallet_balances::AccountData<u64>;
-	type OnNewAccount = ();
-	type OnKilledAccount = ();
-	type SystemWeightInfo = ();
-	type SS58Prefix = SS58Prefix;
+    type BaseCallFilter = ();
+    type BlockWeights = ();
+    type BlockLength = ();
+    type DbWeight = ();
+    type Origin = Origin;
+    type Call = Call;
+    type Index = u64;
+    type BlockNumber = u64;
+    type Hash = H256;
+    type Hashing = BlakeTwo256;
+    type AccountId = u64;
+    type Lookup = IdentityLookup<Self::AccountId>;
+    type Header = Header;
+    type Event = Event;
+    type BlockHashCount = BlockHashCount;
+    type Version = ();
+    type PalletInfo = PalletInfo;
+    type AccountData = pallet_balances::AccountData<u64>;
+    type OnNewAccount = ();
+    type OnKilledAccount = ();
+    type SystemWeightInfo = ();
+    type SS58Prefix = SS58Prefix;
 }
 
 parameter_types! {
-	pub const ExistentialDeposit: u64 = 1;
+    pub const ExistentialDeposit: u64 = 1;
 }
 
 impl pallet_balances::Config for Test {
-	type MaxLocks = ();
-	type Balance = u64;
-	type DustRemoval = ();
-	type Event = Event;
-	type ExistentialDeposit = ExistentialDeposit;
-	type AccountStore = System;
-	type WeightInfo = ();
+    type MaxLocks = ();
+    type Balance = u64;
+    type DustRemoval = ();
+    type Event = Event;
+    type ExistentialDeposit = ExistentialDeposit;
+    type AccountStore = System;
+    type WeightInfo = ();
+    type Accounting = MockAccounting;
 }
 
 parameter_types! {
-	pub const QueueCount: u32 = 3;
-	pub const MaxQueueLen: u32 = 3;
-	pub const FifoQueueLen: u32 = 1;
-	pub const Period: u64 = 3;
-	pub const MinFreeze: u64 = 2;
-	pub const IntakePeriod: u64 = 2;
-	pub const MaxIntakeBids: u32 = 2;
+    pub const MinimumPeriod: u64 = 5;
+}
+
+impl pallet_timestamp::Config for Test {
+    type Moment = u64;
+    type OnTimestampSet = ();
+    type MinimumPeriod = MinimumPeriod;
+    type WeightInfo = ();
+}
+
+impl pallet_accounting::Config for Test {
+    type Event = Event;
+    type AccountingConversions = MockConversions;
+    type ChartOfAccountsOrigin = EnsureRoot<u64>;
 }
-ord_parameter_types! {
-	pub const One: u64 = 1;
+
+parameter_types! {
+    pub const MaxExpiriesPerBlock: u32 = 2;
+    pub const MaxUnfundedInvoices: u32 = 2;
+    pub const MaxOrderItems: u32 = 3;
+    pub const MaxTaxLines: u32 = 2;
+}
+
+impl pallet_prefunding::Config for Test {
+    type Event = Event;
+    type Currency = Balances;
+    type PrefundingConversions = MockConversions;
+    type ArbitrationOrigin = EnsureRoot<u64>;
+    type MaxExpiriesPerBlock = MaxExpiriesPerBlock;
+    type MaxUnfundedInvoices = MaxUnfundedInvoices;
+    type MaxTaxLines = MaxTaxLines;
+    type Orders = Orders;
 }
 
 impl pallet_orders::Config for Test {
-	type Event = Event;
-	type OrderConversions = ();
-	type Accounting = ();
-	type Prefunding = ();
-	type Bonsai = ();
+    type Event = Event;
+    type OrderConversions = MockConversions;
+    type Accounting = MockAccounting;
+    type Prefunding = Prefunding;
+    type Bonsai = MockBonsai;
+    type Projects = MockProjects;
+    type MaxOrderItems = MaxOrderItems;
+    type MaxTaxLines = MaxTaxLines;
+}
+
+pub struct MockBonsai;
+
+impl Storing<H256> for MockBonsai {
+    fn claim_data(_r: H256, _d: H256) -> DispatchResultWithPostInfo {
+        ok()
+    }
+
+    fn store_uuid(_u: H256) -> DispatchResultWithPostInfo {
+        ok()
+    }
+}
+
+// There are no projects, orders are never assigned to one.
+pub struct MockProjects;
+
+impl Validating<u64, H256> for MockProjects {
+    fn is_project_owner(_o: u64, _h: H256) -> bool {
+        false
+    }
+
+    fn is_owner_and_project_valid(_o: u64, _h: H256) -> bool {
+        false
+    }
+
+    fn is_project_valid(_h: H256) -> bool {
+        false
+    }
+
+    fn get_project_owner(_h: H256) -> Option<u64> {
+        None
+    }
+
+    fn get_team_role(_o: u64, _h: H256) -> Option<TeamRole> {
+        None
+    }
+
+    fn has_team_role(_o: u64, _h: H256, _r: TeamRole) -> bool {
+        false
+    }
+}
+
+impl Budgeting<H256> for MockProjects {
+    fn record_time(_h: H256, _blocks: u64) -> DispatchResultWithPostInfo {
+        unimplemented!("Used as a mock, shouldn't be called")
+    }
+
+    fn release_time(_h: H256, _blocks: u64) -> DispatchResultWithPostInfo {
+        unimplemented!("Used as a mock, shouldn't be called")
+    }
+
+    fn record_cost(_h: H256, _amount: u128) -> DispatchResultWithPostInfo {
+        unimplemented!("Used as a mock, shouldn't be called")
+    }
+}
+
+/// The hash of the prefunded order that `commander` creates with `approver`.
+pub fn order_hash(commander: u64, approver: u64) -> H256 {
+    BlakeTwo256::hash_of(&(commander, approver))
+}
+
+/// A posting key: identity, account, amount, debit indicator, reference, change block and applicable block.
+pub type PostingKey = (u64, u64, i128, bool, H256, u64, u64);
+
+thread_local! {
+    static POSTED: RefCell<Vec<PostingKey>> = RefCell::new(Vec::new());
+}
+
+/// Takes the keys posted to the ledgers since the last call.
+pub fn posted() -> Vec<PostingKey> {
+    POSTED.with(|posted| posted.borrow_mut().drain(..).collect())
+}
+
+// Postings of the prefunding are recorded, those of the balances are accepted without being recorded.
+pub struct MockAccounting;
+
+impl Posting<u64, H256, u64, u64> for MockAccounting {
+    type Account = u64;
+    type PostingIndex = u128;
+    type LedgerBalance = i128;
+
+    fn handle_multiposting_amounts(keys: Vec<PostingKey>) -> DispatchResultWithPostInfo {
+        POSTED.with(|posted| posted.borrow_mut().extend(keys));
+        ok()
+    }
+
+    fn account_for_fees(_f: u64, _t: u64, _r: u64, _p: u64) -> DispatchResultWithPostInfo {
+        ok()
+    }
+
+    fn account_for_fee_revenue(_f: u64, _r: u64) -> DispatchResultWithPostInfo {
+        ok()
+    }
+
+    fn account_for_transfer(
+        _f: u64,
+        _fs: BalanceStatus,
+        _t: u64,
+        _ts: BalanceStatus,
+        _a: u64,
+    ) -> DispatchResultWithPostInfo {
+        ok()
+    }
+
+    fn account_for_reserve(_w: u64, _a: u64, _s: BalanceStatus) -> DispatchResultWithPostInfo {
+        ok()
+    }
+
+    fn account_for_slash(_w: u64, _a: u64, _s: BalanceStatus) -> DispatchResultWithPostInfo {
+        ok()
+    }
+
+    fn account_for_deposit(_w: u64, _a: u64, _s: BalanceStatus) -> DispatchResultWithPostInfo {
+        ok()
+    }
+
+    fn account_for_withdrawal(_w: u64, _a: u64, _s: BalanceStatus) -> DispatchResultWithPostInfo {
+        ok()
+    }
+
+    fn account_for_opening_balance(_w: u64) -> DispatchResultWithPostInfo {
+        ok()
+    }
+
+    fn record_sales_tax(_v: u64, _j: u64, _a: Self::LedgerBalance) -> DispatchResultWithPostInfo {
+        ok()
+    }
+
+    fn get_escrow_account() -> u64 {
+        ESCROW
+    }
+
+    fn get_pseudo_random_hash(s: u64, r: u64) -> H256 {
+        order_hash(s, r)
+    }
+}
+
+pub struct MockConversions;
+
+impl Convert<i128, u128> for MockConversions {
+    fn convert(u: i128) -> u128 {
+        u as u128
+    }
+}
+
+impl Convert<i128, u64> for MockConversions {
+    fn convert(u: i128) -> u64 {
+        u as u64
+    }
+}
+
+impl Convert<u64, i128> for MockConversions {
+    fn convert(u: u64) -> i128 {
+        u as i128
+    }
+}
+
+impl Convert<Vec<u8>, [u8; 8]> for MockConversions {
+    fn convert(u: Vec<u8>) -> [u8; 8] {
+        let mut id = [0u8; 8];
+        id.copy_from_slice(&u[..8]);
+        id
+    }
+}
+
+impl Convert<u64, u64> for MockConversions {
+    fn convert(u: u64) -> u64 {
+        u
+    }
+}
+
+impl Convert<u32, u64> for MockConversions {
+    fn convert(u: u32) -> u64 {
+        u as u64
+    }
+}
+
+impl Convert<u64, u32> for MockConversions {
+    fn convert(u: u64) -> u32 {
+        u as u32
+    }
+}
+
+impl Convert<i128, i128> for MockConversions {
+    fn convert(u: i128) -> i128 {
+        u
+    }
+}
+
+impl Convert<u128, i128> for MockConversions {
+    fn convert(u: u128) -> i128 {
+        u as i128
+    }
+}
+
+impl Convert<u64, u128> for MockConversions {
+    fn convert(u: u64) -> u128 {
+        u as u128
+    }
+}
+
+impl Convert<bool, LockStatus> for MockConversions {
+    fn convert(u: bool) -> LockStatus {
+        match u {
+            true => LockStatus::Locked,
+            false => LockStatus::Unlocked,
+        }
+    }
 }
 
 // This function basically just builds a genesis storage key/value store according to
 // our desired mockup.
 pub fn new_test_ext() -> sp_io::TestExternalities {
-	let mut t = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
-	pallet_balances::GenesisConfig::<Test>{
-		balances: vec![(1, 100), (2, 100), (3, 100), (4, 100)],
-	}.assimilate_storage(&mut t).unwrap();
-	GenesisBuild::<Test>::assimilate_storage(&crate::GenesisConfig, &mut t).unwrap();
-	t.into()
-}
-
-pub fn run_to_block(n: u64) {
-	while System::block_number() < n {
-		Orders::on_finalize(System::block_number());
-		Balances::on_finalize(System::block_number());
-		System::on_finalize(System::block_number());
-		System::set_block_number(System::block_number() + 1);
-		System::on_initialize(System::block_number());
-		Balances::on_initialize(System::block_number());
-		Orders::on_initialize(System::block_number());
-	}
+    let mut t = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+    pallet_balances::GenesisConfig::<Test> {
+        balances: vec![(BUYER, 10_000), (SELLER, 10_000), (OTHER_SELLER, 10_000)],
+    }
+    .assimilate_storage(&mut t)
+    .unwrap();
+    let mut ext: sp_io::TestExternalities = t.into();
+    ext.execute_with(|| System::set_block_number(1));
+    ext
 }
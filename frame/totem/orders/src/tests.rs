@@ -1,19 +1,575 @@
 #![cfg(test)]
 
+use crate::mock::*;
+use crate::{
+    ApprovalStatus, Call as OrdersCall, Error, OrderDirection, OrderItem, OrderStatus, OrderType, TXKeysL, TXKeysM,
+};
+
+use frame_support::{assert_noop, assert_ok, dispatch::DispatchResultWithPostInfo, traits::UnfilteredDispatchable};
+use sp_core::H256;
+use totem_utils::chart_of_accounts::LedgerAccount;
+
+/// The earliest prefunding deadline allowed in the first block.
+const DEADLINE: u32 = 1 + 11520;
+/// The earliest due date allowed in the first block.
+const DUE_DATE: u32 = 1 + 11760;
+/// The latest deadline of the proposals against the market order.
+const MARKET_DEADLINE: u32 = 2 * DEADLINE;
+/// The latest due date of the proposals against the market order.
+const MARKET_DUE_DATE: u32 = 2 * DUE_DATE;
+
+fn dispatch(who: u64, call: OrdersCall<Test>) -> DispatchResultWithPostInfo {
+    call.dispatch_bypass_filter(Origin::signed(who))
+}
+
+fn market() -> H256 {
+    H256::repeat_byte(1)
+}
+
+fn proposal(n: u8) -> H256 {
+    H256::repeat_byte(10 + n)
+}
+
+fn uid() -> H256 {
+    H256::repeat_byte(9)
+}
+
+fn free(who: u64) -> u64 {
+    Balances::free_balance(&who)
+}
+
+fn item(unit_price: i128, quantity: u128) -> OrderItem<H256> {
+    OrderItem {
+        product: H256::repeat_byte(7),
+        unit_price,
+        quantity,
+        unit_of_measure: 1,
+    }
+}
+
+fn tx_keys(record_id: H256, parent_id: H256) -> TXKeysL<H256> {
+    TXKeysL {
+        record_id,
+        parent_id,
+        bonsai_token: H256::zero(),
+        tx_uid: uid(),
+    }
+}
+
+fn order_status(h: H256) -> Option<(OrderStatus, ApprovalStatus)> {
+    Orders::orders(h).map(|order| (order.order_status, order.approval_status))
+}
+
+// The buyer posts a market order for 1000, approved by `approver`.
+fn create_market_order(approver: u64) -> DispatchResultWithPostInfo {
+    dispatch(
+        BUYER,
+        OrdersCall::create_order(
+            approver,
+            BUYER,
+            OrderDirection::Buy,
+            1000,
+            true,
+            OrderType::Service,
+            MARKET_DEADLINE,
+            MARKET_DUE_DATE,
+            vec![item(100, 10)],
+            tx_keys(market(), market()),
+        ),
+    )
+}
+
+// `who` proposes to fulfill the market order for `amount`, as the single item of the proposal `h`.
+fn postulate(who: u64, h: H256, amount: i128, deadline: u32, due_date: u32) -> DispatchResultWithPostInfo {
+    dispatch(who, OrdersCall::postulate_order(amount, deadline, due_date, vec![item(amount, 1)], tx_keys(h, market())))
+}
+
+fn accept_proposal(who: u64, h: H256) -> DispatchResultWithPostInfo {
+    dispatch(who, OrdersCall::accept_proposal(market(), h, uid()))
+}
+
+fn rejected_proposals() -> Vec<H256> {
+    System::events()
+        .into_iter()
+        .filter_map(|record| match record.event {
+            Event::pallet_orders(crate::Event::ProposalRejected(h)) => Some(h),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn accepted_proposal_is_prefunded_and_the_others_are_rejected() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(create_market_order(BUYER));
+        assert_eq!(Orders::market_orders(market()), Some(()));
+        assert_ok!(postulate(SELLER, proposal(1), 1000, DEADLINE, DUE_DATE));
+        assert_ok!(postulate(OTHER_SELLER, proposal(2), 900, DEADLINE, DUE_DATE));
+        assert_eq!(Orders::postulate(market()), Some(vec![proposal(1), proposal(2)]));
+
+        assert_ok!(accept_proposal(BUYER, proposal(1)));
+
+        // The proposal becomes a closed order prefunded for the proposer
+        let order = Orders::orders(proposal(1)).unwrap();
+        assert_eq!((order.commander, order.fulfiller, order.market_order), (BUYER, SELLER, false));
+        assert_eq!(order_status(proposal(1)), Some((OrderStatus::Submitted, ApprovalStatus::Accepted)));
+        assert_eq!((free(BUYER), free(ESCROW)), (9000, 1000));
+        assert_eq!(order_status(proposal(2)), Some((OrderStatus::Rejected, ApprovalStatus::Rejected)));
+        assert_eq!(rejected_proposals(), vec![proposal(2)]);
+        // The market order is closed
+        assert_eq!(order_status(market()).map(|(status, _)| status), Some(OrderStatus::Accepted));
+        assert_eq!(Orders::postulate(market()), None);
+        assert_eq!(Orders::market_orders(market()), None);
+
+        // The proposer accepts the order as any other prefunded order
+        assert_ok!(dispatch(SELLER, OrdersCall::handle_spfso(proposal(1), OrderStatus::Accepted, uid())));
+        assert_eq!(order_status(proposal(1)).map(|(status, _)| status), Some(OrderStatus::Accepted));
+    });
+}
+
+#[test]
+fn proposals_are_made_by_others_within_the_dates_of_the_market_order() {
+    new_test_ext().execute_with(|| {
+        assert_noop!(postulate(SELLER, proposal(1), 1000, DEADLINE, DUE_DATE), Error::<Test>::ErrorHashExists2);
+        assert_ok!(create_market_order(BUYER));
+
+        assert_noop!(postulate(BUYER, proposal(1), 1000, DEADLINE, DUE_DATE), Error::<Test>::ErrorFulfiller);
+        assert_noop!(postulate(SELLER, proposal(1), 1000, DEADLINE - 1, DUE_DATE), Error::<Test>::ErrorShortDeadline);
+        assert_noop!(postulate(SELLER, proposal(1), 1000, DUE_DATE, DUE_DATE), Error::<Test>::ErrorShortDueDate);
+        assert_noop!(
+            postulate(SELLER, proposal(1), 1000, MARKET_DEADLINE + 1, MARKET_DUE_DATE),
+            Error::<Test>::ErrorProposalDates
+        );
+        assert_noop!(
+            postulate(SELLER, proposal(1), 1000, DEADLINE, MARKET_DUE_DATE + 1),
+            Error::<Test>::ErrorProposalDates
+        );
+
+        assert_ok!(postulate(SELLER, proposal(1), 1000, MARKET_DEADLINE, MARKET_DUE_DATE));
+        assert_noop!(postulate(OTHER_SELLER, proposal(1), 900, DEADLINE, DUE_DATE), Error::<Test>::ErrorHashExists);
+    });
+}
+
+#[test]
+fn proposals_are_only_made_against_open_market_orders() {
+    new_test_ext().execute_with(|| {
+        // A closed order with a known fulfiller
+        assert_ok!(dispatch(
+            BUYER,
+            OrdersCall::create_order(
+                BUYER,
+                SELLER,
+                OrderDirection::Buy,
+                1000,
+                false,
+                OrderType::Service,
+                DEADLINE,
+                DUE_DATE,
+                vec![item(100, 10)],
+                tx_keys(market(), market()),
+            )
+        ));
+
+        assert_noop!(postulate(OTHER_SELLER, proposal(1), 1000, DEADLINE, DUE_DATE), Error::<Test>::ErrorMarketOrder2);
+    });
+}
+
+#[test]
+fn only_the_buyer_accepts_a_proposal_once_the_market_order_is_approved() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(create_market_order(APPROVER));
+        assert_ok!(postulate(SELLER, proposal(1), 1000, DEADLINE, DUE_DATE));
+
+        assert_noop!(accept_proposal(SELLER, proposal(1)), Error::<Test>::ErrorURNobody);
+        assert_noop!(accept_proposal(BUYER, proposal(1)), Error::<Test>::ErrorNotApproved);
+
+        let approve = OrdersCall::change_approval(market(), ApprovalStatus::Accepted, uid(), uid());
+        assert_ok!(dispatch(APPROVER, approve));
+        assert_noop!(accept_proposal(BUYER, proposal(2)), Error::<Test>::ErrorNotProposal);
+
+        assert_ok!(accept_proposal(BUYER, proposal(1)));
+        // The proposal is approved with the market order
+        assert_eq!(order_status(proposal(1)), Some((OrderStatus::Submitted, ApprovalStatus::Accepted)));
+
+        // The market order is closed to further proposals and acceptances
+        assert_noop!(accept_proposal(BUYER, proposal(1)), Error::<Test>::ErrorStatusTransition);
+        assert_noop!(postulate(OTHER_SELLER, proposal(2), 900, DEADLINE, DUE_DATE), Error::<Test>::ErrorMarketOrder2);
+    });
+}
+
+#[test]
+fn deleting_a_market_order_rejects_its_proposals() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(create_market_order(BUYER));
+        assert_ok!(postulate(SELLER, proposal(1), 1000, DEADLINE, DUE_DATE));
+
+        let keys = TXKeysM {
+            record_id: market(),
+            bonsai_token: H256::zero(),
+            tx_uid: uid(),
+        };
+        assert_ok!(dispatch(BUYER, OrdersCall::delete_order(keys)));
+
+        assert_eq!(Orders::orders(market()), None);
+        assert_eq!(Orders::market_orders(market()), None);
+        assert_eq!(Orders::postulate(market()), None);
+        assert_eq!(order_status(proposal(1)), Some((OrderStatus::Rejected, ApprovalStatus::Rejected)));
+        assert_eq!(rejected_proposals(), vec![proposal(1)]);
+        // Nothing was prefunded
+        assert_eq!(free(BUYER), 10_000);
+    });
+}
+
+fn reference() -> H256 {
+    H256::repeat_byte(2)
+}
+
+// The buyer prefunds a service order for 1000 with the seller, that is approved as the buyer is the approver.
+fn prefunded_order() -> H256 {
+    assert_ok!(dispatch(
+        BUYER,
+        OrdersCall::create_spfso(
+            BUYER,
+            SELLER,
+            OrderDirection::Buy,
+            1000,
+            false,
+            OrderType::Service,
+            DEADLINE,
+            DUE_DATE,
+            item(100, 10),
+            H256::zero(),
+            uid(),
+        )
+    ));
+    order_hash(BUYER, BUYER)
+}
+
+// The buyer orders the items from the seller on credit.
+fn create_unfunded_order(
+    h: H256,
+    amount: i128,
+    order_type: OrderType,
+    items: Vec<OrderItem<H256>>,
+) -> DispatchResultWithPostInfo {
+    dispatch(
+        BUYER,
+        OrdersCall::create_order(
+            BUYER,
+            SELLER,
+            OrderDirection::Buy,
+            amount,
+            false,
+            order_type,
+            DEADLINE,
+            DUE_DATE,
+            items,
+            tx_keys(h, h),
+        ),
+    )
+}
+
+fn handle(who: u64, h: H256, s: OrderStatus) -> DispatchResultWithPostInfo {
+    dispatch(who, OrdersCall::handle_spfso(h, s, uid()))
+}
+
+fn handle_unfunded(who: u64, h: H256, s: OrderStatus) -> DispatchResultWithPostInfo {
+    dispatch(who, OrdersCall::handle_unfunded_order(h, s, uid()))
+}
+
+#[test]
+fn prefunded_order_is_accepted_invoiced_and_paid() {
+    new_test_ext().execute_with(|| {
+        let h = prefunded_order();
+        assert_eq!(order_status(h), Some((OrderStatus::Submitted, ApprovalStatus::Accepted)));
+        assert_eq!((free(BUYER), free(ESCROW)), (9000, 1000));
+
+        assert_ok!(handle(SELLER, h, OrderStatus::Accepted));
+        assert_ok!(handle(SELLER, h, OrderStatus::Invoiced));
+        assert_eq!(order_status(h).map(|(status, _)| status), Some(OrderStatus::Invoiced));
+        assert_ok!(handle(BUYER, h, OrderStatus::Completed));
+
+        assert_eq!(order_status(h).map(|(status, _)| status), Some(OrderStatus::Completed));
+        assert_eq!((free(BUYER), free(SELLER), free(ESCROW)), (9000, 11_000, 0));
+    });
+}
+
+#[test]
+fn rejected_prefunded_order_returns_the_funds_to_the_buyer() {
+    new_test_ext().execute_with(|| {
+        let h = prefunded_order();
+
+        assert_ok!(handle(SELLER, h, OrderStatus::Rejected));
+
+        assert_eq!(order_status(h).map(|(status, _)| status), Some(OrderStatus::Rejected));
+        assert_eq!((free(BUYER), free(ESCROW)), (10_000, 0));
+        assert_noop!(handle(SELLER, h, OrderStatus::Accepted), Error::<Test>::ErrorStatusTransition);
+    });
+}
+
+#[test]
+fn illegal_transitions_of_a_prefunded_order_fail() {
+    new_test_ext().execute_with(|| {
+        let h = prefunded_order();
+
+        assert_noop!(handle(OTHER_SELLER, h, OrderStatus::Accepted), Error::<Test>::ErrorURNobody);
+        // Only the seller accepts the order, and it must be accepted before it is invoiced or disputed
+        assert_noop!(handle(BUYER, h, OrderStatus::Accepted), Error::<Test>::ErrorStatusTransition);
+        assert_noop!(handle(SELLER, h, OrderStatus::Invoiced), Error::<Test>::ErrorStatusTransition);
+        assert_noop!(handle(SELLER, h, OrderStatus::Completed), Error::<Test>::ErrorStatusTransition);
+        assert_noop!(handle(BUYER, h, OrderStatus::Disputed), Error::<Test>::ErrorStatusTransition);
+        // No transition leads to the blocked status
+        assert_noop!(handle(SELLER, h, OrderStatus::Blocked), Error::<Test>::ErrorStatusTransition);
+        // The prefunded order is not handled as an order on credit
+        assert_noop!(handle_unfunded(SELLER, h, OrderStatus::Accepted), Error::<Test>::ErrorPrefundedOrder);
+
+        assert_ok!(handle(SELLER, h, OrderStatus::Accepted));
+        assert_noop!(handle(SELLER, h, OrderStatus::Accepted), Error::<Test>::ErrorStatusTransition);
+        assert_noop!(handle(SELLER, h, OrderStatus::Rejected), Error::<Test>::ErrorStatusTransition);
+        // Only an invoiced order is paid
+        assert_noop!(handle(BUYER, h, OrderStatus::Completed), Error::<Test>::ErrorStatusTransition);
+
+        assert_ok!(handle(SELLER, h, OrderStatus::Invoiced));
+        assert_noop!(handle(SELLER, h, OrderStatus::Invoiced), Error::<Test>::ErrorStatusTransition);
+        assert_noop!(handle(SELLER, h, OrderStatus::Completed), Error::<Test>::ErrorStatusTransition);
+        assert_eq!(order_status(h).map(|(status, _)| status), Some(OrderStatus::Invoiced));
+    });
+}
+
+#[test]
+fn illegal_transitions_of_an_order_on_credit_fail() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(create_unfunded_order(reference(), 1000, OrderType::Service, vec![item(100, 10)]));
+        let invoice = |who: u64| dispatch(who, OrdersCall::invoice_unfunded_order(reference(), 100, uid()));
+
+        assert_noop!(handle_unfunded(OTHER_SELLER, reference(), OrderStatus::Accepted), Error::<Test>::ErrorURNobody);
+        assert_noop!(handle_unfunded(BUYER, reference(), OrderStatus::Accepted), Error::<Test>::ErrorStatusTransition);
+        assert_noop!(invoice(SELLER), Error::<Test>::ErrorStatusTransition);
+
+        assert_ok!(handle_unfunded(SELLER, reference(), OrderStatus::Accepted));
+        assert_noop!(invoice(BUYER), Error::<Test>::ErrorURNobody);
+        // There are no prefunds for an arbiter to rule on
+        assert_noop!(handle_unfunded(SELLER, reference(), OrderStatus::Disputed), Error::<Test>::ErrorStatusTransition);
+        assert_noop!(handle_unfunded(BUYER, reference(), OrderStatus::Invoiced), Error::<Test>::ErrorStatusTransition);
+
+        assert_ok!(invoice(SELLER));
+        assert_noop!(invoice(SELLER), Error::<Test>::ErrorStatusTransition);
+        assert_noop!(
+            handle_unfunded(SELLER, reference(), OrderStatus::Completed),
+            Error::<Test>::ErrorStatusTransition
+        );
+        assert_eq!(order_status(reference()).map(|(status, _)| status), Some(OrderStatus::Invoiced));
+    });
+}
+
+#[test]
+fn rejected_order_is_changed_and_submitted_again_by_the_buyer() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(create_unfunded_order(reference(), 1000, OrderType::Service, vec![item(100, 10)]));
+        let change = |who: u64, fulfiller: u64| {
+            dispatch(
+                who,
+                OrdersCall::change_spfso(
+                    BUYER,
+                    fulfiller,
+                    1200,
+                    DEADLINE,
+                    DUE_DATE,
+                    item(120, 10),
+                    reference(),
+                    H256::zero(),
+                    uid(),
+                ),
+            )
+        };
+        assert_ok!(handle_unfunded(SELLER, reference(), OrderStatus::Rejected));
+
+        assert_noop!(change(SELLER, OTHER_SELLER), Error::<Test>::ErrorURNobody);
+        assert_noop!(change(BUYER, BUYER), Error::<Test>::ErrorFulfiller);
+        assert_ok!(change(BUYER, OTHER_SELLER));
+
+        let order = Orders::orders(reference()).unwrap();
+        assert_eq!((order.order_status, order.fulfiller, order.amount), (OrderStatus::Submitted, OTHER_SELLER, 1200));
+        assert_ok!(handle_unfunded(OTHER_SELLER, reference(), OrderStatus::Accepted));
+        // An accepted order can no longer be changed
+        assert_noop!(change(BUYER, OTHER_SELLER), Error::<Test>::ErrorStatusTransition);
+    });
+}
+
+#[test]
+fn approval_changes_follow_the_approval_transitions() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(create_unfunded_order(reference(), 1000, OrderType::Service, vec![item(100, 10)]));
+        let approve =
+            |who: u64, s: ApprovalStatus| dispatch(who, OrdersCall::change_approval(reference(), s, uid(), uid()));
+
+        assert_noop!(approve(SELLER, ApprovalStatus::Accepted), Error::<Test>::ErrorNotApprover);
+        assert_noop!(approve(BUYER, ApprovalStatus::Submitted), Error::<Test>::ErrorApprStatus);
+        assert_noop!(approve(BUYER, ApprovalStatus::Rejected), Error::<Test>::ErrorApprStatus);
+        assert_ok!(approve(BUYER, ApprovalStatus::Accepted));
+        assert_noop!(approve(BUYER, ApprovalStatus::Accepted), Error::<Test>::ErrorApprStatus);
+        assert_ok!(approve(BUYER, ApprovalStatus::Rejected));
+        assert_ok!(approve(BUYER, ApprovalStatus::Accepted));
+        assert_eq!(order_status(reference()), Some((OrderStatus::Submitted, ApprovalStatus::Accepted)));
+
+        // The approval can only change before the order is accepted
+        assert_ok!(handle_unfunded(SELLER, reference(), OrderStatus::Accepted));
+        assert_noop!(approve(BUYER, ApprovalStatus::Rejected), Error::<Test>::ErrorStatusTransition);
+    });
+}
+
+// The amounts posted to the ledger `account` of `who`, in the order they were posted.
+fn posted_to(keys: &[PostingKey], who: u64, account: LedgerAccount) -> Vec<i128> {
+    keys.iter().filter(|key| key.0 == who && key.1 == account.number()).map(|key| key.2).collect()
+}
+
 #[test]
-fn basic_setup_works() {
-	new_test_ext().execute_with(|| {
-		run_to_block(1);
-
-		for q in 0..3 {
-			assert!(Queues::<Test>::get(q).is_empty());
-		}
-		assert_eq!(ActiveTotal::<Test>::get(), ActiveGiltsTotal {
-			frozen: 0,
-			proportion: Perquintill::zero(),
-			index: 0,
-			target: Perquintill::zero(),
-		});
-		assert_eq!(QueueTotals::<Test>::get(), vec![(0, 0); 3]);
-	});
+fn order_items_must_add_up_to_the_amount() {
+    new_test_ext().execute_with(|| {
+        let create = |amount: i128, items: Vec<OrderItem<H256>>| {
+            create_unfunded_order(reference(), amount, OrderType::Goods, items)
+        };
+
+        assert_noop!(create(0, vec![]), Error::<Test>::ErrorNoItems);
+        assert_noop!(create(400, vec![item(100, 1); 4]), Error::<Test>::ErrorTooManyItems);
+        assert_noop!(create(0, vec![item(0, 10)]), Error::<Test>::ErrorUnitPrice);
+        assert_noop!(create(-1000, vec![item(-100, 10)]), Error::<Test>::ErrorUnitPrice);
+        assert_noop!(create(999, vec![item(100, 10)]), Error::<Test>::ErrorItemsTotal);
+        assert_noop!(create(1000, vec![item(100, 10), item(100, 1)]), Error::<Test>::ErrorItemsTotal);
+        // The line amounts and their total are checked for overflow
+        assert_noop!(create(0, vec![item(i128::MAX, 2)]), Error::<Test>::ErrorItemsOverflow);
+        assert_noop!(create(0, vec![item(i128::MAX, 1), item(1, 1)]), Error::<Test>::ErrorItemsOverflow);
+        assert_noop!(create(0, vec![item(1, u128::MAX)]), Error::<Test>::ErrorItemsOverflow);
+
+        assert_ok!(create(1000, vec![item(100, 3), item(350, 2)]));
+        assert_eq!(Orders::order_items(reference()), Some(vec![item(100, 3), item(350, 2)]));
+    });
+}
+
+#[test]
+fn prefunded_orders_and_proposals_must_add_up_to_the_amount() {
+    new_test_ext().execute_with(|| {
+        assert_noop!(
+            dispatch(
+                BUYER,
+                OrdersCall::create_spfso(
+                    BUYER,
+                    SELLER,
+                    OrderDirection::Buy,
+                    999,
+                    false,
+                    OrderType::Service,
+                    DEADLINE,
+                    DUE_DATE,
+                    item(100, 10),
+                    H256::zero(),
+                    uid(),
+                )
+            ),
+            Error::<Test>::ErrorItemsTotal
+        );
+        assert_eq!(free(BUYER), 10_000);
+
+        assert_ok!(create_market_order(BUYER));
+        let items = vec![item(100, 9), item(i128::MAX, 1)];
+        assert_noop!(
+            dispatch(
+                SELLER,
+                OrdersCall::postulate_order(900, DEADLINE, DUE_DATE, items, tx_keys(proposal(1), market()))
+            ),
+            Error::<Test>::ErrorItemsOverflow
+        );
+        let items = vec![item(100, 9)];
+        assert_noop!(
+            dispatch(
+                SELLER,
+                OrdersCall::postulate_order(1000, DEADLINE, DUE_DATE, items, tx_keys(proposal(1), market()))
+            ),
+            Error::<Test>::ErrorItemsTotal
+        );
+    });
+}
+
+#[test]
+fn invoice_posts_each_item_to_the_accounts_of_the_order_type() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(create_unfunded_order(reference(), 1000, OrderType::Goods, vec![item(100, 3), item(350, 2)]));
+        assert_ok!(handle_unfunded(SELLER, reference(), OrderStatus::Accepted));
+
+        assert_ok!(dispatch(SELLER, OrdersCall::invoice_unfunded_order(reference(), 100, uid())));
+
+        let keys = posted();
+        assert_eq!(posted_to(&keys, SELLER, LedgerAccount::GOODS_SALES), vec![300, 700]);
+        assert_eq!(posted_to(&keys, BUYER, LedgerAccount::PURCHASES), vec![300, 700]);
+        assert_eq!(posted_to(&keys, SELLER, LedgerAccount::ACCOUNTS_RECEIVABLE), vec![1000]);
+        assert_eq!(posted_to(&keys, BUYER, LedgerAccount::ACCOUNTS_PAYABLE), vec![1000]);
+        assert!(posted_to(&keys, SELLER, LedgerAccount::SALES).is_empty());
+
+        assert_ok!(handle_unfunded(BUYER, reference(), OrderStatus::Completed));
+        assert_eq!(order_status(reference()).map(|(status, _)| status), Some(OrderStatus::Completed));
+        assert_eq!((free(BUYER), free(SELLER)), (9000, 11_000));
+    });
+}
+
+#[test]
+fn invoice_of_a_prefunded_service_order_posts_to_sales_and_labour() {
+    new_test_ext().execute_with(|| {
+        let h = prefunded_order();
+        assert_ok!(handle(SELLER, h, OrderStatus::Accepted));
+        posted();
+
+        assert_ok!(handle(SELLER, h, OrderStatus::Invoiced));
+
+        let keys = posted();
+        assert_eq!(posted_to(&keys, SELLER, LedgerAccount::SALES), vec![1000]);
+        assert_eq!(posted_to(&keys, BUYER, LedgerAccount::LABOUR), vec![1000]);
+        assert!(posted_to(&keys, SELLER, LedgerAccount::GOODS_SALES).is_empty());
+    });
+}
+
+// The seller accepts the prefunded order, the buyer disputes it and the arbitration origin releases `release` to the
+// seller.
+fn rule_on_disputed_order(release: u128) -> H256 {
+    let h = prefunded_order();
+    assert_ok!(handle(SELLER, h, OrderStatus::Accepted));
+    assert_ok!(handle(BUYER, h, OrderStatus::Disputed));
+    assert_eq!(order_status(h).map(|(status, _)| status), Some(OrderStatus::Disputed));
+    // The disputed order waits for the ruling
+    assert_noop!(handle(SELLER, h, OrderStatus::Invoiced), Error::<Test>::ErrorStatusTransition);
+
+    let ruling = pallet_prefunding::Call::<Test>::resolve_dispute(h, release, uid());
+    assert_ok!(ruling.dispatch_bypass_filter(Origin::root()));
+    h
+}
+
+#[test]
+fn ruling_releasing_funds_completes_the_disputed_order() {
+    new_test_ext().execute_with(|| {
+        let h = rule_on_disputed_order(1000);
+
+        assert_eq!(order_status(h).map(|(status, _)| status), Some(OrderStatus::Completed));
+        assert_eq!((free(BUYER), free(SELLER), free(ESCROW)), (9000, 11_000, 0));
+    });
+}
+
+#[test]
+fn ruling_releasing_part_of_the_funds_completes_the_disputed_order() {
+    new_test_ext().execute_with(|| {
+        let h = rule_on_disputed_order(400);
+
+        assert_eq!(order_status(h).map(|(status, _)| status), Some(OrderStatus::Completed));
+        assert_eq!((free(BUYER), free(SELLER), free(ESCROW)), (9600, 10_400, 0));
+    });
+}
+
+#[test]
+fn ruling_releasing_no_funds_rejects_the_disputed_order() {
+    new_test_ext().execute_with(|| {
+        let h = rule_on_disputed_order(0);
+
+        assert_eq!(order_status(h).map(|(status, _)| status), Some(OrderStatus::Rejected));
+        assert_eq!((free(BUYER), free(SELLER), free(ESCROW)), (10_000, 10_000, 0));
+        // The rejected order cannot be disputed again
+        assert_noop!(handle(BUYER, h, OrderStatus::Disputed), Error::<Test>::ErrorStatusTransition);
+    });
 }
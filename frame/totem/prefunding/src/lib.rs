@@ -37,33 +37,50 @@
 //
 // This module functions as a pseudo-escrow module, holding funds for a specified period of time and or for a specific beneficiary.
 // In addition to locking funds until a deadline, this module also updates the accounting ledger showing that the assets have moved.
-// There is no automatic release of funds from the locked state so requires that the either the deadline to have past to allow withdrawal
-// or the intervention of the permitted party to withdraw the funds.
+// Funds are released from the locked state when the deadline has passed without the beneficiary accepting, or by the intervention
+// of the permitted party. Prefunding that is not accepted by the deadline expires automatically at the start of the next block:
+// the funds are unlocked for the owner and the prefunding is reversed in the ledgers of the owner. The number of references
+// expired in a block is bounded, and the remainder is carried over to the following block.
 //
 // For the initial use of this prefunding module the intended beneficiary is identified by AccountId.
 // In a later version there may be no intended beneficiary (for example for marketplace transactions)
 // and therefore the funds may be locked until a cadidate secures the funds.
 //
-// A further scenario is forseen where a dispute resolution method that relies upon an independent validator
-// is required to set the lock-release state.
+// Once both parties have locked the funds either of them can raise a dispute. The funds then stay locked until
+// an independent arbiter, nominated by the owner before the order was accepted, or the arbitration origin
+// rules on how much of the prefunded amount is released to the beneficiary. The remainder is returned to the owner
+// and the prefunding (and the invoice if one was issued) is reversed in the ledgers of both parties.
+//
+// Invoices can also be issued on credit, without any prefunding. The receivable and the payable are posted
+// when the invoice is issued, and cleared when the buyer pays the invoice. Unpaid invoices past their due date
+// are listed as overdue.
+//
+// Other pallets can hold funds in escrow through the `Escrow` trait, without order semantics. The owner releases the funds to
+// the beneficiary in full or in part, or the funds are refunded, and the escrow is posted to the ledgers of both parties.
 
 #![cfg_attr(not(feature = "std"), no_std)]
 
+mod mock;
+mod tests;
+
 use frame_support::{
     dispatch::EncodeLike,
     fail,
     pallet_prelude::*,
+    storage::{with_transaction, TransactionOutcome},
     traits::{Currency, LockIdentifier, WithdrawReasons, ExistenceRequirement},
+    transactional,
 };
 use frame_system::pallet_prelude::*;
 use pallet_balances::totem::TotemLockableCurrency;
 
-use sp_runtime::traits::{Convert, Hash};
+use sp_runtime::traits::{Convert, Hash, One, Saturating, Zero};
 use sp_std::{prelude::*, vec};
 
-use totem_utils::traits::{accounting::Posting, prefunding::Encumbrance};
+use totem_utils::chart_of_accounts::LedgerAccount;
+use totem_utils::traits::{accounting::Posting, escrow::Escrow, orders::Arbitrating, prefunding::Encumbrance};
 use totem_utils::{ok, StorageMapExt};
-use totem_utils::types::ComparisonAmounts;
+use totem_utils::types::{ComparisonAmounts, EscrowState, InvoiceLine, TaxLine, TaxLineError};
 
 type AccountOf<T> = <<T as pallet_balances::Config>::Accounting as Posting<
     <T as frame_system::Config>::AccountId,
@@ -125,6 +142,48 @@ pub mod pallet {
     /// Tracking to ensure that we can perform housekeeping on finalization of block.
     pub type ReferenceStatus<T: Config> = StorageMap<_, Blake2_128Concat, T::Hash, Status>;
 
+    #[pallet::storage]
+    #[pallet::getter(fn arbiter)]
+    /// Arbiter nominated by the owner to rule on a dispute over the reference.
+    pub type Arbiter<T: Config> = StorageMap<_, Blake2_128Concat, T::Hash, T::AccountId>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn dispute)]
+    /// Open disputes: the party that raised the dispute and the status of the reference before the dispute.
+    pub type Dispute<T: Config> = StorageMap<_, Blake2_128Concat, T::Hash, (T::AccountId, Status)>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn milestones)]
+    /// Milestones invoiced against the reference: the invoiced amount and its status, invoiced(400) or settled(500).
+    pub type Milestones<T: Config> = StorageMap<_, Blake2_128Concat, T::Hash, Vec<(CurrencyBalanceOf<T>, Status)>>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn invoice_taxes)]
+    /// Sales taxes included in the invoices against the reference. All invoices against a reference carry the same taxes.
+    pub type InvoiceTaxes<T: Config> = StorageMap<_, Blake2_128Concat, T::Hash, Vec<TaxLine<T::AccountId>>>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn invoice_lines)]
+    /// Lines of the first invoice against the reference. Their accounts and the proportions of their amounts are used to
+    /// release escrowed funds and to reverse the invoices if the reference is disputed.
+    pub type InvoiceLines<T: Config> = StorageMap<_, Blake2_128Concat, T::Hash, Vec<InvoiceLine>>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn unfunded_invoice)]
+    /// Invoices issued on credit: the seller, the buyer, the invoiced amount and the due date for payment.
+    pub type UnfundedInvoice<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::Hash, (T::AccountId, T::AccountId, CurrencyBalanceOf<T>, T::BlockNumber)>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn unfunded_invoice_list)]
+    /// Unpaid invoices issued on credit, by seller and by buyer. At most `MaxUnfundedInvoices` per account.
+    pub type UnfundedInvoiceList<T: Config> = StorageMap<_, Blake2_128Concat, T::AccountId, Vec<T::Hash>>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn expiring)]
+    /// References whose prefunding expires at the start of the block, unless the beneficiary has accepted it by then.
+    pub type Expiring<T: Config> = StorageMap<_, Blake2_128Concat, T::BlockNumber, Vec<T::Hash>, ValueQuery>;
+
     #[pallet::config] //TODO declare configs that are constant
     pub trait Config:
         frame_system::Config + pallet_balances::Config + pallet_timestamp::Config + pallet_accounting::Config
@@ -143,6 +202,19 @@ pub mod pallet {
             + Convert<u128, i128>
             + Convert<AccountBalanceOf<Self>, i128>
             + Convert<CurrencyBalanceOf<Self>, u128>;
+        /// Origin allowed to rule on any dispute, in addition to the arbiter nominated for the reference.
+        type ArbitrationOrigin: EnsureOrigin<Self::Origin>;
+        /// The maximum number of references expired at the start of a block.
+        #[pallet::constant]
+        type MaxExpiriesPerBlock: Get<u32>;
+        /// The maximum number of unpaid invoices issued on credit that an account can be a party to.
+        #[pallet::constant]
+        type MaxUnfundedInvoices: Get<u32>;
+        /// The maximum number of sales taxes on an invoice.
+        #[pallet::constant]
+        type MaxTaxLines: Get<u32>;
+        /// The orders whose prefunding is disputed, updated with the ruling of the arbiter.
+        type Orders: Arbitrating<Self::Hash>;
     }
 
     #[pallet::error]
@@ -233,10 +305,92 @@ pub mod pallet {
         ErrorCancelFailed,
         /// Cancelling prefunding failed for some reason
         ErrorCancelFailed2,
+        /// Hash does not exist
+        ErrorHashDoesNotExist4,
+        /// Only the owner can nominate an arbiter, before the order is accepted
+        ErrorArbiterNotAllowed,
+        /// The arbiter cannot be the owner or the beneficiary
+        ErrorArbiterIsParty,
+        /// You are not the owner or the beneficiary
+        ErrorNotAllowed7,
+        /// A dispute can only be raised when funds are locked for intended purpose by both parties.
+        ErrorDisputeNotAllowed,
+        /// The reference is disputed. Wait for the arbiter to rule.
+        ErrorDisputed,
+        /// The reference is not disputed
+        ErrorNotDisputed,
+        /// You are not the arbiter for this reference
+        ErrorNotArbiter,
+        /// Cannot release more than the prefunded amount
+        ErrorReleaseTooHigh,
+        /// An error occured posting to accounts - settle dispute
+        ErrorInAccounting4,
+        /// The order is invoiced by milestones
+        ErrorMilestonesInvoiced,
+        /// Milestones can only be invoiced and settled once the order is accepted and until it is invoiced in full
+        ErrorMilestoneNotAllowed,
+        /// Milestones cannot be zero or be invoiced beyond the prefunded amount
+        ErrorMilestoneAmount,
+        /// The milestone does not exist or is already settled
+        ErrorNoMilestone,
+        /// An error occured posting to accounts - milestone invoice
+        ErrorInAccounting5,
+        /// An error occured posting to accounts - settle milestone
+        ErrorInAccounting6,
+        /// The taxes must be the same for all invoices against the reference
+        ErrorTaxesChanged,
+        /// The taxes could not be calculated for this amount
+        ErrorTaxCalculation,
+        /// Cannot invoice yourself, or invoice nothing
+        ErrorInvoiceNotAllowed,
+        /// The due date must be in the future
+        ErrorDueDate,
+        /// The invoice does not exist or is already paid
+        ErrorNoInvoice,
+        /// An error occured posting to accounts - unfunded invoice
+        ErrorInAccounting7,
+        /// An error occured posting to accounts - settle unfunded invoice
+        ErrorInAccounting8,
+        /// An error occured posting to accounts - cancel prefunding
+        ErrorInAccounting9,
+        /// The owner cannot be the beneficiary of the escrow
+        ErrorEscrowParty,
+        /// The amount released from escrow cannot be zero
+        ErrorEscrowAmount,
+        /// The escrow cannot be released or refunded in its current state, or not by you
+        ErrorEscrowNotAllowed,
+        /// An error occured posting to accounts - release escrow
+        ErrorInAccounting10,
+        /// The seller or the buyer has too many unpaid invoices issued on credit
+        ErrorTooManyInvoices,
+        /// The invoice has too many sales taxes
+        ErrorTooManyTaxLines,
+        /// A sales tax rate cannot be zero
+        ErrorZeroTaxRate,
+        /// Each tax jurisdiction can only be taxed once
+        ErrorDuplicateJurisdiction,
+        /// The amounts overflow
+        ErrorAmountOverflow,
     }
 
     #[pallet::hooks]
-    impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {}
+    impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+        /// Expires the prefunding whose deadline has passed without the beneficiary accepting it.
+        /// At most `MaxExpiriesPerBlock` references are processed, the remainder is carried over to the next block.
+        fn on_initialize(n: T::BlockNumber) -> Weight {
+            let mut expiring = Expiring::<T>::take(n);
+            let limit = T::MaxExpiriesPerBlock::get() as usize;
+            if expiring.len() > limit {
+                let remaining = expiring.split_off(limit);
+                Expiring::<T>::mutate(n.saturating_add(One::one()), |next| next.extend(remaining));
+            }
+            let checked = expiring.len() as Weight;
+            let expired = expiring.into_iter().filter(|h| Self::expire_prefunding(*h)).count() as Weight;
+
+            // Each expiry posts four entries to the ledgers of the owner and cleans up the reference
+            T::DbWeight::get().reads_writes(1 + checked * 3 + expired * 12, 2 + expired * 24)
+        }
+    }
 
     #[pallet::call]
     impl<T: Config> Pallet<T> {
@@ -245,6 +399,7 @@ pub mod pallet {
         /// The prefunded amount remains as an asset of the buyer until the order is accepted
         /// Updates only the accounts of the buyer
         #[pallet::weight(0/*TODO*/)]
+        #[transactional]
         fn prefund_someone(
             origin: OriginFor<T>,
             beneficiary: T::AccountId,
@@ -260,20 +415,29 @@ pub mod pallet {
             Self::prefunding_for(who, beneficiary, amount.into(), deadline, prefunding_hash, tx_uid)
         }
 
-        /// Creates a single line simple invoice without taxes, tariffs or commissions
+        /// Creates a single line simple invoice without tariffs or commissions. The amount includes the sales taxes
         /// This invoice is associated with a prefunded order - therefore needs to provide the hash reference of the order
         /// Updates the accounting for the vendor and the customer
         #[pallet::weight(0/*TODO*/)]
+        #[transactional]
         fn invoice_prefunded_order(
             origin: OriginFor<T>,
             payer: T::AccountId,
             amount: i128,
             reference: T::Hash,
             uid: T::Hash,
+            taxes: Vec<TaxLine<T::AccountId>>,
         ) -> DispatchResultWithPostInfo {
             let who = ensure_signed(origin)?;
 
-            Self::send_simple_invoice(who.clone(), payer.clone(), amount, reference, uid)
+            Self::send_simple_invoice(
+                who.clone(),
+                payer.clone(),
+                vec![InvoiceLine::services(amount)],
+                reference,
+                uid,
+                taxes,
+            )
         }
 
         /// Buyer pays a prefunded order. Needs to supply the correct hash reference
@@ -296,6 +460,93 @@ pub mod pallet {
 
             Self::unlock_funds_for_owner(who.clone(), reference, uid)
         }
+
+        /// Nominates the arbiter that rules on a dispute over the order.
+        /// Can only be used by the buyer before the vendor accepts the order, so that accepting the order also accepts the arbiter
+        #[pallet::weight(0/*TODO*/)]
+        fn nominate_arbiter(
+            origin: OriginFor<T>,
+            reference: T::Hash,
+            arbiter: T::AccountId,
+            uid: T::Hash,
+        ) -> DispatchResultWithPostInfo {
+            let who = ensure_signed(origin)?;
+
+            Self::set_arbiter(who, reference, arbiter, uid)
+        }
+
+        /// Is used by the buyer or the vendor to dispute an order once the funds are locked by both parties
+        #[pallet::weight(0/*TODO*/)]
+        fn dispute_prefunded_order(
+            origin: OriginFor<T>,
+            reference: T::Hash,
+            uid: T::Hash,
+        ) -> DispatchResultWithPostInfo {
+            let who = ensure_signed(origin)?;
+
+            Self::raise_dispute(who, reference, uid)
+        }
+
+        /// Rules on a dispute. The `release` amount is paid to the vendor and the remainder of the prefunded amount is returned to the buyer.
+        /// Can be used by the nominated arbiter or the arbitration origin
+        #[pallet::weight(0/*TODO*/)]
+        #[transactional]
+        fn resolve_dispute(
+            origin: OriginFor<T>,
+            reference: T::Hash,
+            release: u128,
+            uid: T::Hash,
+        ) -> DispatchResultWithPostInfo {
+            let arbiter = T::ArbitrationOrigin::try_origin(origin)
+                .map(|_| None)
+                .or_else(|origin| ensure_signed(origin).map(Some))?;
+            if let Some(who) = arbiter {
+                ensure!(Self::arbiter(&reference) == Some(who), Error::<T>::ErrorNotArbiter);
+            }
+
+            Self::settle_dispute(reference, release, uid)
+        }
+
+        /// Creates an invoice for a portion of a prefunded order, for example when a milestone is reached.
+        /// The order must have been accepted and the milestones cannot exceed the prefunded amount
+        /// The amount includes the sales taxes
+        #[pallet::weight(0/*TODO*/)]
+        #[transactional]
+        fn invoice_milestone(
+            origin: OriginFor<T>,
+            reference: T::Hash,
+            amount: u128,
+            uid: T::Hash,
+            taxes: Vec<TaxLine<T::AccountId>>,
+        ) -> DispatchResultWithPostInfo {
+            let who = ensure_signed(origin)?;
+            let amount: i128 = <T::PrefundingConversions as Convert<u128, i128>>::convert(amount);
+
+            Self::send_milestone_invoice(who, vec![InvoiceLine::services(amount)], reference, uid, taxes)
+        }
+
+        /// Buyer pays an invoiced milestone. Only the milestone amount is released, the remainder stays locked
+        #[pallet::weight(0/*TODO*/)]
+        #[transactional]
+        fn pay_milestone(
+            origin: OriginFor<T>,
+            reference: T::Hash,
+            milestone: u16,
+            uid: T::Hash,
+        ) -> DispatchResultWithPostInfo {
+            let who = ensure_signed(origin)?;
+
+            Self::settle_milestone(who, reference, milestone, uid)
+        }
+
+        /// Buyer pays an invoice issued on credit
+        #[pallet::weight(0/*TODO*/)]
+        #[transactional]
+        fn pay_unfunded_invoice(origin: OriginFor<T>, reference: T::Hash, uid: T::Hash) -> DispatchResultWithPostInfo {
+            let who = ensure_signed(origin)?;
+
+            Self::settle_unfunded_invoice(who, reference, uid)
+        }
     }
 
     #[pallet::event]
@@ -306,6 +557,14 @@ pub mod pallet {
         PrefundingCompleted(T::Hash),
         InvoiceIssued(T::Hash),
         InvoiceSettled(T::Hash),
+        ArbiterNominated(T::Hash),
+        DisputeRaised(T::Hash),
+        DisputeResolved(T::Hash),
+        MilestoneInvoiced(T::Hash),
+        MilestoneSettled(T::Hash),
+        PrefundingExpired(T::Hash),
+        EscrowReleased(T::Hash),
+        EscrowRefunded(T::Hash),
     }
 }
 
@@ -396,6 +655,10 @@ impl<T: Config> Pallet<T> {
         // perform cleanup removing all reference hashes. No accounting posting have been made, so no cleanup needed there
         Prefunding::<T>::remove(&h);
         PrefundingHashOwner::<T>::remove(&h);
+        Arbiter::<T>::remove(&h);
+        Milestones::<T>::remove(&h);
+        InvoiceTaxes::<T>::remove(&h);
+        InvoiceLines::<T>::remove(&h);
         ReferenceStatus::<T>::insert(&h, s); // This sets the status but does not remove the hash
         OwnerPrefundingHashList::<T>::mutate_(&o, |owner_prefunding_hash_list| {
             owner_prefunding_hash_list.retain(|e| e != &h)
@@ -407,6 +670,104 @@ impl<T: Config> Pallet<T> {
         ok()
     }
 
+    /// Reverses the prefunding in the accounts of the owner, when the locked funds are returned to the owner
+    fn post_prefunding_reversal(o: T::AccountId, h: T::Hash) -> DispatchResultWithPostInfo {
+        let (prefunded_amount, _) = Self::prefunding(&h).ok_or(Error::<T>::ErrorNoPrefunding)?;
+        let amount: AccountBalanceOf<T> = T::PrefundingConversions::convert(prefunded_amount);
+        let prefunded: i128 = <T::PrefundingConversions as Convert<AccountBalanceOf<T>, i128>>::convert(amount);
+        let convert = <T::PrefundingConversions as Convert<i128, AccountBalanceOf<T>>>::convert;
+        let current_block = frame_system::Module::<T>::block_number();
+        let current_block_dupe = frame_system::Module::<T>::block_number();
+
+        // Buyer
+        let account_1 = T::PrefundingConversions::convert(LedgerAccount::PREFUNDING.number()); // Credit decrease
+        let account_2 = T::PrefundingConversions::convert(LedgerAccount::XTX_BALANCE.number()); // Debit  increase
+        let account_3 = T::PrefundingConversions::convert(LedgerAccount::RUNTIME_LEDGER_BY_MODULE.number()); // Credit decrease
+        let account_4 = T::PrefundingConversions::convert(LedgerAccount::RUNTIME_LEDGER_CONTROL.number()); // Credit decrease
+
+        // Keys for posting
+        let forward_keys = vec![
+            (o.clone(), account_1, convert(-prefunded), false, h, current_block, current_block_dupe),
+            (o.clone(), account_2, convert(prefunded), true, h, current_block, current_block_dupe),
+            (o.clone(), account_3, convert(-prefunded), false, h, current_block, current_block_dupe),
+            (o.clone(), account_4, convert(-prefunded), false, h, current_block, current_block_dupe),
+        ];
+
+        T::Accounting::handle_multiposting_amounts(forward_keys)?;
+
+        ok()
+    }
+
+    /// Posts the release of escrowed funds to the beneficiary, without an invoice, as a purchase of the owner
+    /// and a sale of the beneficiary.
+    /// The released funds are unlocked and paid through the transfers suspense account, the transfer itself being
+    /// posted by the balances pallet.
+    fn post_escrow_release(o: T::AccountId, b: T::AccountId, amount: u128, h: T::Hash) -> DispatchResultWithPostInfo {
+        let released: i128 = <T::PrefundingConversions as Convert<u128, i128>>::convert(amount);
+        let line_amounts = Self::split_by_invoice_lines(h, released)?;
+        let convert = <T::PrefundingConversions as Convert<i128, AccountBalanceOf<T>>>::convert;
+        let current_block = frame_system::Module::<T>::block_number();
+        let current_block_dupe = frame_system::Module::<T>::block_number();
+
+        // Owner
+        let account_1 = T::PrefundingConversions::convert(LedgerAccount::PREFUNDING.number()); // Credit decrease
+        let account_2 = T::PrefundingConversions::convert(LedgerAccount::RUNTIME_LEDGER_BY_MODULE.number()); // Credit decrease
+        let account_3 = T::PrefundingConversions::convert(LedgerAccount::RUNTIME_LEDGER_CONTROL.number()); // Credit decrease
+        let account_5 = T::PrefundingConversions::convert(LedgerAccount::XTX_BALANCE.number()); // Debit  increase
+        let account_6 = T::PrefundingConversions::convert(LedgerAccount::TRANSFERS_SUSPENSE.number()); // Credit decrease
+
+        // Beneficiary
+        let account_4 = T::PrefundingConversions::convert(LedgerAccount::TRANSFERS_SUSPENSE.number()); // Debit  increase
+
+        // Keys for posting
+        let mut forward_keys = vec![
+            (o.clone(), account_1, convert(-released), false, h, current_block, current_block_dupe),
+            (o.clone(), account_2, convert(-released), false, h, current_block, current_block_dupe),
+            (o.clone(), account_3, convert(-released), false, h, current_block, current_block_dupe),
+            (o.clone(), account_5, convert(released), true, h, current_block, current_block_dupe),
+            (o.clone(), account_6, convert(-released), false, h, current_block, current_block_dupe),
+            (b.clone(), account_4, convert(released), true, h, current_block, current_block_dupe),
+        ];
+        // The purchase of the owner (Debit increase) and the sale of the beneficiary (Credit increase), by line
+        for (seller_account, buyer_account, amount) in line_amounts.into_iter().filter(|(_, _, amount)| *amount != 0) {
+            let buyer_account = T::PrefundingConversions::convert(buyer_account.number());
+            let seller_account = T::PrefundingConversions::convert(seller_account.number());
+            forward_keys.append(&mut vec![
+                (o.clone(), buyer_account, convert(amount), true, h, current_block, current_block_dupe),
+                (b.clone(), seller_account, convert(amount), false, h, current_block, current_block_dupe),
+            ]);
+        }
+
+        T::Accounting::handle_multiposting_amounts(forward_keys)?;
+
+        ok()
+    }
+
+    /// Expires the prefunding if the beneficiary has not accepted it by the deadline. The funds are unlocked and the
+    /// prefunding is reversed in the accounts of the owner. References already accepted or cancelled are left as they are.
+    /// Returns whether the prefunding expired.
+    fn expire_prefunding(h: T::Hash) -> bool {
+        use LockStatus::*;
+
+        match (Self::prefunding_hash_owner(&h), ReferenceStatus::<T>::get(&h)) {
+            (Some((owner, Locked, _, Unlocked)), Some(1)) if Self::prefund_deadline_passed(h) => {
+                with_transaction(|| {
+                    let status: Status = 50; // Abandoned or Cancelled
+                    match Self::post_prefunding_reversal(owner.clone(), h)
+                        .and_then(|_| Self::cancel_prefunding_lock(owner, h, status))
+                    {
+                        Ok(_) => {
+                            Self::deposit_event(Event::PrefundingExpired(h));
+                            TransactionOutcome::Commit(true)
+                        }
+                        Err(_) => TransactionOutcome::Rollback(false),
+                    }
+                })
+            }
+            _ => false,
+        }
+    }
+
     /// unlock & pay beneficiary with funds transfer and account updates (settlement of invoice)
     fn unlock_funds_for_beneficiary(o: T::AccountId, h: T::Hash, _u: T::Hash) -> DispatchResultWithPostInfo {
         use LockStatus::*;
@@ -459,10 +820,423 @@ impl<T: Config> Pallet<T> {
         ok()
     }
 
-    // TODO Check should be made for available balances, and if the amount submitted is more than the invoice amount.
-    /// Settles invoice by updates to various relevant accounts and transfer of funds
-    fn settle_unfunded_invoice() -> DispatchResultWithPostInfo {
-        fail!("TODO")
+    /// Posts the invoice to the accounts of the seller and the buyer.
+    /// The invoiced amount includes the taxes, which are owed by the seller and can be recovered by the buyer.
+    /// The amount net of taxes is allocated to the lines in proportion to their amounts, and each line is posted to its own
+    /// revenue and expense or asset accounts. The taxes must already be validated.
+    fn post_invoice(
+        o: T::AccountId,
+        p: T::AccountId,
+        lines: &[InvoiceLine],
+        h: T::Hash,
+        taxes: &[TaxLine<T::AccountId>],
+    ) -> DispatchResultWithPostInfo {
+        let gross: i128 = InvoiceLine::total(lines).ok_or(Error::<T>::ErrorTaxCalculation)?;
+        let (net, taxes) = TaxLine::split(gross, taxes).ok_or(Error::<T>::ErrorTaxCalculation)?;
+        let line_amounts = Self::allocate_net(lines, gross, net).ok_or(Error::<T>::ErrorTaxCalculation)?;
+        let convert = <T::PrefundingConversions as Convert<i128, AccountBalanceOf<T>>>::convert;
+        let increase_amount: AccountBalanceOf<T> = convert(gross);
+        let current_block = frame_system::Module::<T>::block_number();
+        let current_block_dupe = frame_system::Module::<T>::block_number();
+
+        // Seller
+        let account_1 = T::PrefundingConversions::convert(LedgerAccount::ACCOUNTS_RECEIVABLE.number()); // Debit  increase
+        let account_3 = T::PrefundingConversions::convert(LedgerAccount::SALES_LEDGER_BY_PAYER.number()); // Debit  increase
+        let account_4 = T::PrefundingConversions::convert(LedgerAccount::SALES_LEDGER_CONTROL.number()); // Debit  increase
+        let account_9 = T::PrefundingConversions::convert(LedgerAccount::SALES_TAX_PAYABLE.number()); // Credit increase
+
+        // Buyer
+        let account_5 = T::PrefundingConversions::convert(LedgerAccount::ACCOUNTS_PAYABLE.number()); // Credit increase
+        let account_7 = T::PrefundingConversions::convert(LedgerAccount::PURCHASE_LEDGER_BY_VENDOR.number()); // Debit  increase
+        let account_8 = T::PrefundingConversions::convert(LedgerAccount::PURCHASE_LEDGER_CONTROL.number()); // Debit  increase
+        let account_10 = T::PrefundingConversions::convert(LedgerAccount::INPUT_TAX_RECEIVABLE.number()); // Debit  increase
+
+        // Keys for posting
+        let mut forward_keys = vec![
+            (o.clone(), account_1, increase_amount, true, h, current_block, current_block_dupe),
+            (o.clone(), account_3, increase_amount, true, h, current_block, current_block_dupe),
+            (o.clone(), account_4, increase_amount, true, h, current_block, current_block_dupe),
+            (p.clone(), account_5, increase_amount, false, h, current_block, current_block_dupe),
+            (p.clone(), account_7, increase_amount, true, h, current_block, current_block_dupe),
+            (p.clone(), account_8, increase_amount, true, h, current_block, current_block_dupe),
+        ];
+        for (line, amount) in lines.iter().zip(line_amounts).filter(|(_, amount)| *amount != 0) {
+            let account_2 = T::PrefundingConversions::convert(line.seller_account.number()); // Credit increase
+            let account_6 = T::PrefundingConversions::convert(line.buyer_account.number()); // Debit  increase
+            forward_keys.append(&mut vec![
+                (o.clone(), account_2, convert(amount), false, h, current_block, current_block_dupe),
+                (p.clone(), account_6, convert(amount), true, h, current_block, current_block_dupe),
+            ]);
+        }
+        for (_, tax) in taxes.iter().filter(|(_, tax)| *tax != 0) {
+            forward_keys.append(&mut vec![
+                (o.clone(), account_9, convert(*tax), false, h, current_block, current_block_dupe),
+                (p.clone(), account_10, convert(*tax), true, h, current_block, current_block_dupe),
+            ]);
+        }
+
+        T::Accounting::handle_multiposting_amounts(forward_keys)?;
+
+        for (jurisdiction, tax) in taxes.into_iter().filter(|(_, tax)| *tax != 0) {
+            T::Accounting::record_sales_tax(o.clone(), jurisdiction, convert(tax))?;
+        }
+
+        ok()
+    }
+
+    /// Posts the payment of the invoice from the prefunded amount to the accounts of the buyer and the seller.
+    /// The paid funds are unlocked and paid through the transfers suspense account, the transfer itself being posted
+    /// by the balances pallet.
+    fn post_settlement(
+        o: T::AccountId,
+        s: T::AccountId,
+        amount: AccountBalanceOf<T>,
+        h: T::Hash,
+    ) -> DispatchResultWithPostInfo {
+        // Convert for calculation
+        let inverted = -1 * <T::PrefundingConversions as Convert<AccountBalanceOf<T>, i128>>::convert(amount.clone());
+        let increase_amount = amount;
+        let decrease_amount = <T::PrefundingConversions as Convert<i128, AccountBalanceOf<T>>>::convert(inverted);
+        let current_block = frame_system::Module::<T>::block_number();
+        let current_block_dupe = frame_system::Module::<T>::block_number();
+
+        let account_1 = T::PrefundingConversions::convert(LedgerAccount::ACCOUNTS_PAYABLE.number()); // Debit  decrease
+        let account_2 = T::PrefundingConversions::convert(LedgerAccount::PREFUNDING.number()); // Credit decrease
+        let account_3 = T::PrefundingConversions::convert(LedgerAccount::RUNTIME_LEDGER_BY_MODULE.number()); // Credit decrease
+        let account_4 = T::PrefundingConversions::convert(LedgerAccount::RUNTIME_LEDGER_CONTROL.number()); // Credit decrease
+        let account_5 = T::PrefundingConversions::convert(LedgerAccount::PURCHASE_LEDGER_BY_VENDOR.number()); // Credit decrease
+        let account_6 = T::PrefundingConversions::convert(LedgerAccount::PURCHASE_LEDGER_CONTROL.number()); // Credit decrease
+        let account_11 = T::PrefundingConversions::convert(LedgerAccount::XTX_BALANCE.number()); // Debit  increase
+        let account_12 = T::PrefundingConversions::convert(LedgerAccount::TRANSFERS_SUSPENSE.number()); // Credit decrease
+
+        let account_7 = T::PrefundingConversions::convert(LedgerAccount::TRANSFERS_SUSPENSE.number()); // Debit  increase
+        let account_8 = T::PrefundingConversions::convert(LedgerAccount::ACCOUNTS_RECEIVABLE.number()); // Credit decrease
+        let account_9 = T::PrefundingConversions::convert(LedgerAccount::SALES_LEDGER_BY_PAYER.number()); // Credit decrease
+        let account_10 = T::PrefundingConversions::convert(LedgerAccount::SALES_LEDGER_CONTROL.number()); // Credit decrease
+
+        // Keys for posting
+        let forward_keys = vec![
+            // Buyer
+            (o.clone(), account_1, decrease_amount, true, h, current_block, current_block_dupe),
+            (o.clone(), account_2, decrease_amount, false, h, current_block, current_block_dupe),
+            (o.clone(), account_3, decrease_amount, false, h, current_block, current_block_dupe),
+            (o.clone(), account_4, decrease_amount, false, h, current_block, current_block_dupe),
+            (o.clone(), account_5, decrease_amount, false, h, current_block, current_block_dupe),
+            (o.clone(), account_6, decrease_amount, false, h, current_block, current_block_dupe),
+            (o.clone(), account_11, increase_amount, true, h, current_block, current_block_dupe),
+            (o.clone(), account_12, decrease_amount, false, h, current_block, current_block_dupe),
+            // Seller
+            (s.clone(), account_7, increase_amount, true, h, current_block, current_block_dupe),
+            (s.clone(), account_8, decrease_amount, false, h, current_block, current_block_dupe),
+            (s.clone(), account_9, decrease_amount, false, h, current_block, current_block_dupe),
+            (s.clone(), account_10, decrease_amount, false, h, current_block, current_block_dupe),
+        ];
+
+        T::Accounting::handle_multiposting_amounts(forward_keys)
+    }
+
+    /// Checks that there are no more sales taxes than the maximum, that no rate is zero and that each jurisdiction is
+    /// taxed only once.
+    fn validate_taxes(taxes: &[TaxLine<T::AccountId>]) -> DispatchResultWithPostInfo {
+        match TaxLine::validate(taxes, T::MaxTaxLines::get()) {
+            Ok(()) => ok(),
+            Err(TaxLineError::TooMany) => fail!(Error::<T>::ErrorTooManyTaxLines),
+            Err(TaxLineError::ZeroRate) => fail!(Error::<T>::ErrorZeroTaxRate),
+            Err(TaxLineError::DuplicateJurisdiction) => fail!(Error::<T>::ErrorDuplicateJurisdiction),
+        }
+    }
+
+    /// Total of the milestones invoiced but not yet settled
+    fn outstanding_milestones(h: T::Hash) -> u128 {
+        Self::milestones(&h)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|(_, status)| *status == 400)
+            .map(|(amount, _)| <T::PrefundingConversions as Convert<CurrencyBalanceOf<T>, u128>>::convert(amount))
+            .sum()
+    }
+
+    /// Amounts invoiced but not yet settled: the prefunded amount for an order invoiced in full, otherwise the
+    /// outstanding milestones
+    fn outstanding_invoices(h: T::Hash, status: Status, prefunded: i128) -> Vec<i128> {
+        match status {
+            400 => vec![prefunded],
+            _ => Self::milestones(&h)
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|(_, status)| *status == 400)
+                .map(|(amount, _)| {
+                    let amount = <T::PrefundingConversions as Convert<CurrencyBalanceOf<T>, u128>>::convert(amount);
+                    <T::PrefundingConversions as Convert<u128, i128>>::convert(amount)
+                })
+                .collect(),
+        }
+    }
+
+    /// Allocates the amount net of taxes to the invoice lines in proportion to their amounts.
+    /// The last line takes the rounding difference, so that the allocated amounts always add up to the net amount.
+    fn allocate_net(lines: &[InvoiceLine], gross: i128, net: i128) -> Option<Vec<i128>> {
+        if gross == 0 {
+            return Some(lines.iter().map(|line| line.amount).collect());
+        }
+        let mut allocated: i128 = 0;
+        let mut amounts = Vec::with_capacity(lines.len());
+        for (i, line) in lines.iter().enumerate() {
+            let amount = match i + 1 == lines.len() {
+                true => net.checked_sub(allocated)?,
+                false => line.amount.checked_mul(net)?.checked_div(gross)?,
+            };
+            allocated = allocated.checked_add(amount)?;
+            amounts.push(amount);
+        }
+
+        Some(amounts)
+    }
+
+    /// Splits the amount over the accounts of the lines first invoiced against the reference, in proportion to the line
+    /// amounts. Without invoice lines the amount goes to the sales and labour accounts.
+    fn split_by_invoice_lines(h: T::Hash, amount: i128) -> Result<Vec<(LedgerAccount, LedgerAccount, i128)>, Error<T>> {
+        let lines = Self::invoice_lines(&h).filter(|lines| !lines.is_empty()).unwrap_or_else(|| {
+            vec![InvoiceLine {
+                amount,
+                seller_account: LedgerAccount::SALES,
+                buyer_account: LedgerAccount::LABOUR,
+            }]
+        });
+        let gross = InvoiceLine::total(&lines).ok_or(Error::<T>::ErrorTaxCalculation)?;
+        let amounts = Self::allocate_net(&lines, gross, amount).ok_or(Error::<T>::ErrorTaxCalculation)?;
+
+        Ok(lines.iter().zip(amounts).map(|(line, amount)| (line.seller_account, line.buyer_account, amount)).collect())
+    }
+
+    /// Records the taxes and the lines of the first invoice against the reference,
+    /// and checks that the taxes are valid and that later invoices carry the same taxes
+    fn set_invoice_details(
+        h: T::Hash,
+        lines: &[InvoiceLine],
+        taxes: &[TaxLine<T::AccountId>],
+    ) -> DispatchResultWithPostInfo {
+        Self::validate_taxes(taxes)?;
+        match Self::invoice_taxes(&h) {
+            Some(tax_lines) if tax_lines.as_slice() != taxes => fail!(Error::<T>::ErrorTaxesChanged),
+            Some(_) => (),
+            None => InvoiceTaxes::<T>::insert(&h, taxes.to_vec()),
+        }
+        if !InvoiceLines::<T>::contains_key(&h) && !lines.is_empty() {
+            InvoiceLines::<T>::insert(&h, lines.to_vec());
+        }
+
+        ok()
+    }
+
+    /// Nominates the arbiter for the reference. The beneficiary agrees to the arbiter by accepting the order.
+    fn set_arbiter(o: T::AccountId, h: T::Hash, a: T::AccountId, uid: T::Hash) -> DispatchResultWithPostInfo {
+        use LockStatus::*;
+
+        if Self::reference_valid(h) == false {
+            fail!(Error::<T>::ErrorHashDoesNotExist4);
+        }
+
+        let (owner, _, beneficiary, _) = Self::prefunding_hash_owner(&h).ok_or(Error::<T>::ErrorNoDetails)?;
+        if o != owner || Self::get_release_state(h) != (Locked, Unlocked) {
+            fail!(Error::<T>::ErrorArbiterNotAllowed);
+        }
+        if a == owner || a == beneficiary {
+            fail!(Error::<T>::ErrorArbiterIsParty);
+        }
+
+        Arbiter::<T>::insert(&h, a);
+
+        Self::deposit_event(Event::ArbiterNominated(uid));
+
+        ok()
+    }
+
+    /// Splits the locked funds of a disputed reference as ruled by the arbiter.
+    /// The released amount is paid to the beneficiary and the remainder is returned to the owner.
+    /// The prefunding, and whatever was invoiced including the taxes, is reversed in the accounts of both parties.
+    /// The prefunded amount is unlocked in full and the released amount is paid through the transfers suspense
+    /// account, the transfer itself being posted by the balances pallet.
+    fn settle_dispute(h: T::Hash, release: u128, uid: T::Hash) -> DispatchResultWithPostInfo {
+        let (_, status) = Self::dispute(&h).ok_or(Error::<T>::ErrorNotDisputed)?;
+        let (buyer, _, seller, _) = Self::prefunding_hash_owner(&h).ok_or(Error::<T>::ErrorNoDetails)?;
+        let (prefunded_amount, _) = Self::prefunding(&h).ok_or(Error::<T>::ErrorNoPrefunding)?;
+        let prefunded: u128 =
+            <T::PrefundingConversions as Convert<CurrencyBalanceOf<T>, u128>>::convert(prefunded_amount);
+        if release > prefunded {
+            fail!(Error::<T>::ErrorReleaseTooHigh);
+        }
+
+        // Amounts are always positive, the sign is given by the increase or decrease of the account
+        let prefunded: i128 = <T::PrefundingConversions as Convert<u128, i128>>::convert(prefunded);
+        let released: i128 = <T::PrefundingConversions as Convert<u128, i128>>::convert(release);
+        let invoices = Self::outstanding_invoices(h, status, prefunded);
+        let invoiced: i128 = invoices
+            .iter()
+            .try_fold(0i128, |total, amount| total.checked_add(*amount))
+            .ok_or(Error::<T>::ErrorAmountOverflow)?;
+        // The taxes are reversed in full, by jurisdiction
+        let lines = Self::invoice_taxes(&h).unwrap_or_default();
+        let mut taxes: Vec<(T::AccountId, i128)> = lines.iter().map(|line| (line.jurisdiction.clone(), 0)).collect();
+        for amount in invoices {
+            let (_, split) = TaxLine::split(amount, &lines).ok_or(Error::<T>::ErrorTaxCalculation)?;
+            for ((_, total), (_, tax)) in taxes.iter_mut().zip(split) {
+                *total = total.checked_add(tax).ok_or(Error::<T>::ErrorAmountOverflow)?;
+            }
+        }
+        let total_tax: i128 = taxes
+            .iter()
+            .try_fold(0i128, |total, (_, tax)| total.checked_add(*tax))
+            .ok_or(Error::<T>::ErrorAmountOverflow)?;
+        let net_invoiced: i128 = invoiced.checked_sub(total_tax).ok_or(Error::<T>::ErrorAmountOverflow)?;
+        let adjustment: i128 = released.checked_sub(net_invoiced).ok_or(Error::<T>::ErrorAmountOverflow)?;
+        let line_adjustments = Self::split_by_invoice_lines(h, adjustment)?;
+        let convert = <T::PrefundingConversions as Convert<i128, AccountBalanceOf<T>>>::convert;
+        let current_block = frame_system::Module::<T>::block_number();
+        let current_block_dupe = frame_system::Module::<T>::block_number();
+
+        // Buyer
+        let account_1 = T::PrefundingConversions::convert(LedgerAccount::PREFUNDING.number()); // Credit decrease
+        let account_2 = T::PrefundingConversions::convert(LedgerAccount::RUNTIME_LEDGER_BY_MODULE.number()); // Credit decrease
+        let account_3 = T::PrefundingConversions::convert(LedgerAccount::RUNTIME_LEDGER_CONTROL.number()); // Credit decrease
+        let account_4 = T::PrefundingConversions::convert(LedgerAccount::XTX_BALANCE.number()); // Debit  increase (unlocked)
+        let account_5 = T::PrefundingConversions::convert(LedgerAccount::TRANSFERS_SUSPENSE.number()); // Credit decrease (released)
+        let account_6 = T::PrefundingConversions::convert(LedgerAccount::ACCOUNTS_PAYABLE.number()); // Debit  decrease
+        let account_7 = T::PrefundingConversions::convert(LedgerAccount::PURCHASE_LEDGER_BY_VENDOR.number()); // Credit decrease
+        let account_8 = T::PrefundingConversions::convert(LedgerAccount::PURCHASE_LEDGER_CONTROL.number()); // Credit decrease
+
+        // Seller
+        let account_9 = T::PrefundingConversions::convert(LedgerAccount::TRANSFERS_SUSPENSE.number()); // Debit  increase (released)
+        let account_11 = T::PrefundingConversions::convert(LedgerAccount::ACCOUNTS_RECEIVABLE.number()); // Credit decrease
+        let account_12 = T::PrefundingConversions::convert(LedgerAccount::SALES_LEDGER_BY_PAYER.number()); // Credit decrease
+        let account_13 = T::PrefundingConversions::convert(LedgerAccount::SALES_LEDGER_CONTROL.number()); // Credit decrease
+        let account_14 = T::PrefundingConversions::convert(LedgerAccount::SALES_TAX_PAYABLE.number()); // Debit  decrease
+        let account_15 = T::PrefundingConversions::convert(LedgerAccount::INPUT_TAX_RECEIVABLE.number()); // Credit decrease
+
+        // Keys for posting
+        let mut forward_keys = vec![
+            (buyer.clone(), account_1, convert(-prefunded), false, h, current_block, current_block_dupe),
+            (buyer.clone(), account_2, convert(-prefunded), false, h, current_block, current_block_dupe),
+            (buyer.clone(), account_3, convert(-prefunded), false, h, current_block, current_block_dupe),
+            (buyer.clone(), account_4, convert(prefunded), true, h, current_block, current_block_dupe),
+        ];
+        if released > 0 {
+            forward_keys.append(&mut vec![
+                (buyer.clone(), account_5, convert(-released), false, h, current_block, current_block_dupe),
+                (seller.clone(), account_9, convert(released), true, h, current_block, current_block_dupe),
+            ]);
+        }
+        if invoiced > 0 {
+            // The payable and receivable are cleared
+            forward_keys.append(&mut vec![
+                (buyer.clone(), account_6, convert(-invoiced), true, h, current_block, current_block_dupe),
+                (buyer.clone(), account_7, convert(-invoiced), false, h, current_block, current_block_dupe),
+                (buyer.clone(), account_8, convert(-invoiced), false, h, current_block, current_block_dupe),
+                (seller.clone(), account_11, convert(-invoiced), false, h, current_block, current_block_dupe),
+                (seller.clone(), account_12, convert(-invoiced), false, h, current_block, current_block_dupe),
+                (seller.clone(), account_13, convert(-invoiced), false, h, current_block, current_block_dupe),
+            ]);
+        }
+        // The purchase (Debit increase or Credit decrease) and the sale (Credit increase or Debit decrease) of each line
+        // are adjusted from the net invoiced amount to the released amount
+        for (seller_account, buyer_account, amount) in
+            line_adjustments.into_iter().filter(|(_, _, amount)| *amount != 0)
+        {
+            let buyer_account = T::PrefundingConversions::convert(buyer_account.number());
+            let seller_account = T::PrefundingConversions::convert(seller_account.number());
+            forward_keys.append(&mut vec![
+                (buyer.clone(), buyer_account, convert(amount), amount > 0, h, current_block, current_block_dupe),
+                (seller.clone(), seller_account, convert(amount), amount < 0, h, current_block, current_block_dupe),
+            ]);
+        }
+
+        for (_, tax) in taxes.iter().filter(|(_, tax)| *tax != 0) {
+            forward_keys.append(&mut vec![
+                (seller.clone(), account_14, convert(-tax), true, h, current_block, current_block_dupe),
+                (buyer.clone(), account_15, convert(-tax), false, h, current_block, current_block_dupe),
+            ]);
+        }
+
+        if let Err(_) = T::Accounting::handle_multiposting_amounts(forward_keys) {
+            fail!(Error::<T>::ErrorInAccounting4);
+        }
+        for (jurisdiction, tax) in taxes.into_iter().filter(|(_, tax)| *tax != 0) {
+            T::Accounting::record_sales_tax(seller.clone(), jurisdiction, convert(-tax))?;
+        }
+
+        // Unlock, pay the released amount to the beneficiary and mark hash as settled
+        let status: Status = 500; // Settled
+        Self::cancel_prefunding_lock(buyer.clone(), h, status)?;
+        Dispute::<T>::remove(&h);
+        if released > 0 {
+            let amount: CurrencyBalanceOf<T> = <T::PrefundingConversions as Convert<
+                AccountBalanceOf<T>,
+                CurrencyBalanceOf<T>,
+            >>::convert(convert(released));
+            if let Err(_) = T::Currency::transfer(&buyer, &seller, amount, ExistenceRequirement::KeepAlive) {
+                fail!("Error during transfer")
+            }
+        }
+        T::Orders::dispute_resolved(h, released > 0, uid)?;
+
+        Self::deposit_event(Event::DisputeResolved(uid));
+
+        ok()
+    }
+
+    /// Posts the payment of an invoice issued on credit to the accounts of the buyer and the seller.
+    /// The payment clears the transfers suspense account, the transfer itself being posted by the balances pallet.
+    fn post_unfunded_settlement(
+        o: T::AccountId,
+        s: T::AccountId,
+        amount: AccountBalanceOf<T>,
+        h: T::Hash,
+    ) -> DispatchResultWithPostInfo {
+        // Convert for calculation
+        let inverted = -1 * <T::PrefundingConversions as Convert<AccountBalanceOf<T>, i128>>::convert(amount.clone());
+        let increase_amount = amount;
+        let decrease_amount = <T::PrefundingConversions as Convert<i128, AccountBalanceOf<T>>>::convert(inverted);
+        let current_block = frame_system::Module::<T>::block_number();
+        let current_block_dupe = frame_system::Module::<T>::block_number();
+
+        let account_1 = T::PrefundingConversions::convert(LedgerAccount::ACCOUNTS_PAYABLE.number()); // Debit  decrease
+        let account_2 = T::PrefundingConversions::convert(LedgerAccount::TRANSFERS_SUSPENSE.number()); // Credit decrease
+        let account_3 = T::PrefundingConversions::convert(LedgerAccount::PURCHASE_LEDGER_BY_VENDOR.number()); // Credit decrease
+        let account_4 = T::PrefundingConversions::convert(LedgerAccount::PURCHASE_LEDGER_CONTROL.number()); // Credit decrease
+
+        let account_5 = T::PrefundingConversions::convert(LedgerAccount::TRANSFERS_SUSPENSE.number()); // Debit  increase
+        let account_6 = T::PrefundingConversions::convert(LedgerAccount::ACCOUNTS_RECEIVABLE.number()); // Credit decrease
+        let account_7 = T::PrefundingConversions::convert(LedgerAccount::SALES_LEDGER_BY_PAYER.number()); // Credit decrease
+        let account_8 = T::PrefundingConversions::convert(LedgerAccount::SALES_LEDGER_CONTROL.number()); // Credit decrease
+
+        // Keys for posting
+        let forward_keys = vec![
+            // Buyer
+            (o.clone(), account_1, decrease_amount, true, h, current_block, current_block_dupe),
+            (o.clone(), account_2, decrease_amount, false, h, current_block, current_block_dupe),
+            (o.clone(), account_3, decrease_amount, false, h, current_block, current_block_dupe),
+            (o.clone(), account_4, decrease_amount, false, h, current_block, current_block_dupe),
+            // Seller
+            (s.clone(), account_5, increase_amount, true, h, current_block, current_block_dupe),
+            (s.clone(), account_6, decrease_amount, false, h, current_block, current_block_dupe),
+            (s.clone(), account_7, decrease_amount, false, h, current_block, current_block_dupe),
+            (s.clone(), account_8, decrease_amount, false, h, current_block, current_block_dupe),
+        ];
+
+        T::Accounting::handle_multiposting_amounts(forward_keys)
+    }
+
+    /// Lists the unpaid invoices issued on credit, by or to the identity, that are past their due date
+    pub fn overdue_invoices(who: T::AccountId) -> Vec<T::Hash> {
+        let current_block = frame_system::Module::<T>::block_number();
+        Self::unfunded_invoice_list(&who)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|h| match Self::unfunded_invoice(h) {
+                Some((_, _, _, due_date)) => due_date < current_block,
+                None => false,
+            })
+            .collect()
     }
 }
 
@@ -471,6 +1245,7 @@ pub use pallet::*;
 impl<T: Config> Encumbrance<T::AccountId, T::Hash, T::BlockNumber> for Pallet<T> {
     type LockStatus = LockStatus;
 
+    #[transactional]
     fn prefunding_for(
         who: T::AccountId,
         recipient: T::AccountId,
@@ -512,12 +1287,12 @@ impl<T: Config> Encumbrance<T::AccountId, T::Hash, T::BlockNumber> for Pallet<T>
         if let Err(_) = Self::set_prefunding(who.clone(), amount_converted.clone(), deadline, prefunding_hash, uid) {
             fail!(Error::<T>::ErrorPrefundNotSet);
         }
-        // Deposit taken at this point. Note that if an error occurs beyond here we need to remove the locked funds.
+        // Deposit taken at this point. The call is transactional, so any later error also releases the locked funds.
         // Buyer
-        let account_1 = T::PrefundingConversions::convert(110_10005000_0000_u64); // debit  increase 110100050000000 Prefunding Account
-        let account_2 = T::PrefundingConversions::convert(110_10004000_0000_u64); // credit decrease 110100040000000 XTX Balance
-        let account_3 = T::PrefundingConversions::convert(360_60002000_0000_u64); // debit  increase 360600020000000 Runtime Ledger by Module
-        let account_4 = T::PrefundingConversions::convert(360_60006000_0000_u64); // debit  increase 360600060000000 Runtime Ledger Control
+        let account_1 = T::PrefundingConversions::convert(LedgerAccount::PREFUNDING.number()); // debit  increase
+        let account_2 = T::PrefundingConversions::convert(LedgerAccount::XTX_BALANCE.number()); // credit decrease
+        let account_3 = T::PrefundingConversions::convert(LedgerAccount::RUNTIME_LEDGER_BY_MODULE.number()); // debit  increase
+        let account_4 = T::PrefundingConversions::convert(LedgerAccount::RUNTIME_LEDGER_CONTROL.number()); // debit  increase
 
         // Keys for posting
         let forward_keys = vec![
@@ -526,25 +1301,8 @@ impl<T: Config> Encumbrance<T::AccountId, T::Hash, T::BlockNumber> for Pallet<T>
             (who.clone(), account_3, increase_amount, true, prefunding_hash, current_block, current_block_dupe),
             (who.clone(), account_4, increase_amount, true, prefunding_hash, current_block, current_block_dupe),
         ];
-        // Reversal keys in case of errors
-        let reversal_keys = vec![
-            (who.clone(), account_1, decrease_amount, false, prefunding_hash, current_block, current_block_dupe),
-            (who.clone(), account_2, increase_amount, true, prefunding_hash, current_block, current_block_dupe),
-            (who.clone(), account_3, decrease_amount, false, prefunding_hash, current_block, current_block_dupe),
-            (who.clone(), account_4, decrease_amount, false, prefunding_hash, current_block, current_block_dupe),
-        ];
-
-        let track_rev_keys = Vec::<(
-            T::AccountId,
-            AccountOf<T>,
-            AccountBalanceOf<T>,
-            bool,
-            T::Hash,
-            T::BlockNumber,
-            T::BlockNumber,
-        )>::with_capacity(9);
 
-        if let Err(_) = T::Accounting::handle_multiposting_amounts(forward_keys, reversal_keys, track_rev_keys) {
+        if let Err(_) = T::Accounting::handle_multiposting_amounts(forward_keys) {
             fail!(Error::<T>::ErrorInAccounting1);
         }
 
@@ -556,6 +1314,8 @@ impl<T: Config> Encumbrance<T::AccountId, T::Hash, T::BlockNumber> for Pallet<T>
         OwnerPrefundingHashList::<T>::mutate_(&who, |owner_prefunding_hash_list| {
             owner_prefunding_hash_list.push(prefunding_hash)
         });
+        // The prefunding expires once the deadline has passed
+        Expiring::<T>::mutate(deadline.saturating_add(One::one()), |expiring| expiring.push(prefunding_hash));
 
         // Submitted, Locked by sender.
         if let Err(_) = Self::set_ref_status(prefunding_hash, 1) {
@@ -567,82 +1327,37 @@ impl<T: Config> Encumbrance<T::AccountId, T::Hash, T::BlockNumber> for Pallet<T>
         ok()
     }
 
-    /// Simple invoice. Does not include tax jurisdiction, tax amounts, freight, commissions, tariffs, discounts and other extended line item values
+    /// Simple invoice. The amount includes the sales taxes of each tax jurisdiction.
+    /// Does not include freight, commissions, tariffs, discounts and other extended line item values
     /// must include a connection to the originating reference.
     /// Invoices cannot be made to parties that haven't asked for something identified by a valid hash
     fn send_simple_invoice(
         o: T::AccountId,
         p: T::AccountId,
-        n: i128,
+        lines: Vec<InvoiceLine>,
         h: T::Hash,
         u: T::Hash,
+        taxes: Vec<TaxLine<T::AccountId>>,
     ) -> DispatchResultWithPostInfo {
         // Validate that the hash is indeed assigned to the seller
         if Self::check_ref_beneficiary(o.clone(), h) == false {
             fail!(Error::<T>::ErrorNotAllowed2);
         }
 
+        if Dispute::<T>::contains_key(&h) {
+            fail!(Error::<T>::ErrorDisputed);
+        }
+
+        if Milestones::<T>::contains_key(&h) {
+            fail!(Error::<T>::ErrorMilestonesInvoiced);
+        }
+
         // Amount CAN be negative - this is therefore not an Invoice but a Credit Note!
         // The account postings are identical to an invoice, however we must also handle the refund immediately if possible.
         // In order to proceed with a credit note, validate that the vendor has sufficient funds.
         // If they do not have sufficient funds, the credit note can still be issued, but will remain outstanding until it is settled.
-        // As amount will always be positive, convert for use in accounting
-        let amount_converted: AccountBalanceOf<T> =
-            <T::PrefundingConversions as Convert<i128, AccountBalanceOf<T>>>::convert(n);
-        // invert the amount
-        let inverted: i128 = n * -1;
-        let increase_amount: AccountBalanceOf<T> = amount_converted.clone();
-        let decrease_amount: AccountBalanceOf<T> =
-            <T::PrefundingConversions as Convert<i128, AccountBalanceOf<T>>>::convert(inverted);
-        let current_block = frame_system::Module::<T>::block_number();
-        let current_block_dupe = frame_system::Module::<T>::block_number();
-
-        // Seller
-        let account_1 = T::PrefundingConversions::convert(110_10008000_0000_u64); // Debit  increase 110100080000000	Accounts receivable (Sales Control Account or Trade Debtor's Account)
-        let account_2 = T::PrefundingConversions::convert(240_40001000_0000_u64); // Credit increase 240400010000000	Product or Service Sales
-        let account_3 = T::PrefundingConversions::convert(360_60001000_0000_u64); // Debit  increase 360600010000000	Sales Ledger by Payer
-        let account_4 = T::PrefundingConversions::convert(360_60005000_0000_u64); // Debit  increase 360600050000000	Sales Ledger Control
-
-        // Buyer
-        let account_5 = T::PrefundingConversions::convert(120_20003000_0000_u64); // Credit increase 120200030000000	Accounts payable
-        let account_6 = T::PrefundingConversions::convert(250_50012000_0013_u64); // Debit  increase 250500120000013	Labour
-        let account_7 = T::PrefundingConversions::convert(360_60003000_0000_u64); // Debit  increase 360600030000000	Purchase Ledger by Vendor
-        let account_8 = T::PrefundingConversions::convert(360_60007000_0000_u64); // Debit  increase 360600070000000	Purchase Ledger Control
-
-        // Keys for posting
-        let forward_keys = vec![
-            (o.clone(), account_1, increase_amount, true, h, current_block, current_block_dupe),
-            (o.clone(), account_2, increase_amount, false, h, current_block, current_block_dupe),
-            (o.clone(), account_3, increase_amount, true, h, current_block, current_block_dupe),
-            (o.clone(), account_4, increase_amount, true, h, current_block, current_block_dupe),
-            (p.clone(), account_5, increase_amount, false, h, current_block, current_block_dupe),
-            (p.clone(), account_6, increase_amount, true, h, current_block, current_block_dupe),
-            (p.clone(), account_7, increase_amount, true, h, current_block, current_block_dupe),
-            (p.clone(), account_8, increase_amount, true, h, current_block, current_block_dupe),
-        ];
-
-        // Reversal keys in case of errors
-        let reversal_keys = vec![
-            (o.clone(), account_1, decrease_amount, false, h, current_block, current_block_dupe),
-            (o.clone(), account_2, decrease_amount, true, h, current_block, current_block_dupe),
-            (o.clone(), account_3, decrease_amount, false, h, current_block, current_block_dupe),
-            (o.clone(), account_4, decrease_amount, false, h, current_block, current_block_dupe),
-            (p.clone(), account_5, decrease_amount, true, h, current_block, current_block_dupe),
-            (p.clone(), account_6, decrease_amount, false, h, current_block, current_block_dupe),
-            (p.clone(), account_7, decrease_amount, false, h, current_block, current_block_dupe),
-        ];
-
-        let track_rev_keys = Vec::<(
-            T::AccountId,
-            AccountOf<T>,
-            AccountBalanceOf<T>,
-            bool,
-            T::Hash,
-            T::BlockNumber,
-            T::BlockNumber,
-        )>::with_capacity(9);
-
-        if let Err(_) = T::Accounting::handle_multiposting_amounts(forward_keys, reversal_keys, track_rev_keys) {
+        Self::set_invoice_details(h, &lines, &taxes)?;
+        if let Err(_) = Self::post_invoice(o.clone(), p.clone(), &lines, h, &taxes) {
             fail!(Error::<T>::ErrorInAccounting2);
         }
 
@@ -661,6 +1376,10 @@ impl<T: Config> Encumbrance<T::AccountId, T::Hash, T::BlockNumber> for Pallet<T>
     fn settle_prefunded_invoice(o: T::AccountId, h: T::Hash, uid: T::Hash) -> DispatchResultWithPostInfo {
         use LockStatus::*;
 
+        if Dispute::<T>::contains_key(&h) {
+            fail!(Error::<T>::ErrorDisputed);
+        }
+
         // release state must be 11
         // sender must be owner
         // accounts updated before payment, because if there is an error then the accounting can be rolled back
@@ -683,70 +1402,7 @@ impl<T: Config> Encumbrance<T::AccountId, T::Hash, T::BlockNumber> for Pallet<T>
                     CurrencyBalanceOf<T>,
                     AccountBalanceOf<T>,
                 >>::convert(prefunded_amount.into());
-                // Convert for calculation
-                let inverted =
-                    -1 * <T::PrefundingConversions as Convert<AccountBalanceOf<T>, i128>>::convert(amount.clone());
-                let increase_amount = amount;
-                let decrease_amount =
-                    <T::PrefundingConversions as Convert<i128, AccountBalanceOf<T>>>::convert(inverted);
-                let current_block = frame_system::Module::<T>::block_number();
-                let current_block_dupe = frame_system::Module::<T>::block_number();
-
-                let account_1 = T::PrefundingConversions::convert(120_20003000_0000_u64); // 120200030000000	Debit  decrease Accounts payable
-                let account_2 = T::PrefundingConversions::convert(110_10005000_0000_u64); // 110100050000000	Credit decrease Totem Runtime Deposit (Escrow)
-                let account_3 = T::PrefundingConversions::convert(360_60002000_0000_u64); // 360600020000000	Credit decrease Runtime Ledger by Module
-                let account_4 = T::PrefundingConversions::convert(360_60006000_0000_u64); // 360600060000000	Credit decrease Runtime Ledger Control
-                let account_5 = T::PrefundingConversions::convert(360_60003000_0000_u64); // 360600030000000	Credit decrease Purchase Ledger by Vendor
-                let account_6 = T::PrefundingConversions::convert(360_60007000_0000_u64); // 360600070000000	Credit decrease Purchase Ledger Control
-
-                let account_7 = T::PrefundingConversions::convert(110_10004000_0000_u64); // 110100040000000	Debit  increase XTX Balance
-                let account_8 = T::PrefundingConversions::convert(110_10008000_0000_u64); // 110100080000000	Credit decrease Accounts receivable (Sales Control Account or Trade Debtor's Account)
-                let account_9 = T::PrefundingConversions::convert(360_60001000_0000_u64); // 360600010000000	Credit decrease Sales Ledger by Payer
-                let account_10 = T::PrefundingConversions::convert(360_60005000_0000_u64); // 360600050000000	Credit decrease Sales Ledger Control
-
-                // Keys for posting
-                let forward_keys = vec![
-                    // Buyer
-                    (o.clone(), account_1, decrease_amount, true, h, current_block, current_block_dupe),
-                    (o.clone(), account_2, decrease_amount, false, h, current_block, current_block_dupe),
-                    (o.clone(), account_3, decrease_amount, false, h, current_block, current_block_dupe),
-                    (o.clone(), account_4, decrease_amount, false, h, current_block, current_block_dupe),
-                    (o.clone(), account_5, decrease_amount, false, h, current_block, current_block_dupe),
-                    (o.clone(), account_6, decrease_amount, false, h, current_block, current_block_dupe),
-                    // Seller
-                    (details.clone(), account_7, increase_amount, true, h, current_block, current_block_dupe),
-                    (details.clone(), account_8, decrease_amount, false, h, current_block, current_block_dupe),
-                    (details.clone(), account_9, decrease_amount, false, h, current_block, current_block_dupe),
-                    (details.clone(), account_10, decrease_amount, false, h, current_block, current_block_dupe),
-                ];
-
-                // Reversal keys in case of errors
-                let reversal_keys = vec![
-                    // Buyer
-                    (o.clone(), account_1, increase_amount, false, h, current_block, current_block_dupe),
-                    (o.clone(), account_2, increase_amount, true, h, current_block, current_block_dupe),
-                    (o.clone(), account_3, increase_amount, true, h, current_block, current_block_dupe),
-                    (o.clone(), account_4, increase_amount, true, h, current_block, current_block_dupe),
-                    (o.clone(), account_5, increase_amount, true, h, current_block, current_block_dupe),
-                    (o.clone(), account_6, increase_amount, true, h, current_block, current_block_dupe),
-                    // Seller
-                    (details.clone(), account_7, decrease_amount, false, h, current_block, current_block_dupe),
-                    (details.clone(), account_8, increase_amount, true, h, current_block, current_block_dupe),
-                    (details.clone(), account_9, increase_amount, true, h, current_block, current_block_dupe),
-                ];
-
-                let track_rev_keys = Vec::<(
-                    T::AccountId,
-                    AccountOf<T>,
-                    AccountBalanceOf<T>,
-                    bool,
-                    T::Hash,
-                    T::BlockNumber,
-                    T::BlockNumber,
-                )>::with_capacity(9);
-
-                if let Err(_) = T::Accounting::handle_multiposting_amounts(forward_keys, reversal_keys, track_rev_keys)
-                {
+                if let Err(_) = Self::post_settlement(o.clone(), details.clone(), amount, h) {
                     fail!(Error::<T>::ErrorInAccounting3);
                 }
 
@@ -787,6 +1443,11 @@ impl<T: Config> Encumbrance<T::AccountId, T::Hash, T::BlockNumber> for Pallet<T>
     fn set_release_state(o: T::AccountId, o_lock: LockStatus, h: T::Hash, uid: T::Hash) -> DispatchResultWithPostInfo {
         use LockStatus::*;
 
+        // Only the arbiter can release disputed funds
+        if Dispute::<T>::contains_key(&h) {
+            fail!(Error::<T>::ErrorDisputed);
+        }
+
         // 0= false, 1=true
         // 10, sender can take after deadline (initial state)
         // 11, accepted by recipient. (funds locked, nobody can take)
@@ -902,6 +1563,9 @@ impl<T: Config> Encumbrance<T::AccountId, T::Hash, T::BlockNumber> for Pallet<T>
             // Check if the dealine has passed. If not funds cannot be release
             (Locked, Unlocked) => {
                 if Self::prefund_deadline_passed(h) {
+                    if let Err(_) = Self::post_prefunding_reversal(o.clone(), h) {
+                        fail!(Error::<T>::ErrorInAccounting9);
+                    }
                     let status: Status = 50; // Abandoned or Cancelled
                     if let Err(_) = Self::cancel_prefunding_lock(o.clone(), h, status) {
                         fail!(Error::<T>::ErrorCancelFailed2);
@@ -914,6 +1578,9 @@ impl<T: Config> Encumbrance<T::AccountId, T::Hash, T::BlockNumber> for Pallet<T>
             (Unlocked, Locked) => fail!(Error::<T>::ErrorNotAllowed6),
             (Unlocked, Unlocked) => {
                 // Owner has been  given permission by beneficiary to release funds
+                if let Err(_) = Self::post_prefunding_reversal(o.clone(), h) {
+                    fail!(Error::<T>::ErrorInAccounting9);
+                }
                 let status: Status = 50; // Abandoned or cancelled
                 if let Err(_) = Self::cancel_prefunding_lock(o.clone(), h, status) {
                     fail!(Error::<T>::ErrorCancellingPrefund);
@@ -923,4 +1590,351 @@ impl<T: Config> Encumbrance<T::AccountId, T::Hash, T::BlockNumber> for Pallet<T>
 
         ok()
     }
+
+    /// Raises a dispute over funds locked by both parties. Can be used by the owner or the beneficiary.
+    /// Until the arbiter rules, the reference can no longer be invoiced, settled or released.
+    fn raise_dispute(o: T::AccountId, h: T::Hash, uid: T::Hash) -> DispatchResultWithPostInfo {
+        use LockStatus::*;
+
+        if Self::reference_valid(h) == false {
+            fail!(Error::<T>::ErrorHashDoesNotExist4);
+        }
+
+        if Self::check_ref_owner(o.clone(), h) == false && Self::check_ref_beneficiary(o.clone(), h) == false {
+            fail!(Error::<T>::ErrorNotAllowed7);
+        }
+
+        if Dispute::<T>::contains_key(&h) {
+            fail!(Error::<T>::ErrorDisputed);
+        }
+
+        match Self::get_release_state(h) {
+            (Locked, Locked) => (),
+            _ => fail!(Error::<T>::ErrorDisputeNotAllowed),
+        }
+
+        // Keep the current status, it determines what is reversed when the dispute is settled
+        let status = ReferenceStatus::<T>::get(&h).ok_or(Error::<T>::ErrorHashDoesNotExist4)?;
+        Dispute::<T>::insert(&h, (o, status));
+        let new_status: Status = 300; // disputed(300)
+        Self::set_ref_status(h, new_status)?;
+
+        Self::deposit_event(Event::DisputeRaised(uid));
+
+        ok()
+    }
+
+    /// Invoices a portion of an accepted order. The invoice is posted in the same way as a simple invoice,
+    /// but the order stays accepted so that further milestones can be invoiced up to the prefunded amount.
+    fn send_milestone_invoice(
+        o: T::AccountId,
+        lines: Vec<InvoiceLine>,
+        h: T::Hash,
+        uid: T::Hash,
+        taxes: Vec<TaxLine<T::AccountId>>,
+    ) -> DispatchResultWithPostInfo {
+        use LockStatus::*;
+
+        // Validate that the hash is indeed assigned to the seller
+        if Self::check_ref_beneficiary(o.clone(), h) == false {
+            fail!(Error::<T>::ErrorNotAllowed2);
+        }
+
+        if Dispute::<T>::contains_key(&h) {
+            fail!(Error::<T>::ErrorDisputed);
+        }
+
+        // Funds must be locked by both parties and the order not invoiced in full
+        match (Self::get_release_state(h), ReferenceStatus::<T>::get(&h)) {
+            ((Locked, Locked), Some(1)) => (),
+            _ => fail!(Error::<T>::ErrorMilestoneNotAllowed),
+        }
+
+        let (payer, _, _, _) = Self::prefunding_hash_owner(&h).ok_or(Error::<T>::ErrorNoDetails)?;
+        let (prefunded_amount, _) = Self::prefunding(&h).ok_or(Error::<T>::ErrorNoPrefunding)?;
+        let prefunded: u128 =
+            <T::PrefundingConversions as Convert<CurrencyBalanceOf<T>, u128>>::convert(prefunded_amount);
+        let n: u128 = match InvoiceLine::total(&lines) {
+            Some(n) if n > 0 => n as u128,
+            _ => fail!(Error::<T>::ErrorMilestoneAmount),
+        };
+        if Self::outstanding_milestones(h).saturating_add(n) > prefunded {
+            fail!(Error::<T>::ErrorMilestoneAmount);
+        }
+
+        let amount_converted: AccountBalanceOf<T> =
+            <T::PrefundingConversions as Convert<u128, AccountBalanceOf<T>>>::convert(n);
+        Self::set_invoice_details(h, &lines, &taxes)?;
+        if let Err(_) = Self::post_invoice(o, payer, &lines, h, &taxes) {
+            fail!(Error::<T>::ErrorInAccounting5);
+        }
+
+        let amount: CurrencyBalanceOf<T> = T::PrefundingConversions::convert(amount_converted);
+        let status: Status = 400; // invoiced(400)
+        Milestones::<T>::append(&h, (amount, status));
+
+        Self::deposit_event(Event::MilestoneInvoiced(uid));
+
+        ok()
+    }
+
+    /// Settles an invoiced milestone. Only the milestone amount is paid to the seller, the remainder of the
+    /// prefunded amount stays locked. Settling the last of the prefunded amount completes the prefunding.
+    fn settle_milestone(o: T::AccountId, h: T::Hash, m: u16, uid: T::Hash) -> DispatchResultWithPostInfo {
+        use LockStatus::*;
+
+        // Validate that the hash is indeed owned by the buyer
+        if Self::check_ref_owner(o.clone(), h) == false {
+            fail!(Error::<T>::ErrorNotAllowed3);
+        }
+
+        if Dispute::<T>::contains_key(&h) {
+            fail!(Error::<T>::ErrorDisputed);
+        }
+
+        if Self::get_release_state(h) != (Locked, Locked) {
+            fail!(Error::<T>::ErrorMilestoneNotAllowed);
+        }
+
+        let (_, _, beneficiary, _) = Self::prefunding_hash_owner(&h).ok_or(Error::<T>::ErrorNoDetails)?;
+        let (prefunded_amount, deadline) = Self::prefunding(&h).ok_or(Error::<T>::ErrorNoPrefunding)?;
+        let mut milestones = Self::milestones(&h).unwrap_or_default();
+        let amount = match milestones.get(m as usize) {
+            Some((amount, 400)) => *amount,
+            _ => fail!(Error::<T>::ErrorNoMilestone),
+        };
+
+        let amount_converted: AccountBalanceOf<T> =
+            <T::PrefundingConversions as Convert<CurrencyBalanceOf<T>, AccountBalanceOf<T>>>::convert(amount);
+        if let Err(_) = Self::post_settlement(o.clone(), beneficiary.clone(), amount_converted, h) {
+            fail!(Error::<T>::ErrorInAccounting6);
+        }
+
+        // Release only the settled slice of the lock
+        let remaining = prefunded_amount.saturating_sub(amount);
+        if remaining.is_zero() {
+            let status: Status = 500; // Settled
+            Self::cancel_prefunding_lock(o.clone(), h, status)?;
+        } else {
+            T::Currency::totem_set_lock(Self::get_prefunding_id(h), &o, remaining, deadline, WithdrawReasons::RESERVE);
+            Prefunding::<T>::insert(&h, (remaining, deadline));
+            milestones[m as usize].1 = 500; // settled(500)
+            Milestones::<T>::insert(&h, milestones);
+        }
+
+        // TODO when currency conversion is implemnted the payment should be at the current rate for the currency
+        if let Err(_) = T::Currency::transfer(&o, &beneficiary, amount, ExistenceRequirement::KeepAlive) {
+            fail!("Error during transfer")
+        }
+
+        Self::deposit_event(Event::MilestoneSettled(uid));
+
+        ok()
+    }
+
+    /// Invoice issued on credit, without prefunding. The amount includes the sales taxes of each tax jurisdiction.
+    /// The reference must not be used by any prefunding, and the invoice remains outstanding until the buyer pays it.
+    /// There is no extrinsic for this: it is only issued by the orders pallet, for an accepted unfunded order.
+    fn send_unfunded_invoice(
+        o: T::AccountId,
+        p: T::AccountId,
+        lines: Vec<InvoiceLine>,
+        d: T::BlockNumber,
+        h: T::Hash,
+        uid: T::Hash,
+        taxes: Vec<TaxLine<T::AccountId>>,
+    ) -> DispatchResultWithPostInfo {
+        let n: u128 = match InvoiceLine::total(&lines) {
+            Some(n) if n > 0 && o != p => n as u128,
+            _ => fail!(Error::<T>::ErrorInvoiceNotAllowed),
+        };
+
+        if ReferenceStatus::<T>::contains_key(&h) {
+            fail!(Error::<T>::ErrorHashExists);
+        }
+
+        if d <= frame_system::Module::<T>::block_number() {
+            fail!(Error::<T>::ErrorDueDate);
+        }
+
+        let max_invoices = T::MaxUnfundedInvoices::get() as usize;
+        if Self::unfunded_invoice_list(&o).map_or(false, |l| l.len() >= max_invoices)
+            || Self::unfunded_invoice_list(&p).map_or(false, |l| l.len() >= max_invoices)
+        {
+            fail!(Error::<T>::ErrorTooManyInvoices);
+        }
+
+        let amount_converted: AccountBalanceOf<T> =
+            <T::PrefundingConversions as Convert<u128, AccountBalanceOf<T>>>::convert(n);
+        Self::validate_taxes(&taxes)?;
+        if let Err(_) = Self::post_invoice(o.clone(), p.clone(), &lines, h, &taxes) {
+            fail!(Error::<T>::ErrorInAccounting7);
+        }
+
+        let amount: CurrencyBalanceOf<T> = T::PrefundingConversions::convert(amount_converted);
+        UnfundedInvoice::<T>::insert(&h, (o.clone(), p.clone(), amount, d));
+        UnfundedInvoiceList::<T>::append(&o, h);
+        UnfundedInvoiceList::<T>::append(&p, h);
+        let new_status: Status = 400; // invoiced(400)
+        Self::set_ref_status(h, new_status)?;
+
+        Self::deposit_event(Event::InvoiceIssued(uid));
+
+        ok()
+    }
+
+    /// Settles an invoice issued on credit by transfer of the invoiced amount from the buyer to the seller.
+    /// The invoice can be paid after the due date.
+    fn settle_unfunded_invoice(o: T::AccountId, h: T::Hash, uid: T::Hash) -> DispatchResultWithPostInfo {
+        let (seller, payer, amount, _) = Self::unfunded_invoice(&h).ok_or(Error::<T>::ErrorNoInvoice)?;
+        if o != payer {
+            fail!(Error::<T>::ErrorNotAllowed3);
+        }
+
+        let amount_converted: AccountBalanceOf<T> =
+            <T::PrefundingConversions as Convert<CurrencyBalanceOf<T>, AccountBalanceOf<T>>>::convert(amount);
+        if let Err(_) = Self::post_unfunded_settlement(o.clone(), seller.clone(), amount_converted, h) {
+            fail!(Error::<T>::ErrorInAccounting8);
+        }
+
+        // TODO when currency conversion is implemnted the payment should be at the current rate for the currency
+        if let Err(_) = T::Currency::transfer(&o, &seller, amount, ExistenceRequirement::KeepAlive) {
+            fail!("Error during transfer")
+        }
+
+        UnfundedInvoice::<T>::remove(&h);
+        UnfundedInvoiceList::<T>::mutate_(&seller, |invoice_list| invoice_list.retain(|e| e != &h));
+        UnfundedInvoiceList::<T>::mutate_(&o, |invoice_list| invoice_list.retain(|e| e != &h));
+        let new_status: Status = 500; // Settled
+        Self::set_ref_status(h, new_status)?;
+
+        Self::deposit_event(Event::InvoiceSettled(uid));
+
+        ok()
+    }
+}
+
+impl<T: Config> Escrow<T::AccountId, T::Hash, T::BlockNumber> for Pallet<T> {
+    /// Locks the funds of the owner in the same way as prefunding an order, and nominates the arbiter if there is one.
+    fn create_escrow(
+        o: T::AccountId,
+        b: T::AccountId,
+        amount: u128,
+        d: T::BlockNumber,
+        a: Option<T::AccountId>,
+        h: T::Hash,
+        uid: T::Hash,
+    ) -> DispatchResultWithPostInfo {
+        if o == b {
+            fail!(Error::<T>::ErrorEscrowParty);
+        }
+        // Check the arbiter before any funds are locked
+        if let Some(arbiter) = &a {
+            if arbiter == &o || arbiter == &b {
+                fail!(Error::<T>::ErrorArbiterIsParty);
+            }
+        }
+
+        Self::prefunding_for(o.clone(), b, amount, d, h, uid)?;
+        if let Some(arbiter) = a {
+            Self::set_arbiter(o, h, arbiter, uid)?;
+        }
+
+        ok()
+    }
+
+    /// Pays part or all of the escrow to the beneficiary. Escrow that has been invoiced is settled through the invoice.
+    fn release_escrow(o: T::AccountId, h: T::Hash, amount: u128, uid: T::Hash) -> DispatchResultWithPostInfo {
+        let (owner, _, beneficiary, _) = Self::prefunding_hash_owner(&h).ok_or(Error::<T>::ErrorNoDetails)?;
+        if o != owner || ReferenceStatus::<T>::get(&h) != Some(1) || Milestones::<T>::contains_key(&h) {
+            fail!(Error::<T>::ErrorEscrowNotAllowed);
+        }
+
+        let (prefunded_amount, deadline) = Self::prefunding(&h).ok_or(Error::<T>::ErrorNoPrefunding)?;
+        let prefunded: u128 =
+            <T::PrefundingConversions as Convert<CurrencyBalanceOf<T>, u128>>::convert(prefunded_amount);
+        if amount == 0 {
+            fail!(Error::<T>::ErrorEscrowAmount);
+        }
+        if amount > prefunded {
+            fail!(Error::<T>::ErrorReleaseTooHigh);
+        }
+
+        if let Err(_) = Self::post_escrow_release(o.clone(), beneficiary.clone(), amount, h) {
+            fail!(Error::<T>::ErrorInAccounting10);
+        }
+
+        let amount_converted: AccountBalanceOf<T> =
+            <T::PrefundingConversions as Convert<u128, AccountBalanceOf<T>>>::convert(amount);
+        let amount: CurrencyBalanceOf<T> = T::PrefundingConversions::convert(amount_converted);
+        // Release only the released slice of the lock
+        let remaining = prefunded_amount.saturating_sub(amount);
+        if remaining.is_zero() {
+            let status: Status = 500; // Settled
+            Self::cancel_prefunding_lock(o.clone(), h, status)?;
+        } else {
+            T::Currency::totem_set_lock(Self::get_prefunding_id(h), &o, remaining, deadline, WithdrawReasons::RESERVE);
+            Prefunding::<T>::insert(&h, (remaining, deadline));
+        }
+
+        if let Err(_) = T::Currency::transfer(&o, &beneficiary, amount, ExistenceRequirement::KeepAlive) {
+            fail!("Error during transfer")
+        }
+
+        Self::deposit_event(Event::EscrowReleased(uid));
+
+        ok()
+    }
+
+    /// Unlocks the escrow for the owner and reverses it in the accounts of the owner.
+    fn refund_escrow(who: T::AccountId, h: T::Hash, uid: T::Hash) -> DispatchResultWithPostInfo {
+        use LockStatus::*;
+
+        let (owner, owner_lock, beneficiary, beneficiary_lock) =
+            Self::prefunding_hash_owner(&h).ok_or(Error::<T>::ErrorNoDetails)?;
+        // Invoiced or disputed escrow is settled through the invoice or by the arbiter
+        if ReferenceStatus::<T>::get(&h) != Some(1) || Self::outstanding_milestones(h) != 0 {
+            fail!(Error::<T>::ErrorEscrowNotAllowed);
+        }
+
+        let allowed = match (owner_lock, beneficiary_lock) {
+            _ if who == beneficiary => true,
+            (Locked, Unlocked) if who == owner => Self::prefund_deadline_passed(h),
+            (Unlocked, Unlocked) if who == owner => true,
+            _ => false,
+        };
+        if !allowed {
+            fail!(Error::<T>::ErrorEscrowNotAllowed);
+        }
+
+        if let Err(_) = Self::post_prefunding_reversal(owner.clone(), h) {
+            fail!(Error::<T>::ErrorInAccounting9);
+        }
+        let status: Status = 50; // Abandoned or Cancelled
+        Self::cancel_prefunding_lock(owner, h, status)?;
+
+        Self::deposit_event(Event::EscrowRefunded(uid));
+
+        ok()
+    }
+
+    /// References that are not escrowed, such as invoices issued on credit, have nothing locked.
+    fn escrow_state(h: T::Hash) -> Option<(EscrowState, u128)> {
+        let locked: u128 = Self::prefunding(&h)
+            .map(|(amount, _)| <T::PrefundingConversions as Convert<CurrencyBalanceOf<T>, u128>>::convert(amount))
+            .unwrap_or_default();
+        let state = match ReferenceStatus::<T>::get(&h)? {
+            1 => match Self::prefunding_hash_owner(&h)? {
+                (_, _, _, LockStatus::Locked) => EscrowState::Locked,
+                _ => EscrowState::Pending,
+            },
+            300 => EscrowState::Disputed,
+            400 => EscrowState::Invoiced,
+            500 => EscrowState::Released,
+            50 => EscrowState::Refunded,
+            _ => return None,
+        };
+
+        Some((state, locked))
+    }
 }
@@ -0,0 +1,292 @@
+#![cfg(test)]
+
+use crate as pallet_prefunding;
+
+use frame_support::{dispatch::DispatchResultWithPostInfo, parameter_types, traits::BalanceStatus};
+use frame_system::EnsureRoot;
+use sp_core::H256;
+use sp_runtime::{
+    testing::Header,
+    traits::{BlakeTwo256, Convert, IdentityLookup},
+};
+use std::cell::RefCell;
+use totem_utils::ok;
+use totem_utils::traits::{accounting::Posting, orders::Arbitrating};
+
+type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
+type Block = frame_system::mocking::MockBlock<Test>;
+
+pub const BUYER: u64 = 1;
+pub const SELLER: u64 = 2;
+pub const ARBITER: u64 = 3;
+pub const TAX_OFFICE: u64 = 4;
+pub const OTHER_TAX_OFFICE: u64 = 5;
+/// Holds the locked funds.
+pub const ESCROW: u64 = 99;
+
+// Configure a mock runtime to test the pallet.
+frame_support::construct_runtime!(
+    pub enum Test where
+        Block = Block,
+        NodeBlock = Block,
+        UncheckedExtrinsic = UncheckedExtrinsic,
+    {
+        System: frame_system::{Module, Call, Config, Storage, Event<T>},
+        Balances: pallet_balances::{Module, Call, Config<T>, Storage, Event<T>},
+        Timestamp: pallet_timestamp::{Module, Call, Storage, Inherent},
+        Accounting: pallet_accounting::{Module, Call, Config, Storage, Event<T>},
+        Prefunding: pallet_prefunding::{Module, Call, Storage, Event<T>},
+    }
+);
+
+parameter_types! {
+    pub const BlockHashCount: u64 = 250;
+    pub const SS58Prefix: u8 = 42;
+}
+
+impl frame_system::Config for Test {
+    type BaseCallFilter = ();
+    type BlockWeights = ();
+    type BlockLength = ();
+    type DbWeight = ();
+    type Origin = Origin;
+    type Call = Call;
+    type Index = u64;
+    type BlockNumber = u64;
+    type Hash = H256;
+    type Hashing = BlakeTwo256;
+    type AccountId = u64;
+    type Lookup = IdentityLookup<Self::AccountId>;
+    type Header = Header;
+    type Event = Event;
+    type BlockHashCount = BlockHashCount;
+    type Version = ();
+    type PalletInfo = PalletInfo;
+    type AccountData = pallet_balances::AccountData<u64>;
+    type OnNewAccount = ();
+    type OnKilledAccount = ();
+    type SystemWeightInfo = ();
+    type SS58Prefix = SS58Prefix;
+}
+
+parameter_types! {
+    pub const ExistentialDeposit: u64 = 1;
+}
+
+impl pallet_balances::Config for Test {
+    type MaxLocks = ();
+    type Balance = u64;
+    type DustRemoval = ();
+    type Event = Event;
+    type ExistentialDeposit = ExistentialDeposit;
+    type AccountStore = System;
+    type WeightInfo = ();
+    type Accounting = MockAccounting;
+}
+
+parameter_types! {
+    pub const MinimumPeriod: u64 = 5;
+}
+
+impl pallet_timestamp::Config for Test {
+    type Moment = u64;
+    type OnTimestampSet = ();
+    type MinimumPeriod = MinimumPeriod;
+    type WeightInfo = ();
+}
+
+impl pallet_accounting::Config for Test {
+    type Event = Event;
+    type AccountingConversions = MockConversions;
+    type ChartOfAccountsOrigin = EnsureRoot<u64>;
+}
+
+parameter_types! {
+    pub const MaxExpiriesPerBlock: u32 = 2;
+    pub const MaxUnfundedInvoices: u32 = 2;
+    pub const MaxTaxLines: u32 = 2;
+}
+
+impl pallet_prefunding::Config for Test {
+    type Event = Event;
+    type Currency = Balances;
+    type PrefundingConversions = MockConversions;
+    type ArbitrationOrigin = EnsureRoot<u64>;
+    type MaxExpiriesPerBlock = MaxExpiriesPerBlock;
+    type MaxUnfundedInvoices = MaxUnfundedInvoices;
+    type MaxTaxLines = MaxTaxLines;
+    type Orders = MockOrders;
+}
+
+thread_local! {
+    static RESOLVED: RefCell<Vec<(H256, bool)>> = RefCell::new(Vec::new());
+}
+
+/// Takes the rulings passed on to the orders since the last call: the reference and whether funds were released.
+pub fn disputes_resolved() -> Vec<(H256, bool)> {
+    RESOLVED.with(|resolved| resolved.borrow_mut().drain(..).collect())
+}
+
+pub struct MockOrders;
+
+impl Arbitrating<H256> for MockOrders {
+    fn dispute_resolved(h: H256, released: bool, _uid: H256) -> DispatchResultWithPostInfo {
+        RESOLVED.with(|resolved| resolved.borrow_mut().push((h, released)));
+        ok()
+    }
+}
+
+/// A posting key: identity, account, amount, debit indicator, reference, change block and applicable block.
+pub type PostingKey = (u64, u64, i128, bool, H256, u64, u64);
+
+thread_local! {
+    static POSTED: RefCell<Vec<PostingKey>> = RefCell::new(Vec::new());
+    static SALES_TAX: RefCell<Vec<(u64, u64, i128)>> = RefCell::new(Vec::new());
+}
+
+/// Takes the keys posted to the ledgers since the last call.
+pub fn posted() -> Vec<PostingKey> {
+    POSTED.with(|posted| posted.borrow_mut().drain(..).collect())
+}
+
+/// Takes the sales taxes recorded since the last call: the vendor, the tax jurisdiction and the amount.
+pub fn sales_taxes() -> Vec<(u64, u64, i128)> {
+    SALES_TAX.with(|recorded| recorded.borrow_mut().drain(..).collect())
+}
+
+// Postings and sales taxes of the pallet are recorded, those of the balances are accepted without being recorded.
+pub struct MockAccounting;
+
+impl Posting<u64, H256, u64, u64> for MockAccounting {
+    type Account = u64;
+    type PostingIndex = u128;
+    type LedgerBalance = i128;
+
+    fn handle_multiposting_amounts(keys: Vec<PostingKey>) -> DispatchResultWithPostInfo {
+        POSTED.with(|posted| posted.borrow_mut().extend(keys));
+        ok()
+    }
+
+    fn account_for_fees(_f: u64, _t: u64, _r: u64, _p: u64) -> DispatchResultWithPostInfo {
+        ok()
+    }
+
+    fn account_for_fee_revenue(_f: u64, _r: u64) -> DispatchResultWithPostInfo {
+        ok()
+    }
+
+    fn account_for_transfer(
+        _f: u64,
+        _fs: BalanceStatus,
+        _t: u64,
+        _ts: BalanceStatus,
+        _a: u64,
+    ) -> DispatchResultWithPostInfo {
+        ok()
+    }
+
+    fn account_for_reserve(_w: u64, _a: u64, _s: BalanceStatus) -> DispatchResultWithPostInfo {
+        ok()
+    }
+
+    fn account_for_slash(_w: u64, _a: u64, _s: BalanceStatus) -> DispatchResultWithPostInfo {
+        ok()
+    }
+
+    fn account_for_deposit(_w: u64, _a: u64, _s: BalanceStatus) -> DispatchResultWithPostInfo {
+        ok()
+    }
+
+    fn account_for_withdrawal(_w: u64, _a: u64, _s: BalanceStatus) -> DispatchResultWithPostInfo {
+        ok()
+    }
+
+    fn account_for_opening_balance(_w: u64) -> DispatchResultWithPostInfo {
+        ok()
+    }
+
+    fn record_sales_tax(v: u64, j: u64, a: Self::LedgerBalance) -> DispatchResultWithPostInfo {
+        SALES_TAX.with(|recorded| recorded.borrow_mut().push((v, j, a)));
+        ok()
+    }
+
+    fn get_escrow_account() -> u64 {
+        ESCROW
+    }
+
+    fn get_pseudo_random_hash(_s: u64, _r: u64) -> H256 {
+        unimplemented!("Used as a mock, shouldn't be called")
+    }
+}
+
+pub struct MockConversions;
+
+impl Convert<i128, u128> for MockConversions {
+    fn convert(u: i128) -> u128 {
+        u as u128
+    }
+}
+
+impl Convert<i128, u64> for MockConversions {
+    fn convert(u: i128) -> u64 {
+        u as u64
+    }
+}
+
+impl Convert<u64, i128> for MockConversions {
+    fn convert(u: u64) -> i128 {
+        u as i128
+    }
+}
+
+impl Convert<Vec<u8>, [u8; 8]> for MockConversions {
+    fn convert(u: Vec<u8>) -> [u8; 8] {
+        let mut id = [0u8; 8];
+        id.copy_from_slice(&u[..8]);
+        id
+    }
+}
+
+impl Convert<u64, u64> for MockConversions {
+    fn convert(u: u64) -> u64 {
+        u
+    }
+}
+
+impl Convert<u32, u64> for MockConversions {
+    fn convert(u: u32) -> u64 {
+        u as u64
+    }
+}
+
+impl Convert<i128, i128> for MockConversions {
+    fn convert(u: i128) -> i128 {
+        u
+    }
+}
+
+impl Convert<u128, i128> for MockConversions {
+    fn convert(u: u128) -> i128 {
+        u as i128
+    }
+}
+
+impl Convert<u64, u128> for MockConversions {
+    fn convert(u: u64) -> u128 {
+        u as u128
+    }
+}
+
+// This function basically just builds a genesis storage key/value store according to
+// our desired mockup.
+pub fn new_test_ext() -> sp_io::TestExternalities {
+    let mut t = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+    pallet_balances::GenesisConfig::<Test> {
+        balances: vec![(BUYER, 10_000), (SELLER, 10_000)],
+    }
+    .assimilate_storage(&mut t)
+    .unwrap();
+    let mut ext: sp_io::TestExternalities = t.into();
+    ext.execute_with(|| System::set_block_number(1));
+    ext
+}
@@ -0,0 +1,666 @@
+#![cfg(test)]
+
+use crate::mock::*;
+use crate::{Call as PrefundingCall, Error, LockStatus};
+
+use frame_support::{
+    assert_noop, assert_ok,
+    dispatch::DispatchResultWithPostInfo,
+    traits::{OnInitialize, UnfilteredDispatchable},
+};
+use sp_core::H256;
+use sp_runtime::Permill;
+use totem_utils::chart_of_accounts::LedgerAccount;
+use totem_utils::traits::{escrow::Escrow, prefunding::Encumbrance};
+use totem_utils::types::{EscrowState, InvoiceLine, TaxLine};
+
+/// The prefunded amount of an order.
+const AMOUNT: u128 = 1000;
+/// The earliest deadline allowed when prefunding in the first block.
+const DEADLINE: u64 = 1 + 11520;
+
+fn dispatch(origin: Origin, call: PrefundingCall<Test>) -> DispatchResultWithPostInfo {
+    call.dispatch_bypass_filter(origin)
+}
+
+fn reference() -> H256 {
+    H256::repeat_byte(1)
+}
+
+fn uid() -> H256 {
+    H256::repeat_byte(9)
+}
+
+fn free(who: u64) -> u64 {
+    Balances::free_balance(&who)
+}
+
+// The net amount posted to the ledger `account` of `who`.
+fn posted_to(keys: &[PostingKey], who: u64, account: LedgerAccount) -> i128 {
+    keys.iter().filter(|key| key.0 == who && key.1 == account.number()).map(|key| key.2).sum()
+}
+
+// The buyer locks `AMOUNT` for the seller.
+fn prefund(h: H256) -> DispatchResultWithPostInfo {
+    Prefunding::prefunding_for(BUYER, SELLER, AMOUNT, DEADLINE, h, uid())
+}
+
+// The seller accepts the order, locking the funds for both parties.
+fn accept(h: H256) -> DispatchResultWithPostInfo {
+    Prefunding::set_release_state(SELLER, LockStatus::Locked, h, uid())
+}
+
+// The buyer prefunds an order with an arbiter, the seller accepts it and the buyer disputes it.
+fn disputed_order(h: H256) {
+    assert_ok!(prefund(h));
+    assert_ok!(dispatch(Origin::signed(BUYER), PrefundingCall::nominate_arbiter(h, ARBITER, uid())));
+    assert_ok!(accept(h));
+    assert_ok!(dispatch(Origin::signed(BUYER), PrefundingCall::dispute_prefunded_order(h, uid())));
+    posted();
+}
+
+fn rule(origin: Origin, h: H256, release: u128) -> DispatchResultWithPostInfo {
+    dispatch(origin, PrefundingCall::resolve_dispute(h, release, uid()))
+}
+
+#[test]
+fn ruling_of_a_full_release_pays_the_prefunded_amount_to_the_seller() {
+    new_test_ext().execute_with(|| {
+        disputed_order(reference());
+        assert_eq!(Prefunding::reference_status(reference()), Some(300));
+
+        assert_ok!(rule(Origin::signed(ARBITER), reference(), AMOUNT));
+
+        assert_eq!((free(BUYER), free(SELLER), free(ESCROW)), (9000, 11_000, 0));
+        assert_eq!(Prefunding::reference_status(reference()), Some(500));
+        assert_eq!(Prefunding::dispute(reference()), None);
+        assert!(Balances::totem_locks(&BUYER).is_empty());
+        assert_eq!(disputes_resolved(), vec![(reference(), true)]);
+
+        let keys = posted();
+        assert_eq!(posted_to(&keys, BUYER, LedgerAccount::PREFUNDING), -1000);
+        assert_eq!(posted_to(&keys, BUYER, LedgerAccount::XTX_BALANCE), 1000);
+        assert_eq!(posted_to(&keys, BUYER, LedgerAccount::TRANSFERS_SUSPENSE), -1000);
+        assert_eq!(posted_to(&keys, SELLER, LedgerAccount::TRANSFERS_SUSPENSE), 1000);
+        assert_eq!(posted_to(&keys, BUYER, LedgerAccount::LABOUR), 1000);
+        assert_eq!(posted_to(&keys, SELLER, LedgerAccount::SALES), 1000);
+    });
+}
+
+#[test]
+fn ruling_of_a_partial_release_splits_the_prefunded_amount() {
+    new_test_ext().execute_with(|| {
+        disputed_order(reference());
+
+        assert_ok!(rule(Origin::signed(ARBITER), reference(), 600));
+
+        assert_eq!((free(BUYER), free(SELLER), free(ESCROW)), (9400, 10_600, 0));
+        assert_eq!(Prefunding::reference_status(reference()), Some(500));
+        assert_eq!(disputes_resolved(), vec![(reference(), true)]);
+
+        // The prefunding is unlocked in full and only the released amount is paid
+        let keys = posted();
+        assert_eq!(posted_to(&keys, BUYER, LedgerAccount::PREFUNDING), -1000);
+        assert_eq!(posted_to(&keys, BUYER, LedgerAccount::XTX_BALANCE), 1000);
+        assert_eq!(posted_to(&keys, BUYER, LedgerAccount::TRANSFERS_SUSPENSE), -600);
+        assert_eq!(posted_to(&keys, SELLER, LedgerAccount::TRANSFERS_SUSPENSE), 600);
+        assert_eq!(posted_to(&keys, BUYER, LedgerAccount::LABOUR), 600);
+        assert_eq!(posted_to(&keys, SELLER, LedgerAccount::SALES), 600);
+    });
+}
+
+#[test]
+fn ruling_of_no_release_returns_the_prefunded_amount_to_the_buyer() {
+    new_test_ext().execute_with(|| {
+        disputed_order(reference());
+
+        assert_ok!(rule(Origin::root(), reference(), 0));
+
+        assert_eq!((free(BUYER), free(SELLER), free(ESCROW)), (10_000, 10_000, 0));
+        assert_eq!(Prefunding::reference_status(reference()), Some(500));
+        assert_eq!(disputes_resolved(), vec![(reference(), false)]);
+
+        let keys = posted();
+        assert_eq!(posted_to(&keys, BUYER, LedgerAccount::PREFUNDING), -1000);
+        assert_eq!(posted_to(&keys, BUYER, LedgerAccount::XTX_BALANCE), 1000);
+        assert!(keys.iter().all(|key| key.1 != LedgerAccount::TRANSFERS_SUSPENSE.number()));
+        assert!(keys.iter().all(|key| key.0 == BUYER));
+    });
+}
+
+#[test]
+fn only_the_arbiter_or_the_arbitration_origin_can_rule() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(prefund(reference()));
+        assert_ok!(accept(reference()));
+        assert_noop!(rule(Origin::root(), reference(), 0), Error::<Test>::ErrorNotDisputed);
+        assert_ok!(dispatch(Origin::signed(SELLER), PrefundingCall::dispute_prefunded_order(reference(), uid())));
+
+        // No arbiter was nominated
+        assert_noop!(rule(Origin::signed(ARBITER), reference(), 0), Error::<Test>::ErrorNotArbiter);
+        assert_noop!(rule(Origin::signed(SELLER), reference(), AMOUNT), Error::<Test>::ErrorNotArbiter);
+        assert_noop!(rule(Origin::root(), reference(), AMOUNT + 1), Error::<Test>::ErrorReleaseTooHigh);
+
+        assert_ok!(rule(Origin::root(), reference(), 500));
+        assert_eq!((free(BUYER), free(SELLER)), (9500, 10_500));
+    });
+}
+
+#[test]
+fn disputed_funds_stay_locked_until_the_ruling() {
+    new_test_ext().execute_with(|| {
+        disputed_order(reference());
+        let invoice = PrefundingCall::invoice_prefunded_order(BUYER, 1000, reference(), uid(), vec![]);
+
+        assert_noop!(dispatch(Origin::signed(SELLER), invoice), Error::<Test>::ErrorDisputed);
+        assert_noop!(
+            dispatch(Origin::signed(BUYER), PrefundingCall::pay_prefunded_invoice(reference(), uid())),
+            Error::<Test>::ErrorDisputed
+        );
+        assert_noop!(
+            Prefunding::set_release_state(SELLER, LockStatus::Unlocked, reference(), uid()),
+            Error::<Test>::ErrorDisputed
+        );
+        assert_noop!(
+            dispatch(Origin::signed(SELLER), PrefundingCall::dispute_prefunded_order(reference(), uid())),
+            Error::<Test>::ErrorDisputed
+        );
+        assert_eq!(free(ESCROW), 1000);
+    });
+}
+
+#[test]
+fn arbiter_is_nominated_by_the_buyer_before_the_order_is_accepted() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(prefund(reference()));
+        let nominate = |who: u64, arbiter: u64| {
+            dispatch(Origin::signed(who), PrefundingCall::nominate_arbiter(reference(), arbiter, uid()))
+        };
+
+        assert_noop!(nominate(SELLER, ARBITER), Error::<Test>::ErrorArbiterNotAllowed);
+        assert_noop!(nominate(BUYER, SELLER), Error::<Test>::ErrorArbiterIsParty);
+        // A dispute can only be raised once the order is accepted
+        assert_noop!(
+            dispatch(Origin::signed(BUYER), PrefundingCall::dispute_prefunded_order(reference(), uid())),
+            Error::<Test>::ErrorDisputeNotAllowed
+        );
+
+        assert_ok!(accept(reference()));
+        assert_noop!(nominate(BUYER, ARBITER), Error::<Test>::ErrorArbiterNotAllowed);
+        assert_eq!(Prefunding::arbiter(reference()), None);
+    });
+}
+
+fn invoice_milestone(amount: u128) -> DispatchResultWithPostInfo {
+    dispatch(Origin::signed(SELLER), PrefundingCall::invoice_milestone(reference(), amount, uid(), vec![]))
+}
+
+fn pay_milestone(who: u64, milestone: u16) -> DispatchResultWithPostInfo {
+    dispatch(Origin::signed(who), PrefundingCall::pay_milestone(reference(), milestone, uid()))
+}
+
+#[test]
+fn paid_milestone_releases_only_its_slice_of_the_prefunding() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(prefund(reference()));
+        assert_ok!(accept(reference()));
+        assert_ok!(invoice_milestone(400));
+        posted();
+
+        assert_ok!(pay_milestone(BUYER, 0));
+
+        assert_eq!((free(BUYER), free(SELLER), free(ESCROW)), (9000, 10_400, 600));
+        assert_eq!(Prefunding::prefunding(reference()), Some((600, DEADLINE)));
+        assert_eq!(Balances::totem_locks(&BUYER).iter().map(|l| l.amount).collect::<Vec<_>>(), vec![600]);
+        assert_eq!(Prefunding::milestones(reference()), Some(vec![(400, 500)]));
+        assert_eq!(Prefunding::reference_status(reference()), Some(1));
+        let keys = posted();
+        assert_eq!(posted_to(&keys, BUYER, LedgerAccount::PREFUNDING), -400);
+        assert_eq!(posted_to(&keys, SELLER, LedgerAccount::TRANSFERS_SUSPENSE), 400);
+
+        // The last milestone settles the prefunding
+        assert_ok!(invoice_milestone(600));
+        assert_ok!(pay_milestone(BUYER, 1));
+
+        assert_eq!((free(BUYER), free(SELLER), free(ESCROW)), (9000, 11_000, 0));
+        assert_eq!(Prefunding::prefunding(reference()), None);
+        assert_eq!(Prefunding::milestones(reference()), None);
+        assert!(Balances::totem_locks(&BUYER).is_empty());
+        assert_eq!(Prefunding::reference_status(reference()), Some(500));
+    });
+}
+
+#[test]
+fn milestones_cannot_exceed_the_prefunded_amount() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(prefund(reference()));
+        assert_noop!(invoice_milestone(400), Error::<Test>::ErrorMilestoneNotAllowed);
+        assert_ok!(accept(reference()));
+
+        assert_noop!(invoice_milestone(0), Error::<Test>::ErrorMilestoneAmount);
+        assert_noop!(invoice_milestone(AMOUNT + 1), Error::<Test>::ErrorMilestoneAmount);
+        assert_ok!(invoice_milestone(600));
+        assert_noop!(invoice_milestone(500), Error::<Test>::ErrorMilestoneAmount);
+        assert_noop!(
+            Prefunding::send_simple_invoice(
+                SELLER,
+                BUYER,
+                vec![InvoiceLine::services(1000)],
+                reference(),
+                uid(),
+                vec![]
+            ),
+            Error::<Test>::ErrorMilestonesInvoiced
+        );
+
+        assert_noop!(pay_milestone(SELLER, 0), Error::<Test>::ErrorNotAllowed3);
+        assert_noop!(pay_milestone(BUYER, 1), Error::<Test>::ErrorNoMilestone);
+        assert_ok!(pay_milestone(BUYER, 0));
+        assert_noop!(pay_milestone(BUYER, 0), Error::<Test>::ErrorNoMilestone);
+
+        // Only the unpaid remainder can be invoiced
+        assert_noop!(invoice_milestone(500), Error::<Test>::ErrorMilestoneAmount);
+        assert_ok!(invoice_milestone(400));
+    });
+}
+
+#[test]
+fn ruling_on_milestones_reverses_only_the_outstanding_milestones() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(prefund(reference()));
+        assert_ok!(accept(reference()));
+        assert_ok!(invoice_milestone(400));
+        assert_ok!(pay_milestone(BUYER, 0));
+        assert_ok!(invoice_milestone(300));
+        assert_ok!(dispatch(Origin::signed(SELLER), PrefundingCall::dispute_prefunded_order(reference(), uid())));
+        posted();
+
+        // The arbiter releases the outstanding milestone and returns the rest
+        assert_ok!(rule(Origin::root(), reference(), 300));
+
+        assert_eq!((free(BUYER), free(SELLER), free(ESCROW)), (9300, 10_700, 0));
+        assert_eq!(Prefunding::reference_status(reference()), Some(500));
+        let keys = posted();
+        assert_eq!(posted_to(&keys, BUYER, LedgerAccount::PREFUNDING), -600);
+        assert_eq!(posted_to(&keys, SELLER, LedgerAccount::ACCOUNTS_RECEIVABLE), -300);
+        assert_eq!(posted_to(&keys, BUYER, LedgerAccount::ACCOUNTS_PAYABLE), -300);
+        // The released amount is what was invoiced, the sale and the purchase stand as invoiced
+        assert_eq!(posted_to(&keys, SELLER, LedgerAccount::SALES), 0);
+        assert_eq!(posted_to(&keys, BUYER, LedgerAccount::LABOUR), 0);
+    });
+}
+
+fn tax(percent: u32, jurisdiction: u64) -> TaxLine<u64> {
+    TaxLine {
+        rate: Permill::from_percent(percent),
+        jurisdiction,
+    }
+}
+
+fn invoice(taxes: Vec<TaxLine<u64>>) -> DispatchResultWithPostInfo {
+    dispatch(Origin::signed(SELLER), PrefundingCall::invoice_prefunded_order(BUYER, 1000, reference(), uid(), taxes))
+}
+
+#[test]
+fn invoice_splits_the_taxes_by_jurisdiction() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(prefund(reference()));
+        assert_ok!(accept(reference()));
+        posted();
+        let taxes = vec![tax(10, TAX_OFFICE), tax(5, OTHER_TAX_OFFICE)];
+
+        assert_ok!(invoice(taxes.clone()));
+
+        // 1000 includes 15% of taxes on 869, rounded down by jurisdiction
+        assert_eq!(sales_taxes(), vec![(SELLER, TAX_OFFICE, 86), (SELLER, OTHER_TAX_OFFICE, 43)]);
+        assert_eq!(Prefunding::invoice_taxes(reference()), Some(taxes));
+        assert_eq!(Prefunding::reference_status(reference()), Some(400));
+        let keys = posted();
+        assert_eq!(posted_to(&keys, SELLER, LedgerAccount::ACCOUNTS_RECEIVABLE), 1000);
+        assert_eq!(posted_to(&keys, SELLER, LedgerAccount::SALES), 871);
+        assert_eq!(posted_to(&keys, SELLER, LedgerAccount::SALES_TAX_PAYABLE), 129);
+        assert_eq!(posted_to(&keys, BUYER, LedgerAccount::ACCOUNTS_PAYABLE), 1000);
+        assert_eq!(posted_to(&keys, BUYER, LedgerAccount::LABOUR), 871);
+        assert_eq!(posted_to(&keys, BUYER, LedgerAccount::INPUT_TAX_RECEIVABLE), 129);
+
+        // The taxes are paid with the invoice
+        assert_ok!(dispatch(Origin::signed(BUYER), PrefundingCall::pay_prefunded_invoice(reference(), uid())));
+        assert_eq!((free(BUYER), free(SELLER), free(ESCROW)), (9000, 11_000, 0));
+        assert_eq!(Prefunding::reference_status(reference()), Some(500));
+        assert!(sales_taxes().is_empty());
+    });
+}
+
+#[test]
+fn invoice_posts_each_line_net_of_taxes() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(prefund(reference()));
+        assert_ok!(accept(reference()));
+        posted();
+        let goods = InvoiceLine {
+            amount: 460,
+            seller_account: LedgerAccount::GOODS_SALES,
+            buyer_account: LedgerAccount::INVENTORY,
+        };
+        let lines = vec![InvoiceLine::services(690), goods];
+
+        assert_ok!(Prefunding::send_simple_invoice(
+            SELLER,
+            BUYER,
+            lines,
+            reference(),
+            uid(),
+            vec![tax(15, TAX_OFFICE)]
+        ));
+
+        // 1150 is 1000 net of taxes, allocated in proportion to the lines
+        assert_eq!(sales_taxes(), vec![(SELLER, TAX_OFFICE, 150)]);
+        let keys = posted();
+        assert_eq!(posted_to(&keys, SELLER, LedgerAccount::SALES), 600);
+        assert_eq!(posted_to(&keys, SELLER, LedgerAccount::GOODS_SALES), 400);
+        assert_eq!(posted_to(&keys, BUYER, LedgerAccount::LABOUR), 600);
+        assert_eq!(posted_to(&keys, BUYER, LedgerAccount::INVENTORY), 400);
+        assert_eq!(posted_to(&keys, BUYER, LedgerAccount::INPUT_TAX_RECEIVABLE), 150);
+    });
+}
+
+#[test]
+fn invalid_taxes_are_rejected() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(prefund(reference()));
+        assert_ok!(accept(reference()));
+
+        let too_many = vec![tax(10, TAX_OFFICE), tax(5, OTHER_TAX_OFFICE), tax(1, ARBITER)];
+        assert_noop!(invoice(too_many), Error::<Test>::ErrorTooManyTaxLines);
+        assert_noop!(invoice(vec![tax(0, TAX_OFFICE)]), Error::<Test>::ErrorZeroTaxRate);
+        assert_noop!(invoice(vec![tax(10, TAX_OFFICE), tax(5, TAX_OFFICE)]), Error::<Test>::ErrorDuplicateJurisdiction);
+
+        // Every milestone carries the taxes of the first one
+        let milestone = |taxes| PrefundingCall::invoice_milestone(reference(), 400, uid(), taxes);
+        assert_ok!(dispatch(Origin::signed(SELLER), milestone(vec![tax(10, TAX_OFFICE)])));
+        assert_noop!(dispatch(Origin::signed(SELLER), milestone(vec![])), Error::<Test>::ErrorTaxesChanged);
+    });
+}
+
+#[test]
+fn ruling_on_an_invoiced_order_reverses_the_invoice_and_its_taxes() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(prefund(reference()));
+        assert_ok!(accept(reference()));
+        // 1000 includes 90 of taxes
+        assert_ok!(invoice(vec![tax(10, TAX_OFFICE)]));
+        assert_ok!(dispatch(Origin::signed(BUYER), PrefundingCall::dispute_prefunded_order(reference(), uid())));
+        posted();
+        sales_taxes();
+
+        assert_ok!(rule(Origin::root(), reference(), 600));
+
+        assert_eq!((free(BUYER), free(SELLER), free(ESCROW)), (9400, 10_600, 0));
+        assert_eq!(sales_taxes(), vec![(SELLER, TAX_OFFICE, -90)]);
+        assert_eq!(Prefunding::invoice_taxes(reference()), None);
+        let keys = posted();
+        assert_eq!(posted_to(&keys, SELLER, LedgerAccount::ACCOUNTS_RECEIVABLE), -1000);
+        assert_eq!(posted_to(&keys, SELLER, LedgerAccount::SALES_TAX_PAYABLE), -90);
+        assert_eq!(posted_to(&keys, BUYER, LedgerAccount::ACCOUNTS_PAYABLE), -1000);
+        assert_eq!(posted_to(&keys, BUYER, LedgerAccount::INPUT_TAX_RECEIVABLE), -90);
+        // The sale and the purchase of 910 net of taxes are adjusted to the 600 released
+        assert_eq!(posted_to(&keys, SELLER, LedgerAccount::SALES), -310);
+        assert_eq!(posted_to(&keys, BUYER, LedgerAccount::LABOUR), -310);
+    });
+}
+
+// The seller invoices 500 on credit, due at the block `due`.
+fn unfunded_invoice(h: H256, due: u64) -> DispatchResultWithPostInfo {
+    Prefunding::send_unfunded_invoice(SELLER, BUYER, vec![InvoiceLine::services(500)], due, h, uid(), vec![])
+}
+
+#[test]
+fn unfunded_invoice_is_paid_by_the_buyer() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(unfunded_invoice(reference(), 100));
+        assert_eq!(Prefunding::unfunded_invoice(reference()), Some((SELLER, BUYER, 500, 100)));
+        assert_eq!(Prefunding::unfunded_invoice_list(SELLER), Some(vec![reference()]));
+        assert_eq!(Prefunding::unfunded_invoice_list(BUYER), Some(vec![reference()]));
+        assert_eq!(Prefunding::reference_status(reference()), Some(400));
+        // Nothing is locked
+        assert_eq!(Prefunding::escrow_state(reference()), Some((EscrowState::Invoiced, 0)));
+        let keys = posted();
+        assert_eq!(posted_to(&keys, SELLER, LedgerAccount::ACCOUNTS_RECEIVABLE), 500);
+        assert_eq!(posted_to(&keys, BUYER, LedgerAccount::ACCOUNTS_PAYABLE), 500);
+
+        let pay = |who: u64| dispatch(Origin::signed(who), PrefundingCall::pay_unfunded_invoice(reference(), uid()));
+        assert_noop!(pay(SELLER), Error::<Test>::ErrorNotAllowed3);
+        assert_ok!(pay(BUYER));
+
+        assert_eq!((free(BUYER), free(SELLER)), (9500, 10_500));
+        assert_eq!(Prefunding::unfunded_invoice(reference()), None);
+        assert_eq!(Prefunding::unfunded_invoice_list(SELLER), Some(vec![]));
+        assert_eq!(Prefunding::unfunded_invoice_list(BUYER), Some(vec![]));
+        assert_eq!(Prefunding::reference_status(reference()), Some(500));
+        let keys = posted();
+        assert_eq!(posted_to(&keys, SELLER, LedgerAccount::ACCOUNTS_RECEIVABLE), -500);
+        assert_eq!(posted_to(&keys, SELLER, LedgerAccount::TRANSFERS_SUSPENSE), 500);
+        assert_eq!(posted_to(&keys, BUYER, LedgerAccount::ACCOUNTS_PAYABLE), -500);
+        assert_eq!(posted_to(&keys, BUYER, LedgerAccount::TRANSFERS_SUSPENSE), -500);
+
+        assert_noop!(pay(BUYER), Error::<Test>::ErrorNoInvoice);
+    });
+}
+
+#[test]
+fn unpaid_invoice_is_overdue_after_its_due_date() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(unfunded_invoice(reference(), 10));
+
+        System::set_block_number(10);
+        assert!(Prefunding::overdue_invoices(BUYER).is_empty());
+
+        System::set_block_number(11);
+        assert_eq!(Prefunding::overdue_invoices(BUYER), vec![reference()]);
+        assert_eq!(Prefunding::overdue_invoices(SELLER), vec![reference()]);
+        assert!(Prefunding::overdue_invoices(ARBITER).is_empty());
+
+        // An overdue invoice can still be paid
+        assert_ok!(dispatch(Origin::signed(BUYER), PrefundingCall::pay_unfunded_invoice(reference(), uid())));
+        assert!(Prefunding::overdue_invoices(BUYER).is_empty());
+        assert!(Prefunding::overdue_invoices(SELLER).is_empty());
+    });
+}
+
+#[test]
+fn unfunded_invoice_checks_the_parties_the_due_date_and_the_open_invoices() {
+    new_test_ext().execute_with(|| {
+        let send = |seller: u64, amount: i128, due: u64, h: H256| {
+            Prefunding::send_unfunded_invoice(seller, BUYER, vec![InvoiceLine::services(amount)], due, h, uid(), vec![])
+        };
+        assert_noop!(send(BUYER, 500, 100, reference()), Error::<Test>::ErrorInvoiceNotAllowed);
+        assert_noop!(send(SELLER, 0, 100, reference()), Error::<Test>::ErrorInvoiceNotAllowed);
+        assert_noop!(send(SELLER, 500, 1, reference()), Error::<Test>::ErrorDueDate);
+        assert_noop!(
+            Prefunding::send_unfunded_invoice(
+                SELLER,
+                BUYER,
+                vec![InvoiceLine::services(500)],
+                100,
+                reference(),
+                uid(),
+                vec![tax(0, TAX_OFFICE)]
+            ),
+            Error::<Test>::ErrorZeroTaxRate
+        );
+
+        // A prefunded reference cannot be invoiced on credit
+        assert_ok!(prefund(reference()));
+        assert_noop!(send(SELLER, 500, 100, reference()), Error::<Test>::ErrorHashExists);
+
+        // The buyer has at most two unpaid invoices on credit
+        assert_ok!(unfunded_invoice(H256::repeat_byte(2), 100));
+        assert_ok!(unfunded_invoice(H256::repeat_byte(3), 100));
+        assert_noop!(unfunded_invoice(H256::repeat_byte(4), 100), Error::<Test>::ErrorTooManyInvoices);
+        assert_ok!(dispatch(Origin::signed(BUYER), PrefundingCall::pay_unfunded_invoice(H256::repeat_byte(2), uid())));
+        assert_ok!(unfunded_invoice(H256::repeat_byte(4), 100));
+    });
+}
+
+#[test]
+fn unaccepted_prefunding_expires_after_the_deadline() {
+    new_test_ext().execute_with(|| {
+        let references: Vec<H256> = (1..=4).map(H256::repeat_byte).collect();
+        for h in &references {
+            assert_ok!(prefund(*h));
+        }
+        // The last order is accepted, it no longer expires
+        assert_ok!(accept(references[3]));
+        assert_eq!(Prefunding::expiring(DEADLINE + 1), references);
+        assert_eq!(free(BUYER), 6000);
+        posted();
+
+        System::set_block_number(DEADLINE + 1);
+        Prefunding::on_initialize(DEADLINE + 1);
+
+        // At most two references expire in a block, the others are carried over to the next block
+        assert_eq!(free(BUYER), 8000);
+        assert_eq!(Prefunding::reference_status(references[0]), Some(50));
+        assert_eq!(Prefunding::reference_status(references[1]), Some(50));
+        assert_eq!(Prefunding::reference_status(references[2]), Some(1));
+        assert!(Prefunding::expiring(DEADLINE + 1).is_empty());
+        assert_eq!(Prefunding::expiring(DEADLINE + 2), references[2..].to_vec());
+        assert_eq!(posted_to(&posted(), BUYER, LedgerAccount::PREFUNDING), -2000);
+
+        System::set_block_number(DEADLINE + 2);
+        Prefunding::on_initialize(DEADLINE + 2);
+
+        assert_eq!(free(BUYER), 9000);
+        assert_eq!(Prefunding::reference_status(references[2]), Some(50));
+        assert_eq!(Prefunding::reference_status(references[3]), Some(1));
+        assert_eq!(Prefunding::prefunding(references[3]), Some((1000, DEADLINE)));
+        assert!(Prefunding::expiring(DEADLINE + 2).is_empty());
+        assert!(Prefunding::expiring(DEADLINE + 3).is_empty());
+        assert_eq!(posted_to(&posted(), BUYER, LedgerAccount::PREFUNDING), -1000);
+    });
+}
+
+#[test]
+fn prefunding_cancelled_by_the_buyer_is_not_expired_again() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(prefund(reference()));
+        let cancel =
+            || dispatch(Origin::signed(BUYER), PrefundingCall::cancel_prefunded_closed_order(reference(), uid()));
+        assert_noop!(cancel(), Error::<Test>::ErrorDeadlineInPlay);
+
+        System::set_block_number(DEADLINE + 1);
+        assert_ok!(cancel());
+        assert_eq!(free(BUYER), 10_000);
+        posted();
+
+        Prefunding::on_initialize(DEADLINE + 1);
+
+        assert_eq!(free(BUYER), 10_000);
+        assert_eq!(Prefunding::reference_status(reference()), Some(50));
+        assert!(posted().is_empty());
+    });
+}
+
+fn create_escrow(arbiter: Option<u64>) -> DispatchResultWithPostInfo {
+    Prefunding::create_escrow(BUYER, SELLER, AMOUNT, DEADLINE, arbiter, reference(), uid())
+}
+
+#[test]
+fn escrow_is_released_in_part_then_in_full() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(create_escrow(None));
+        assert_eq!(Prefunding::escrow_state(reference()), Some((EscrowState::Pending, 1000)));
+        posted();
+
+        assert_ok!(Prefunding::release_escrow(BUYER, reference(), 400, uid()));
+
+        assert_eq!((free(BUYER), free(SELLER), free(ESCROW)), (9000, 10_400, 600));
+        assert_eq!(Prefunding::escrow_state(reference()), Some((EscrowState::Pending, 600)));
+        let keys = posted();
+        assert_eq!(posted_to(&keys, BUYER, LedgerAccount::PREFUNDING), -400);
+        assert_eq!(posted_to(&keys, BUYER, LedgerAccount::LABOUR), 400);
+        assert_eq!(posted_to(&keys, SELLER, LedgerAccount::SALES), 400);
+        assert_eq!(posted_to(&keys, SELLER, LedgerAccount::TRANSFERS_SUSPENSE), 400);
+
+        assert_ok!(Prefunding::release_escrow(BUYER, reference(), 600, uid()));
+
+        assert_eq!((free(BUYER), free(SELLER), free(ESCROW)), (9000, 11_000, 0));
+        assert_eq!(Prefunding::escrow_state(reference()), Some((EscrowState::Released, 0)));
+        assert!(Balances::totem_locks(&BUYER).is_empty());
+    });
+}
+
+#[test]
+fn escrow_is_released_by_the_owner_up_to_the_escrowed_amount() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(create_escrow(None));
+
+        assert_noop!(Prefunding::release_escrow(SELLER, reference(), 400, uid()), Error::<Test>::ErrorEscrowNotAllowed);
+        assert_noop!(Prefunding::release_escrow(BUYER, reference(), 0, uid()), Error::<Test>::ErrorEscrowAmount);
+        assert_noop!(
+            Prefunding::release_escrow(BUYER, reference(), AMOUNT + 1, uid()),
+            Error::<Test>::ErrorReleaseTooHigh
+        );
+        assert_noop!(
+            Prefunding::release_escrow(BUYER, H256::repeat_byte(2), 400, uid()),
+            Error::<Test>::ErrorNoDetails
+        );
+    });
+}
+
+#[test]
+fn escrow_is_refunded_by_the_beneficiary_or_by_the_owner_after_the_deadline() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(create_escrow(Some(ARBITER)));
+        assert_eq!(Prefunding::arbiter(reference()), Some(ARBITER));
+
+        assert_noop!(Prefunding::refund_escrow(BUYER, reference(), uid()), Error::<Test>::ErrorEscrowNotAllowed);
+        assert_noop!(Prefunding::refund_escrow(ARBITER, reference(), uid()), Error::<Test>::ErrorEscrowNotAllowed);
+        posted();
+
+        assert_ok!(Prefunding::refund_escrow(SELLER, reference(), uid()));
+
+        assert_eq!((free(BUYER), free(SELLER), free(ESCROW)), (10_000, 10_000, 0));
+        assert_eq!(Prefunding::escrow_state(reference()), Some((EscrowState::Refunded, 0)));
+        assert_eq!(posted_to(&posted(), BUYER, LedgerAccount::PREFUNDING), -1000);
+
+        // The owner can only take the escrow back once the deadline has passed
+        let h = H256::repeat_byte(2);
+        assert_ok!(Prefunding::create_escrow(BUYER, SELLER, AMOUNT, DEADLINE, None, h, uid()));
+        System::set_block_number(DEADLINE + 1);
+        assert_ok!(Prefunding::refund_escrow(BUYER, h, uid()));
+        assert_eq!(free(BUYER), 10_000);
+        assert_eq!(Prefunding::escrow_state(h), Some((EscrowState::Refunded, 0)));
+    });
+}
+
+#[test]
+fn escrow_has_distinct_parties() {
+    new_test_ext().execute_with(|| {
+        assert_noop!(
+            Prefunding::create_escrow(BUYER, BUYER, AMOUNT, DEADLINE, None, reference(), uid()),
+            Error::<Test>::ErrorEscrowParty
+        );
+        assert_noop!(create_escrow(Some(SELLER)), Error::<Test>::ErrorArbiterIsParty);
+        assert_noop!(create_escrow(Some(BUYER)), Error::<Test>::ErrorArbiterIsParty);
+
+        assert_eq!(free(BUYER), 10_000);
+        assert_eq!(Prefunding::escrow_state(reference()), None);
+    });
+}
+
+#[test]
+fn accepted_escrow_is_released_and_invoiced_escrow_is_settled_by_the_invoice() {
+    new_test_ext().execute_with(|| {
+        assert_ok!(create_escrow(None));
+        assert_ok!(accept(reference()));
+        assert_eq!(Prefunding::escrow_state(reference()), Some((EscrowState::Locked, 1000)));
+        assert_noop!(Prefunding::refund_escrow(BUYER, reference(), uid()), Error::<Test>::ErrorEscrowNotAllowed);
+
+        assert_ok!(invoice(vec![]));
+
+        assert_eq!(Prefunding::escrow_state(reference()), Some((EscrowState::Invoiced, 1000)));
+        assert_noop!(Prefunding::release_escrow(BUYER, reference(), 400, uid()), Error::<Test>::ErrorEscrowNotAllowed);
+        assert_noop!(Prefunding::refund_escrow(SELLER, reference(), uid()), Error::<Test>::ErrorEscrowNotAllowed);
+    });
+}
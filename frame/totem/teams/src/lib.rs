@@ -63,12 +63,16 @@
 
 #![cfg_attr(not(feature = "std"), no_std)]
 
+mod mock;
+mod tests;
+
 use frame_support::{fail, pallet_prelude::*};
 use frame_system::pallet_prelude::*;
 
 use sp_std::prelude::*;
 
-use totem_utils::traits::teams::Validating;
+use totem_utils::traits::teams::{Budgeting, Validating};
+use totem_utils::types::TeamRole;
 use totem_utils::{ok, StorageMapExt};
 
 /// Reference supplied externally.
@@ -82,6 +86,30 @@ pub struct DeletedProject<AccountId, ProjectStatus> {
     pub status: ProjectStatus,
 }
 
+/// The budget of a project, including its sub-projects, in the functional currency (XTX) and in blocks of time.
+/// A missing amount or number of blocks is not budgeted.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Encode, Decode, Default)]
+pub struct Budget {
+    pub amount: Option<u128>,
+    pub blocks: Option<u64>,
+    /// Costs and time exceeding an enforced budget are rejected.
+    pub enforced: bool,
+}
+
+impl Budget {
+    pub fn is_exceeded_by(&self, actuals: &Actuals) -> bool {
+        self.amount.map_or(false, |amount| actuals.amount > amount)
+            || self.blocks.map_or(false, |blocks| actuals.blocks > blocks)
+    }
+}
+
+/// The invoiced costs and the accepted time of a project, including its sub-projects.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Encode, Decode, Default)]
+pub struct Actuals {
+    pub amount: u128,
+    pub blocks: u64,
+}
+
 #[frame_support::pallet]
 mod pallet {
 
@@ -112,9 +140,42 @@ mod pallet {
     ///
     pub type OwnerProjectsList<T: Config> = StorageMap<_, Blake2_128Concat, T::AccountId, Vec<T::Hash>>;
 
+    #[pallet::storage]
+    #[pallet::getter(fn project_team_role)]
+    /// The role granted to a member of the team of a project. The owner of the project is not listed.
+    pub type ProjectTeamRole<T: Config> = StorageMap<_, Blake2_128Concat, (T::Hash, T::AccountId), TeamRole>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn project_team)]
+    /// The members of the team of a project who have been granted a role.
+    pub type ProjectTeam<T: Config> = StorageMap<_, Blake2_128Concat, T::Hash, Vec<T::AccountId>>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn parent_project)]
+    /// The parent of a sub-project.
+    pub type ParentProject<T: Config> = StorageMap<_, Blake2_128Concat, T::Hash, T::Hash>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn sub_projects)]
+    /// The sub-projects of a project.
+    pub type SubProjects<T: Config> = StorageMap<_, Blake2_128Concat, T::Hash, Vec<T::Hash>>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn project_budget)]
+    /// The budget of a project, if one was set.
+    pub type ProjectBudget<T: Config> = StorageMap<_, Blake2_128Concat, T::Hash, Budget>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn project_actuals)]
+    /// The actuals of a project, rolled up from its sub-projects.
+    pub type ProjectActuals<T: Config> = StorageMap<_, Blake2_128Concat, T::Hash, Actuals, ValueQuery>;
+
     #[pallet::config]
     pub trait Config: frame_system::Config {
         type Event: From<Event<Self>> + IsType<<Self as frame_system::Config>::Event>;
+        /// The maximum number of levels of sub-projects below a top level project.
+        #[pallet::constant]
+        type MaxProjectDepth: Get<u32>;
     }
 
     #[pallet::error]
@@ -143,6 +204,22 @@ mod pallet {
         ProjectCannotCloseNotOwned,
         /// You cannot change a project you do not own.
         ProjectCannotChangeNotOwned,
+        /// Only the owner and managers can grant or revoke roles below their own.
+        RoleNotAuthorised,
+        /// The owner role is changed by reassigning the project.
+        RoleCannotBeOwner,
+        /// The identity has no role in the team of the project.
+        RoleNotFound,
+        /// Only the owner and managers of the parent project can add a sub-project.
+        ProjectCannotAddSubProject,
+        /// The sub-project would be too deep in the project hierarchy.
+        ProjectTooDeep,
+        /// A project with sub-projects cannot be removed.
+        ProjectHasSubProjects,
+        /// Only the owner and managers of the project can set its budget.
+        ProjectCannotSetBudget,
+        /// The project, or one of its parent projects, would exceed its enforced budget.
+        BudgetExceeded,
     }
 
     #[pallet::hooks]
@@ -152,22 +229,33 @@ mod pallet {
     impl<T: Config> Pallet<T> {
         #[pallet::weight(0/*TODO*/)]
         fn add_new_project(origin: OriginFor<T>, project_hash: T::Hash) -> DispatchResultWithPostInfo {
-            // Check that the project does not exist
-            ensure!(!ProjectHashStatus::<T>::contains_key(project_hash.clone()), Error::<T>::ProjectAlreadyExists);
+            let who = ensure_signed(origin)?;
 
-            // Check that the project was not deleted already
-            ensure!(!DeletedProjects::<T>::contains_key(project_hash.clone()), Error::<T>::ProjectAlreadyDeleted);
+            Self::register_project(who, project_hash)
+        }
 
-            // proceed to store project
+        #[pallet::weight(0/*TODO*/)]
+        /// Adds a new project below a parent project. Only the owner and managers of the parent can add sub-projects.
+        fn add_sub_project(
+            origin: OriginFor<T>,
+            parent_hash: T::Hash,
+            project_hash: T::Hash,
+        ) -> DispatchResultWithPostInfo {
             let who = ensure_signed(origin)?;
-            let project_status: ProjectStatus = 0;
 
-            // TODO limit nr of Projects per Account.
-            ProjectHashStatus::<T>::insert(project_hash.clone(), &project_status);
-            ProjectHashOwner::<T>::insert(project_hash.clone(), &who);
-            OwnerProjectsList::<T>::mutate_(&who, |owner_projects_list| owner_projects_list.push(project_hash.clone()));
+            ensure!(
+                Self::has_team_role(who.clone(), parent_hash.clone(), TeamRole::Manager),
+                Error::<T>::ProjectCannotAddSubProject
+            );
+            // The sub-project sits one level below the parent and all of its ancestors
+            let depth = Self::project_lineage(parent_hash.clone()).len();
+            ensure!(depth as u32 <= T::MaxProjectDepth::get(), Error::<T>::ProjectTooDeep);
 
-            Self::deposit_event(Event::ProjectRegistered(project_hash, who));
+            Self::register_project(who.clone(), project_hash.clone())?;
+            ParentProject::<T>::insert(&project_hash, &parent_hash);
+            SubProjects::<T>::mutate_(&parent_hash, |sub_projects| sub_projects.push(project_hash.clone()));
+
+            Self::deposit_event(Event::SubProjectRegistered(project_hash, parent_hash, who));
 
             ok()
         }
@@ -186,6 +274,10 @@ mod pallet {
             // TODO Implement a sudo for cleaning data in cases where owner is lost
             // Otherwise only the owner can change the data
             ensure!(project_owner == changer, "You cannot delete a project you do not own");
+            ensure!(
+                Self::sub_projects(&project_hash).unwrap_or_default().is_empty(),
+                Error::<T>::ProjectHasSubProjects
+            );
 
             let changed_by: T::AccountId = changer.clone();
 
@@ -203,6 +295,22 @@ mod pallet {
             // remove project from owner
             ProjectHashOwner::<T>::remove(project_hash.clone());
 
+            // remove the team
+            for member in ProjectTeam::<T>::take(&project_hash).unwrap_or_default() {
+                ProjectTeamRole::<T>::remove((project_hash.clone(), member));
+            }
+
+            // remove the time and costs of the project from its parent projects
+            let actuals = Self::project_actuals(&project_hash);
+            Self::subtract_actuals(project_hash.clone(), actuals.amount, actuals.blocks);
+
+            // remove the project from its parent
+            if let Some(parent_hash) = ParentProject::<T>::take(&project_hash) {
+                SubProjects::<T>::mutate_(&parent_hash, |sub_projects| sub_projects.retain(|h| h != &project_hash));
+            }
+            ProjectBudget::<T>::remove(&project_hash);
+            ProjectActuals::<T>::remove(&project_hash);
+
             // remove status record
             ProjectHashStatus::<T>::remove(project_hash.clone());
 
@@ -242,6 +350,8 @@ mod pallet {
 
             // Set new owner for hash
             ProjectHashOwner::<T>::insert(project_hash.clone(), &new_owner);
+            // The new owner no longer needs a role in the team
+            Self::remove_team_role(project_hash.clone(), new_owner.clone());
             OwnerProjectsList::<T>::mutate_(&new_owner, |owner_projects_list| owner_projects_list.push(project_hash));
 
             Self::deposit_event(Event::ProjectReassigned(project_hash, new_owner, changed_by));
@@ -364,6 +474,77 @@ mod pallet {
 
             ok()
         }
+
+        #[pallet::weight(0/*TODO*/)]
+        /// Grants a role in the team of the project, replacing any previous role of the member.
+        /// The owner can grant any role, a manager only roles below manager.
+        fn grant_team_role(
+            origin: OriginFor<T>,
+            project_hash: T::Hash,
+            member: T::AccountId,
+            role: TeamRole,
+        ) -> DispatchResultWithPostInfo {
+            let who = ensure_signed(origin)?;
+
+            ensure!(ProjectHashStatus::<T>::contains_key(project_hash.clone()), Error::<T>::ProjectDoesNotExist);
+            ensure!(role != TeamRole::Owner, Error::<T>::RoleCannotBeOwner);
+            let current_role = Self::get_team_role(member.clone(), project_hash.clone());
+            ensure!(current_role != Some(TeamRole::Owner), Error::<T>::RoleCannotBeOwner);
+            Self::ensure_can_change_role(who, project_hash.clone(), current_role.unwrap_or(role).max(role))?;
+
+            if current_role.is_none() {
+                ProjectTeam::<T>::mutate_(&project_hash, |project_team| project_team.push(member.clone()));
+            }
+            ProjectTeamRole::<T>::insert((project_hash.clone(), member.clone()), role);
+
+            Self::deposit_event(Event::TeamRoleGranted(project_hash, member, role));
+
+            ok()
+        }
+
+        #[pallet::weight(0/*TODO*/)]
+        /// Revokes the role of a member in the team of the project.
+        /// The owner can revoke any role, a manager only roles below manager. Members can always leave the team.
+        fn revoke_team_role(
+            origin: OriginFor<T>,
+            project_hash: T::Hash,
+            member: T::AccountId,
+        ) -> DispatchResultWithPostInfo {
+            let who = ensure_signed(origin)?;
+
+            let role =
+                Self::project_team_role((project_hash.clone(), member.clone())).ok_or(Error::<T>::RoleNotFound)?;
+            if who != member {
+                Self::ensure_can_change_role(who, project_hash.clone(), role)?;
+            }
+
+            Self::remove_team_role(project_hash.clone(), member.clone());
+
+            Self::deposit_event(Event::TeamRoleRevoked(project_hash, member));
+
+            ok()
+        }
+
+        #[pallet::weight(0/*TODO*/)]
+        /// Sets the budget of the project, including its sub-projects. Only the owner and managers can set it.
+        fn set_project_budget(
+            origin: OriginFor<T>,
+            project_hash: T::Hash,
+            budget: Budget,
+        ) -> DispatchResultWithPostInfo {
+            let who = ensure_signed(origin)?;
+
+            ensure!(
+                Self::has_team_role(who, project_hash.clone(), TeamRole::Manager),
+                Error::<T>::ProjectCannotSetBudget
+            );
+
+            ProjectBudget::<T>::insert(&project_hash, budget);
+
+            Self::deposit_event(Event::ProjectBudgetSet(project_hash, budget));
+
+            ok()
+        }
     }
 
     #[pallet::event]
@@ -373,11 +554,104 @@ mod pallet {
         ProjectDeleted(T::Hash, T::AccountId, T::AccountId, ProjectStatus),
         ProjectReassigned(T::Hash, T::AccountId, T::AccountId),
         ProjectChanged(T::Hash, T::AccountId, ProjectStatus),
+        TeamRoleGranted(T::Hash, T::AccountId, TeamRole),
+        TeamRoleRevoked(T::Hash, T::AccountId),
+        SubProjectRegistered(T::Hash, T::Hash, T::AccountId),
+        ProjectBudgetSet(T::Hash, Budget),
+        ProjectBudgetExceeded(T::Hash),
     }
 }
 
 pub use pallet::*;
 
+impl<T: Config> Pallet<T> {
+    fn register_project(who: T::AccountId, project_hash: T::Hash) -> DispatchResultWithPostInfo {
+        // Check that the project does not exist
+        ensure!(!ProjectHashStatus::<T>::contains_key(project_hash.clone()), Error::<T>::ProjectAlreadyExists);
+
+        // Check that the project was not deleted already
+        ensure!(!DeletedProjects::<T>::contains_key(project_hash.clone()), Error::<T>::ProjectAlreadyDeleted);
+
+        // proceed to store project
+        let project_status: ProjectStatus = 0;
+
+        // TODO limit nr of Projects per Account.
+        ProjectHashStatus::<T>::insert(project_hash.clone(), &project_status);
+        ProjectHashOwner::<T>::insert(project_hash.clone(), &who);
+        OwnerProjectsList::<T>::mutate_(&who, |owner_projects_list| owner_projects_list.push(project_hash.clone()));
+
+        Self::deposit_event(Event::ProjectRegistered(project_hash, who));
+
+        ok()
+    }
+
+    /// The project followed by its parent projects, up to the top level project.
+    pub fn project_lineage(project_hash: T::Hash) -> Vec<T::Hash> {
+        let mut lineage = vec![project_hash.clone()];
+        let mut current = project_hash;
+        while let Some(parent_hash) = Self::parent_project(&current) {
+            lineage.push(parent_hash.clone());
+            current = parent_hash;
+        }
+
+        lineage
+    }
+
+    // Adds to the actuals of the project and of its parent projects. All the enforced budgets are checked before any
+    // actuals change. Each project whose budget becomes exceeded is notified.
+    fn add_actuals(project_hash: T::Hash, amount: u128, blocks: u64) -> DispatchResultWithPostInfo {
+        let mut updates = Vec::new();
+        for h in Self::project_lineage(project_hash) {
+            let actuals = Self::project_actuals(&h);
+            let new_actuals = Actuals {
+                amount: actuals.amount.saturating_add(amount),
+                blocks: actuals.blocks.saturating_add(blocks),
+            };
+            let mut exceeded = false;
+            if let Some(budget) = Self::project_budget(&h) {
+                if budget.is_exceeded_by(&new_actuals) {
+                    ensure!(!budget.enforced, Error::<T>::BudgetExceeded);
+                    exceeded = !budget.is_exceeded_by(&actuals);
+                }
+            }
+            updates.push((h, new_actuals, exceeded));
+        }
+
+        for (h, new_actuals, exceeded) in updates {
+            ProjectActuals::<T>::insert(&h, new_actuals);
+            if exceeded {
+                Self::deposit_event(Event::ProjectBudgetExceeded(h));
+            }
+        }
+
+        ok()
+    }
+
+    // Subtracts from the actuals of the project and of its parent projects.
+    fn subtract_actuals(project_hash: T::Hash, amount: u128, blocks: u64) {
+        for h in Self::project_lineage(project_hash) {
+            ProjectActuals::<T>::mutate(&h, |actuals| {
+                actuals.amount = actuals.amount.saturating_sub(amount);
+                actuals.blocks = actuals.blocks.saturating_sub(blocks);
+            });
+        }
+    }
+
+    // Only the owner and managers can change a role, and only below their own
+    fn ensure_can_change_role(who: T::AccountId, project_hash: T::Hash, role: TeamRole) -> DispatchResultWithPostInfo {
+        match Self::get_team_role(who, project_hash) {
+            Some(own_role) if own_role >= TeamRole::Manager && own_role > role => ok(),
+            _ => fail!(Error::<T>::RoleNotAuthorised),
+        }
+    }
+
+    fn remove_team_role(project_hash: T::Hash, member: T::AccountId) {
+        if ProjectTeamRole::<T>::take((project_hash.clone(), member.clone())).is_some() {
+            ProjectTeam::<T>::mutate_(&project_hash, |project_team| project_team.retain(|m| m != &member));
+        }
+    }
+}
+
 impl<T: Config> Validating<T::AccountId, T::Hash> for Pallet<T> {
     fn is_project_owner(o: T::AccountId, h: T::Hash) -> bool {
         // set default return value
@@ -430,4 +704,36 @@ impl<T: Config> Validating<T::AccountId, T::Hash> for Pallet<T> {
 
         return valid;
     }
+
+    fn get_project_owner(h: T::Hash) -> Option<T::AccountId> {
+        Self::project_hash_owner(h)
+    }
+
+    fn get_team_role(o: T::AccountId, h: T::Hash) -> Option<TeamRole> {
+        if Self::project_hash_owner(h.clone()).as_ref() == Some(&o) {
+            Some(TeamRole::Owner)
+        } else {
+            Self::project_team_role((h, o))
+        }
+    }
+
+    fn has_team_role(o: T::AccountId, h: T::Hash, r: TeamRole) -> bool {
+        Self::is_project_valid(h.clone()) && Self::get_team_role(o, h).map_or(false, |role| role >= r)
+    }
+}
+
+impl<T: Config> Budgeting<T::Hash> for Pallet<T> {
+    fn record_time(h: T::Hash, blocks: u64) -> DispatchResultWithPostInfo {
+        Self::add_actuals(h, 0, blocks)
+    }
+
+    fn release_time(h: T::Hash, blocks: u64) -> DispatchResultWithPostInfo {
+        Self::subtract_actuals(h, 0, blocks);
+
+        ok()
+    }
+
+    fn record_cost(h: T::Hash, amount: u128) -> DispatchResultWithPostInfo {
+        Self::add_actuals(h, amount, 0)
+    }
 }
@@ -0,0 +1,164 @@
+#![cfg(test)]
+
+use crate::mock::*;
+use crate::{Actuals, Budget, Call as TeamsCall, Error};
+
+use frame_support::{assert_noop, assert_ok, dispatch::DispatchResultWithPostInfo, traits::UnfilteredDispatchable};
+use sp_core::H256;
+use totem_utils::traits::teams::Budgeting;
+use totem_utils::types::TeamRole;
+
+fn dispatch(who: u64, call: TeamsCall<Test>) -> DispatchResultWithPostInfo {
+    call.dispatch_bypass_filter(Origin::signed(who))
+}
+
+fn project(n: u8) -> H256 {
+    H256::repeat_byte(n)
+}
+
+// Registers project 1 owned by `OWNER`, with `MANAGER` as a manager and `MEMBER` as a member of the team.
+fn add_project() -> H256 {
+    assert_ok!(dispatch(OWNER, TeamsCall::add_new_project(project(1))));
+    assert_ok!(dispatch(OWNER, TeamsCall::grant_team_role(project(1), MANAGER, TeamRole::Manager)));
+    assert_ok!(dispatch(OWNER, TeamsCall::grant_team_role(project(1), MEMBER, TeamRole::Member)));
+    project(1)
+}
+
+fn set_budget(h: H256, amount: Option<u128>, blocks: Option<u64>, enforced: bool) {
+    let budget = Budget {
+        amount,
+        blocks,
+        enforced,
+    };
+    assert_ok!(dispatch(OWNER, TeamsCall::set_project_budget(h, budget)));
+}
+
+fn actuals(amount: u128, blocks: u64) -> Actuals {
+    Actuals {
+        amount,
+        blocks,
+    }
+}
+
+fn budget_exceeded_events() -> Vec<H256> {
+    System::events()
+        .into_iter()
+        .filter_map(|record| match record.event {
+            Event::pallet_teams(crate::Event::ProjectBudgetExceeded(h)) => Some(h),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn only_managers_set_the_project_budget() {
+    new_test_ext().execute_with(|| {
+        let h = add_project();
+        let budget = Budget {
+            amount: Some(100),
+            blocks: None,
+            enforced: true,
+        };
+
+        assert_noop!(dispatch(MEMBER, TeamsCall::set_project_budget(h, budget)), Error::<Test>::ProjectCannotSetBudget);
+        assert_ok!(dispatch(MANAGER, TeamsCall::set_project_budget(h, budget)));
+        assert_eq!(Teams::project_budget(h), Some(budget));
+    });
+}
+
+#[test]
+fn enforced_budget_rejects_time_and_costs_exceeding_it() {
+    new_test_ext().execute_with(|| {
+        let h = add_project();
+        set_budget(h, Some(100), Some(20), true);
+
+        assert_ok!(Teams::record_time(h, 15));
+        assert_noop!(Teams::record_time(h, 10), Error::<Test>::BudgetExceeded);
+        assert_ok!(Teams::record_time(h, 5));
+
+        assert_ok!(Teams::record_cost(h, 100));
+        assert_noop!(Teams::record_cost(h, 1), Error::<Test>::BudgetExceeded);
+        assert_eq!(Teams::project_actuals(h), actuals(100, 20));
+        assert!(budget_exceeded_events().is_empty());
+    });
+}
+
+#[test]
+fn exceeding_a_budget_is_notified_once() {
+    new_test_ext().execute_with(|| {
+        let h = add_project();
+        set_budget(h, None, Some(10), false);
+
+        assert_ok!(Teams::record_time(h, 10));
+        assert!(budget_exceeded_events().is_empty());
+        assert_ok!(Teams::record_time(h, 5));
+        assert_ok!(Teams::record_time(h, 5));
+        assert_eq!(budget_exceeded_events(), vec![h]);
+        assert_eq!(Teams::project_actuals(h), actuals(0, 20));
+    });
+}
+
+#[test]
+fn actuals_roll_up_to_parent_projects() {
+    new_test_ext().execute_with(|| {
+        let parent = add_project();
+        assert_ok!(dispatch(MANAGER, TeamsCall::add_sub_project(parent, project(2))));
+        assert_ok!(dispatch(MANAGER, TeamsCall::add_sub_project(project(2), project(3))));
+        assert_eq!(Teams::project_lineage(project(3)), vec![project(3), project(2), parent]);
+
+        assert_ok!(Teams::record_time(project(2), 5));
+        assert_ok!(Teams::record_cost(project(3), 40));
+        assert_eq!(Teams::project_actuals(project(3)), actuals(40, 0));
+        assert_eq!(Teams::project_actuals(project(2)), actuals(40, 5));
+        assert_eq!(Teams::project_actuals(parent), actuals(40, 5));
+
+        // The enforced budget of the parent applies to the time of its sub-projects
+        set_budget(parent, None, Some(8), true);
+        assert_noop!(Teams::record_time(project(3), 5), Error::<Test>::BudgetExceeded);
+
+        assert_ok!(Teams::release_time(project(2), 5));
+        assert_eq!(Teams::project_actuals(project(2)), actuals(40, 0));
+        assert_eq!(Teams::project_actuals(parent), actuals(40, 0));
+        assert_ok!(Teams::record_time(project(3), 5));
+    });
+}
+
+#[test]
+fn sub_projects_are_limited_in_depth() {
+    new_test_ext().execute_with(|| {
+        let parent = add_project();
+
+        assert_noop!(
+            dispatch(MEMBER, TeamsCall::add_sub_project(parent, project(2))),
+            Error::<Test>::ProjectCannotAddSubProject
+        );
+        assert_ok!(dispatch(OWNER, TeamsCall::add_sub_project(parent, project(2))));
+        assert_ok!(dispatch(OWNER, TeamsCall::add_sub_project(project(2), project(3))));
+        assert_noop!(
+            dispatch(OWNER, TeamsCall::add_sub_project(project(3), project(4))),
+            Error::<Test>::ProjectTooDeep
+        );
+        assert_eq!(Teams::sub_projects(parent), Some(vec![project(2)]));
+        assert_eq!(Teams::parent_project(project(3)), Some(project(2)));
+    });
+}
+
+#[test]
+fn removing_a_sub_project_removes_its_actuals_from_the_parents() {
+    new_test_ext().execute_with(|| {
+        let parent = add_project();
+        assert_ok!(dispatch(OWNER, TeamsCall::add_sub_project(parent, project(2))));
+        assert_ok!(dispatch(OWNER, TeamsCall::add_sub_project(parent, project(3))));
+        assert_ok!(Teams::record_time(project(2), 5));
+        assert_ok!(Teams::record_cost(project(2), 40));
+        assert_ok!(Teams::record_time(project(3), 7));
+
+        assert_noop!(dispatch(OWNER, TeamsCall::remove_project(parent)), Error::<Test>::ProjectHasSubProjects);
+        assert_ok!(dispatch(OWNER, TeamsCall::remove_project(project(2))));
+
+        assert_eq!(Teams::project_actuals(project(2)), Actuals::default());
+        assert_eq!(Teams::project_actuals(parent), actuals(0, 7));
+        assert_eq!(Teams::sub_projects(parent), Some(vec![project(3)]));
+        assert_eq!(Teams::parent_project(project(2)), None);
+    });
+}
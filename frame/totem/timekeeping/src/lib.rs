@@ -45,19 +45,65 @@
 //!
 //! It should be noted that validators timestamp each new block with the "correct" timestamp, which can be retrieved
 //! when needed to provide time analysis for accounting entries.
+//!
+//! Accepted time records are invoiced by the worker to the project owner at the rate set by the project owner for the
+//! worker, or for the project. The rate is the amount charged per block of time booked. Invoicing posts the receivable
+//! and the sale to the accounts of the worker, and the payable and the labour expense to the accounts of the project owner.
+//! Paying the invoice transfers the funds to the worker, clears the receivable and the payable, and locks the time record.
+//!
+//! Time records are booked in the fiscal period of the worker in which they end, as defined by the fiscal calendar of
+//! the worker in the accounting module. Time cannot be submitted into a closed period.
+//!
+//! Accepted time counts towards the budget of the project in the teams module, and is released again when the record
+//! is reset for correction. Time exceeding an enforced project budget cannot be accepted.
+//!
+//! The project owner can ban a worker from the project with a reason code. A banned worker can no longer submit time,
+//! and their time records awaiting authorisation are blocked. The project owner can also lock and unlock individual
+//! time records, recording the reason for the change.
 
 #![cfg_attr(not(feature = "std"), no_std)]
 
+mod mock;
 mod tests;
 
-use frame_support::{dispatch::EncodeLike, dispatch::Input, fail, pallet_prelude::*};
+use frame_support::{
+    dispatch::EncodeLike,
+    dispatch::Input,
+    fail,
+    pallet_prelude::*,
+    traits::{Currency, ExistenceRequirement},
+    transactional,
+};
 use frame_system::pallet_prelude::*;
 
+use sp_runtime::traits::{Convert, Hash, SaturatedConversion};
 use sp_std::prelude::*;
 
-use totem_utils::traits::{teams::Validating as ProjectValidating, timekeeping::Validating};
+use totem_utils::chart_of_accounts::LedgerAccount;
+use totem_utils::traits::{
+    accounting::{FiscalCalendar, Posting},
+    teams::{Budgeting as ProjectBudgeting, Validating as ProjectValidating},
+    timekeeping::Validating,
+};
+use totem_utils::types::TeamRole;
 use totem_utils::{ok, StorageMapExt};
 
+type CurrencyBalanceOf<T> = <<T as Config>::Currency as Currency<<T as frame_system::Config>::AccountId>>::Balance;
+
+type AccountOf<T> = <<T as Config>::Accounting as Posting<
+    <T as frame_system::Config>::AccountId,
+    <T as frame_system::Config>::Hash,
+    <T as frame_system::Config>::BlockNumber,
+    CurrencyBalanceOf<T>,
+>>::Account;
+
+type AccountBalanceOf<T> = <<T as Config>::Accounting as Posting<
+    <T as frame_system::Config>::AccountId,
+    <T as frame_system::Config>::Hash,
+    <T as frame_system::Config>::BlockNumber,
+    CurrencyBalanceOf<T>,
+>>::LedgerBalance;
+
 /// Number of pauses of the timer.
 pub type NumberOfBreaks = u16;
 
@@ -88,7 +134,7 @@ pub enum StatusOfTimeRecord {
     Blocked = 999,
 }
 
-/// Not calendar period, but fiscal periods 1-15 (0-14).
+/// Not calendar period, but the fiscal period of the worker in which the time is booked, numbered from 0.
 pub type PostingPeriod = u16;
 
 //TODO: create an enum: Accepted (true)/Pending (false)
@@ -109,6 +155,9 @@ pub type ReasonCodeType = u16;
 /// Ban status (default is false).
 pub type BanStatus = bool;
 
+/// Amount invoiced per block of time booked, in the functional currency.
+pub type Rate = u128;
+
 /// Reason why the code changes.
 #[derive(PartialEq, Eq, Clone, Debug, Encode, Decode, Default)]
 pub struct ReasonCodeStruct(ReasonCode, ReasonCodeType);
@@ -228,6 +277,11 @@ mod pallet {
     /// Simple getter to associate time record to owner.
     pub type TimeHashOwner<T: Config> = StorageMap<_, Blake2_128Concat, T::Hash, T::AccountId>;
 
+    #[pallet::storage]
+    #[pallet::getter(fn time_record_nonce)]
+    /// Number of time records created by the worker, used to derive the hash of the next time record.
+    pub type TimeRecordNonce<T: Config> = StorageMap<_, Blake2_128Concat, T::AccountId, u64, ValueQuery>;
+
     #[pallet::storage]
     #[pallet::getter(fn project_time_records_hash_list)]
     /// All the time records for a given project.
@@ -268,10 +322,31 @@ mod pallet {
     /// ARCHIVE Experimental! May go somewhere else in future.
     pub type ProjectTimeRecordsHashListArchive<T: Config> = StorageMap<_, Blake2_128Concat, T::Hash, Vec<T::Hash>>;
 
+    #[pallet::storage]
+    #[pallet::getter(fn project_rate)]
+    /// Rate invoiced for the time booked on the project, unless the worker has their own rate.
+    pub type ProjectRate<T: Config> = StorageMap<_, Blake2_128Concat, T::Hash, Rate>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn worker_rate)]
+    /// Rate invoiced for the time booked by the worker on the project.
+    pub type WorkerRate<T: Config> = StorageMap<_, Blake2_128Concat, (T::Hash, T::AccountId), Rate>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn time_invoice)]
+    /// Invoiced time records awaiting payment: the project owner paying the invoice and the invoiced amount.
+    pub type TimeInvoice<T: Config> = StorageMap<_, Blake2_128Concat, T::Hash, (T::AccountId, u128)>;
+
     #[pallet::config] //TODO declare configs that are constant
     pub trait Config: frame_system::Config {
         type Event: From<Event<Self>> + IsType<<Self as frame_system::Config>::Event>;
-        type Projects: ProjectValidating<Self::AccountId, Self::Hash>;
+        type Projects: ProjectValidating<Self::AccountId, Self::Hash> + ProjectBudgeting<Self::Hash>;
+        type Currency: Currency<Self::AccountId>;
+        type Accounting: Posting<Self::AccountId, Self::Hash, Self::BlockNumber, CurrencyBalanceOf<Self>>;
+        type Calendar: FiscalCalendar<Self::AccountId, Self::BlockNumber>;
+        type TimekeepingConversions: Convert<u64, AccountOf<Self>>
+            + Convert<i128, AccountBalanceOf<Self>>
+            + Convert<u128, CurrencyBalanceOf<Self>>;
     }
 
     #[pallet::error]
@@ -284,8 +359,14 @@ mod pallet {
         WorkerNotAssigned,
         /// This worker is banned.
         WorkerBanned,
+        /// This worker is not banned.
+        WorkerNotBanned,
+        /// The project owner cannot ban themselves.
+        CannotBanOwner,
         /// Invalid project or project owner is not correct.
         InvalidProjectOrOwner,
+        /// Only the project owner or a manager can authorise time.
+        NotProjectManager,
         /// Project not active.
         ProjectInactive,
         /// Cannot remove project that has been accepted already.
@@ -294,8 +375,14 @@ mod pallet {
         ProjectCannotBeChanged,
         /// Time record not from the worker.
         TimeRecordNotFromWorker,
+        /// A time record with this hash already exists.
+        TimeRecordAlreadyExists,
         /// You cannot change a locked time record.
         TimeRecordLocked,
+        /// The time record is not locked.
+        TimeRecordNotLocked,
+        /// The time record is paid and can no longer be unlocked.
+        TimeRecordPaid,
         /// You cannot change a time record you do not own.
         TimeRecordNotOwned,
         /// Time record already invoiced. It cannot be changed.
@@ -316,6 +403,26 @@ mod pallet {
         StatusIdentical,
         /// This status cannot be set here.
         StatusCannotBeSetHere,
+        /// Only accepted time records can be invoiced.
+        TimeRecordNotAccepted,
+        /// The time record has not been invoiced, or the invoice is already paid.
+        TimeRecordNotInvoiced,
+        /// No rate has been set for the worker or the project.
+        RateNotSet,
+        /// The invoiced amount is too large.
+        InvoiceAmountOverflow,
+        /// The project owner cannot invoice their own time.
+        CannotInvoiceOwnProject,
+        /// There is no accepted time to invoice.
+        NothingToInvoice,
+        /// An error occured posting to accounts.
+        ErrorInAccounting,
+        /// The payment of the invoice failed.
+        ErrorInPayment,
+        /// The posting period is not the fiscal period in which the time record ends.
+        InvalidPostingPeriod,
+        /// The fiscal period in which the time record ends has been closed.
+        PostingPeriodClosed,
     }
 
     #[pallet::hooks]
@@ -444,10 +551,11 @@ mod pallet {
 
         #[pallet::weight(0/*TODO*/)]
         /// Worker submits/resubmits time record.
+        /// A new time record is submitted without a time record hash, and is either a draft or submitted.
         fn submit_time(
             origin: OriginFor<T>,
             project_hash: T::Hash,
-            input_time_hash: T::Hash,
+            input_time_hash: Option<T::Hash>,
             submit_status: StatusOfTimeRecord,
             reason_for_change: ReasonCodeStruct,
             number_of_blocks: NumberOfBlocks,
@@ -461,80 +569,25 @@ mod pallet {
             // Check that this project is still active (not closed or deleted or with no status)
             ensure!(T::Projects::is_project_valid(project_hash.clone()), Error::<T>::ProjectInactive);
 
-            // Check worker is not on the banned list
-            let ban_list_key = (project_hash.clone(), who.clone());
-            ensure!(!ProjectWorkersBanList::<T>::contains_key(&ban_list_key), Error::<T>::WorkerBanned);
-            // Check worker is part of the team
-            let check_team_member = who.clone();
-            Self::project_workers_list(project_hash.clone())
-                .into_iter()
-                .flatten()
-                .find(|x| x == &check_team_member)
-                .ok_or(Error::<T>::WorkerNotAssigned)?;
-
-            // For testing
-            // let input_time_hash_2 = hex!("e4d673a76e8b32ca3989dbb9f444f71813c88d36120170b15151d58c7106cc83");
-            // let default_hash: TimeHash = hex!("e4d673a76e8b32ca3989dbb9f444f71813c88d36120170b15151d58c7106cc83");
-            // 0x6c9596f9ca96adf2334c4761bc161442a32ef16896427b6d43fc5e9353bbab63
-
-            let default_bytes = "Default hash";
-            let default_hash: T::Hash = todo!(); //T::Hashing::hash(&default_bytes.encode().as_slice()); // default hash BlakeTwo256
-
             // set default lock and reason code and type default values (TODO should come from extrinsic in future)
             let initial_submit_reason = ReasonCodeStruct(0, 0);
             let initial_reason_for_lock = ReasonCodeStruct(0, 0);
 
-            // check that the submission is using either the default hash or some other hash.
-            if input_time_hash == default_hash {
-                // This is the default hash therefore it is a new submission.
-
-                // prepare new time record
-                let time_data = Timekeeper {
-                    worker: who.clone(),
-                    project_hash: project_hash.clone(),
-                    total_blocks: number_of_blocks,
-                    locked_status: false,
-                    locked_reason: initial_reason_for_lock,
-                    submit_status: StatusOfTimeRecord::Submitted, // new record always gets status 1
-                    reason_code: initial_submit_reason,
-                    posting_period: 0, // temporary for this version of totem (meccano).
-                    start_block: start_block_number,
-                    end_block: end_block_number,
-                    nr_of_breaks: break_counter,
-                };
-
-                // Create a new random hash
-                let time_hash: T::Hash = todo!(); //time_data.clone().using_encoded(<T as frame_system::Config>::Hashing::hash);
-
-                // Now update all time relevant records
-                WorkerTimeRecordsHashList::<T>::mutate_(&who, |worker_time_records_hash_list| {
-                    worker_time_records_hash_list.push(time_hash.clone())
-                });
-
-                // Add time hash to project list
-                ProjectTimeRecordsHashList::<T>::mutate_(&project_hash, |project_time_hash_list| {
-                    project_time_hash_list.push(time_hash.clone())
-                });
-
-                TimeHashOwner::<T>::insert(time_hash.clone(), who.clone());
-
-                // Insert record
-                TimeRecord::<T>::insert(time_hash.clone(), &time_data);
-                Self::deposit_event(Event::SubmitedTimeRecord(time_hash));
-            } else {
-                // find out if this is a genuine original key
-                let original_time_key = input_time_hash.clone();
-
-                let old_time_record =
+            // check whether the submission is a new time record or changes an existing one.
+            if let Some(original_time_key) = input_time_hash {
+                let mut old_time_record =
                     Self::time_record(&original_time_key).ok_or(Error::<T>::TimeRecordNotFromWorker)?;
+                ensure!(old_time_record.project_hash == project_hash, Error::<T>::InvalidProjectOrOwner);
                 ensure!(old_time_record.locked_status == false, Error::<T>::TimeRecordLocked);
 
-                // reverse out previously accepted time record
-                Self::undo_update_totals(
-                    old_time_record.worker.clone(),
-                    old_time_record.project_hash,
-                    old_time_record.total_blocks,
-                )?;
+                // The project owner or a manager has already accepted, but a correction is agreed with the worker.
+                if old_time_record.submit_status == StatusOfTimeRecord::Accepted {
+                    return Self::reset_accepted_time(who, original_time_key, old_time_record, submit_status);
+                }
+
+                Self::ensure_worker_can_submit(&who, &project_hash)?;
+                // The time is booked in the open fiscal period of the worker in which it ends
+                Self::validate_posting_period(old_time_record.worker.clone(), end_block_number, posting_period)?;
 
                 let proposed_new_status = submit_status.clone();
 
@@ -547,7 +600,7 @@ mod pallet {
                     locked_reason: initial_reason_for_lock,
                     submit_status: submit_status,
                     reason_code: initial_submit_reason,
-                    posting_period: 0, // not implemented in totem meccano
+                    posting_period,
                     start_block: start_block_number,
                     end_block: end_block_number,
                     nr_of_breaks: break_counter,
@@ -612,30 +665,19 @@ mod pallet {
                         // 0, 0 initial reason code is the default
                         old_time_record.reason_code = ReasonCodeStruct(0, 0);
                     }
-                    StatusOfTimeRecord::Accepted => {
-                        // The project owner has already accepted, but a correction is agreed with worker.
-                        // therefore reset the record to "draft"
-                        let hash_has_correct_owner =
-                            T::Projects::is_owner_and_project_valid(who.clone(), project_hash.clone());
-                        ensure!(hash_has_correct_owner, Error::<T>::InvalidProjectOrOwner);
-
-                        // ensure that a correct reason is given by project owner
-                        // TODO inspect reason code values, change if necessary
-
-                        // force change pending above
-                        // [1, 1] = [time record can be re-edited by the team member, set in time module]
-                        old_time_record.reason_code = ReasonCodeStruct(1, 1);
-
-                        match proposed_new_status {
-                            StatusOfTimeRecord::Draft => old_time_record.submit_status = proposed_new_status, // Draft to submitted.
-                            // not appropriate to set these codes here. Other specific functions exist.
-                            _ => fail!(Error::<T>::StatusCannotBeSetHere),
-                        }
-                    }
+                    // Accepted time can only be reset by a manager, above.
+                    StatusOfTimeRecord::Accepted => fail!(Error::<T>::StatusCannotBeSetHere),
                     StatusOfTimeRecord::Invoiced => fail!(Error::<T>::TimeRecordAlreadyInvoiced),
                     StatusOfTimeRecord::Blocked => fail!(Error::<T>::TimeBlocked),
                 };
 
+                // reverse out previously accepted time record
+                Self::undo_update_totals(
+                    old_time_record.worker.clone(),
+                    old_time_record.project_hash,
+                    old_time_record.total_blocks,
+                )?;
+
                 // update all relevant fields from the incoming data
                 // setting status to submitted (1)
                 old_time_record.locked_status = false;
@@ -646,13 +688,56 @@ mod pallet {
                 old_time_record.nr_of_breaks = new_time_data.nr_of_breaks;
 
                 Self::update_time_record(original_time_key, old_time_record)?;
+            } else {
+                Self::ensure_worker_can_submit(&who, &project_hash)?;
+                // The time is booked in the open fiscal period of the worker in which it ends
+                Self::validate_posting_period(who.clone(), end_block_number, posting_period)?;
+
+                // This is a new submission.
+                match submit_status {
+                    StatusOfTimeRecord::Draft | StatusOfTimeRecord::Submitted => (),
+                    _ => fail!(Error::<T>::StatusCannotBeSetHere),
+                }
+
+                // prepare new time record
+                let time_data = Timekeeper {
+                    worker: who.clone(),
+                    project_hash: project_hash.clone(),
+                    total_blocks: number_of_blocks,
+                    locked_status: false,
+                    locked_reason: initial_reason_for_lock,
+                    submit_status,
+                    reason_code: initial_submit_reason,
+                    posting_period,
+                    start_block: start_block_number,
+                    end_block: end_block_number,
+                    nr_of_breaks: break_counter,
+                };
+
+                let time_hash = Self::new_time_record_hash(&who, &project_hash, start_block_number)?;
+
+                // Now update all time relevant records
+                WorkerTimeRecordsHashList::<T>::mutate_(&who, |worker_time_records_hash_list| {
+                    worker_time_records_hash_list.push(time_hash.clone())
+                });
+
+                // Add time hash to project list
+                ProjectTimeRecordsHashList::<T>::mutate_(&project_hash, |project_time_hash_list| {
+                    project_time_hash_list.push(time_hash.clone())
+                });
+
+                TimeHashOwner::<T>::insert(time_hash.clone(), who.clone());
+
+                // Insert record
+                TimeRecord::<T>::insert(time_hash.clone(), &time_data);
+                Self::deposit_event(Event::SubmitedTimeRecord(time_hash));
             }
 
             ok()
         }
 
         #[pallet::weight(0/*TODO*/)]
-        /// Project owner sets authorisation status of time record.
+        /// Project owner or manager sets authorisation status of time record.
         fn authorise_time(
             origin: OriginFor<T>,
             worker: T::AccountId,
@@ -663,9 +748,9 @@ mod pallet {
         ) -> DispatchResultWithPostInfo {
             let who = ensure_signed(origin)?;
 
-            // ensure that the caller is the project owner
-            let hash_has_correct_owner = T::Projects::is_owner_and_project_valid(who.clone(), project_hash.clone());
-            ensure!(hash_has_correct_owner, Error::<T>::InvalidProjectOrOwner);
+            // ensure that the caller is the project owner or a manager
+            let can_authorise = T::Projects::has_team_role(who.clone(), project_hash.clone(), TeamRole::Manager);
+            ensure!(can_authorise, Error::<T>::NotProjectManager);
 
             // prepare new time key
             let original_time_key = input_time_hash.clone();
@@ -674,6 +759,7 @@ mod pallet {
             // and get the details using the resubmitted hash
             let mut changing_time_record = Self::time_record(&original_time_key)
                 .ok_or("Time record does not exist, or this is not from the worker.")?;
+            ensure!(changing_time_record.project_hash == project_hash, Error::<T>::InvalidProjectOrOwner);
             ensure!(!changing_time_record.locked_status, Error::<T>::TimeRecordLocked);
 
             let proposed_new_status = status_of_record.clone();
@@ -704,6 +790,11 @@ mod pallet {
                 | StatusOfTimeRecord::Blocked => fail!(Error::<T>::ProjectCannotBeChanged),
             };
 
+            // accepted time counts against the project budget, which may reject it
+            if changing_time_record.submit_status == StatusOfTimeRecord::Accepted {
+                T::Projects::record_time(project_hash.clone(), changing_time_record.total_blocks)?;
+            }
+
             ProjectFirstSeen::<T>::insert(&changing_time_record.project_hash, changing_time_record.start_block);
 
             // Perform update on total amounts of time
@@ -720,82 +811,213 @@ mod pallet {
         }
 
         #[pallet::weight(0/*TODO*/)]
-        /// Worker invoices the time record.
-        /// TODO: The following functions are placeholders for future functionality
+        /// Project owner sets the rate invoiced for the time booked on the project.
+        fn set_project_rate(origin: OriginFor<T>, project_hash: T::Hash, rate: Rate) -> DispatchResultWithPostInfo {
+            let who = ensure_signed(origin)?;
+
+            let hash_has_correct_owner = T::Projects::is_owner_and_project_valid(who, project_hash.clone());
+            ensure!(hash_has_correct_owner, Error::<T>::InvalidProjectOrOwner);
+
+            ProjectRate::<T>::insert(&project_hash, rate);
+            Self::deposit_event(Event::ProjectRateSet(project_hash, rate));
+
+            ok()
+        }
+
+        #[pallet::weight(0/*TODO*/)]
+        /// Project owner sets the rate invoiced for the time booked by the worker on the project.
+        fn set_worker_rate(
+            origin: OriginFor<T>,
+            project_hash: T::Hash,
+            worker: T::AccountId,
+            rate: Rate,
+        ) -> DispatchResultWithPostInfo {
+            let who = ensure_signed(origin)?;
+
+            let hash_has_correct_owner = T::Projects::is_owner_and_project_valid(who, project_hash.clone());
+            ensure!(hash_has_correct_owner, Error::<T>::InvalidProjectOrOwner);
+
+            WorkerRate::<T>::insert((project_hash.clone(), worker.clone()), rate);
+            Self::deposit_event(Event::WorkerRateSet(project_hash, worker, rate));
+
+            ok()
+        }
+
+        #[pallet::weight(0/*TODO*/)]
+        /// Worker invoices the accepted time record to the project owner.
+        #[transactional]
         fn invoice_time(
             origin: OriginFor<T>,
-            _project_hash: T::Hash,
-            _input_time_hash: T::Hash,
+            project_hash: T::Hash,
+            input_time_hash: T::Hash,
         ) -> DispatchResultWithPostInfo {
             let who = ensure_signed(origin)?;
-            // TODO This is normally set by the invoice module not by the time module
-            // This needs to be reviewed once the invoice module is being developed.
-            // Could be that this calls a function from within the invoice module.
-            // can only invoice when time is accepted
-
-            // Set StatusOfTimeRecord
-            // invoiced,
-            Self::deposit_event(Event::InvoiceTime(who));
+
+            Self::invoice_time_record(who, project_hash, input_time_hash)
+        }
+
+        #[pallet::weight(0/*TODO*/)]
+        /// Worker invoices all their accepted time records on the project for the posting period.
+        #[transactional]
+        fn invoice_project_time(
+            origin: OriginFor<T>,
+            project_hash: T::Hash,
+            posting_period: PostingPeriod,
+        ) -> DispatchResultWithPostInfo {
+            let who = ensure_signed(origin)?;
+
+            let time_hashes: Vec<T::Hash> = Self::project_time_records_hash_list(&project_hash)
+                .into_iter()
+                .flatten()
+                .filter(|h| match Self::time_record(h) {
+                    Some(record) => {
+                        record.worker == who
+                            && record.submit_status == StatusOfTimeRecord::Accepted
+                            && !record.locked_status
+                            && record.posting_period == posting_period
+                    }
+                    None => false,
+                })
+                .collect();
+            ensure!(!time_hashes.is_empty(), Error::<T>::NothingToInvoice);
+
+            for time_hash in time_hashes {
+                Self::invoice_time_record(who.clone(), project_hash.clone(), time_hash)?;
+            }
+
             ok()
         }
 
         #[pallet::weight(0/*TODO*/)]
-        /// Project owner pays invoice.
+        /// Project owner pays invoice. The paid time record is locked.
+        #[transactional]
         fn pay_time(
             origin: OriginFor<T>,
-            _project_hash: T::Hash,
-            _input_time_hash: T::Hash,
+            project_hash: T::Hash,
+            input_time_hash: T::Hash,
         ) -> DispatchResultWithPostInfo {
             let who = ensure_signed(origin)?;
 
-            Self::deposit_event(Event::PayTime(who));
-            // Self::lock_time_record(who.clone(), project_hash.clone(), input_time_hash.clone());
-            Self::deposit_event(Event::LockTimeRecord());
+            let time_record = Self::time_record(&input_time_hash).ok_or(Error::<T>::TimeRecordNotFromWorker)?;
+            ensure!(time_record.project_hash == project_hash, Error::<T>::InvalidProjectOrOwner);
+            ensure!(!time_record.locked_status, Error::<T>::TimeRecordLocked);
+            let (payer, amount) = Self::time_invoice(&input_time_hash).ok_or(Error::<T>::TimeRecordNotInvoiced)?;
+            ensure!(payer == who, Error::<T>::InvalidProjectOrOwner);
+
+            Self::post_time_payment(who.clone(), time_record.worker.clone(), amount, input_time_hash)?;
+            let currency_amount: CurrencyBalanceOf<T> = T::TimekeepingConversions::convert(amount);
+            if let Err(_) =
+                T::Currency::transfer(&who, &time_record.worker, currency_amount, ExistenceRequirement::KeepAlive)
+            {
+                fail!(Error::<T>::ErrorInPayment);
+            }
+            TimeInvoice::<T>::remove(&input_time_hash);
+
+            Self::deposit_event(Event::PayTime(who, input_time_hash.clone()));
+
+            // Paid time can no longer be changed
+            Self::set_time_record_lock(input_time_hash, time_record, true, ReasonCodeStruct::default());
+
             ok()
         }
 
         #[pallet::weight(0/*TODO*/)]
-        /// Full payment triggers locked record.
+        /// Project owner locks the time record so that it can no longer be changed.
         fn lock_time_record(
-            _origin: OriginFor<T>,
-            _project_hash: T::Hash,
-            _input_time_hash: T::Hash,
+            origin: OriginFor<T>,
+            project_hash: T::Hash,
+            input_time_hash: T::Hash,
+            reason: ReasonCodeStruct,
         ) -> DispatchResultWithPostInfo {
-            Self::deposit_event(Event::LockTimeRecord());
+            let who = ensure_signed(origin)?;
+
+            let hash_has_correct_owner = T::Projects::is_owner_and_project_valid(who, project_hash.clone());
+            ensure!(hash_has_correct_owner, Error::<T>::InvalidProjectOrOwner);
+
+            let time_record = Self::time_record(&input_time_hash).ok_or(Error::<T>::TimeRecordNotFromWorker)?;
+            ensure!(time_record.project_hash == project_hash, Error::<T>::InvalidProjectOrOwner);
+            ensure!(!time_record.locked_status, Error::<T>::TimeRecordLocked);
+
+            Self::set_time_record_lock(input_time_hash, time_record, true, reason);
+
             ok()
         }
 
         #[pallet::weight(0/*TODO*/)]
-        /// In case of error unlock record.
+        /// In case of error the project owner unlocks the time record. Paid time records cannot be unlocked.
         fn unlock_time_record(
-            _origin: OriginFor<T>,
-            _project_hash: T::Hash,
-            _input_time_hash: T::Hash,
+            origin: OriginFor<T>,
+            project_hash: T::Hash,
+            input_time_hash: T::Hash,
+            reason: ReasonCodeStruct,
         ) -> DispatchResultWithPostInfo {
-            Self::deposit_event(Event::UnLockTimeRecord());
+            let who = ensure_signed(origin)?;
+
+            let hash_has_correct_owner = T::Projects::is_owner_and_project_valid(who, project_hash.clone());
+            ensure!(hash_has_correct_owner, Error::<T>::InvalidProjectOrOwner);
+
+            let time_record = Self::time_record(&input_time_hash).ok_or(Error::<T>::TimeRecordNotFromWorker)?;
+            ensure!(time_record.project_hash == project_hash, Error::<T>::InvalidProjectOrOwner);
+            ensure!(time_record.locked_status, Error::<T>::TimeRecordNotLocked);
+            // Paid time can no longer be changed
+            let paid = time_record.submit_status == StatusOfTimeRecord::Invoiced
+                && !TimeInvoice::<T>::contains_key(&input_time_hash);
+            ensure!(!paid, Error::<T>::TimeRecordPaid);
+
+            Self::set_time_record_lock(input_time_hash, time_record, false, reason);
+
             ok()
         }
 
         #[pallet::weight(0/*TODO*/)]
         /// Worker or team member is banned from submitting time against this project.
+        /// Time records of the worker awaiting authorisation are blocked.
         fn ban_worker(
-            _origin: OriginFor<T>,
-            _project_hash: T::Hash,
-            _worker: T::AccountId,
+            origin: OriginFor<T>,
+            project_hash: T::Hash,
+            worker: T::AccountId,
+            reason: ReasonCodeStruct,
         ) -> DispatchResultWithPostInfo {
-            // check that you are not banning is not yourself!
-            Self::deposit_event(Event::Banned());
+            let who = ensure_signed(origin)?;
+
+            let hash_has_correct_owner = T::Projects::is_owner_and_project_valid(who.clone(), project_hash.clone());
+            ensure!(hash_has_correct_owner, Error::<T>::InvalidProjectOrOwner);
+
+            // check that you are not banning yourself!
+            ensure!(worker != who, Error::<T>::CannotBanOwner);
+
+            let ban_list_key = (project_hash.clone(), worker.clone());
+            ensure!(!ProjectWorkersBanList::<T>::contains_key(&ban_list_key), Error::<T>::WorkerBanned);
+
+            ProjectWorkersBanList::<T>::insert(&ban_list_key, BannedStruct(true, reason.clone()));
+
+            Self::block_pending_time_records(&project_hash, &worker, reason);
+
+            Self::deposit_event(Event::Banned(project_hash, worker));
+
             ok()
         }
 
         #[pallet::weight(0/*TODO*/)]
         /// Worker or team member is released from ban from submitting time against this project.
+        /// Time records blocked by the ban remain blocked.
         fn unban_worker(
-            _origin: OriginFor<T>,
-            _project_hash: T::Hash,
-            _worker: T::AccountId,
+            origin: OriginFor<T>,
+            project_hash: T::Hash,
+            worker: T::AccountId,
         ) -> DispatchResultWithPostInfo {
-            Self::deposit_event(Event::UnBanned());
+            let who = ensure_signed(origin)?;
+
+            let hash_has_correct_owner = T::Projects::is_owner_and_project_valid(who, project_hash.clone());
+            ensure!(hash_has_correct_owner, Error::<T>::InvalidProjectOrOwner);
+
+            let ban_list_key = (project_hash.clone(), worker.clone());
+            ensure!(ProjectWorkersBanList::<T>::contains_key(&ban_list_key), Error::<T>::WorkerNotBanned);
+
+            ProjectWorkersBanList::<T>::remove(&ban_list_key);
+
+            Self::deposit_event(Event::UnBanned(project_hash, worker));
+
             ok()
         }
     }
@@ -807,21 +1029,143 @@ mod pallet {
         NotifyProjectWorker(T::AccountId, T::Hash),
         WorkerAcceptanceStatus(T::AccountId, T::Hash, AcceptAssignedStatus),
         SetAuthoriseStatus(T::AccountId),
-        InvoiceTime(T::AccountId),
-        PayTime(T::AccountId),
-        LockTimeRecord(),
-        UnLockTimeRecord(),
-        Banned(),
-        UnBanned(),
+        InvoiceTime(T::AccountId, T::Hash),
+        PayTime(T::AccountId, T::Hash),
+        LockTimeRecord(T::Hash),
+        UnLockTimeRecord(T::Hash),
+        Banned(T::Hash, T::AccountId),
+        UnBanned(T::Hash, T::AccountId),
         IncreaseTotalBlocks(T::AccountId, T::Hash, NumberOfBlocks),
         DecreaseTotalBlocks(T::AccountId, T::Hash, NumberOfBlocks),
+        ProjectRateSet(T::Hash, Rate),
+        WorkerRateSet(T::Hash, T::AccountId, Rate),
     }
 }
 
 pub use pallet::*;
 
 impl<T: Config> Pallet<T> {
-    // TODO Move lock/unlock to private function
+    // Locks or unlocks the time record, recording the reason
+    fn set_time_record_lock(
+        time_hash: T::Hash,
+        mut time_record: Timekeeper<
+            T::AccountId,
+            T::Hash,
+            NumberOfBlocks,
+            LockStatus,
+            StatusOfTimeRecord,
+            ReasonCodeStruct,
+            PostingPeriod,
+            StartOrEndBlockNumber,
+            NumberOfBreaks,
+        >,
+        locked: LockStatus,
+        reason: ReasonCodeStruct,
+    ) {
+        time_record.locked_status = locked;
+        time_record.locked_reason = reason;
+        TimeRecord::<T>::insert(&time_hash, time_record);
+
+        if locked {
+            Self::deposit_event(Event::LockTimeRecord(time_hash));
+        } else {
+            Self::deposit_event(Event::UnLockTimeRecord(time_hash));
+        }
+    }
+
+    // Derives the hash of a new time record from the worker, the project, the start block and the nonce of the worker
+    fn new_time_record_hash(
+        who: &T::AccountId,
+        project_hash: &T::Hash,
+        start_block: StartOrEndBlockNumber,
+    ) -> Result<T::Hash, Error<T>> {
+        let nonce = Self::time_record_nonce(who);
+        let time_hash = T::Hashing::hash_of(&(who, project_hash, start_block, nonce));
+        ensure!(!TimeRecord::<T>::contains_key(&time_hash), Error::<T>::TimeRecordAlreadyExists);
+        TimeRecordNonce::<T>::insert(who, nonce.wrapping_add(1));
+
+        Ok(time_hash)
+    }
+
+    // Checks that the posting period is the fiscal period of the worker in which the time record ends, and that it is
+    // still open
+    // The worker must be in the team of the project and not banned from it
+    fn ensure_worker_can_submit(who: &T::AccountId, project_hash: &T::Hash) -> Result<(), Error<T>> {
+        // Check worker is not on the banned list
+        let ban_list_key = (project_hash.clone(), who.clone());
+        ensure!(!ProjectWorkersBanList::<T>::contains_key(&ban_list_key), Error::<T>::WorkerBanned);
+        // Check worker is part of the team
+        Self::project_workers_list(project_hash)
+            .into_iter()
+            .flatten()
+            .find(|x| x == who)
+            .ok_or(Error::<T>::WorkerNotAssigned)?;
+
+        Ok(())
+    }
+
+    // A manager resets accepted time to draft, so that the worker can correct it. Only the status and the reason
+    // change, and the time is released from the totals and from the project budget.
+    fn reset_accepted_time(
+        who: T::AccountId,
+        time_hash: T::Hash,
+        mut time_record: Timekeeper<
+            T::AccountId,
+            T::Hash,
+            NumberOfBlocks,
+            LockStatus,
+            StatusOfTimeRecord,
+            ReasonCodeStruct,
+            PostingPeriod,
+            StartOrEndBlockNumber,
+            NumberOfBreaks,
+        >,
+        new_status: StatusOfTimeRecord,
+    ) -> DispatchResultWithPostInfo {
+        let can_authorise = T::Projects::has_team_role(who, time_record.project_hash.clone(), TeamRole::Manager);
+        ensure!(can_authorise, Error::<T>::NotProjectManager);
+        // not appropriate to set other codes here. Other specific functions exist.
+        ensure!(new_status == StatusOfTimeRecord::Draft, Error::<T>::StatusCannotBeSetHere);
+
+        T::Projects::release_time(time_record.project_hash.clone(), time_record.total_blocks)?;
+        Self::undo_update_totals(time_record.worker.clone(), time_record.project_hash, time_record.total_blocks)?;
+
+        // [1, 1] = [time record can be re-edited by the team member, set in time module]
+        time_record.submit_status = new_status;
+        time_record.reason_code = ReasonCodeStruct(1, 1);
+
+        Self::update_time_record(time_hash, time_record)
+    }
+
+    fn validate_posting_period(
+        who: T::AccountId,
+        end_block: StartOrEndBlockNumber,
+        posting_period: PostingPeriod,
+    ) -> Result<(), Error<T>> {
+        let period =
+            T::Calendar::fiscal_period(who, end_block.saturated_into()).ok_or(Error::<T>::InvalidPostingPeriod)?;
+        ensure!(period.index == posting_period, Error::<T>::InvalidPostingPeriod);
+        ensure!(!period.closed, Error::<T>::PostingPeriodClosed);
+
+        Ok(())
+    }
+
+    // Blocks the submitted time records of a banned worker, so that they cannot be accepted
+    fn block_pending_time_records(project_hash: &T::Hash, worker: &T::AccountId, reason: ReasonCodeStruct) {
+        for time_hash in Self::project_time_records_hash_list(project_hash).into_iter().flatten() {
+            TimeRecord::<T>::mutate(&time_hash, |record| {
+                if let Some(record) = record {
+                    if &record.worker == worker
+                        && !record.locked_status
+                        && record.submit_status == StatusOfTimeRecord::Submitted
+                    {
+                        record.submit_status = StatusOfTimeRecord::Blocked;
+                        record.reason_code = reason.clone();
+                    }
+                }
+            });
+        }
+    }
 
     // When the worker accepts to work on the project, they are added to the team
     fn store_worker_acceptance(project_hash: T::Hash, who: T::AccountId) -> DispatchResultWithPostInfo {
@@ -926,6 +1270,116 @@ impl<T: Config> Pallet<T> {
         ok()
     }
 
+    // Invoices the accepted time record of the worker to the project owner, at the rate of the worker or of the project.
+    fn invoice_time_record(who: T::AccountId, project_hash: T::Hash, time_hash: T::Hash) -> DispatchResultWithPostInfo {
+        let mut time_record = Self::time_record(&time_hash).ok_or(Error::<T>::TimeRecordNotFromWorker)?;
+        ensure!(time_record.worker == who, Error::<T>::TimeRecordNotOwned);
+        ensure!(time_record.project_hash == project_hash, Error::<T>::InvalidProjectOrOwner);
+        ensure!(!time_record.locked_status, Error::<T>::TimeRecordLocked);
+        match time_record.submit_status {
+            StatusOfTimeRecord::Accepted => (),
+            StatusOfTimeRecord::Invoiced => fail!(Error::<T>::TimeRecordAlreadyInvoiced),
+            _ => fail!(Error::<T>::TimeRecordNotAccepted),
+        }
+
+        let project_owner =
+            T::Projects::get_project_owner(project_hash.clone()).ok_or(Error::<T>::InvalidProjectOrOwner)?;
+        ensure!(project_owner != who, Error::<T>::CannotInvoiceOwnProject);
+        let rate = Self::worker_rate((project_hash.clone(), who.clone()))
+            .or_else(|| Self::project_rate(&project_hash))
+            .ok_or(Error::<T>::RateNotSet)?;
+        let amount = rate.checked_mul(time_record.total_blocks as u128).ok_or(Error::<T>::InvoiceAmountOverflow)?;
+        ensure!(amount > 0, Error::<T>::NothingToInvoice);
+
+        T::Projects::record_cost(project_hash, amount)?;
+        Self::post_time_invoice(who.clone(), project_owner.clone(), amount, time_hash)?;
+        TimeInvoice::<T>::insert(&time_hash, (project_owner, amount));
+        time_record.submit_status = StatusOfTimeRecord::Invoiced;
+        TimeRecord::<T>::insert(&time_hash, time_record);
+
+        Self::deposit_event(Event::InvoiceTime(who, time_hash));
+
+        ok()
+    }
+
+    // Posts the invoice to the accounts of the worker (seller) and the project owner (buyer).
+    fn post_time_invoice(o: T::AccountId, p: T::AccountId, amount: u128, h: T::Hash) -> DispatchResultWithPostInfo {
+        let amount: i128 = i128::try_from(amount).map_err(|_| Error::<T>::InvoiceAmountOverflow)?;
+        let increase_amount: AccountBalanceOf<T> = T::TimekeepingConversions::convert(amount);
+        let current_block = frame_system::Module::<T>::block_number();
+        let current_block_dupe = frame_system::Module::<T>::block_number();
+
+        // Worker
+        let account_1 = T::TimekeepingConversions::convert(LedgerAccount::ACCOUNTS_RECEIVABLE.number()); // Debit  increase
+        let account_2 = T::TimekeepingConversions::convert(LedgerAccount::SALES.number()); // Credit increase
+        let account_3 = T::TimekeepingConversions::convert(LedgerAccount::SALES_LEDGER_BY_PAYER.number()); // Debit  increase
+        let account_4 = T::TimekeepingConversions::convert(LedgerAccount::SALES_LEDGER_CONTROL.number()); // Debit  increase
+
+        // Project owner
+        let account_5 = T::TimekeepingConversions::convert(LedgerAccount::ACCOUNTS_PAYABLE.number()); // Credit increase
+        let account_6 = T::TimekeepingConversions::convert(LedgerAccount::LABOUR.number()); // Debit  increase
+        let account_7 = T::TimekeepingConversions::convert(LedgerAccount::PURCHASE_LEDGER_BY_VENDOR.number()); // Debit  increase
+        let account_8 = T::TimekeepingConversions::convert(LedgerAccount::PURCHASE_LEDGER_CONTROL.number()); // Debit  increase
+
+        // Keys for posting
+        let forward_keys = vec![
+            (o.clone(), account_1, increase_amount, true, h, current_block, current_block_dupe),
+            (o.clone(), account_2, increase_amount, false, h, current_block, current_block_dupe),
+            (o.clone(), account_3, increase_amount, true, h, current_block, current_block_dupe),
+            (o.clone(), account_4, increase_amount, true, h, current_block, current_block_dupe),
+            (p.clone(), account_5, increase_amount, false, h, current_block, current_block_dupe),
+            (p.clone(), account_6, increase_amount, true, h, current_block, current_block_dupe),
+            (p.clone(), account_7, increase_amount, true, h, current_block, current_block_dupe),
+            (p.clone(), account_8, increase_amount, true, h, current_block, current_block_dupe),
+        ];
+
+        if let Err(_) = T::Accounting::handle_multiposting_amounts(forward_keys) {
+            fail!(Error::<T>::ErrorInAccounting);
+        }
+
+        ok()
+    }
+
+    // Posts the payment of the invoice to the accounts of the project owner (buyer) and the worker (seller).
+    // The payment clears the transfers suspense account, the transfer itself being posted by the balances pallet.
+    fn post_time_payment(o: T::AccountId, w: T::AccountId, amount: u128, h: T::Hash) -> DispatchResultWithPostInfo {
+        let amount: i128 = i128::try_from(amount).map_err(|_| Error::<T>::InvoiceAmountOverflow)?;
+        let increase_amount: AccountBalanceOf<T> = T::TimekeepingConversions::convert(amount);
+        let decrease_amount: AccountBalanceOf<T> = T::TimekeepingConversions::convert(-amount);
+        let current_block = frame_system::Module::<T>::block_number();
+        let current_block_dupe = frame_system::Module::<T>::block_number();
+
+        // Project owner
+        let account_1 = T::TimekeepingConversions::convert(LedgerAccount::ACCOUNTS_PAYABLE.number()); // Debit  decrease
+        let account_2 = T::TimekeepingConversions::convert(LedgerAccount::TRANSFERS_SUSPENSE.number()); // Credit decrease
+        let account_3 = T::TimekeepingConversions::convert(LedgerAccount::PURCHASE_LEDGER_BY_VENDOR.number()); // Credit decrease
+        let account_4 = T::TimekeepingConversions::convert(LedgerAccount::PURCHASE_LEDGER_CONTROL.number()); // Credit decrease
+
+        // Worker
+        let account_5 = T::TimekeepingConversions::convert(LedgerAccount::TRANSFERS_SUSPENSE.number()); // Debit  increase
+        let account_6 = T::TimekeepingConversions::convert(LedgerAccount::ACCOUNTS_RECEIVABLE.number()); // Credit decrease
+        let account_7 = T::TimekeepingConversions::convert(LedgerAccount::SALES_LEDGER_BY_PAYER.number()); // Credit decrease
+        let account_8 = T::TimekeepingConversions::convert(LedgerAccount::SALES_LEDGER_CONTROL.number()); // Credit decrease
+
+        // Keys for posting
+        let forward_keys = vec![
+            (o.clone(), account_1, decrease_amount, true, h, current_block, current_block_dupe),
+            (o.clone(), account_2, decrease_amount, false, h, current_block, current_block_dupe),
+            (o.clone(), account_3, decrease_amount, false, h, current_block, current_block_dupe),
+            (o.clone(), account_4, decrease_amount, false, h, current_block, current_block_dupe),
+            (w.clone(), account_5, increase_amount, true, h, current_block, current_block_dupe),
+            (w.clone(), account_6, decrease_amount, false, h, current_block, current_block_dupe),
+            (w.clone(), account_7, decrease_amount, false, h, current_block, current_block_dupe),
+            (w.clone(), account_8, decrease_amount, false, h, current_block, current_block_dupe),
+        ];
+
+        if let Err(_) = T::Accounting::handle_multiposting_amounts(forward_keys) {
+            fail!(Error::<T>::ErrorInAccounting);
+        }
+
+        ok()
+    }
+
     fn set_project_time_archive(
         time_hash: T::Hash,
         project_hash: T::Hash,
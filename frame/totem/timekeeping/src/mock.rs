@@ -0,0 +1,307 @@
+#![cfg(test)]
+
+use crate as pallet_timekeeping;
+
+use frame_support::{dispatch::DispatchResultWithPostInfo, parameter_types, traits::BalanceStatus};
+use sp_core::H256;
+use sp_runtime::{
+    testing::Header,
+    traits::{BlakeTwo256, Convert, IdentityLookup},
+};
+use std::cell::RefCell;
+use totem_utils::ok;
+use totem_utils::traits::{
+    accounting::{FiscalCalendar, Posting},
+    teams::{Budgeting, Validating},
+};
+use totem_utils::types::{FiscalPeriod, TeamRole};
+
+type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
+type Block = frame_system::mocking::MockBlock<Test>;
+
+pub const PROJECT_OWNER: u64 = 1;
+pub const WORKER: u64 = 2;
+pub const MANAGER: u64 = 3;
+
+// Configure a mock runtime to test the pallet.
+frame_support::construct_runtime!(
+    pub enum Test where
+        Block = Block,
+        NodeBlock = Block,
+        UncheckedExtrinsic = UncheckedExtrinsic,
+    {
+        System: frame_system::{Module, Call, Config, Storage, Event<T>},
+        Balances: pallet_balances::{Module, Call, Config<T>, Storage, Event<T>},
+        Timekeeping: pallet_timekeeping::{Module, Call, Storage, Event<T>},
+    }
+);
+
+parameter_types! {
+    pub const BlockHashCount: u64 = 250;
+    pub const SS58Prefix: u8 = 42;
+}
+
+impl frame_system::Config for Test {
+    type BaseCallFilter = ();
+    type BlockWeights = ();
+    type BlockLength = ();
+    type DbWeight = ();
+    type Origin = Origin;
+    type Call = Call;
+    type Index = u64;
+    type BlockNumber = u64;
+    type Hash = H256;
+    type Hashing = BlakeTwo256;
+    type AccountId = u64;
+    type Lookup = IdentityLookup<Self::AccountId>;
+    type Header = Header;
+    type Event = Event;
+    type BlockHashCount = BlockHashCount;
+    type Version = ();
+    type PalletInfo = PalletInfo;
+    type AccountData = pallet_balances::AccountData<u64>;
+    type OnNewAccount = ();
+    type OnKilledAccount = ();
+    type SystemWeightInfo = ();
+    type SS58Prefix = SS58Prefix;
+}
+
+parameter_types! {
+    pub const ExistentialDeposit: u64 = 1;
+}
+
+impl pallet_balances::Config for Test {
+    type MaxLocks = ();
+    type Balance = u64;
+    type DustRemoval = ();
+    type Event = Event;
+    type ExistentialDeposit = ExistentialDeposit;
+    type AccountStore = System;
+    type WeightInfo = ();
+    type Accounting = MockAccounting;
+}
+
+impl pallet_timekeeping::Config for Test {
+    type Event = Event;
+    type Projects = MockProjects;
+    type Currency = Balances;
+    type Accounting = MockAccounting;
+    type Calendar = MockCalendar;
+    type TimekeepingConversions = MockConversions;
+}
+
+/// The only valid project, owned by `PROJECT_OWNER` and managed by `MANAGER`.
+pub fn project() -> H256 {
+    H256::repeat_byte(1)
+}
+
+pub struct MockProjects;
+
+impl Validating<u64, H256> for MockProjects {
+    fn is_project_owner(o: u64, h: H256) -> bool {
+        o == PROJECT_OWNER && h == project()
+    }
+
+    fn is_owner_and_project_valid(o: u64, h: H256) -> bool {
+        Self::is_project_owner(o, h)
+    }
+
+    fn is_project_valid(h: H256) -> bool {
+        h == project()
+    }
+
+    fn get_project_owner(h: H256) -> Option<u64> {
+        if Self::is_project_valid(h) {
+            Some(PROJECT_OWNER)
+        } else {
+            None
+        }
+    }
+
+    fn get_team_role(o: u64, h: H256) -> Option<TeamRole> {
+        match o {
+            PROJECT_OWNER if h == project() => Some(TeamRole::Owner),
+            MANAGER if h == project() => Some(TeamRole::Manager),
+            _ => None,
+        }
+    }
+
+    fn has_team_role(o: u64, h: H256, r: TeamRole) -> bool {
+        Self::is_project_valid(h) && Self::get_team_role(o, h).map_or(false, |role| role >= r)
+    }
+}
+
+thread_local! {
+    static BUDGETED_BLOCKS: RefCell<Option<u64>> = RefCell::new(None);
+    static ACCEPTED_BLOCKS: RefCell<u64> = RefCell::new(0);
+    static RECORDED_COST: RefCell<u128> = RefCell::new(0);
+}
+
+/// Enforces a budget of `blocks` of time on the project.
+pub fn set_budgeted_blocks(blocks: u64) {
+    BUDGETED_BLOCKS.with(|budgeted| *budgeted.borrow_mut() = Some(blocks));
+}
+
+/// The blocks of accepted time recorded against the project budget.
+pub fn accepted_blocks() -> u64 {
+    ACCEPTED_BLOCKS.with(|accepted| *accepted.borrow())
+}
+
+/// The invoiced costs recorded against the project budget.
+pub fn recorded_cost() -> u128 {
+    RECORDED_COST.with(|recorded| *recorded.borrow())
+}
+
+impl Budgeting<H256> for MockProjects {
+    fn record_time(_h: H256, blocks: u64) -> DispatchResultWithPostInfo {
+        let accepted = accepted_blocks() + blocks;
+        if BUDGETED_BLOCKS.with(|budgeted| budgeted.borrow().map_or(false, |budgeted| accepted > budgeted)) {
+            return Err("Budget exceeded".into());
+        }
+        ACCEPTED_BLOCKS.with(|recorded| *recorded.borrow_mut() = accepted);
+        ok()
+    }
+
+    fn release_time(_h: H256, blocks: u64) -> DispatchResultWithPostInfo {
+        ACCEPTED_BLOCKS.with(|accepted| {
+            let released = accepted.borrow().saturating_sub(blocks);
+            *accepted.borrow_mut() = released
+        });
+        ok()
+    }
+
+    fn record_cost(_h: H256, amount: u128) -> DispatchResultWithPostInfo {
+        RECORDED_COST.with(|recorded| *recorded.borrow_mut() += amount);
+        ok()
+    }
+}
+
+/// A posting key: identity, account, amount, debit indicator, reference, change block and applicable block.
+pub type PostingKey = (u64, u64, i128, bool, H256, u64, u64);
+
+thread_local! {
+    static POSTED: RefCell<Vec<PostingKey>> = RefCell::new(Vec::new());
+}
+
+/// Takes the keys posted to the ledgers since the last call.
+pub fn posted() -> Vec<PostingKey> {
+    POSTED.with(|posted| posted.borrow_mut().drain(..).collect())
+}
+
+// Postings of the pallet are recorded, those of the balances are accepted without being recorded.
+pub struct MockAccounting;
+
+impl Posting<u64, H256, u64, u64> for MockAccounting {
+    type Account = u64;
+    type PostingIndex = u128;
+    type LedgerBalance = i128;
+
+    fn handle_multiposting_amounts(keys: Vec<PostingKey>) -> DispatchResultWithPostInfo {
+        POSTED.with(|posted| posted.borrow_mut().extend(keys));
+        ok()
+    }
+
+    fn account_for_fees(_f: u64, _t: u64, _r: u64, _p: u64) -> DispatchResultWithPostInfo {
+        ok()
+    }
+
+    fn account_for_fee_revenue(_f: u64, _r: u64) -> DispatchResultWithPostInfo {
+        ok()
+    }
+
+    fn account_for_transfer(
+        _f: u64,
+        _fs: BalanceStatus,
+        _t: u64,
+        _ts: BalanceStatus,
+        _a: u64,
+    ) -> DispatchResultWithPostInfo {
+        ok()
+    }
+
+    fn account_for_reserve(_w: u64, _a: u64, _s: BalanceStatus) -> DispatchResultWithPostInfo {
+        ok()
+    }
+
+    fn account_for_slash(_w: u64, _a: u64, _s: BalanceStatus) -> DispatchResultWithPostInfo {
+        ok()
+    }
+
+    fn account_for_deposit(_w: u64, _a: u64, _s: BalanceStatus) -> DispatchResultWithPostInfo {
+        ok()
+    }
+
+    fn account_for_withdrawal(_w: u64, _a: u64, _s: BalanceStatus) -> DispatchResultWithPostInfo {
+        ok()
+    }
+
+    fn account_for_opening_balance(_w: u64) -> DispatchResultWithPostInfo {
+        ok()
+    }
+
+    fn record_sales_tax(_v: u64, _j: u64, _a: Self::LedgerBalance) -> DispatchResultWithPostInfo {
+        ok()
+    }
+
+    fn get_escrow_account() -> u64 {
+        unimplemented!("Used as a mock, shouldn't be called")
+    }
+
+    fn get_pseudo_random_hash(_s: u64, _r: u64) -> H256 {
+        unimplemented!("Used as a mock, shouldn't be called")
+    }
+}
+
+thread_local! {
+    static CLOSED_UNTIL: RefCell<Option<u64>> = RefCell::new(None);
+}
+
+/// The last block of each fiscal period, for every identity.
+pub const FISCAL_PERIOD_ENDS: [u64; 2] = [20, 40];
+
+/// Closes the fiscal periods up to and including the block `b`.
+pub fn close_periods_until(b: u64) {
+    CLOSED_UNTIL.with(|closed_until| *closed_until.borrow_mut() = Some(b));
+}
+
+pub struct MockCalendar;
+
+impl FiscalCalendar<u64, u64> for MockCalendar {
+    fn fiscal_period(_o: u64, b: u64) -> Option<FiscalPeriod<u64>> {
+        FiscalPeriod::of(&FISCAL_PERIOD_ENDS, CLOSED_UNTIL.with(|closed_until| *closed_until.borrow()), b)
+    }
+}
+
+pub struct MockConversions;
+
+impl Convert<u64, u64> for MockConversions {
+    fn convert(u: u64) -> u64 {
+        u
+    }
+}
+
+impl Convert<i128, i128> for MockConversions {
+    fn convert(u: i128) -> i128 {
+        u
+    }
+}
+
+impl Convert<u128, u64> for MockConversions {
+    fn convert(u: u128) -> u64 {
+        u as u64
+    }
+}
+
+// This function basically just builds a genesis storage key/value store according to
+// our desired mockup.
+pub fn new_test_ext() -> sp_io::TestExternalities {
+    let mut t = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+    pallet_balances::GenesisConfig::<Test> {
+        balances: vec![(PROJECT_OWNER, 1000), (WORKER, 1000)],
+    }
+    .assimilate_storage(&mut t)
+    .unwrap();
+    let mut ext: sp_io::TestExternalities = t.into();
+    ext.execute_with(|| System::set_block_number(1));
+    ext
+}
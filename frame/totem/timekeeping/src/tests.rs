@@ -1,9 +1,525 @@
 #![cfg(test)]
 
-#[derive(Clone, PartialEq, Eq, Debug)]
-pub struct Test;
+use crate::mock::*;
+use crate::{BannedStruct, Call as TimekeepingCall, Error, ReasonCodeStruct, StatusOfTimeRecord};
+
+use frame_support::{assert_noop, assert_ok, dispatch::DispatchResultWithPostInfo, traits::UnfilteredDispatchable};
+use sp_core::H256;
+use sp_runtime::traits::{BlakeTwo256, Hash};
+use totem_utils::chart_of_accounts::LedgerAccount;
+
+fn dispatch(who: u64, call: TimekeepingCall<Test>) -> DispatchResultWithPostInfo {
+    call.dispatch_bypass_filter(Origin::signed(who))
+}
+
+fn join_project() {
+    assert_ok!(dispatch(PROJECT_OWNER, TimekeepingCall::notify_project_worker(WORKER, project())));
+    assert_ok!(dispatch(WORKER, TimekeepingCall::worker_acceptance_project(project(), true)));
+}
+
+// Submits 10 blocks of time starting at `start_block`, in the first fiscal period.
+fn submit_time(
+    time_hash: Option<H256>,
+    submit_status: StatusOfTimeRecord,
+    start_block: u64,
+) -> DispatchResultWithPostInfo {
+    submit_time_in_period(time_hash, submit_status, start_block, 0)
+}
+
+// Submits 10 blocks of time starting at `start_block`, in the fiscal period `posting_period`.
+fn submit_time_in_period(
+    time_hash: Option<H256>,
+    submit_status: StatusOfTimeRecord,
+    start_block: u64,
+    posting_period: u16,
+) -> DispatchResultWithPostInfo {
+    dispatch(
+        WORKER,
+        TimekeepingCall::submit_time(
+            project(),
+            time_hash,
+            submit_status,
+            ReasonCodeStruct::default(),
+            10,
+            posting_period,
+            start_block,
+            start_block + 10,
+            0,
+        ),
+    )
+}
+
+// A manager resets the accepted time record to draft, with time arguments that differ from the record.
+fn reset_accepted_time(manager: u64, time_hash: H256) -> DispatchResultWithPostInfo {
+    dispatch(
+        manager,
+        TimekeepingCall::submit_time(
+            project(),
+            Some(time_hash),
+            StatusOfTimeRecord::Draft,
+            ReasonCodeStruct::default(),
+            30,
+            1,
+            21,
+            51,
+            2,
+        ),
+    )
+}
+
+fn accept_time(time_hash: H256) -> DispatchResultWithPostInfo {
+    dispatch(
+        PROJECT_OWNER,
+        TimekeepingCall::authorise_time(
+            WORKER,
+            project(),
+            time_hash,
+            StatusOfTimeRecord::Accepted,
+            ReasonCodeStruct::default(),
+        ),
+    )
+}
+
+fn expected_time_hash(start_block: u64, nonce: u64) -> H256 {
+    BlakeTwo256::hash_of(&(WORKER, project(), start_block, nonce))
+}
+
+#[test]
+fn new_time_record_hash_is_derived_from_worker_project_start_block_and_nonce() {
+    new_test_ext().execute_with(|| {
+        join_project();
+
+        assert_ok!(submit_time(None, StatusOfTimeRecord::Draft, 1));
+        assert_ok!(submit_time(None, StatusOfTimeRecord::Draft, 1));
+
+        let first = expected_time_hash(1, 0);
+        let second = expected_time_hash(1, 1);
+        assert_ne!(first, second);
+        assert_eq!(Timekeeping::time_record(first).map(|r| r.submit_status), Some(StatusOfTimeRecord::Draft));
+        assert_eq!(Timekeeping::time_record(second).map(|r| r.submit_status), Some(StatusOfTimeRecord::Draft));
+        assert_eq!(Timekeeping::time_hash_owner(first), Some(WORKER));
+        assert_eq!(Timekeeping::project_time_records_hash_list(project()), Some(vec![first, second]));
+        assert_eq!(Timekeeping::time_record_nonce(WORKER), 2);
+    });
+}
+
+#[test]
+fn new_time_record_must_be_draft_or_submitted() {
+    new_test_ext().execute_with(|| {
+        join_project();
+
+        assert_noop!(submit_time(None, StatusOfTimeRecord::Accepted, 1), Error::<Test>::StatusCannotBeSetHere);
+        assert_noop!(submit_time(None, StatusOfTimeRecord::Invoiced, 1), Error::<Test>::StatusCannotBeSetHere);
+    });
+}
+
+#[test]
+fn unknown_time_record_cannot_be_changed() {
+    new_test_ext().execute_with(|| {
+        join_project();
+
+        assert_noop!(
+            submit_time(Some(H256::repeat_byte(9)), StatusOfTimeRecord::Submitted, 1),
+            Error::<Test>::TimeRecordNotFromWorker
+        );
+    });
+}
+
+#[test]
+fn time_is_booked_in_the_fiscal_period_in_which_it_ends() {
+    new_test_ext().execute_with(|| {
+        join_project();
+
+        // The time from block 15 to block 25 ends in the second period
+        assert_noop!(
+            submit_time_in_period(None, StatusOfTimeRecord::Submitted, 15, 0),
+            Error::<Test>::InvalidPostingPeriod
+        );
+        assert_ok!(submit_time_in_period(None, StatusOfTimeRecord::Submitted, 15, 1));
+        assert_eq!(Timekeeping::time_record(expected_time_hash(15, 0)).map(|r| r.posting_period), Some(1));
+
+        // The time from block 45 to block 55 ends after the last defined period
+        assert_ok!(submit_time_in_period(None, StatusOfTimeRecord::Submitted, 45, 2));
+        assert_eq!(Timekeeping::time_record(expected_time_hash(45, 1)).map(|r| r.posting_period), Some(2));
+    });
+}
+
+#[test]
+fn time_cannot_be_submitted_into_a_closed_period() {
+    new_test_ext().execute_with(|| {
+        join_project();
+        assert_ok!(submit_time(None, StatusOfTimeRecord::Draft, 1));
+
+        close_periods_until(20);
+
+        assert_noop!(submit_time(None, StatusOfTimeRecord::Draft, 1), Error::<Test>::PostingPeriodClosed);
+        assert_noop!(
+            submit_time(Some(expected_time_hash(1, 0)), StatusOfTimeRecord::Submitted, 1),
+            Error::<Test>::PostingPeriodClosed
+        );
+        assert_ok!(submit_time_in_period(None, StatusOfTimeRecord::Draft, 21, 1));
+    });
+}
+
+#[test]
+fn time_record_lifecycle_from_draft_to_paid() {
+    new_test_ext().execute_with(|| {
+        join_project();
+
+        // Draft
+        assert_ok!(submit_time(None, StatusOfTimeRecord::Draft, 1));
+        let time_hash = expected_time_hash(1, 0);
+        assert_noop!(
+            dispatch(
+                PROJECT_OWNER,
+                TimekeepingCall::authorise_time(
+                    WORKER,
+                    project(),
+                    time_hash,
+                    StatusOfTimeRecord::Accepted,
+                    ReasonCodeStruct::default(),
+                )
+            ),
+            Error::<Test>::TimeRecordNotFinalised
+        );
+
+        // Submitted
+        assert_ok!(submit_time(Some(time_hash), StatusOfTimeRecord::Submitted, 1));
+        assert_eq!(Timekeeping::time_record(time_hash).map(|r| r.submit_status), Some(StatusOfTimeRecord::Submitted));
+        assert_noop!(
+            submit_time(Some(time_hash), StatusOfTimeRecord::Submitted, 1),
+            Error::<Test>::StatusAlreadySubmitted
+        );
+
+        // Accepted
+        assert_ok!(dispatch(
+            PROJECT_OWNER,
+            TimekeepingCall::authorise_time(
+                WORKER,
+                project(),
+                time_hash,
+                StatusOfTimeRecord::Accepted,
+                ReasonCodeStruct::default(),
+            )
+        ));
+        assert_eq!(Timekeeping::time_record(time_hash).map(|r| r.submit_status), Some(StatusOfTimeRecord::Accepted));
+        assert_eq!(Timekeeping::total_blocks_per_project(project()), Some(10));
+
+        // Invoiced
+        assert_noop!(dispatch(WORKER, TimekeepingCall::invoice_time(project(), time_hash)), Error::<Test>::RateNotSet);
+        assert_ok!(dispatch(PROJECT_OWNER, TimekeepingCall::set_project_rate(project(), 5)));
+        assert!(posted().is_empty());
+        assert_ok!(dispatch(WORKER, TimekeepingCall::invoice_time(project(), time_hash)));
+        assert_eq!(Timekeeping::time_record(time_hash).map(|r| r.submit_status), Some(StatusOfTimeRecord::Invoiced));
+        assert_eq!(Timekeeping::time_invoice(time_hash), Some((PROJECT_OWNER, 50)));
+        assert_eq!(recorded_cost(), 50);
+        assert_eq!(
+            posted(),
+            vec![
+                (WORKER, LedgerAccount::ACCOUNTS_RECEIVABLE.number(), 50, true, time_hash, 1, 1),
+                (WORKER, LedgerAccount::SALES.number(), 50, false, time_hash, 1, 1),
+                (WORKER, LedgerAccount::SALES_LEDGER_BY_PAYER.number(), 50, true, time_hash, 1, 1),
+                (WORKER, LedgerAccount::SALES_LEDGER_CONTROL.number(), 50, true, time_hash, 1, 1),
+                (PROJECT_OWNER, LedgerAccount::ACCOUNTS_PAYABLE.number(), 50, false, time_hash, 1, 1),
+                (PROJECT_OWNER, LedgerAccount::LABOUR.number(), 50, true, time_hash, 1, 1),
+                (PROJECT_OWNER, LedgerAccount::PURCHASE_LEDGER_BY_VENDOR.number(), 50, true, time_hash, 1, 1),
+                (PROJECT_OWNER, LedgerAccount::PURCHASE_LEDGER_CONTROL.number(), 50, true, time_hash, 1, 1),
+            ]
+        );
+        assert_noop!(
+            submit_time(Some(time_hash), StatusOfTimeRecord::Draft, 1),
+            Error::<Test>::TimeRecordAlreadyInvoiced
+        );
+
+        // Paid
+        assert_ok!(dispatch(PROJECT_OWNER, TimekeepingCall::pay_time(project(), time_hash)));
+        assert_eq!(Balances::free_balance(PROJECT_OWNER), 950);
+        assert_eq!(Balances::free_balance(WORKER), 1050);
+        assert_eq!(Timekeeping::time_invoice(time_hash), None);
+        assert_eq!(
+            posted(),
+            vec![
+                (PROJECT_OWNER, LedgerAccount::ACCOUNTS_PAYABLE.number(), -50, true, time_hash, 1, 1),
+                (PROJECT_OWNER, LedgerAccount::TRANSFERS_SUSPENSE.number(), -50, false, time_hash, 1, 1),
+                (PROJECT_OWNER, LedgerAccount::PURCHASE_LEDGER_BY_VENDOR.number(), -50, false, time_hash, 1, 1),
+                (PROJECT_OWNER, LedgerAccount::PURCHASE_LEDGER_CONTROL.number(), -50, false, time_hash, 1, 1),
+                (WORKER, LedgerAccount::TRANSFERS_SUSPENSE.number(), 50, true, time_hash, 1, 1),
+                (WORKER, LedgerAccount::ACCOUNTS_RECEIVABLE.number(), -50, false, time_hash, 1, 1),
+                (WORKER, LedgerAccount::SALES_LEDGER_BY_PAYER.number(), -50, false, time_hash, 1, 1),
+                (WORKER, LedgerAccount::SALES_LEDGER_CONTROL.number(), -50, false, time_hash, 1, 1),
+            ]
+        );
+        assert_eq!(Timekeeping::time_record(time_hash).map(|r| r.locked_status), Some(true));
+    });
+}
+
+#[test]
+fn managers_authorise_time_for_the_project_owner() {
+    new_test_ext().execute_with(|| {
+        join_project();
+        assert_ok!(submit_time(None, StatusOfTimeRecord::Submitted, 1));
+        let time_hash = expected_time_hash(1, 0);
+
+        assert_noop!(
+            dispatch(
+                WORKER,
+                TimekeepingCall::authorise_time(
+                    WORKER,
+                    project(),
+                    time_hash,
+                    StatusOfTimeRecord::Accepted,
+                    ReasonCodeStruct::default(),
+                )
+            ),
+            Error::<Test>::NotProjectManager
+        );
+        assert_ok!(dispatch(
+            MANAGER,
+            TimekeepingCall::authorise_time(
+                WORKER,
+                project(),
+                time_hash,
+                StatusOfTimeRecord::Accepted,
+                ReasonCodeStruct::default(),
+            )
+        ));
+        assert_eq!(Timekeeping::time_record(time_hash).map(|r| r.submit_status), Some(StatusOfTimeRecord::Accepted));
+    });
+}
+
+#[test]
+fn accepted_time_is_tracked_against_the_project_budget() {
+    new_test_ext().execute_with(|| {
+        join_project();
+        set_budgeted_blocks(15);
+        assert_ok!(submit_time(None, StatusOfTimeRecord::Submitted, 1));
+        assert_ok!(submit_time(None, StatusOfTimeRecord::Submitted, 5));
+        let first = expected_time_hash(1, 0);
+        let second = expected_time_hash(5, 1);
+        let accept = |time_hash| {
+            dispatch(
+                PROJECT_OWNER,
+                TimekeepingCall::authorise_time(
+                    WORKER,
+                    project(),
+                    time_hash,
+                    StatusOfTimeRecord::Accepted,
+                    ReasonCodeStruct::default(),
+                ),
+            )
+        };
+
+        assert_ok!(accept(first));
+        assert_eq!(accepted_blocks(), 10);
+        assert_noop!(accept(second), "Budget exceeded");
+
+        // Resetting the accepted record for correction releases its time
+        assert_ok!(reset_accepted_time(MANAGER, first));
+        assert_eq!(accepted_blocks(), 0);
+        assert_ok!(accept(second));
+        assert_eq!(accepted_blocks(), 10);
+    });
+}
+
+#[test]
+fn managers_reset_accepted_time_without_joining_the_project() {
+    new_test_ext().execute_with(|| {
+        join_project();
+        assert_ok!(submit_time(None, StatusOfTimeRecord::Submitted, 1));
+        let time_hash = expected_time_hash(1, 0);
+        assert_ok!(dispatch(
+            MANAGER,
+            TimekeepingCall::authorise_time(
+                WORKER,
+                project(),
+                time_hash,
+                StatusOfTimeRecord::Accepted,
+                ReasonCodeStruct::default(),
+            )
+        ));
+
+        assert_noop!(reset_accepted_time(WORKER, time_hash), Error::<Test>::NotProjectManager);
+        assert_ok!(reset_accepted_time(MANAGER, time_hash));
+
+        // Only the status and the reason change
+        let record = Timekeeping::time_record(time_hash).unwrap();
+        assert_eq!(record.submit_status, StatusOfTimeRecord::Draft);
+        assert_eq!(record.reason_code, ReasonCodeStruct(1, 1));
+        assert_eq!(record.worker, WORKER);
+        assert_eq!((record.total_blocks, record.posting_period, record.start_block, record.end_block), (10, 0, 1, 11));
+        assert_eq!(record.nr_of_breaks, 0);
+        assert_eq!(Timekeeping::total_blocks_per_project(project()), Some(0));
+    });
+}
+
+#[test]
+fn worker_rate_overrides_project_rate() {
+    new_test_ext().execute_with(|| {
+        join_project();
+        assert_ok!(dispatch(PROJECT_OWNER, TimekeepingCall::set_project_rate(project(), 5)));
+        assert_ok!(submit_time(None, StatusOfTimeRecord::Submitted, 1));
+        assert_ok!(submit_time(None, StatusOfTimeRecord::Submitted, 5));
+        let first = expected_time_hash(1, 0);
+        let second = expected_time_hash(5, 1);
+        assert_ok!(accept_time(first));
+        assert_ok!(accept_time(second));
+
+        assert_ok!(dispatch(WORKER, TimekeepingCall::invoice_time(project(), first)));
+        assert_eq!(Timekeeping::time_invoice(first), Some((PROJECT_OWNER, 50)));
+
+        assert_noop!(
+            dispatch(WORKER, TimekeepingCall::set_worker_rate(project(), WORKER, 3)),
+            Error::<Test>::InvalidProjectOrOwner
+        );
+        assert_ok!(dispatch(PROJECT_OWNER, TimekeepingCall::set_worker_rate(project(), WORKER, 3)));
+        assert_ok!(dispatch(WORKER, TimekeepingCall::invoice_time(project(), second)));
+        assert_eq!(Timekeeping::time_invoice(second), Some((PROJECT_OWNER, 30)));
+    });
+}
+
+#[test]
+fn accepted_time_is_invoiced_by_posting_period() {
+    new_test_ext().execute_with(|| {
+        join_project();
+        assert_ok!(dispatch(PROJECT_OWNER, TimekeepingCall::set_project_rate(project(), 5)));
+        assert_ok!(submit_time(None, StatusOfTimeRecord::Submitted, 1));
+        assert_ok!(submit_time(None, StatusOfTimeRecord::Submitted, 5));
+        assert_ok!(submit_time_in_period(None, StatusOfTimeRecord::Submitted, 25, 1));
+        assert_ok!(submit_time(None, StatusOfTimeRecord::Submitted, 8));
+        let first = expected_time_hash(1, 0);
+        let second = expected_time_hash(5, 1);
+        let next_period = expected_time_hash(25, 2);
+        let not_accepted = expected_time_hash(8, 3);
+        assert_ok!(accept_time(first));
+        assert_ok!(accept_time(second));
+        assert_ok!(accept_time(next_period));
+
+        assert_ok!(dispatch(WORKER, TimekeepingCall::invoice_project_time(project(), 0)));
+        assert_eq!(Timekeeping::time_invoice(first), Some((PROJECT_OWNER, 50)));
+        assert_eq!(Timekeeping::time_invoice(second), Some((PROJECT_OWNER, 50)));
+        assert_eq!(Timekeeping::time_record(first).map(|r| r.submit_status), Some(StatusOfTimeRecord::Invoiced));
+        assert_eq!(Timekeeping::time_record(second).map(|r| r.submit_status), Some(StatusOfTimeRecord::Invoiced));
+        assert_eq!(Timekeeping::time_invoice(next_period), None);
+        assert_eq!(Timekeeping::time_invoice(not_accepted), None);
+
+        assert_noop!(
+            dispatch(WORKER, TimekeepingCall::invoice_project_time(project(), 0)),
+            Error::<Test>::NothingToInvoice
+        );
+        assert_ok!(dispatch(WORKER, TimekeepingCall::invoice_project_time(project(), 1)));
+        assert_eq!(Timekeeping::time_invoice(next_period), Some((PROJECT_OWNER, 50)));
+    });
+}
+
+#[test]
+fn paid_time_record_cannot_be_unlocked() {
+    new_test_ext().execute_with(|| {
+        join_project();
+        assert_ok!(dispatch(PROJECT_OWNER, TimekeepingCall::set_project_rate(project(), 5)));
+        assert_ok!(submit_time(None, StatusOfTimeRecord::Submitted, 1));
+        let time_hash = expected_time_hash(1, 0);
+        assert_ok!(accept_time(time_hash));
+        assert_ok!(dispatch(WORKER, TimekeepingCall::invoice_time(project(), time_hash)));
+        assert_ok!(dispatch(PROJECT_OWNER, TimekeepingCall::pay_time(project(), time_hash)));
+
+        assert_noop!(
+            dispatch(
+                PROJECT_OWNER,
+                TimekeepingCall::unlock_time_record(project(), time_hash, ReasonCodeStruct::default())
+            ),
+            Error::<Test>::TimeRecordPaid
+        );
+    });
+}
+
+#[test]
+fn banned_worker_cannot_submit_time_and_pending_time_is_blocked() {
+    new_test_ext().execute_with(|| {
+        join_project();
+        assert_ok!(submit_time(None, StatusOfTimeRecord::Submitted, 1));
+        assert_ok!(submit_time(None, StatusOfTimeRecord::Draft, 5));
+        let submitted = expected_time_hash(1, 0);
+        let draft = expected_time_hash(5, 1);
+        let reason = ReasonCodeStruct(3, 1);
+
+        assert_noop!(
+            dispatch(WORKER, TimekeepingCall::ban_worker(project(), WORKER, reason.clone())),
+            Error::<Test>::InvalidProjectOrOwner
+        );
+        assert_noop!(
+            dispatch(PROJECT_OWNER, TimekeepingCall::ban_worker(project(), PROJECT_OWNER, reason.clone())),
+            Error::<Test>::CannotBanOwner
+        );
+        assert_ok!(dispatch(PROJECT_OWNER, TimekeepingCall::ban_worker(project(), WORKER, reason.clone())));
+        assert_eq!(
+            Timekeeping::project_workers_ban_list((project(), WORKER)),
+            Some(BannedStruct(true, reason.clone()))
+        );
+        assert_noop!(
+            dispatch(PROJECT_OWNER, TimekeepingCall::ban_worker(project(), WORKER, reason.clone())),
+            Error::<Test>::WorkerBanned
+        );
+
+        // Time awaiting authorisation is blocked, drafts are left alone
+        let blocked = Timekeeping::time_record(submitted).unwrap();
+        assert_eq!(blocked.submit_status, StatusOfTimeRecord::Blocked);
+        assert_eq!(blocked.reason_code, reason);
+        assert_eq!(Timekeeping::time_record(draft).map(|r| r.submit_status), Some(StatusOfTimeRecord::Draft));
+        assert_noop!(accept_time(submitted), Error::<Test>::ProjectCannotBeChanged);
+
+        assert_noop!(submit_time(None, StatusOfTimeRecord::Submitted, 10), Error::<Test>::WorkerBanned);
+        assert_noop!(submit_time(Some(draft), StatusOfTimeRecord::Submitted, 5), Error::<Test>::WorkerBanned);
+    });
+}
 
 #[test]
-fn basic_integration() {
-    //TODO
+fn unbanned_worker_can_submit_time_again() {
+    new_test_ext().execute_with(|| {
+        join_project();
+        assert_ok!(submit_time(None, StatusOfTimeRecord::Submitted, 1));
+        let blocked = expected_time_hash(1, 0);
+
+        assert_noop!(
+            dispatch(PROJECT_OWNER, TimekeepingCall::unban_worker(project(), WORKER)),
+            Error::<Test>::WorkerNotBanned
+        );
+        assert_ok!(dispatch(PROJECT_OWNER, TimekeepingCall::ban_worker(project(), WORKER, ReasonCodeStruct(3, 1))));
+        assert_noop!(
+            dispatch(WORKER, TimekeepingCall::unban_worker(project(), WORKER)),
+            Error::<Test>::InvalidProjectOrOwner
+        );
+        assert_ok!(dispatch(PROJECT_OWNER, TimekeepingCall::unban_worker(project(), WORKER)));
+        assert_eq!(Timekeeping::project_workers_ban_list((project(), WORKER)), None);
+
+        assert_ok!(submit_time(None, StatusOfTimeRecord::Submitted, 10));
+        // Time blocked by the ban remains blocked
+        assert_eq!(Timekeeping::time_record(blocked).map(|r| r.submit_status), Some(StatusOfTimeRecord::Blocked));
+    });
+}
+
+#[test]
+fn project_owner_locks_and_unlocks_time_records_with_a_reason() {
+    new_test_ext().execute_with(|| {
+        join_project();
+        assert_ok!(submit_time(None, StatusOfTimeRecord::Draft, 1));
+        let time_hash = expected_time_hash(1, 0);
+        let lock = |who, reason| dispatch(who, TimekeepingCall::lock_time_record(project(), time_hash, reason));
+        let unlock = |who, reason| dispatch(who, TimekeepingCall::unlock_time_record(project(), time_hash, reason));
+
+        assert_noop!(unlock(PROJECT_OWNER, ReasonCodeStruct(2, 1)), Error::<Test>::TimeRecordNotLocked);
+        assert_noop!(lock(WORKER, ReasonCodeStruct(2, 1)), Error::<Test>::InvalidProjectOrOwner);
+        assert_ok!(lock(PROJECT_OWNER, ReasonCodeStruct(2, 1)));
+        let record = Timekeeping::time_record(time_hash).unwrap();
+        assert_eq!((record.locked_status, record.locked_reason), (true, ReasonCodeStruct(2, 1)));
+        assert_noop!(lock(PROJECT_OWNER, ReasonCodeStruct(2, 1)), Error::<Test>::TimeRecordLocked);
+
+        // Locked time cannot be changed
+        assert_noop!(submit_time(Some(time_hash), StatusOfTimeRecord::Submitted, 1), Error::<Test>::TimeRecordLocked);
+
+        assert_noop!(unlock(WORKER, ReasonCodeStruct(2, 2)), Error::<Test>::InvalidProjectOrOwner);
+        assert_ok!(unlock(PROJECT_OWNER, ReasonCodeStruct(2, 2)));
+        let record = Timekeeping::time_record(time_hash).unwrap();
+        assert_eq!((record.locked_status, record.locked_reason), (false, ReasonCodeStruct(2, 2)));
+        assert_ok!(submit_time(Some(time_hash), StatusOfTimeRecord::Submitted, 1));
+    });
 }
@@ -0,0 +1,363 @@
+//! Totem chart of accounts.
+//!
+//! A ledger account number is the concatenation of (from left to right):
+//!
+//! * Financial Statement Type Number, 1 digit
+//! * Account Category Number, 1 digit
+//! * Account Category Group Number, 1 digit
+//! * Accounting Group Number, 8 digits
+//! * Accounting Subgroup Number, 4 digits
+//!
+//! For example `250_50012000_0013` is Profit and Loss (2) > Expenses (5) > Operating Expenses (0) >
+//! Services (50012000) > Labour (0013).
+
+use crate::types::Account;
+use codec::{Error, Input};
+use frame_support::pallet_prelude::*;
+use sp_std::convert::TryFrom;
+
+const STATEMENT_TYPE_FACTOR: Account = 100_000_000_000_000;
+const CATEGORY_FACTOR: Account = 10_000_000_000_000;
+const CATEGORY_GROUP_FACTOR: Account = 1_000_000_000_000;
+const ACCOUNTING_GROUP_FACTOR: Account = 10_000;
+
+/// Largest accounting group number (8 digits).
+const MAX_ACCOUNTING_GROUP: u32 = 99_999_999;
+/// Smallest accounting group number (8 digits, the first four incrementing from 1000).
+const MIN_ACCOUNTING_GROUP: u32 = 10_000_000;
+/// Largest accounting subgroup number (4 digits).
+const MAX_SUBGROUP: u16 = 9_999;
+
+/// Financial statement the account is reported in.
+#[repr(u8)]
+#[derive(Decode, Encode, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatementType {
+    BalanceSheet = 1,
+    ProfitAndLoss = 2,
+    Memorandum = 3,
+}
+
+/// Main category of the account within its financial statement.
+#[repr(u8)]
+#[derive(Decode, Encode, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Category {
+    Assets = 1,
+    Liabilities = 2,
+    Equity = 3,
+    Revenue = 4,
+    Expenses = 5,
+    /// Memorandum (non balance sheet) accounts.
+    NonBalanceSheet = 6,
+}
+
+impl Category {
+    /// The financial statement in which this category is reported.
+    pub fn statement_type(&self) -> StatementType {
+        match self {
+            Category::Assets | Category::Liabilities | Category::Equity => StatementType::BalanceSheet,
+            Category::Revenue | Category::Expenses => StatementType::ProfitAndLoss,
+            Category::NonBalanceSheet => StatementType::Memorandum,
+        }
+    }
+}
+
+impl TryFrom<u8> for StatementType {
+    type Error = ();
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(Self::BalanceSheet),
+            2 => Ok(Self::ProfitAndLoss),
+            3 => Ok(Self::Memorandum),
+            _ => Err(()),
+        }
+    }
+}
+
+impl TryFrom<u8> for Category {
+    type Error = ();
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(Self::Assets),
+            2 => Ok(Self::Liabilities),
+            3 => Ok(Self::Equity),
+            4 => Ok(Self::Revenue),
+            5 => Ok(Self::Expenses),
+            6 => Ok(Self::NonBalanceSheet),
+            _ => Err(()),
+        }
+    }
+}
+
+/// A validated general ledger account number.
+/// Decoding validates the number in the same way as `new`.
+#[derive(Encode, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct LedgerAccount(Account);
+
+impl LedgerAccount {
+    // Balance Sheet > Assets > Current Assets
+    /// 110100040000000 XTX Balance
+    pub const XTX_BALANCE: Self = Self(110_10004000_0000);
+    /// 110100040000001 XTX Reserved Balance
+    pub const XTX_RESERVED: Self = Self(110_10004000_0001);
+    /// 110100050000000 Totem Runtime Deposit (Escrow) or Prefunding Account
+    pub const PREFUNDING: Self = Self(110_10005000_0000);
+    /// 110100060000000 Inventory
+    pub const INVENTORY: Self = Self(110_10006000_0000);
+    /// 110100080000000 Accounts receivable (Sales Control Account or Trade Debtor's Account)
+    pub const ACCOUNTS_RECEIVABLE: Self = Self(110_10008000_0000);
+    /// 110100090000000 XTX Transfers Suspense (transfers not yet allocated by the identity)
+    pub const TRANSFERS_SUSPENSE: Self = Self(110_10009000_0000);
+    /// 110100100000000 Sales Tax Receivable (Input Tax)
+    pub const INPUT_TAX_RECEIVABLE: Self = Self(110_10010000_0000);
+
+    // Balance Sheet > Assets > Non-current Assets
+    /// 111110010000000 Property, Plant and Equipment
+    pub const FIXED_ASSETS: Self = Self(111_11001000_0000);
+
+    // Balance Sheet > Liabilities > Current Liabilities
+    /// 120200030000000 Accounts payable
+    pub const ACCOUNTS_PAYABLE: Self = Self(120_20003000_0000);
+    /// 120200040000000 Sales Tax Payable (Output Tax)
+    pub const SALES_TAX_PAYABLE: Self = Self(120_20004000_0000);
+
+    // Balance Sheet > Equity
+    /// 130300010000000 Retained earnings
+    pub const RETAINED_EARNINGS: Self = Self(130_30001000_0000);
+
+    // Profit and Loss > Revenue
+    /// 240400010000000 Product or Service Sales
+    pub const SALES: Self = Self(240_40001000_0000);
+    /// 240400010000001 Sales of Goods
+    pub const GOODS_SALES: Self = Self(240_40001000_0001);
+    /// 240400020000000 Totem Transaction Fee Revenue
+    pub const TRANSACTION_FEE_REVENUE: Self = Self(240_40002000_0000);
+
+    // Profit and Loss > Expenses > Operating Expenses
+    /// 250500010000000 Purchases of Goods
+    pub const PURCHASES: Self = Self(250_50001000_0000);
+    /// 250500120000013 Labour
+    pub const LABOUR: Self = Self(250_50012000_0013);
+    /// 250500290000000 Totem Transaction Fees
+    pub const TRANSACTION_FEES: Self = Self(250_50029000_0000);
+    /// 250500290000001 Totem Transaction Tips
+    pub const TRANSACTION_TIPS: Self = Self(250_50029000_0001);
+    /// 250500300000000 XTX Slashing Losses
+    pub const SLASHING_LOSSES: Self = Self(250_50030000_0000);
+
+    // Memorandum > Non Balance Sheet
+    /// 360600010000000 Sales Ledger by Payer
+    pub const SALES_LEDGER_BY_PAYER: Self = Self(360_60001000_0000);
+    /// 360600020000000 Runtime Ledger by Module
+    pub const RUNTIME_LEDGER_BY_MODULE: Self = Self(360_60002000_0000);
+    /// 360600030000000 Purchase Ledger by Vendor
+    pub const PURCHASE_LEDGER_BY_VENDOR: Self = Self(360_60003000_0000);
+    /// 360600050000000 Sales Ledger Control
+    pub const SALES_LEDGER_CONTROL: Self = Self(360_60005000_0000);
+    /// 360600060000000 Runtime Ledger Control
+    pub const RUNTIME_LEDGER_CONTROL: Self = Self(360_60006000_0000);
+    /// 360600070000000 Purchase Ledger Control
+    pub const PURCHASE_LEDGER_CONTROL: Self = Self(360_60007000_0000);
+
+    /// Builds the account number from its components.
+    /// Returns `None` if a component is out of range or if the category does not belong to the statement type.
+    pub fn new(
+        statement_type: StatementType,
+        category: Category,
+        category_group: u8,
+        accounting_group: u32,
+        subgroup: u16,
+    ) -> Option<Self> {
+        if category.statement_type() != statement_type
+            || category_group > 9
+            || accounting_group < MIN_ACCOUNTING_GROUP
+            || accounting_group > MAX_ACCOUNTING_GROUP
+            || subgroup > MAX_SUBGROUP
+        {
+            return None;
+        }
+
+        Some(Self(
+            statement_type as Account * STATEMENT_TYPE_FACTOR
+                + category as Account * CATEGORY_FACTOR
+                + category_group as Account * CATEGORY_GROUP_FACTOR
+                + accounting_group as Account * ACCOUNTING_GROUP_FACTOR
+                + subgroup as Account,
+        ))
+    }
+
+    /// The raw account number as posted to the ledgers.
+    pub const fn number(&self) -> Account {
+        self.0
+    }
+
+    pub fn statement_type(&self) -> StatementType {
+        // The digit has been validated on construction.
+        StatementType::try_from((self.0 / STATEMENT_TYPE_FACTOR) as u8).unwrap_or(StatementType::Memorandum)
+    }
+
+    pub fn category(&self) -> Category {
+        // The digit has been validated on construction.
+        Category::try_from((self.0 / CATEGORY_FACTOR % 10) as u8).unwrap_or(Category::NonBalanceSheet)
+    }
+
+    pub fn category_group(&self) -> u8 {
+        (self.0 / CATEGORY_GROUP_FACTOR % 10) as u8
+    }
+
+    pub fn accounting_group(&self) -> u32 {
+        (self.0 / ACCOUNTING_GROUP_FACTOR % 100_000_000) as u32
+    }
+
+    pub fn subgroup(&self) -> u16 {
+        (self.0 % ACCOUNTING_GROUP_FACTOR) as u16
+    }
+
+    /// Decodes the account number back into its components.
+    pub fn components(&self) -> (StatementType, Category, u8, u32, u16) {
+        (self.statement_type(), self.category(), self.category_group(), self.accounting_group(), self.subgroup())
+    }
+}
+
+impl TryFrom<Account> for LedgerAccount {
+    type Error = ();
+
+    fn try_from(number: Account) -> Result<Self, Self::Error> {
+        let statement_type = StatementType::try_from((number / STATEMENT_TYPE_FACTOR) as u8)?;
+        let category = Category::try_from((number / CATEGORY_FACTOR % 10) as u8)?;
+
+        Self::new(
+            statement_type,
+            category,
+            (number / CATEGORY_GROUP_FACTOR % 10) as u8,
+            (number / ACCOUNTING_GROUP_FACTOR % 100_000_000) as u32,
+            (number % ACCOUNTING_GROUP_FACTOR) as u16,
+        )
+        .filter(|account| account.0 == number)
+        .ok_or(())
+    }
+}
+
+impl Decode for LedgerAccount {
+    fn decode<I: Input>(input: &mut I) -> Result<Self, Error> {
+        Self::try_from(Account::decode(input)?).map_err(|_| "Invalid ledger account number".into())
+    }
+}
+
+impl From<LedgerAccount> for Account {
+    fn from(account: LedgerAccount) -> Self {
+        account.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NAMED_ACCOUNTS: [LedgerAccount; 25] = [
+        LedgerAccount::XTX_BALANCE,
+        LedgerAccount::XTX_RESERVED,
+        LedgerAccount::PREFUNDING,
+        LedgerAccount::INVENTORY,
+        LedgerAccount::ACCOUNTS_RECEIVABLE,
+        LedgerAccount::TRANSFERS_SUSPENSE,
+        LedgerAccount::INPUT_TAX_RECEIVABLE,
+        LedgerAccount::FIXED_ASSETS,
+        LedgerAccount::ACCOUNTS_PAYABLE,
+        LedgerAccount::SALES_TAX_PAYABLE,
+        LedgerAccount::RETAINED_EARNINGS,
+        LedgerAccount::SALES,
+        LedgerAccount::GOODS_SALES,
+        LedgerAccount::TRANSACTION_FEE_REVENUE,
+        LedgerAccount::PURCHASES,
+        LedgerAccount::LABOUR,
+        LedgerAccount::TRANSACTION_FEES,
+        LedgerAccount::TRANSACTION_TIPS,
+        LedgerAccount::SLASHING_LOSSES,
+        LedgerAccount::SALES_LEDGER_BY_PAYER,
+        LedgerAccount::RUNTIME_LEDGER_BY_MODULE,
+        LedgerAccount::PURCHASE_LEDGER_BY_VENDOR,
+        LedgerAccount::SALES_LEDGER_CONTROL,
+        LedgerAccount::RUNTIME_LEDGER_CONTROL,
+        LedgerAccount::PURCHASE_LEDGER_CONTROL,
+    ];
+
+    fn decode(number: Account) -> Option<LedgerAccount> {
+        LedgerAccount::decode(&mut &number.encode()[..]).ok()
+    }
+
+    #[test]
+    fn new_concatenates_the_components() {
+        assert_eq!(
+            LedgerAccount::new(StatementType::ProfitAndLoss, Category::Expenses, 0, 50012000, 13),
+            Some(LedgerAccount::LABOUR),
+        );
+        assert_eq!(
+            LedgerAccount::new(StatementType::BalanceSheet, Category::Assets, 9, MAX_ACCOUNTING_GROUP, MAX_SUBGROUP)
+                .map(|account| account.number()),
+            Some(119_99999999_9999),
+        );
+    }
+
+    #[test]
+    fn new_rejects_out_of_range_components() {
+        let new = |statement_type, category, category_group, accounting_group, subgroup| {
+            LedgerAccount::new(statement_type, category, category_group, accounting_group, subgroup)
+        };
+
+        // The category must be reported in the statement type.
+        assert_eq!(new(StatementType::BalanceSheet, Category::Revenue, 0, 40001000, 0), None);
+        assert_eq!(new(StatementType::ProfitAndLoss, Category::Assets, 0, 10004000, 0), None);
+        assert_eq!(new(StatementType::Memorandum, Category::Equity, 0, 30001000, 0), None);
+        // Every component must fit its digits.
+        assert_eq!(new(StatementType::BalanceSheet, Category::Assets, 10, 10004000, 0), None);
+        assert_eq!(new(StatementType::BalanceSheet, Category::Assets, 0, MIN_ACCOUNTING_GROUP - 1, 0), None);
+        assert_eq!(new(StatementType::BalanceSheet, Category::Assets, 0, MAX_ACCOUNTING_GROUP + 1, 0), None);
+        assert_eq!(new(StatementType::BalanceSheet, Category::Assets, 0, 10004000, MAX_SUBGROUP + 1), None);
+    }
+
+    #[test]
+    fn try_from_rejects_invalid_numbers() {
+        assert_eq!(LedgerAccount::try_from(110_10004000_0000), Ok(LedgerAccount::XTX_BALANCE));
+
+        // Unknown statement type or category.
+        assert_eq!(LedgerAccount::try_from(10_10004000_0000), Err(()));
+        assert_eq!(LedgerAccount::try_from(410_10004000_0000), Err(()));
+        assert_eq!(LedgerAccount::try_from(100_10004000_0000), Err(()));
+        assert_eq!(LedgerAccount::try_from(170_10004000_0000), Err(()));
+        // Category of another statement type.
+        assert_eq!(LedgerAccount::try_from(140_40001000_0000), Err(()));
+        // Accounting group below the first one.
+        assert_eq!(LedgerAccount::try_from(110_09999999_0000), Err(()));
+        // More digits than an account number has.
+        assert_eq!(LedgerAccount::try_from(1110_10004000_0000), Err(()));
+        assert_eq!(LedgerAccount::try_from(Account::MAX), Err(()));
+    }
+
+    #[test]
+    fn decode_rejects_invalid_numbers() {
+        assert_eq!(decode(110_10004000_0000), Some(LedgerAccount::XTX_BALANCE));
+
+        assert_eq!(decode(0), None);
+        assert_eq!(decode(410_10004000_0000), None);
+        assert_eq!(decode(140_40001000_0000), None);
+        assert_eq!(decode(110_09999999_0000), None);
+        assert_eq!(decode(1110_10004000_0000), None);
+    }
+
+    #[test]
+    fn named_accounts_round_trip() {
+        for account in NAMED_ACCOUNTS.iter() {
+            let (statement_type, category, category_group, accounting_group, subgroup) = account.components();
+
+            assert_eq!(category.statement_type(), statement_type);
+            assert_eq!(
+                LedgerAccount::new(statement_type, category, category_group, accounting_group, subgroup),
+                Some(*account),
+            );
+            assert_eq!(LedgerAccount::try_from(account.number()), Ok(*account));
+            assert_eq!(decode(account.number()), Some(*account));
+        }
+    }
+}
@@ -1,7 +1,12 @@
 #![cfg(any(test, feature = "mock"))]
 
+use super::ok;
 use super::traits::accounting::Posting;
+use super::traits::orders::Arbitrating;
+use super::traits::teams::{Budgeting, Validating};
+use super::types::TeamRole;
 use frame_support::dispatch::DispatchResultWithPostInfo;
+use frame_support::traits::BalanceStatus;
 use sp_std::vec::Vec;
 
 impl<AccountId, Hash, BlockNumber, CoinAmount> Posting<AccountId, Hash, BlockNumber, CoinAmount> for () {
@@ -10,15 +15,52 @@ impl<AccountId, Hash, BlockNumber, CoinAmount> Posting<AccountId, Hash, BlockNum
     type LedgerBalance = i128;
 
     fn handle_multiposting_amounts(
-        fwd: Vec<(AccountId, Self::Account, Self::LedgerBalance, bool, Hash, BlockNumber, BlockNumber)>,
-        rev: Vec<(AccountId, Self::Account, Self::LedgerBalance, bool, Hash, BlockNumber, BlockNumber)>,
-        trk: Vec<(AccountId, Self::Account, Self::LedgerBalance, bool, Hash, BlockNumber, BlockNumber)>,
+        keys: Vec<(AccountId, Self::Account, Self::LedgerBalance, bool, Hash, BlockNumber, BlockNumber)>,
     ) -> DispatchResultWithPostInfo {
         unimplemented!("Used as a mock, shouldn't be called")
     }
 
-    fn account_for_fees(f: CoinAmount, p: AccountId) -> DispatchResultWithPostInfo {
-        unimplemented!("Used as a mock, shouldn't be called")
+    // Fees and balance movements happen in most pallets, mocks without accounting simply do not record them.
+    fn account_for_fees(f: CoinAmount, t: CoinAmount, r: CoinAmount, p: AccountId) -> DispatchResultWithPostInfo {
+        ok()
+    }
+
+    fn account_for_fee_revenue(f: CoinAmount, r: AccountId) -> DispatchResultWithPostInfo {
+        ok()
+    }
+
+    fn account_for_transfer(
+        f: AccountId,
+        fs: BalanceStatus,
+        t: AccountId,
+        ts: BalanceStatus,
+        a: CoinAmount,
+    ) -> DispatchResultWithPostInfo {
+        ok()
+    }
+
+    fn account_for_reserve(w: AccountId, a: CoinAmount, s: BalanceStatus) -> DispatchResultWithPostInfo {
+        ok()
+    }
+
+    fn account_for_slash(w: AccountId, a: CoinAmount, s: BalanceStatus) -> DispatchResultWithPostInfo {
+        ok()
+    }
+
+    fn account_for_deposit(w: AccountId, a: CoinAmount, s: BalanceStatus) -> DispatchResultWithPostInfo {
+        ok()
+    }
+
+    fn account_for_withdrawal(w: AccountId, a: CoinAmount, s: BalanceStatus) -> DispatchResultWithPostInfo {
+        ok()
+    }
+
+    fn account_for_opening_balance(w: AccountId) -> DispatchResultWithPostInfo {
+        ok()
+    }
+
+    fn record_sales_tax(v: AccountId, j: AccountId, a: Self::LedgerBalance) -> DispatchResultWithPostInfo {
+        ok()
     }
 
     fn get_escrow_account() -> AccountId {
@@ -29,3 +71,51 @@ impl<AccountId, Hash, BlockNumber, CoinAmount> Posting<AccountId, Hash, BlockNum
         unimplemented!("Used as a mock, shouldn't be called")
     }
 }
+
+// Mocks without orders have no orders to update.
+impl<Hash> Arbitrating<Hash> for () {
+    fn dispute_resolved(h: Hash, released: bool, uid: Hash) -> DispatchResultWithPostInfo {
+        ok()
+    }
+}
+
+// Mocks without teams have no projects.
+impl<AccountId, Hash> Validating<AccountId, Hash> for () {
+    fn is_project_owner(o: AccountId, h: Hash) -> bool {
+        false
+    }
+
+    fn is_owner_and_project_valid(o: AccountId, h: Hash) -> bool {
+        false
+    }
+
+    fn is_project_valid(h: Hash) -> bool {
+        false
+    }
+
+    fn get_project_owner(h: Hash) -> Option<AccountId> {
+        None
+    }
+
+    fn get_team_role(o: AccountId, h: Hash) -> Option<TeamRole> {
+        None
+    }
+
+    fn has_team_role(o: AccountId, h: Hash, r: TeamRole) -> bool {
+        false
+    }
+}
+
+impl<Hash> Budgeting<Hash> for () {
+    fn record_time(h: Hash, blocks: u64) -> DispatchResultWithPostInfo {
+        ok()
+    }
+
+    fn release_time(h: Hash, blocks: u64) -> DispatchResultWithPostInfo {
+        ok()
+    }
+
+    fn record_cost(h: Hash, amount: u128) -> DispatchResultWithPostInfo {
+        ok()
+    }
+}
@@ -39,6 +39,8 @@ use sp_std::prelude::*;
 
 pub mod accounting {
     use super::*;
+    use crate::types::FiscalPeriod;
+    use frame_support::traits::BalanceStatus;
 
     /// Main Totem accounting trait.
     pub trait Posting<AccountId, Hash, BlockNumber, CoinAmount> {
@@ -46,18 +48,55 @@ pub mod accounting {
         type PostingIndex: Member + Copy + Into<u128> + Encode + Decode + Eq;
         type LedgerBalance: Member + Copy + Into<i128> + Encode + Decode + Eq;
 
+        /// Posts all the keys, or none of them if any posting fails.
         fn handle_multiposting_amounts(
-            fwd: Vec<(AccountId, Self::Account, Self::LedgerBalance, bool, Hash, BlockNumber, BlockNumber)>,
-            rev: Vec<(AccountId, Self::Account, Self::LedgerBalance, bool, Hash, BlockNumber, BlockNumber)>,
-            trk: Vec<(AccountId, Self::Account, Self::LedgerBalance, bool, Hash, BlockNumber, BlockNumber)>,
+            keys: Vec<(AccountId, Self::Account, Self::LedgerBalance, bool, Hash, BlockNumber, BlockNumber)>,
         ) -> DispatchResultWithPostInfo;
 
-        fn account_for_fees(f: CoinAmount, p: AccountId) -> DispatchResultWithPostInfo;
+        /// Posts the transaction fee `f` paid by `p`, of which the tip `t`, less the refund `r`.
+        fn account_for_fees(f: CoinAmount, t: CoinAmount, r: CoinAmount, p: AccountId) -> DispatchResultWithPostInfo;
+
+        /// Posts the share `f` of the transaction fees received by `r` as revenue.
+        fn account_for_fee_revenue(f: CoinAmount, r: AccountId) -> DispatchResultWithPostInfo;
+
+        /// Posts the move of `a` from the `fs` balance of `f` to the `ts` balance of `t`.
+        fn account_for_transfer(
+            f: AccountId,
+            fs: BalanceStatus,
+            t: AccountId,
+            ts: BalanceStatus,
+            a: CoinAmount,
+        ) -> DispatchResultWithPostInfo;
+
+        /// Posts the move of `a` between the free and the reserved balance of `w`, `s` being the balance it moves to.
+        fn account_for_reserve(w: AccountId, a: CoinAmount, s: BalanceStatus) -> DispatchResultWithPostInfo;
+
+        /// Posts the loss of `a` slashed from the `s` balance of `w`.
+        fn account_for_slash(w: AccountId, a: CoinAmount, s: BalanceStatus) -> DispatchResultWithPostInfo;
+
+        /// Posts `a` deposited into the `s` balance of `w` from outside of the ledgers, such as newly issued XTX.
+        fn account_for_deposit(w: AccountId, a: CoinAmount, s: BalanceStatus) -> DispatchResultWithPostInfo;
+
+        /// Posts `a` withdrawn from the `s` balance of `w` to outside of the ledgers, such as burnt XTX.
+        fn account_for_withdrawal(w: AccountId, a: CoinAmount, s: BalanceStatus) -> DispatchResultWithPostInfo;
+
+        /// Posts the balances `w` holds without them having been posted, such as a genesis endowment, against equity.
+        fn account_for_opening_balance(w: AccountId) -> DispatchResultWithPostInfo;
+
+        /// Adds the sales tax `a` on an invoice of the vendor `v` to the liability owed to the jurisdiction `j`.
+        /// Reversed invoices record a negative amount.
+        fn record_sales_tax(v: AccountId, j: AccountId, a: Self::LedgerBalance) -> DispatchResultWithPostInfo;
 
         fn get_escrow_account() -> AccountId;
 
         fn get_pseudo_random_hash(s: AccountId, r: AccountId) -> Hash;
     }
+
+    /// Fiscal calendar of each identity, its periods being ranges of blocks.
+    pub trait FiscalCalendar<AccountId, BlockNumber> {
+        /// The fiscal period of `o` that the block `b` falls in.
+        fn fiscal_period(o: AccountId, b: BlockNumber) -> Option<FiscalPeriod<BlockNumber>>;
+    }
 }
 
 pub mod bonsai {
@@ -72,6 +111,7 @@ pub mod bonsai {
 
 pub mod prefunding {
     use super::*;
+    use crate::types::{InvoiceLine, TaxLine};
 
     pub trait Encumbrance<AccountId, Hash, BlockNumber> {
         type LockStatus: Member + Copy;
@@ -85,7 +125,14 @@ pub mod prefunding {
             uid: Hash,
         ) -> DispatchResultWithPostInfo;
 
-        fn send_simple_invoice(o: AccountId, p: AccountId, n: i128, h: Hash, uid: Hash) -> DispatchResultWithPostInfo;
+        fn send_simple_invoice(
+            o: AccountId,
+            p: AccountId,
+            lines: Vec<InvoiceLine>,
+            h: Hash,
+            uid: Hash,
+            taxes: Vec<TaxLine<AccountId>>,
+        ) -> DispatchResultWithPostInfo;
 
         fn settle_prefunded_invoice(o: AccountId, h: Hash, uid: Hash) -> DispatchResultWithPostInfo;
 
@@ -96,22 +143,108 @@ pub mod prefunding {
         fn check_ref_owner(o: AccountId, h: Hash) -> bool;
 
         fn check_ref_beneficiary(o: AccountId, h: Hash) -> bool;
+
+        fn raise_dispute(o: AccountId, h: Hash, uid: Hash) -> DispatchResultWithPostInfo;
+
+        fn send_milestone_invoice(
+            o: AccountId,
+            lines: Vec<InvoiceLine>,
+            h: Hash,
+            uid: Hash,
+            taxes: Vec<TaxLine<AccountId>>,
+        ) -> DispatchResultWithPostInfo;
+
+        fn settle_milestone(o: AccountId, h: Hash, m: u16, uid: Hash) -> DispatchResultWithPostInfo;
+
+        fn send_unfunded_invoice(
+            o: AccountId,
+            p: AccountId,
+            lines: Vec<InvoiceLine>,
+            d: BlockNumber,
+            h: Hash,
+            uid: Hash,
+            taxes: Vec<TaxLine<AccountId>>,
+        ) -> DispatchResultWithPostInfo;
+
+        fn settle_unfunded_invoice(o: AccountId, h: Hash, uid: Hash) -> DispatchResultWithPostInfo;
+    }
+}
+
+pub mod escrow {
+    use super::*;
+    use crate::types::EscrowState;
+
+    /// Locks funds of an owner for a beneficiary, with the accounting for both parties.
+    pub trait Escrow<AccountId, Hash, BlockNumber> {
+        /// Locks `amount` of the owner `o` for the beneficiary `b` under the reference `h`. The owner can recover the funds
+        /// if the beneficiary does not accept by the deadline `d`. The arbiter `a`, if any, rules on disputes.
+        fn create_escrow(
+            o: AccountId,
+            b: AccountId,
+            amount: u128,
+            d: BlockNumber,
+            a: Option<AccountId>,
+            h: Hash,
+            uid: Hash,
+        ) -> DispatchResultWithPostInfo;
+
+        /// Releases `amount` of the escrow to the beneficiary. Can only be used by the owner `o`.
+        /// The remainder stays locked.
+        fn release_escrow(o: AccountId, h: Hash, amount: u128, uid: Hash) -> DispatchResultWithPostInfo;
+
+        /// Returns the escrow to the owner. The beneficiary can refund at any time, the owner only if the beneficiary
+        /// has not accepted by the deadline, or has agreed to the refund.
+        fn refund_escrow(who: AccountId, h: Hash, uid: Hash) -> DispatchResultWithPostInfo;
+
+        /// The state of the escrow and the amount still locked.
+        fn escrow_state(h: Hash) -> Option<(EscrowState, u128)>;
     }
 }
 
 pub mod orders {
+    use super::*;
+
     pub trait Validating<AccountId, Hash> {
         fn is_order_party(o: AccountId, r: Hash) -> bool;
     }
+
+    pub trait Arbitrating<Hash> {
+        /// The arbiter has ruled on the disputed reference, and released funds to the beneficiary or not.
+        fn dispute_resolved(h: Hash, released: bool, uid: Hash) -> DispatchResultWithPostInfo;
+    }
 }
 
 pub mod teams {
+    use super::*;
+    use crate::types::TeamRole;
+
     pub trait Validating<AccountId, Hash> {
         fn is_project_owner(o: AccountId, h: Hash) -> bool;
 
         fn is_owner_and_project_valid(o: AccountId, h: Hash) -> bool;
 
         fn is_project_valid(h: Hash) -> bool;
+
+        fn get_project_owner(h: Hash) -> Option<AccountId>;
+
+        /// The role of `o` in the team of the project `h`, the owner having the `Owner` role.
+        fn get_team_role(o: AccountId, h: Hash) -> Option<TeamRole>;
+
+        /// Whether the project `h` is valid and `o` has at least the role `r` in its team.
+        fn has_team_role(o: AccountId, h: Hash, r: TeamRole) -> bool;
+    }
+
+    /// Tracks the actual time and costs of projects against their budgets. The actuals of a project include those of
+    /// its sub-projects.
+    pub trait Budgeting<Hash> {
+        /// Adds `blocks` of accepted time to the project `h`. Fails if it exceeds an enforced budget.
+        fn record_time(h: Hash, blocks: u64) -> DispatchResultWithPostInfo;
+
+        /// Removes `blocks` of time that is no longer accepted from the project `h`.
+        fn release_time(h: Hash, blocks: u64) -> DispatchResultWithPostInfo;
+
+        /// Adds the invoiced `amount` to the costs of the project `h`. Fails if it exceeds an enforced budget.
+        fn record_cost(h: Hash, amount: u128) -> DispatchResultWithPostInfo;
     }
 }
 
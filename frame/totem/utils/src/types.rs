@@ -1,3 +1,12 @@
+use crate::chart_of_accounts::LedgerAccount;
+use frame_support::pallet_prelude::*;
+use sp_runtime::{
+    traits::{AtLeast32BitUnsigned, One, Zero},
+    PerThing, Permill,
+};
+use sp_std::convert::TryFrom;
+use sp_std::prelude::*;
+
 /// Balance on an account can be negative
 pub type LedgerBalance = i128;
 
@@ -9,3 +18,152 @@ pub type PostingIndex = u128;
 
 /// Used for comparisons
 pub type ComparisonAmounts = u128;
+
+/// A sales tax applied to an invoice, at `rate`, owed to the tax `jurisdiction`.
+#[derive(Decode, Encode, Debug, Clone, PartialEq, Eq)]
+pub struct TaxLine<AccountId> {
+    pub rate: Permill,
+    pub jurisdiction: AccountId,
+}
+
+/// Why the sales taxes of an invoice are rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaxLineError {
+    /// There are more tax lines than allowed.
+    TooMany,
+    /// A tax rate is zero.
+    ZeroRate,
+    /// A jurisdiction is taxed more than once.
+    DuplicateJurisdiction,
+}
+
+/// A line of an invoice. The amount, including sales taxes, is posted to the revenue account of the seller and to
+/// the expense or asset account of the buyer.
+#[derive(Decode, Encode, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvoiceLine {
+    pub amount: LedgerBalance,
+    pub seller_account: LedgerAccount,
+    pub buyer_account: LedgerAccount,
+}
+
+impl InvoiceLine {
+    /// A line for services, posted to sales and labour.
+    pub fn services(amount: LedgerBalance) -> Self {
+        InvoiceLine {
+            amount,
+            seller_account: LedgerAccount::SALES,
+            buyer_account: LedgerAccount::LABOUR,
+        }
+    }
+
+    /// The total of the lines. Returns `None` on overflow.
+    pub fn total(lines: &[Self]) -> Option<LedgerBalance> {
+        lines.iter().try_fold(0 as LedgerBalance, |total, line| total.checked_add(line.amount))
+    }
+}
+
+/// The state of funds held in escrow, as seen by the pallets using the escrow.
+#[derive(Decode, Encode, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EscrowState {
+    /// Locked by the owner, not yet accepted by the beneficiary.
+    Pending,
+    /// Locked by both the owner and the beneficiary.
+    Locked,
+    /// Invoiced by the beneficiary, awaiting settlement.
+    Invoiced,
+    /// Disputed, awaiting the ruling of the arbiter.
+    Disputed,
+    /// Released to the beneficiary.
+    Released,
+    /// Returned to the owner.
+    Refunded,
+}
+
+/// The role of a member in the team of a project. Each role has the permissions of the roles below it.
+#[repr(u8)]
+#[derive(Decode, Encode, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TeamRole {
+    /// Can see the project.
+    Viewer = 0,
+    /// Works on the project.
+    Member = 1,
+    /// Approves orders for the project.
+    Approver = 2,
+    /// Authorises time and manages the team, below the owner.
+    Manager = 3,
+    /// Owns the project. There is only one owner, changed by reassigning the project.
+    Owner = 4,
+}
+
+impl<AccountId: Clone + PartialEq> TaxLine<AccountId> {
+    /// Checks that there are no more than `max` lines, that no rate is zero and that each jurisdiction is taxed
+    /// only once.
+    pub fn validate(lines: &[Self], max: u32) -> Result<(), TaxLineError> {
+        if lines.len() > max as usize {
+            return Err(TaxLineError::TooMany);
+        }
+        for (i, line) in lines.iter().enumerate() {
+            if line.rate.deconstruct() == 0 {
+                return Err(TaxLineError::ZeroRate);
+            }
+            if lines[..i].iter().any(|other| other.jurisdiction == line.jurisdiction) {
+                return Err(TaxLineError::DuplicateJurisdiction);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Splits an invoiced amount, which includes the taxes, into the net amount and the tax for each line.
+    /// Taxes are rounded down, so that the net amount and the taxes always add up to the invoiced amount.
+    /// Returns `None` on overflow.
+    pub fn split(gross: LedgerBalance, lines: &[Self]) -> Option<(LedgerBalance, Vec<(AccountId, LedgerBalance)>)> {
+        let accuracy = Permill::ACCURACY as LedgerBalance;
+        let rates = lines.iter().map(|line| line.rate.deconstruct() as LedgerBalance).sum::<LedgerBalance>();
+        let net = gross.checked_mul(accuracy)? / (accuracy + rates);
+        let taxes = lines
+            .iter()
+            .map(|line| {
+                Some((line.jurisdiction.clone(), net.checked_mul(line.rate.deconstruct() as LedgerBalance)? / accuracy))
+            })
+            .collect::<Option<Vec<_>>>()?;
+        let total_tax = taxes.iter().map(|(_, tax)| tax).sum::<LedgerBalance>();
+
+        Some((gross - total_tax, taxes))
+    }
+}
+
+/// A fiscal period of an identity, as a range of blocks. Periods are numbered from 0, the first one starting at genesis.
+#[derive(Decode, Encode, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FiscalPeriod<BlockNumber> {
+    pub index: u16,
+    pub start: BlockNumber,
+    /// The last block of the period, `None` for the open-ended period after the last defined period.
+    pub end: Option<BlockNumber>,
+    /// No more postings can be made to a closed period.
+    pub closed: bool,
+}
+
+impl<BlockNumber: AtLeast32BitUnsigned + Copy> FiscalPeriod<BlockNumber> {
+    /// The period the block `b` falls in, given the last block of each period in ascending order, and the last block
+    /// of the latest closed period. Returns `None` if the period number overflows.
+    pub fn of(ends: &[BlockNumber], closed_until: Option<BlockNumber>, b: BlockNumber) -> Option<Self> {
+        let index = ends.iter().position(|end| b <= *end).unwrap_or(ends.len());
+        let start = match index.checked_sub(1) {
+            Some(previous) => ends[previous].saturating_add(One::one()),
+            None => Zero::zero(),
+        };
+        let end = ends.get(index).copied();
+        let closed = match (end, closed_until) {
+            (Some(end), Some(closed_until)) => end <= closed_until,
+            _ => false,
+        };
+
+        Some(FiscalPeriod {
+            index: u16::try_from(index).ok()?,
+            start,
+            end,
+            closed,
+        })
+    }
+}
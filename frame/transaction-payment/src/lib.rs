@@ -50,13 +50,13 @@
 use sp_std::prelude::*;
 use codec::{Encode, Decode};
 use frame_support::{
-	decl_storage, decl_module,
+	decl_storage, decl_module, decl_event,
 	traits::Get,
 	weights::{
 		Weight, DispatchInfo, PostDispatchInfo, GetDispatchInfo, Pays, WeightToFeePolynomial,
 		WeightToFeeCoefficient, DispatchClass,
 	},
-	dispatch::DispatchResult,
+	dispatch::{DispatchResult, DispatchResultWithPostInfo},
 };
 use sp_runtime::{
 	FixedU128, FixedPointNumber, FixedPointOperand, Perquintill, RuntimeDebug,
@@ -69,6 +69,8 @@ use sp_runtime::{
 	},
 };
 
+use totem_utils::traits::accounting::Posting;
+
 mod payment;
 mod types;
 
@@ -230,6 +232,9 @@ impl Default for Releases {
 }
 
 pub trait Config: frame_system::Config {
+	/// The overarching event type.
+	type Event: From<Event<Self>> + Into<<Self as frame_system::Config>::Event>;
+
 	/// Handler for withdrawing, refunding and depositing the transaction fee.
 	/// Transaction fees are withdrawn before the transaction is executed.
 	/// After the transaction was executed the transaction weight can be
@@ -246,6 +251,9 @@ pub trait Config: frame_system::Config {
 
 	/// Update the multiplier of the next block, based on the previous block's weight.
 	type FeeMultiplierUpdate: MultiplierUpdate;
+
+	/// Totem Accounting type, records the fees in the ledgers of the payer.
+	type Accounting: Posting<Self::AccountId, Self::Hash, Self::BlockNumber, BalanceOf<Self>>;
 }
 
 decl_storage! {
@@ -256,6 +264,13 @@ decl_storage! {
 	}
 }
 
+decl_event!(
+	pub enum Event<T> where AccountId = <T as frame_system::Config>::AccountId {
+		/// A transaction fee of an account could not be posted to the Totem ledgers. \[who\]
+		LedgerPostingFailed(AccountId),
+	}
+);
+
 decl_module! {
 	pub struct Module<T: Config> for enum Call where origin: T::Origin {
 		/// The fee to be paid for making a transaction; the per-byte portion.
@@ -265,6 +280,8 @@ decl_module! {
 		const WeightToFee: Vec<WeightToFeeCoefficient<BalanceOf<T>>> =
 			T::WeightToFee::polynomial().to_vec();
 
+		fn deposit_event() = default;
+
 		fn on_finalize() {
 			NextFeeMultiplier::mutate(|fm| {
 				*fm = T::FeeMultiplierUpdate::convert(*fm);
@@ -457,6 +474,23 @@ impl<T: Config> Module<T> where
 	}
 }
 
+impl<T: Config> Module<T> {
+	/// Reports a failed posting of a transaction fee of `who`.
+	/// The fee is settled whatever the ledgers say, so the failure is logged and signalled with an event rather
+	/// than invalidating the transaction that paid it.
+	pub fn post_to_ledgers(who: &T::AccountId, posting: DispatchResultWithPostInfo) {
+		if let Err(e) = posting {
+			log::error!(
+				target: "runtime::transaction-payment",
+				"Failed to post a transaction fee of {:?} to the ledgers: {:?}",
+				who,
+				e.error,
+			);
+			Self::deposit_event(RawEvent::LedgerPostingFailed(who.clone()));
+		}
+	}
+}
+
 impl<T> Convert<Weight, BalanceOf<T>> for Module<T> where
 	T: Config,
 	BalanceOf<T>: FixedPointOperand,
@@ -609,17 +643,18 @@ mod tests {
 			DispatchClass, DispatchInfo, PostDispatchInfo, GetDispatchInfo, Weight,
 			WeightToFeePolynomial, WeightToFeeCoefficients, WeightToFeeCoefficient,
 		},
-		traits::Currency,
+		traits::{BalanceStatus as Status, Currency},
 	};
 	use pallet_balances::Call as BalancesCall;
 	use sp_core::H256;
 	use sp_runtime::{
 		testing::{Header, TestXt},
 		traits::{BlakeTwo256, IdentityLookup},
-		Perbill,
+		DispatchError, Perbill,
 	};
 	use std::cell::RefCell;
 	use smallvec::smallvec;
+	use totem_utils::ok;
 
 	type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Runtime>;
 	type Block = frame_system::mocking::MockBlock<Runtime>;
@@ -632,7 +667,7 @@ mod tests {
 		{
 			System: system::{Module, Call, Config, Storage, Event<T>},
 			Balances: pallet_balances::{Module, Call, Storage, Config<T>, Event<T>},
-			TransactionPayment: pallet_transaction_payment::{Module, Storage},
+			TransactionPayment: pallet_transaction_payment::{Module, Storage, Event<T>},
 		}
 	);
 
@@ -641,6 +676,76 @@ mod tests {
 
 	thread_local! {
 		static EXTRINSIC_BASE_WEIGHT: RefCell<u64> = RefCell::new(0);
+		static FEES_POSTED: RefCell<Vec<(u64, u64, u64, u64)>> = RefCell::new(Vec::new());
+		static FAIL_POSTING: RefCell<bool> = RefCell::new(false);
+	}
+
+	/// Takes the `(fee, tip, refund, payer)` of the fees posted since the last call.
+	fn fees_posted() -> Vec<(u64, u64, u64, u64)> {
+		FEES_POSTED.with(|f| f.borrow_mut().drain(..).collect())
+	}
+
+	/// Records the posted fees instead of posting them, or fails when `FAIL_POSTING` is set.
+	pub struct RecordingAccounting;
+
+	impl Posting<u64, H256, u64, u64> for RecordingAccounting {
+		type Account = u64;
+		type PostingIndex = u128;
+		type LedgerBalance = i128;
+
+		fn handle_multiposting_amounts(
+			_keys: Vec<(u64, Self::Account, Self::LedgerBalance, bool, H256, u64, u64)>,
+		) -> DispatchResultWithPostInfo {
+			unimplemented!("Transaction payment only posts fees")
+		}
+
+		fn account_for_fees(f: u64, t: u64, r: u64, p: u64) -> DispatchResultWithPostInfo {
+			if FAIL_POSTING.with(|v| *v.borrow()) {
+				return Err(DispatchError::Other("posting failed").into());
+			}
+			FEES_POSTED.with(|v| v.borrow_mut().push((f, t, r, p)));
+			ok()
+		}
+
+		fn account_for_fee_revenue(_f: u64, _r: u64) -> DispatchResultWithPostInfo {
+			ok()
+		}
+
+		fn account_for_transfer(_f: u64, _fs: Status, _t: u64, _ts: Status, _a: u64) -> DispatchResultWithPostInfo {
+			ok()
+		}
+
+		fn account_for_reserve(_w: u64, _a: u64, _s: Status) -> DispatchResultWithPostInfo {
+			ok()
+		}
+
+		fn account_for_slash(_w: u64, _a: u64, _s: Status) -> DispatchResultWithPostInfo {
+			ok()
+		}
+
+		fn account_for_deposit(_w: u64, _a: u64, _s: Status) -> DispatchResultWithPostInfo {
+			ok()
+		}
+
+		fn account_for_withdrawal(_w: u64, _a: u64, _s: Status) -> DispatchResultWithPostInfo {
+			ok()
+		}
+
+		fn account_for_opening_balance(_w: u64) -> DispatchResultWithPostInfo {
+			ok()
+		}
+
+		fn record_sales_tax(_v: u64, _j: u64, _a: Self::LedgerBalance) -> DispatchResultWithPostInfo {
+			ok()
+		}
+
+		fn get_escrow_account() -> u64 {
+			unimplemented!("Used as a mock, shouldn't be called")
+		}
+
+		fn get_pseudo_random_hash(_s: u64, _r: u64) -> H256 {
+			unimplemented!("Used as a mock, shouldn't be called")
+		}
 	}
 
 	pub struct BlockWeights;
@@ -722,6 +827,8 @@ mod tests {
 		type TransactionByteFee = TransactionByteFee;
 		type WeightToFee = WeightToFee;
 		type FeeMultiplierUpdate = ();
+		type Accounting = RecordingAccounting;
+		type Event = Event;
 	}
 
 	pub struct ExtBuilder {
@@ -847,6 +954,57 @@ mod tests {
 		});
 	}
 
+	#[test]
+	fn fee_tip_and_refund_are_posted_to_the_payer() {
+		ExtBuilder::default()
+			.balance_factor(10)
+			.base_weight(5)
+			.build()
+			.execute_with(||
+		{
+			let len = 10;
+			let pre = ChargeTransactionPayment::<Runtime>::from(5 /* tipped */)
+				.pre_dispatch(&2, CALL, &info_from_weight(100), len)
+				.unwrap();
+			assert!(fees_posted().is_empty());
+
+			assert!(
+				ChargeTransactionPayment::<Runtime>
+					::post_dispatch(pre, &info_from_weight(100), &post_info_from_weight(50), len, &Ok(()))
+					.is_ok()
+			);
+			// The whole withdrawal is posted with the tip and the refund of the unused weight.
+			assert_eq!(fees_posted(), vec![(5 + 10 + 100 + 5, 5, 50, 2)]);
+		});
+	}
+
+	#[test]
+	fn failed_fee_posting_is_signalled_without_invalidating_the_transaction() {
+		ExtBuilder::default()
+			.balance_factor(10)
+			.base_weight(5)
+			.build()
+			.execute_with(||
+		{
+			System::set_block_number(1);
+			FAIL_POSTING.with(|v| *v.borrow_mut() = true);
+			let len = 10;
+			let pre = ChargeTransactionPayment::<Runtime>::from(0)
+				.pre_dispatch(&1, CALL, &info_from_weight(5), len)
+				.unwrap();
+
+			assert!(
+				ChargeTransactionPayment::<Runtime>
+					::post_dispatch(pre, &info_from_weight(5), &default_post_info(), len, &Ok(()))
+					.is_ok()
+			);
+			assert_eq!(Balances::free_balance(1), 100 - 5 - 5 - 10);
+			assert!(System::events().iter().any(|event| {
+				event.event == Event::pallet_transaction_payment(RawEvent::LedgerPostingFailed(1))
+			}));
+		});
+	}
+
 	#[test]
 	fn signed_extension_transaction_payment_multiplied_refund_works() {
 		ExtBuilder::default()
@@ -10,6 +10,7 @@ use sp_runtime::{
 	transaction_validity::InvalidTransaction,
 };
 use sp_std::{fmt::Debug, marker::PhantomData};
+use totem_utils::traits::accounting::Posting;
 
 type NegativeImbalanceOf<C, T> =
 	<C as Currency<<T as frame_system::Config>::AccountId>>::NegativeImbalance;
@@ -63,6 +64,7 @@ where
 	C::NegativeImbalance:
 		Imbalance<<C as Currency<<T as frame_system::Config>::AccountId>>::Balance, Opposite = C::PositiveImbalance>,
 	OU: OnUnbalanced<NegativeImbalanceOf<C, T>>,
+	T::Accounting: Posting<T::AccountId, T::Hash, T::BlockNumber, <C as Currency<T::AccountId>>::Balance>,
 {
 	type LiquidityInfo = Option<NegativeImbalanceOf<C, T>>;
 	type Balance = <C as Currency<<T as frame_system::Config>::AccountId>>::Balance;
@@ -95,7 +97,8 @@ where
 
 	/// Hand the fee and the tip over to the `[OnUnbalanced]` implementation.
 	/// Since the predicted fee might have been too high, parts of the fee may
-	/// be refunded.
+	/// be refunded. The fee, the tip and the refund are posted to the ledgers
+	/// of the payer.
 	///
 	/// Note: The `fee` already includes the `tip`.
 	fn correct_and_deposit_fee(
@@ -114,6 +117,12 @@ where
 			// that case we don't refund anything.
 			let refund_imbalance =
 				C::deposit_into_existing(&who, refund_amount).unwrap_or_else(|_| C::PositiveImbalance::zero());
+			let refunded = refund_imbalance.peek();
+			crate::Module::<T>::post_to_ledgers(
+				who,
+				<T::Accounting as Posting<T::AccountId, T::Hash, T::BlockNumber, Self::Balance>>
+					::account_for_fees(paid.peek(), tip, refunded, who.clone()),
+			);
 			// merge the imbalance caused by paying the fees and refunding parts of it again.
 			let adjusted_paid = paid
 				.offset(refund_imbalance)